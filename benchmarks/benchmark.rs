@@ -2,6 +2,9 @@
 //!
 //! This measures both one-shot setup (PBF parse, graph build, spatial index)
 //! and steady-state hot-path work (snap origin, Dijkstra, contour construction).
+//! A counting allocator reports the heap held by the built graph, and the
+//! edge attribute storage is compared with what per-edge copies of tags,
+//! shape points and way ids would take.
 //!
 //! Usage:
 //! This file is a repo-local profiling harness. If you want to run it through
@@ -17,7 +20,11 @@
 //!     RETAIN_ALL=1      skip graph simplification
 //!     PROFILE_LOOP=1    run the production hot path repeatedly for profiler sampling
 
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashSet;
 use std::env;
+use std::mem::{size_of, size_of_val};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
@@ -29,6 +36,41 @@ use graphways::overpass::NetworkType;
 use graphways::pbf::read_pbf;
 use graphways::reachability::ReachabilityResult;
 
+/// System allocator that tracks live and peak heap bytes.
+struct CountingAllocator;
+
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+static PEAK_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let live = LIVE_BYTES.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK_BYTES.fetch_max(live, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+            let live = LIVE_BYTES.fetch_add(new_size, Ordering::Relaxed) + new_size;
+            PEAK_BYTES.fetch_max(live, Ordering::Relaxed);
+        }
+        new_ptr
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
 #[derive(Debug)]
 struct Config {
     path: String,
@@ -51,6 +93,17 @@ struct SetupTimings {
     pois: usize,
     graph_nodes: usize,
     graph_edges: usize,
+    graph_heap_bytes: usize,
+    peak_heap_bytes: usize,
+    edge_storage: EdgeStorage,
+}
+
+/// Heap bytes behind edge tags, shape points and way ids.
+struct EdgeStorage {
+    /// As stored: interned strings, shared tag records, one coordinate buffer.
+    shared: usize,
+    /// What every edge owning its own copies would take.
+    per_edge: usize,
 }
 
 struct Stage {
@@ -204,6 +257,10 @@ fn build_graph(
     let mut spatial_graph = SpatialGraph::new(graph);
     spatial_graph.snap_pois(&pois);
     let spatial_index = t.elapsed();
+    drop(pois);
+    let graph_heap_bytes = LIVE_BYTES.load(Ordering::Relaxed);
+    let peak_heap_bytes = PEAK_BYTES.load(Ordering::Relaxed);
+    let edge_storage = measure_edge_storage(&spatial_graph);
 
     Ok((
         spatial_graph,
@@ -216,10 +273,45 @@ fn build_graph(
             pois: poi_count,
             graph_nodes,
             graph_edges,
+            graph_heap_bytes,
+            peak_heap_bytes,
+            edge_storage,
         },
     ))
 }
 
+fn measure_edge_storage(sg: &SpatialGraph) -> EdgeStorage {
+    const ARC_COUNTS: usize = 2 * size_of::<usize>();
+    let mut records = HashSet::new();
+    let mut strings = HashSet::new();
+    let mut shared = 0;
+    let mut per_edge = 0;
+    for way in sg.graph.edge_weights() {
+        if records.insert(way.tags.as_ptr()) {
+            shared += ARC_COUNTS + size_of_val(&*way.tags);
+            for tag in way.tags.iter() {
+                for text in [&tag.key, &tag.value] {
+                    if strings.insert(text.as_ptr()) {
+                        shared += ARC_COUNTS + text.len();
+                    }
+                }
+            }
+        }
+        shared += size_of_val(way.geometry.as_slice());
+        shared += size_of_val(way.way_ids.as_slice());
+
+        per_edge += way.tags.len() * 2 * size_of::<String>();
+        per_edge += way
+            .tags
+            .iter()
+            .map(|tag| tag.key.len() + tag.value.len())
+            .sum::<usize>();
+        per_edge += way.geometry.len().max(2) * size_of::<(f64, f64)>();
+        per_edge += size_of_val(way.osm_way_ids());
+    }
+    EdgeStorage { shared, per_edge }
+}
+
 fn run_hot_path(
     config: &Config,
     sg: &SpatialGraph,
@@ -373,6 +465,19 @@ fn print_setup(setup: &SetupTimings) {
         fmt(setup.spatial_index)
     );
     println!();
+    println!("memory:");
+    println!(
+        "  graph_heap     {}  (peak {} during setup)",
+        fmt_mib(setup.graph_heap_bytes),
+        fmt_mib(setup.peak_heap_bytes)
+    );
+    println!(
+        "  edge_storage   {}  (per-edge copies: {}, {:.1}x)",
+        fmt_mib(setup.edge_storage.shared),
+        fmt_mib(setup.edge_storage.per_edge),
+        setup.edge_storage.per_edge as f64 / setup.edge_storage.shared.max(1) as f64
+    );
+    println!();
 }
 
 fn print_hot_path(config: &Config, setup: &SetupTimings, stats: &HotPathStats) {
@@ -513,6 +618,10 @@ fn parse_limits(value: &str) -> Vec<f64> {
         .collect()
 }
 
+fn fmt_mib(bytes: usize) -> String {
+    format!("{:>8.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+}
+
 fn fmt(duration: Duration) -> String {
     format!("{:>8.2}", duration.as_secs_f64() * 1e3)
 }
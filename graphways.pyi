@@ -12,7 +12,11 @@ from __future__ import annotations
 # ---------------------------------------------------------------------------
 
 class SnapResult:
-    """Nearest-network-node snap diagnostics for coordinate-based operations."""
+    """Where a coordinate meets the network, for coordinate-based operations.
+
+    Coordinates snap to the closest point on an edge; ``node_*`` describe the
+    nearer end of that edge.
+    """
 
     @property
     def input_lat(self) -> float: ...
@@ -32,7 +36,23 @@ class SnapResult:
     @property
     def distance_m(self) -> float: ...
 
-    def as_dict(self) -> dict[str, float | int]: ...
+    @property
+    def snapped_lat(self) -> float: ...
+
+    @property
+    def snapped_lon(self) -> float: ...
+
+    @property
+    def edge(self) -> tuple[int, int] | None:
+        """``(from_node_id, to_node_id)`` of the snapped edge; ``None`` without edges."""
+        ...
+
+    @property
+    def edge_offset_m(self) -> float:
+        """Distance along the snapped edge from its first node to the snapped point."""
+        ...
+
+    def as_dict(self) -> dict[str, float | int | tuple[int, int] | None]: ...
 
     def __repr__(self) -> str: ...
 
@@ -51,6 +71,51 @@ class RouteResult:
     @property
     def duration_s(self) -> float: ...
 
+    @property
+    def elevations_m(self) -> list[float]:
+        """Elevation at each coordinate; empty unless the graph has elevations."""
+        ...
+
+    @property
+    def ascent_m(self) -> float: ...
+
+    @property
+    def descent_m(self) -> float: ...
+
+    def elevation_profile(self) -> list[tuple[float, float]]:
+        """``(distance along the route in metres, elevation in metres)`` pairs."""
+        ...
+
+    @property
+    def ferry_legs(self) -> list[dict[str, float]]:
+        """
+        Ferry crossings on the route, each with ``start_index`` and
+        ``end_index`` into ``coordinates``, ``distance_m`` and ``duration_s``.
+        """
+        ...
+
+    @property
+    def legs(self) -> list[dict[str, object]]:
+        """
+        Waypoint-to-waypoint legs, one unless the route came from
+        ``route_via``. Each has ``start_index`` and ``end_index`` into
+        ``coordinates``, ``distance_m``, ``duration_s``, and ``origin_snap``
+        and ``destination_snap`` dicts.
+        """
+        ...
+
+    @property
+    def maneuvers(self) -> list[dict[str, object]]:
+        """
+        Turn-by-turn steps from ``depart`` to ``arrive``. Each has a ``type``
+        (``"turn-left"``, ``"roundabout"``, ...), an ``exit`` number for
+        roundabouts, a ``waypoint`` number for intermediate stops, an English ``instruction``, the ``street`` followed next
+        (or ``None``), its ``coordinate_index`` and ``(lat, lon)``
+        ``location``, the ``bearing_deg`` when leaving it, and the
+        ``distance_m`` and ``duration_s`` to the next step.
+        """
+        ...
+
     @property
     def origin_snap(self) -> SnapResult: ...
 
@@ -65,6 +130,65 @@ class RouteResult:
 
     def __repr__(self) -> str: ...
 
+class TravelTimeMatrix:
+    """Travel times and distances from every origin to every destination.
+
+    ``durations_s[i][j]`` and ``distances_m[i][j]`` hold the fastest path from
+    origin ``i`` to destination ``j``, or ``None`` when there is none or a
+    point could not be snapped. ``numpy.array(m.durations_s, dtype=float)``
+    turns the ``None`` cells into ``nan``. Both lists are built once, so every
+    access returns the same list; copy it before changing it.
+    """
+
+    @property
+    def durations_s(self) -> list[list[float | None]]: ...
+
+    @property
+    def distances_m(self) -> list[list[float | None]]: ...
+
+    @property
+    def origin_snaps(self) -> list[SnapResult | None]: ...
+
+    @property
+    def destination_snaps(self) -> list[SnapResult | None]: ...
+
+    @property
+    def shape(self) -> tuple[int, int]:
+        """``(number of origins, number of destinations)``."""
+        ...
+
+    def __repr__(self) -> str: ...
+
+class TourResult:
+    """Visiting order for a delivery tour and the route that follows it."""
+
+    @property
+    def order(self) -> list[int]:
+        """Indices into the stops, in visiting order."""
+        ...
+
+    @property
+    def arrival_times_s(self) -> list[float]:
+        """Seconds after leaving the depot at which each stop in ``order`` is reached."""
+        ...
+
+    @property
+    def duration_s(self) -> float:
+        """Seconds from leaving the depot to the end of the tour, including waits."""
+        ...
+
+    @property
+    def lateness_s(self) -> float:
+        """Total seconds by which stops are reached after their windows close."""
+        ...
+
+    @property
+    def route(self) -> RouteResult:
+        """Route from the depot through the stops, one leg per hop."""
+        ...
+
+    def __repr__(self) -> str: ...
+
 class IsochroneResult:
     """One isochrone polygon for one travel-time threshold."""
 
@@ -289,6 +413,96 @@ class PrismGraph:
 
     def __repr__(self) -> str: ...
 
+class SpeedProfile:
+    """
+    Speeds used to derive edge travel times when a graph is built.
+
+    ``highway_speeds`` maps ``highway`` tag values to drive speeds in km/h and
+    is merged over the defaults. Tagged ``maxspeed`` values are multiplied by
+    ``maxspeed_factor``; every drive speed is capped at ``max_speed_kph`` when
+    set. ``bike_push_speed_kph`` adds walking-speed "push the bike" edges
+    against oneways on bike graphs. ``bike_surface_factors`` maps
+    ``"key=value"`` tags (``surface``, ``smoothness``, ``tracktype``,
+    ``mtb:scale``) to cycling speed multipliers and is merged over the
+    defaults; ways below ``bike_min_surface_factor`` cannot be cycled.
+    Ferry crossings take their ``duration`` tag, or their length at
+    ``ferry_speed_kph``, plus ``ferry_boarding_s``. Vehicles and cyclists
+    lose ``traffic_signal_delay_s`` at traffic signals and ``stop_delay_s`` at
+    stop signs; walkers lose ``crossing_delay_s`` at crossings.
+    ``hourly_speeds_csv`` loads drive speeds per way, direction and hour of
+    the week, used by queries given a ``departure``. Raises ``ValueError``
+    for non-positive speeds or factors, negative delays and invalid hourly
+    speed rows.
+    """
+
+    def __init__(
+        self,
+        highway_speeds: dict[str, float] | None = None,
+        fallback_speed_kph: float | None = None,
+        walk_speed_kph: float | None = None,
+        bike_speed_kph: float | None = None,
+        maxspeed_factor: float | None = None,
+        max_speed_kph: float | None = None,
+        bike_push_speed_kph: float | None = None,
+        bike_surface_factors: dict[str, float] | None = None,
+        bike_min_surface_factor: float | None = None,
+        ferry_speed_kph: float | None = None,
+        ferry_boarding_s: float | None = None,
+        traffic_signal_delay_s: float | None = None,
+        stop_delay_s: float | None = None,
+        crossing_delay_s: float | None = None,
+        hourly_speeds_csv: str | None = None,
+    ) -> None: ...
+
+    @staticmethod
+    def road_bike() -> SpeedProfile:
+        """Default speeds with cycling limited to surfaces fit for road bikes."""
+        ...
+
+    @property
+    def highway_speeds(self) -> dict[str, float]: ...
+
+    @property
+    def fallback_speed_kph(self) -> float: ...
+
+    @property
+    def walk_speed_kph(self) -> float: ...
+
+    @property
+    def bike_speed_kph(self) -> float: ...
+
+    @property
+    def maxspeed_factor(self) -> float: ...
+
+    @property
+    def max_speed_kph(self) -> float | None: ...
+
+    @property
+    def bike_push_speed_kph(self) -> float | None: ...
+
+    @property
+    def bike_surface_factors(self) -> dict[str, float]: ...
+
+    @property
+    def bike_min_surface_factor(self) -> float | None: ...
+
+    @property
+    def ferry_speed_kph(self) -> float: ...
+
+    @property
+    def ferry_boarding_s(self) -> float: ...
+
+    @property
+    def traffic_signal_delay_s(self) -> float: ...
+
+    @property
+    def stop_delay_s(self) -> float: ...
+
+    @property
+    def crossing_delay_s(self) -> float: ...
+
+    def __repr__(self) -> str: ...
+
 class SpatialGraph:
     """
     A road-network graph loaded from OpenStreetMap.
@@ -301,15 +515,23 @@ class SpatialGraph:
 
     @staticmethod
     def from_pbf(
-        path: str,
+        path: str | list[str],
         network: str,
         retain_all: bool = False,
+        speed_profile: SpeedProfile | None = None,
+        largest_component: str | None = None,
     ) -> SpatialGraph:
         """
         Load a local OSM PBF file into a reusable ``SpatialGraph``.
 
+        Pass a list of paths to merge neighbouring extracts into one graph;
+        nodes, ways and POIs shared along their boundary are kept once.
+
         ``network`` accepts ``"drive"``, ``"drive_service"``, ``"walk"``,
-        ``"bike"``, ``"all"``, or ``"all_private"``.
+        ``"bike"``, ``"all"``, or ``"all_private"``. ``speed_profile``
+        overrides the default speeds used for edge travel times.
+        ``largest_component="strong"`` (or ``"weak"``) drops every node outside
+        the largest strongly (weakly) connected component.
         """
         ...
 
@@ -318,12 +540,17 @@ class SpatialGraph:
         xml: str,
         network: str,
         retain_all: bool = False,
+        speed_profile: SpeedProfile | None = None,
+        largest_component: str | None = None,
     ) -> SpatialGraph:
         """
         Parse an OSM XML string into a reusable ``SpatialGraph``.
 
         ``network`` accepts ``"drive"``, ``"drive_service"``, ``"walk"``,
-        ``"bike"``, ``"all"``, or ``"all_private"``.
+        ``"bike"``, ``"all"``, or ``"all_private"``. ``speed_profile``
+        overrides the default speeds used for edge travel times.
+        ``largest_component="strong"`` (or ``"weak"``) drops every node outside
+        the largest strongly (weakly) connected component.
         """
         ...
 
@@ -333,15 +560,85 @@ class SpatialGraph:
         network: str,
         max_dist: float | None = None,
         retain_all: bool = False,
+        speed_profile: SpeedProfile | None = None,
+        largest_component: str | None = None,
     ) -> SpatialGraph:
         """
         Geocode a place name and build a reusable ``SpatialGraph`` around it.
 
         ``network`` accepts ``"drive"``, ``"drive_service"``, ``"walk"``,
-        ``"bike"``, ``"all"``, or ``"all_private"``.
+        ``"bike"``, ``"all"``, or ``"all_private"``. ``speed_profile``
+        overrides the default speeds used for edge travel times.
+        ``largest_component="strong"`` (or ``"weak"``) drops every node outside
+        the largest strongly (weakly) connected component.
+        """
+        ...
+
+    @staticmethod
+    def load(path: str, network: str) -> SpatialGraph:
+        """
+        Load a graph written by :meth:`save`.
+
+        ``network`` is the network type the graph was built for. Raises
+        ``ValueError`` for files that are not graph files or were written by an
+        incompatible graphways version.
+        """
+        ...
+
+    def save(self, path: str) -> None:
+        """
+        Write the graph, its spatial index, snapped POIs, turn restrictions and
+        turn penalties to a compact binary file for fast reloading.
+        """
+        ...
+
+    def with_elevation(self, dem_paths: list[str]) -> SpatialGraph:
+        """
+        Return a copy of this graph with elevations from local DEM tiles.
+
+        ``dem_paths`` lists SRTM ``.hgt`` files or single-band GeoTIFFs in
+        lat/lon coordinates. Walk times follow Tobler's hiking function and
+        bike times slow on climbs (pushed bikes like walking), so routes,
+        reachability and isochrones account for hills. Ferry times are
+        unchanged. Routes gain ``elevations_m``, ``ascent_m`` and
+        ``descent_m``.
         """
         ...
 
+    def with_closures(
+        self,
+        way_ids: list[int] | None = None,
+        node_pairs: list[tuple[int, int]] | None = None,
+        areas: list[str] | None = None,
+    ) -> SpatialGraph:
+        """
+        Return a copy of this graph with some edges closed in both directions.
+
+        ``way_ids`` closes OSM ways, ``node_pairs`` the edges directly between
+        two graph nodes, and ``areas`` every edge touching a GeoJSON Polygon or
+        MultiPolygon (geometry, Feature or FeatureCollection). The copy shares
+        the graph's data, so it is cheap to create per request. ``route``,
+        ``reachable``, ``isochrone`` and ``prism`` avoid closed edges and
+        snapping skips them. Raises ``ValueError`` for invalid GeoJSON.
+        """
+        ...
+
+    def with_contraction_hierarchy(self) -> SpatialGraph:
+        """
+        Return a copy of this graph with a contraction hierarchy for its
+        network type.
+
+        Building it takes a one-off pass over the whole graph; afterwards
+        ``route`` answers long queries much faster and returns the same
+        routes. Graphs with closures, routes with a ``departure`` and routes
+        that would take a restricted turn use A* instead.
+        """
+        ...
+
+    def has_contraction_hierarchy(self) -> bool:
+        """Whether this graph has a contraction hierarchy for its network type."""
+        ...
+
     def node_count(self) -> int:
         """Number of nodes in the graph."""
         ...
@@ -350,6 +647,15 @@ class SpatialGraph:
         """Number of directed edges in the graph."""
         ...
 
+    def components(self, connectivity: str = "strong") -> list[dict[str, object]]:
+        """
+        Connected components, largest first, as dicts with ``size`` and
+        ``node_ids`` (OSM node ids). ``connectivity`` is ``"strong"`` or
+        ``"weak"``. Only edges the graph's network type can travel connect
+        nodes.
+        """
+        ...
+
     def nearest_node(
         self, lat: float, lon: float
     ) -> tuple[int, float, float] | None:
@@ -361,11 +667,30 @@ class SpatialGraph:
         """
         ...
 
+    def nodes_by_id(
+        self, node_ids: list[int]
+    ) -> list[tuple[int, float, float] | None]:
+        """
+        Return ``(osm_id, lat, lon)`` for each id in ``node_ids``, or ``None``
+        for ids not in the graph. Merged nodes are found by their synthetic
+        (negative) id. Each lookup is O(1).
+        """
+        ...
+
+    def edges_by_way_id(self, way_ids: list[int]) -> list[list[tuple[int, int]]]:
+        """
+        Return the directed edges covering each OSM way in ``way_ids`` as
+        ``(from_node_id, to_node_id)`` pairs. Ways not in the graph give an
+        empty list.
+        """
+        ...
+
     def isochrone(
         self,
         origin: tuple[float, float],
         minutes: list[float],
         max_snap_m: float | None = 100.0,
+        departure: str | None = None,
     ) -> list[IsochroneResult]:
         """
         Compute isochrones from ``(lat, lon)`` using this graph.
@@ -376,6 +701,10 @@ class SpatialGraph:
             ``(lat, lon)`` origin coordinates.
         minutes:
             Travel-time thresholds in minutes.
+        departure:
+            Optional departure time such as ``"Mo 08:00"``. Edges with
+            conditional access restrictions are closed while they apply,
+            and hourly drive speeds apply for the hour each edge is reached.
         Returns
         -------
         list[IsochroneResult]
@@ -390,11 +719,15 @@ class SpatialGraph:
         origin: tuple[float, float],
         destination: tuple[float, float],
         max_snap_m: float | None = 100.0,
+        departure: str | None = None,
     ) -> RouteResult:
         """
         Find the fastest route between two coordinates using A*.
 
         The network type (drive/walk/bike) is inherited from the ``SpatialGraph``.
+        With ``departure`` (e.g. ``"Mo 08:00"``), edges are avoided while a
+        conditional access restriction applies at the time they are reached,
+        and hourly drive speeds from the ``SpeedProfile`` apply for that hour.
 
         Returns
         -------
@@ -405,6 +738,79 @@ class SpatialGraph:
         """
         ...
 
+    def optimize_tour(
+        self,
+        depot: tuple[float, float],
+        stops: list[tuple[float, float]],
+        return_to_depot: bool = True,
+        time_windows: list[tuple[float, float] | None] | None = None,
+        max_snap_m: float | None = 100.0,
+        max_evaluations: int = 1_000_000,
+    ) -> TourResult:
+        """
+        A good order to visit ``stops`` from ``depot``, and the route through them.
+
+        Orders come from nearest insertion improved by 2-opt and Or-opt moves
+        over a travel-time matrix for the graph's network type.
+        ``time_windows`` holds one ``(earliest_s, latest_s)`` pair or ``None``
+        per stop, in seconds after leaving the depot: early arrivals wait, and
+        late arrivals are avoided wherever possible. Improvement stops after
+        ``max_evaluations`` candidate orders. Raises ``ValueError`` when a
+        point does not snap within ``max_snap_m``.
+        """
+        ...
+
+    def route_via(
+        self,
+        points: list[tuple[float, float]],
+        max_snap_m: float | None = 100.0,
+    ) -> RouteResult:
+        """
+        Fastest route through ``(lat, lon)`` points in order, as one
+        continuous route whose ``legs`` cover each consecutive pair.
+
+        Every point must snap within ``max_snap_m``. A leg that cannot be
+        snapped or routed raises the same error as ``route`` would, with a
+        message naming the failing leg. Turn restrictions and penalties apply
+        within each leg but reset at every waypoint.
+        """
+        ...
+
+    def route_alternatives(
+        self,
+        origin: tuple[float, float],
+        destination: tuple[float, float],
+        k: int = 3,
+        max_shared: float = 0.5,
+        max_stretch: float = 1.4,
+        max_snap_m: float | None = 100.0,
+    ) -> list[RouteResult]:
+        """
+        Up to ``k`` meaningfully different routes, fastest first.
+
+        The first route is the one ``route`` returns. Each further route takes
+        at most ``max_stretch`` times as long and shares at most
+        ``max_shared`` of its length with every route before it, so fewer
+        than ``k`` come back when the network offers no such detours. ``k``
+        counts the fastest route; ``k=0`` raises ``ValueError``.
+        """
+        ...
+
+    def travel_time_matrix(
+        self,
+        origins: list[tuple[float, float]],
+        destinations: list[tuple[float, float]],
+        max_snap_m: float | None = 100.0,
+    ) -> TravelTimeMatrix:
+        """
+        Travel times from every ``(lat, lon)`` origin to every destination.
+
+        Each point is snapped once and each origin runs one search, in
+        parallel across origins. Points farther than ``max_snap_m`` from the
+        network get ``None`` snaps and unreachable cells rather than raising.
+        """
+        ...
+
     def fetch_pois(self, isochrone: IsochroneResult | str) -> PoiCollection:
         """
         Fetch OSM points of interest within a given isochrone polygon.
@@ -423,11 +829,42 @@ class SpatialGraph:
         """
         ...
 
-    def snap_point(self, lat: float, lon: float) -> SnapResult | None:
+    def snap_point(
+        self,
+        lat: float,
+        lon: float,
+        max_snap_m: float | None = None,
+        network: str | None = None,
+        highways: list[str] | None = None,
+        min_component_size: int | None = None,
+        connectivity: str = "strong",
+    ) -> SnapResult | None:
+        """
+        Snap ``(lat, lon)`` to the closest point on an edge.
+
+        ``network`` keeps only edges a graph of that network type would
+        contain, ``highways`` only the listed highway classes, and
+        ``min_component_size`` only edges in ``connectivity`` components of at
+        least that many nodes. Returns ``None`` if no edge passes within
+        ``max_snap_m``. Use ``as_dict()`` if you need a plain dictionary.
         """
-        Return snap diagnostics for the nearest graph node to ``(lat, lon)``.
+        ...
 
-        Use ``as_dict()`` if you need a plain dictionary.
+    def snap_candidates(
+        self,
+        lat: float,
+        lon: float,
+        k: int = 5,
+        max_snap_m: float | None = None,
+        network: str | None = None,
+        highways: list[str] | None = None,
+        min_component_size: int | None = None,
+        connectivity: str = "strong",
+    ) -> list[SnapResult]:
+        """
+        Up to ``k`` snaps onto distinct edges, closest first, with the same
+        filters as ``snap_point``. Retry a failed query from the next
+        candidate's ``(snapped_lat, snapped_lon)``.
         """
         ...
 
@@ -436,9 +873,14 @@ class SpatialGraph:
         origin: tuple[float, float],
         minutes: float,
         max_snap_m: float | None = 100.0,
+        departure: str | None = None,
     ) -> ReachableGraph:
         """
         Compute one-sided reachability from ``(lat, lon)`` within ``minutes``.
+
+        ``departure`` (e.g. ``"Mo 08:00"``) closes edges while a conditional
+        access restriction applies at the time they are reached and applies
+        hourly drive speeds for that hour.
         """
         ...
 
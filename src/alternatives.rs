@@ -0,0 +1,262 @@
+//! Alternative routes.
+//!
+//! [`SpatialGraph::route_alternatives`] uses the penalty method: after the
+//! fastest route, each search makes the edges of every route found so far
+//! more expensive, which pushes the next search onto other streets. A
+//! candidate is kept when it is not much slower than the fastest route and
+//! does not share too much of its length with any route already kept.
+
+use std::collections::HashMap;
+
+use petgraph::graph::EdgeIndex;
+
+use crate::error::OsmGraphError;
+use crate::graph::SpatialGraph;
+use crate::overpass::NetworkType;
+use crate::reachability::EdgeInfo;
+use crate::routing::{a_star, route_along, shortest_path, snap_endpoints, Route};
+use crate::search::SearchPath;
+
+/// Limits on the routes returned by [`SpatialGraph::route_alternatives`].
+#[derive(Debug, Clone, Copy)]
+pub struct AlternativeOptions {
+    /// Most routes to return, counting the fastest one. Must be at least 1.
+    pub max_alternatives: usize,
+    /// Largest share of an alternative's length that may overlap any route
+    /// returned before it, between 0 and 1.
+    pub max_shared_fraction: f64,
+    /// Longest an alternative may take, as a multiple of the fastest
+    /// route's duration.
+    pub max_stretch: f64,
+    /// Factor applied to the cost of an edge each time it appears in a route
+    /// found so far.
+    pub penalty_factor: f64,
+}
+
+impl Default for AlternativeOptions {
+    fn default() -> Self {
+        Self {
+            max_alternatives: 3,
+            max_shared_fraction: 0.5,
+            max_stretch: 1.4,
+            penalty_factor: 1.4,
+        }
+    }
+}
+
+/// The part of each edge a path covers, as fractions along it.
+type EdgeSpans = HashMap<EdgeIndex, (f64, f64)>;
+
+fn edge_spans(path: &SearchPath) -> EdgeSpans {
+    (0..path.edges.len())
+        .map(|i| (path.edges[i], path.edge_span(i)))
+        .collect()
+}
+
+/// Metres of `path` that lie on the edge spans of another path.
+fn shared_length_m(sg: &SpatialGraph, path: &SearchPath, other: &EdgeSpans) -> f64 {
+    (0..path.edges.len())
+        .filter_map(|i| {
+            let edge = path.edges[i];
+            let (from, to) = path.edge_span(i);
+            let &(other_from, other_to) = other.get(&edge)?;
+            let overlap = to.min(other_to) - from.max(other_from);
+            Some(overlap.max(0.0) * sg.graph[edge].length)
+        })
+        .sum()
+}
+
+impl SpatialGraph {
+    /// Up to `options.max_alternatives` meaningfully different routes
+    /// between two lat/lon points, fastest first.
+    ///
+    /// The first route is the one [`SpatialGraph::route`] returns. Each
+    /// further route takes at most `max_stretch` times as long and shares at
+    /// most `max_shared_fraction` of its length with every route before it,
+    /// so fewer routes come back when the network offers no such detours.
+    /// Fails like [`SpatialGraph::route`] when there is no route at all, and
+    /// with [`OsmGraphError::InvalidInput`] when `max_alternatives` is 0, so
+    /// an empty result never stands for a missing route.
+    #[allow(clippy::too_many_arguments)]
+    pub fn route_alternatives(
+        &self,
+        origin_lat: f64,
+        origin_lon: f64,
+        dest_lat: f64,
+        dest_lon: f64,
+        network_type: NetworkType,
+        max_snap_m: Option<f64>,
+        options: &AlternativeOptions,
+    ) -> Result<Vec<Route>, OsmGraphError> {
+        if options.max_alternatives == 0 {
+            return Err(OsmGraphError::InvalidInput(
+                "max_alternatives must be at least 1".to_string(),
+            ));
+        }
+        let (origin_snap, destination_snap) =
+            snap_endpoints(self, origin_lat, origin_lon, dest_lat, dest_lon, max_snap_m)?;
+        let origin = self.anchor(&origin_snap);
+        let destination = self.anchor(&destination_snap);
+        let fastest = shortest_path(self, &origin, &destination, network_type, None)
+            .ok_or(OsmGraphError::PathNotFound)?;
+        let route = |path: &SearchPath| {
+            route_along(
+                self,
+                origin_snap,
+                destination_snap,
+                &origin,
+                path,
+                network_type,
+                None,
+            )
+        };
+
+        let max_duration_s = route(&fastest).duration_s * options.max_stretch;
+        let mut penalties: HashMap<EdgeIndex, f64> = HashMap::new();
+        let mut seen: Vec<Vec<EdgeIndex>> = Vec::new();
+        let mut kept: Vec<(Route, EdgeSpans)> = Vec::new();
+        let mut candidate = Some(fastest);
+        // Each search penalises one more route, so a few searches per
+        // wanted route are enough to walk away from the fastest corridor.
+        for _ in 0..options.max_alternatives * 4 {
+            let Some(path) = candidate.take() else {
+                break;
+            };
+            for &edge in &path.edges {
+                *penalties.entry(edge).or_insert(1.0) *= options.penalty_factor;
+            }
+            if !seen.contains(&path.edges) {
+                seen.push(path.edges.clone());
+                let route = route(&path);
+                let length_m = route.distance_m;
+                let distinct = kept.iter().all(|(_, spans)| {
+                    shared_length_m(self, &path, spans) <= options.max_shared_fraction * length_m
+                });
+                if kept.is_empty() || (route.duration_s <= max_duration_s && distinct) {
+                    kept.push((route, edge_spans(&path)));
+                    if kept.len() >= options.max_alternatives {
+                        break;
+                    }
+                }
+            }
+            candidate = a_star(
+                self,
+                &origin,
+                &destination,
+                network_type,
+                |e: EdgeInfo<'_>, elapsed_s| {
+                    self.search_cost(e.id, network_type, None, elapsed_s)
+                        * penalties.get(&e.id).copied().unwrap_or(1.0)
+                },
+            );
+        }
+        Ok(kept.into_iter().map(|(route, _)| route).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two ways from 1 to 4: a fast northern street through 2 and a slightly
+    // longer southern one through 3, plus a longer detour through 5. Node 6
+    // leads into 1 from the west.
+    const XML: &str = r#"<osm>
+          <node id="1" lat="48.000" lon="11.000" />
+          <node id="2" lat="48.001" lon="11.005" />
+          <node id="3" lat="47.9985" lon="11.005" />
+          <node id="4" lat="48.000" lon="11.010" />
+          <node id="5" lat="47.995" lon="11.005" />
+          <node id="6" lat="48.000" lon="10.995" />
+          <way id="10"><nd ref="1" /><nd ref="2" /><nd ref="4" />
+            <tag k="highway" v="primary" /></way>
+          <way id="11"><nd ref="1" /><nd ref="3" /><nd ref="4" />
+            <tag k="highway" v="primary" /></way>
+          <way id="12"><nd ref="1" /><nd ref="5" /><nd ref="4" />
+            <tag k="highway" v="primary" /></way>
+          <way id="13"><nd ref="6" /><nd ref="1" /><tag k="highway" v="primary" /></way>
+        </osm>"#;
+
+    fn graph() -> SpatialGraph {
+        SpatialGraph::from_osm(XML, NetworkType::Drive, Some(true), None).unwrap()
+    }
+
+    #[test]
+    fn alternatives_take_other_streets_within_the_stretch() {
+        let sg = graph();
+        let fastest = sg
+            .route(48.0, 11.0, 48.0, 11.01, NetworkType::Drive, None)
+            .unwrap();
+        let routes = sg
+            .route_alternatives(
+                48.0,
+                11.0,
+                48.0,
+                11.01,
+                NetworkType::Drive,
+                None,
+                &AlternativeOptions::default(),
+            )
+            .unwrap();
+
+        // The detour through 5 takes more than 1.4 times as long.
+        assert_eq!(routes.len(), 2);
+        assert_eq!(routes[0].coordinates, fastest.coordinates);
+        assert!((routes[0].duration_s - fastest.duration_s).abs() < 1e-9);
+        assert_eq!(routes[1].coordinates[1], (47.9985, 11.005));
+        assert!(routes[1].duration_s > fastest.duration_s);
+        assert_eq!(
+            routes[1].cumulative_times_s.len(),
+            routes[1].coordinates.len()
+        );
+        assert_eq!(
+            *routes[1].cumulative_times_s.last().unwrap(),
+            routes[1].duration_s
+        );
+
+        let loose = AlternativeOptions {
+            max_stretch: 10.0,
+            ..AlternativeOptions::default()
+        };
+        let routes = sg
+            .route_alternatives(48.0, 11.0, 48.0, 11.01, NetworkType::Drive, None, &loose)
+            .unwrap();
+        assert_eq!(routes.len(), 3);
+        assert_eq!(routes[2].coordinates[1], (47.995, 11.005));
+    }
+
+    #[test]
+    fn alternatives_respect_the_shared_length_limit() {
+        // Every route from node 6 shares the street into node 1.
+        let sg = graph();
+        let strict = AlternativeOptions {
+            max_shared_fraction: 0.0,
+            max_stretch: 10.0,
+            ..AlternativeOptions::default()
+        };
+        let routes = sg
+            .route_alternatives(48.0, 10.995, 48.0, 11.01, NetworkType::Drive, None, &strict)
+            .unwrap();
+        assert_eq!(routes.len(), 1);
+
+        let one = AlternativeOptions {
+            max_alternatives: 1,
+            ..AlternativeOptions::default()
+        };
+        let routes = sg
+            .route_alternatives(48.0, 11.0, 48.0, 11.01, NetworkType::Drive, None, &one)
+            .unwrap();
+        assert_eq!(routes.len(), 1);
+    }
+
+    #[test]
+    fn zero_alternatives_is_rejected() {
+        let none = AlternativeOptions {
+            max_alternatives: 0,
+            ..AlternativeOptions::default()
+        };
+        let result =
+            graph().route_alternatives(48.0, 11.0, 48.0, 11.01, NetworkType::Drive, None, &none);
+        assert!(matches!(result, Err(OsmGraphError::InvalidInput(_))));
+    }
+}
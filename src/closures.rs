@@ -0,0 +1,305 @@
+//! Runtime edge closures layered over a shared graph.
+//!
+//! Road works or a flooded district close part of the network for a while,
+//! which should not require rebuilding the graph from source.
+//! [`SpatialGraph::with_closures`] returns a graph that shares its edges,
+//! spatial indexes and turn data with the original and adds an overlay of
+//! closed edges. `route`, `reachability`, `isochrones` and `prism` never
+//! travel a closed edge, and snapping skips them.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use geo::{BoundingRect, Coord, Intersects, LineString, Polygon};
+use petgraph::graph::EdgeIndex;
+use petgraph::visit::EdgeRef;
+
+use crate::conditional::{timed_travel_time, DepartureTime};
+use crate::error::OsmGraphError;
+use crate::graph::{directed_edge_geometry, reverse_twin, SpatialGraph};
+use crate::overpass::NetworkType;
+
+/// Edges to close, by OSM way id, by node pair or by area. Every closure
+/// applies to both directions of travel.
+#[derive(Debug, Clone, Default)]
+pub struct EdgeClosures {
+    way_ids: HashSet<i64>,
+    node_pairs: HashSet<(i64, i64)>,
+    areas: Vec<Polygon<f64>>,
+}
+
+impl EdgeClosures {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Close every edge of OSM way `way_id`. A simplified edge covering the
+    /// way together with others is closed as a whole.
+    pub fn with_way(mut self, way_id: i64) -> Self {
+        self.way_ids.insert(way_id);
+        self
+    }
+
+    /// [`EdgeClosures::with_way`] for each id in `way_ids`.
+    pub fn with_ways<I: IntoIterator<Item = i64>>(mut self, way_ids: I) -> Self {
+        self.way_ids.extend(way_ids);
+        self
+    }
+
+    /// Close the edges running directly between graph nodes `a` and `b`.
+    /// Nodes merged into an edge by simplification are not graph nodes and
+    /// match nothing.
+    pub fn with_node_pair(mut self, a: i64, b: i64) -> Self {
+        self.node_pairs.insert((a.min(b), a.max(b)));
+        self
+    }
+
+    /// Close every edge whose geometry touches `area`, in the library's
+    /// `x = lat, y = lon` convention.
+    pub fn with_area(mut self, area: Polygon<f64>) -> Self {
+        self.areas.push(area);
+        self
+    }
+
+    /// Close every edge touching the Polygon or MultiPolygon areas of a
+    /// GeoJSON geometry, Feature or FeatureCollection.
+    pub fn with_geojson(mut self, geojson: &str) -> Result<Self, OsmGraphError> {
+        let geojson: geojson::GeoJson = geojson
+            .parse()
+            .map_err(|_| OsmGraphError::InvalidInput("invalid GeoJSON".into()))?;
+        let geometries: Vec<geojson::Value> = match geojson {
+            geojson::GeoJson::Geometry(geometry) => vec![geometry.value],
+            geojson::GeoJson::Feature(feature) => {
+                feature.geometry.map(|g| g.value).into_iter().collect()
+            }
+            geojson::GeoJson::FeatureCollection(collection) => collection
+                .features
+                .into_iter()
+                .filter_map(|feature| feature.geometry.map(|g| g.value))
+                .collect(),
+        };
+        for value in geometries {
+            match value {
+                geojson::Value::Polygon(rings) => self.areas.push(polygon_from_rings(&rings)?),
+                geojson::Value::MultiPolygon(polygons) => {
+                    for rings in &polygons {
+                        self.areas.push(polygon_from_rings(rings)?);
+                    }
+                }
+                _ => {
+                    return Err(OsmGraphError::InvalidInput(
+                        "expected Polygon or MultiPolygon geometry".into(),
+                    ))
+                }
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.way_ids.is_empty() && self.node_pairs.is_empty() && self.areas.is_empty()
+    }
+
+    fn extend(&mut self, other: &EdgeClosures) {
+        self.way_ids.extend(&other.way_ids);
+        self.node_pairs.extend(&other.node_pairs);
+        self.areas.extend(other.areas.iter().cloned());
+    }
+
+    /// The edges of `sg` these closures apply to.
+    fn resolve(&self, sg: &SpatialGraph) -> HashSet<EdgeIndex> {
+        let mut edges: HashSet<EdgeIndex> = self
+            .way_ids
+            .iter()
+            .flat_map(|&way_id| sg.edges_for_osm_way_id(way_id))
+            .copied()
+            .collect();
+        for &(a, b) in &self.node_pairs {
+            let (Some(a), Some(b)) = (sg.node_index_for_osm_id(a), sg.node_index_for_osm_id(b))
+            else {
+                continue;
+            };
+            edges.extend(sg.graph.edges_connecting(a, b).map(|edge| edge.id()));
+            edges.extend(sg.graph.edges_connecting(b, a).map(|edge| edge.id()));
+        }
+        for area in &self.areas {
+            let Some(bounds) = area.bounding_rect() else {
+                continue;
+            };
+            for edge in sg.indexed_edges_within(bounds.min(), bounds.max()) {
+                let line: LineString<f64> = directed_edge_geometry(&sg.graph, edge).into();
+                if line.intersects(area) {
+                    edges.insert(edge);
+                    edges.extend(reverse_twin(&sg.graph, edge));
+                }
+            }
+        }
+        edges
+    }
+}
+
+/// GeoJSON `[lon, lat]` rings as a polygon in `x = lat, y = lon` order.
+fn polygon_from_rings(rings: &[Vec<Vec<f64>>]) -> Result<Polygon<f64>, OsmGraphError> {
+    let mut rings = rings.iter().map(|ring| {
+        ring.iter()
+            .map(|position| match position.as_slice() {
+                [lon, lat, ..] => Ok(Coord { x: *lat, y: *lon }),
+                _ => Err(OsmGraphError::InvalidInput(
+                    "GeoJSON positions need a longitude and a latitude".into(),
+                )),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(LineString::from)
+    });
+    let exterior = rings
+        .next()
+        .ok_or_else(|| OsmGraphError::InvalidInput("polygon has no rings".into()))??;
+    Ok(Polygon::new(exterior, rings.collect::<Result<_, _>>()?))
+}
+
+/// Closures resolved against the edges of one graph.
+#[derive(Debug, Default)]
+pub(crate) struct ClosureOverlay {
+    closures: EdgeClosures,
+    edges: HashSet<EdgeIndex>,
+}
+
+impl SpatialGraph {
+    /// This graph with `closures` closed on top of any closures it already
+    /// has. The result shares the graph's data, so it is cheap to build per
+    /// request; the original graph is unchanged.
+    pub fn with_closures(mut self, closures: &EdgeClosures) -> Self {
+        if closures.is_empty() {
+            return self;
+        }
+        let mut overlay = ClosureOverlay::default();
+        if let Some(existing) = &self.closures {
+            overlay.closures.extend(&existing.closures);
+            overlay.edges.extend(&existing.edges);
+        }
+        overlay.closures.extend(closures);
+        overlay.edges.extend(closures.resolve(&self));
+        self.closures = Some(Arc::new(overlay));
+        self
+    }
+
+    /// The closures applied to this graph; empty when it has none.
+    pub fn closures(&self) -> EdgeClosures {
+        self.closures
+            .as_ref()
+            .map(|overlay| overlay.closures.clone())
+            .unwrap_or_default()
+    }
+
+    /// Whether `edge` is closed by [`SpatialGraph::with_closures`].
+    pub fn is_edge_closed(&self, edge: EdgeIndex) -> bool {
+        self.closures
+            .as_ref()
+            .is_some_and(|overlay| overlay.edges.contains(&edge))
+    }
+
+    /// Cost of `edge` for a `network_type` search: its travel time, or
+    /// infinity while it is closed, conditionally at `departure` plus
+    /// `elapsed_s` or by the closure overlay.
+    pub(crate) fn search_cost(
+        &self,
+        edge: EdgeIndex,
+        network_type: NetworkType,
+        departure: Option<DepartureTime>,
+        elapsed_s: f64,
+    ) -> f64 {
+        if self.is_edge_closed(edge) {
+            return f64::INFINITY;
+        }
+        timed_travel_time(&self.graph[edge], network_type, departure, elapsed_s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two parallel streets between node 1 and node 4: a direct one through
+    // node 2 and a longer detour through node 3.
+    const XML: &str = r#"<osm>
+          <node id="1" lat="48.0000" lon="11.0000" />
+          <node id="2" lat="48.0000" lon="11.0010" />
+          <node id="3" lat="48.0010" lon="11.0010" />
+          <node id="4" lat="48.0000" lon="11.0020" />
+          <way id="10"><nd ref="1" /><nd ref="2" /><tag k="highway" v="residential" /></way>
+          <way id="11"><nd ref="2" /><nd ref="4" /><tag k="highway" v="residential" /></way>
+          <way id="20"><nd ref="1" /><nd ref="3" /><nd ref="4" />
+            <tag k="highway" v="residential" /></way>
+        </osm>"#;
+
+    fn graph() -> SpatialGraph {
+        SpatialGraph::from_osm(XML, NetworkType::Drive, Some(true), None).unwrap()
+    }
+
+    fn route_distance(sg: &SpatialGraph) -> Option<f64> {
+        sg.route(48.0, 11.0, 48.0, 11.002, NetworkType::Drive, Some(5.0))
+            .ok()
+            .map(|route| route.distance_m)
+    }
+
+    #[test]
+    fn closures_detour_routes_without_copying_the_graph() {
+        let sg = graph();
+        let direct = route_distance(&sg).unwrap();
+
+        let by_way = sg.clone().with_closures(&EdgeClosures::new().with_way(11));
+        let by_pair = sg
+            .clone()
+            .with_closures(&EdgeClosures::new().with_node_pair(4, 2));
+        let area = r#"{"type": "Polygon", "coordinates": [[[11.0009, 47.9999],
+            [11.0011, 47.9999], [11.0011, 48.0001], [11.0009, 48.0001], [11.0009, 47.9999]]]}"#;
+        let by_area = sg
+            .clone()
+            .with_closures(&EdgeClosures::new().with_geojson(area).unwrap());
+
+        for closed in [&by_way, &by_pair, &by_area] {
+            assert!(Arc::ptr_eq(&closed.graph, &sg.graph));
+            assert!(route_distance(closed).unwrap() > direct * 1.5);
+        }
+        assert_eq!(route_distance(&sg), Some(direct));
+        let both = by_way.with_closures(&EdgeClosures::new().with_way(20));
+        assert_eq!(both.closures().way_ids.len(), 2);
+        assert_eq!(route_distance(&both), None);
+    }
+
+    #[test]
+    fn closures_apply_to_reachability_isochrones_prism_and_snapping() {
+        let sg = graph();
+        let closed = sg.clone().with_closures(&EdgeClosures::new().with_way(20));
+        let node_3 = |sg: &SpatialGraph| sg.node_index_for_osm_id(3).unwrap();
+
+        let open = sg
+            .reachability(48.0, 11.0, 3_600.0, NetworkType::Drive, None)
+            .unwrap();
+        assert!(open.distances.contains_key(&node_3(&sg)));
+        let reach = closed
+            .reachability(48.0, 11.0, 3_600.0, NetworkType::Drive, None)
+            .unwrap();
+        assert!(!reach.distances.contains_key(&node_3(&closed)));
+        assert!(!closed
+            .reachable_graph(48.0, 11.0, 3_600.0, NetworkType::Drive, None)
+            .unwrap()
+            .materialize()
+            .graph
+            .edge_indices()
+            .any(|edge| closed.graph[edge].id == 20));
+
+        let prism = closed
+            .prism(48.0, 11.0, 48.0, 11.002, 3_600.0, NetworkType::Drive, None)
+            .unwrap()
+            .unwrap();
+        assert!(!prism.result.feasible.contains_key(&node_3(&closed)));
+        assert!(closed
+            .isochrones(48.0, 11.0, vec![3_600.0], NetworkType::Drive, None)
+            .is_some());
+
+        let snap = closed.snap_point(48.0006, 11.0005).unwrap();
+        let (from, to) = snap.edge_node_ids.unwrap();
+        assert_eq!((from.min(to), from.max(to)), (1, 2));
+    }
+}
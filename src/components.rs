@@ -0,0 +1,250 @@
+//! Connected components of a road graph.
+//!
+//! OSM extracts routinely contain islands: parking-lot fragments, private
+//! estates or ways cut at the extract boundary. Snapping onto one makes routes
+//! fail with [`crate::error::OsmGraphError::PathNotFound`] and shrinks
+//! isochrones to a few metres. [`SpatialGraph::components`] lists them and
+//! [`SpatialGraph::retain_largest_component`] drops everything but the main
+//! network. Both only follow edges a network type can travel, so a road
+//! walkers may use but cars may not does not join two drive islands.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use petgraph::algo::tarjan_scc;
+use petgraph::graph::{DiGraph, NodeIndex};
+use petgraph::unionfind::UnionFind;
+use petgraph::visit::{EdgeFiltered, EdgeRef};
+
+use crate::graph::{SpatialGraph, XmlNode, XmlWay};
+use crate::overpass::NetworkType;
+
+/// Which notion of connectivity to use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Connectivity {
+    /// Every node can reach every other node following edge directions.
+    /// Use for drive and bike graphs, where oneways can trap traffic.
+    Strong,
+    /// Nodes are connected ignoring edge directions.
+    Weak,
+}
+
+/// Components of `graph` for `network_type`, largest first. Each component
+/// lists its nodes. Edges with an infinite travel time for `network_type`
+/// connect nothing.
+pub fn connected_components(
+    graph: &DiGraph<XmlNode, XmlWay>,
+    network_type: NetworkType,
+    connectivity: Connectivity,
+) -> Vec<Vec<NodeIndex>> {
+    let travelable = |edge: petgraph::graph::EdgeReference<'_, XmlWay>| {
+        edge.weight().travel_time(network_type).is_finite()
+    };
+    let mut components = match connectivity {
+        Connectivity::Strong => tarjan_scc(&EdgeFiltered::from_fn(graph, travelable)),
+        Connectivity::Weak => {
+            let mut sets = UnionFind::new(graph.node_count());
+            for edge in graph.edge_references().filter(|&edge| travelable(edge)) {
+                sets.union(edge.source().index(), edge.target().index());
+            }
+            let mut by_root: HashMap<usize, Vec<NodeIndex>> = HashMap::new();
+            for node in graph.node_indices() {
+                by_root
+                    .entry(sets.find(node.index()))
+                    .or_default()
+                    .push(node);
+            }
+            by_root.into_values().collect()
+        }
+    };
+    for component in &mut components {
+        component.sort_unstable();
+    }
+    // Ties are broken by lowest node index so the order is deterministic.
+    components.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a[0].cmp(&b[0])));
+    components
+}
+
+/// Size of the component containing each node, by node index.
+type NodeComponentSizes = Arc<[u32]>;
+
+/// [`NodeComponentSizes`] computed on first use for each network type and
+/// kind of connectivity.
+#[derive(Debug, Default)]
+pub(crate) struct ComponentSizes {
+    sizes: Mutex<HashMap<(NetworkType, Connectivity), NodeComponentSizes>>,
+}
+
+/// Subgraph of `graph` induced by `keep`.
+fn induced_subgraph(
+    graph: &DiGraph<XmlNode, XmlWay>,
+    keep: &HashSet<NodeIndex>,
+) -> DiGraph<XmlNode, XmlWay> {
+    graph.filter_map(
+        |index, node| keep.contains(&index).then(|| node.clone()),
+        |_, way| Some(way.clone()),
+    )
+}
+
+impl SpatialGraph {
+    /// Components of this graph for `network_type`, largest first.
+    pub fn components(
+        &self,
+        network_type: NetworkType,
+        connectivity: Connectivity,
+    ) -> Vec<Vec<NodeIndex>> {
+        connected_components(&self.graph, network_type, connectivity)
+    }
+
+    /// Number of nodes in the `connectivity` component for `network_type`
+    /// containing `node`. Sizes for the whole graph are computed on the
+    /// first call.
+    pub fn component_size(
+        &self,
+        node: NodeIndex,
+        network_type: NetworkType,
+        connectivity: Connectivity,
+    ) -> usize {
+        let sizes = {
+            let mut cache = self.component_sizes.sizes.lock().unwrap();
+            Arc::clone(
+                cache
+                    .entry((network_type, connectivity))
+                    .or_insert_with(|| {
+                        let mut sizes = vec![0; self.graph.node_count()];
+                        for component in self.components(network_type, connectivity) {
+                            for node in &component {
+                                sizes[node.index()] = component.len() as u32;
+                            }
+                        }
+                        sizes.into()
+                    }),
+            )
+        };
+        sizes[node.index()] as usize
+    }
+
+    /// Keep only the largest component for `network_type`, dropping islands
+    /// that routes cannot leave or enter. Turn restrictions and penalties
+    /// carry over; POI snaps do not, so call [`SpatialGraph::snap_pois`]
+    /// afterwards.
+    pub fn retain_largest_component(
+        self,
+        network_type: NetworkType,
+        connectivity: Connectivity,
+    ) -> Self {
+        let components = self.components(network_type, connectivity);
+        if components.len() <= 1 {
+            return self;
+        }
+        let keep: HashSet<NodeIndex> = components[0].iter().copied().collect();
+        self.derive(induced_subgraph(&self.graph, &keep))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::overpass::NetworkType;
+
+    // A two-way triangle 1-2-3, a oneway spur 3 -> 4 and a separate
+    // two-node island 5-6.
+    const XML: &str = r#"<osm>
+          <node id="1" lat="48.000" lon="11.000" />
+          <node id="2" lat="48.000" lon="11.001" />
+          <node id="3" lat="48.001" lon="11.000" />
+          <node id="4" lat="48.002" lon="11.000" />
+          <node id="5" lat="48.010" lon="11.010" />
+          <node id="6" lat="48.010" lon="11.011" />
+          <way id="10"><nd ref="1" /><nd ref="2" /><nd ref="3" /><nd ref="1" />
+            <tag k="highway" v="residential" /></way>
+          <way id="11"><nd ref="3" /><nd ref="4" /><tag k="highway" v="residential" />
+            <tag k="oneway" v="yes" /></way>
+          <way id="12"><nd ref="5" /><nd ref="6" /><tag k="highway" v="service" /></way>
+        </osm>"#;
+
+    fn sizes(components: &[Vec<NodeIndex>]) -> Vec<usize> {
+        components.iter().map(Vec::len).collect()
+    }
+
+    #[test]
+    fn lists_strong_and_weak_components_largest_first() {
+        let sg = SpatialGraph::from_osm(XML, NetworkType::Drive, Some(true), None).unwrap();
+        assert_eq!(
+            sizes(&sg.components(NetworkType::Drive, Connectivity::Strong)),
+            vec![3, 2, 1]
+        );
+        assert_eq!(
+            sizes(&sg.components(NetworkType::Drive, Connectivity::Weak)),
+            vec![4, 2]
+        );
+    }
+
+    #[test]
+    fn retains_only_the_largest_component() {
+        let sg = SpatialGraph::from_osm(XML, NetworkType::Drive, Some(true), None).unwrap();
+
+        let strong = sg
+            .clone()
+            .retain_largest_component(NetworkType::Drive, Connectivity::Strong);
+        let mut ids: Vec<i64> = strong.graph.node_weights().map(|n| n.id).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, vec![1, 2, 3]);
+        assert_eq!(strong.graph.edge_count(), 6);
+        // The spatial index only knows the remaining nodes.
+        let island = strong.nearest_node(48.010, 11.010).unwrap();
+        assert_ne!(strong.graph[island].id, 5);
+
+        let weak = sg
+            .clone()
+            .retain_largest_component(NetworkType::Drive, Connectivity::Weak);
+        assert_eq!(weak.graph.node_count(), 4);
+        assert_eq!(weak.graph.edge_count(), 7);
+
+        let spur = sg.node_index_for_osm_id(4).unwrap();
+        assert_eq!(
+            sg.component_size(spur, NetworkType::Drive, Connectivity::Strong),
+            1
+        );
+        assert_eq!(
+            sg.component_size(spur, NetworkType::Drive, Connectivity::Weak),
+            4
+        );
+    }
+
+    #[test]
+    fn edges_closed_to_the_network_type_do_not_connect() {
+        // A street through a bollard at node 3: cars cannot leave node 3.
+        let xml = r#"<osm>
+              <node id="1" lat="48.000" lon="11.000" />
+              <node id="2" lat="48.000" lon="11.001" />
+              <node id="3" lat="48.000" lon="11.002"><tag k="barrier" v="bollard" /></node>
+              <node id="4" lat="48.000" lon="11.003" />
+              <way id="10"><nd ref="1" /><nd ref="2" /><nd ref="3" /><nd ref="4" />
+                <tag k="highway" v="residential" /></way>
+            </osm>"#;
+        let sg = SpatialGraph::from_osm(xml, NetworkType::Drive, Some(true), None).unwrap();
+
+        assert_eq!(
+            sizes(&sg.components(NetworkType::Drive, Connectivity::Strong)),
+            vec![2, 1, 1]
+        );
+        assert_eq!(
+            sizes(&sg.components(NetworkType::Walk, Connectivity::Strong)),
+            vec![4]
+        );
+        let kept = sg
+            .clone()
+            .retain_largest_component(NetworkType::Drive, Connectivity::Strong);
+        assert_eq!(kept.graph.node_count(), 2);
+        let far_end = sg.node_index_for_osm_id(4).unwrap();
+        assert_eq!(
+            sg.component_size(far_end, NetworkType::Drive, Connectivity::Strong),
+            1
+        );
+        assert_eq!(
+            sg.component_size(far_end, NetworkType::Walk, Connectivity::Strong),
+            4
+        );
+    }
+}
@@ -0,0 +1,690 @@
+//! Time-conditional access restrictions.
+//!
+//! Tags such as `access:conditional=no @ (Mo-Fr 07:00-09:00)` or
+//! `oneway:conditional=yes @ (Sa 06:00-14:00)` are parsed into weekly
+//! [`TimeWindow`]s at graph construction and stored on each directed edge as
+//! [`XmlWay::closed_during`]. Queries given a [`DepartureTime`] treat an edge
+//! as closed while the time at which the search enters it falls inside one of
+//! those windows; queries without a departure time ignore them.
+//!
+//! Conditional tags can also open an edge its base tags close:
+//! `access=no` or `motor_vehicle=no` with a matching `:conditional=yes @ (...)`,
+//! or a one-way street with `oneway:conditional=no @ (...)`. The road filters
+//! keep such ways rather than dropping them for their plain tag (see
+//! [`grants_access`]). Such an edge is marked
+//! [`XmlWay::closed_by_default`], which keeps it closed to queries without a
+//! departure time, and is closed outside the opening windows otherwise.
+//!
+//! Only the time-of-week subset of the opening-hours syntax is understood:
+//! weekday lists and ranges (`Mo,We`, `Mo-Fr`) and clock ranges (`07:00-09:00`,
+//! overnight `22:00-06:00`). Conditions using anything else (public holidays,
+//! months, vehicle properties, `AND`) are skipped rather than guessed at.
+
+use std::str::FromStr;
+
+use crate::error::OsmGraphError;
+use crate::graph::{XmlTag, XmlWay};
+use crate::overpass::NetworkType;
+
+const SECONDS_PER_DAY: f64 = 86_400.0;
+const SECONDS_PER_WEEK: f64 = 7.0 * SECONDS_PER_DAY;
+const MINUTES_PER_DAY: u16 = 24 * 60;
+const DAY_NAMES: [&str; 7] = ["mo", "tu", "we", "th", "fr", "sa", "su"];
+pub(crate) const RESTRICTIVE_VALUES: &[&str] =
+    &["no", "private", "agricultural", "forestry", "delivery"];
+const PERMISSIVE_VALUES: &[&str] = &["yes", "permissive", "designated", "destination"];
+
+/// A point in the week, used as the departure time of a query.
+///
+/// Stored as seconds since Monday 00:00 local time; adding travel time wraps
+/// around the end of the week.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DepartureTime {
+    seconds_since_monday: f64,
+}
+
+impl DepartureTime {
+    /// `weekday` counts from Monday = 0 to Sunday = 6. Returns `None` for out
+    /// of range values.
+    pub fn new(weekday: u8, hour: u8, minute: u8) -> Option<Self> {
+        if weekday > 6 || hour > 23 || minute > 59 {
+            return None;
+        }
+        Some(Self::from_week_seconds(
+            weekday as f64 * SECONDS_PER_DAY + hour as f64 * 3600.0 + minute as f64 * 60.0,
+        ))
+    }
+
+    pub fn from_week_seconds(seconds: f64) -> Self {
+        Self {
+            seconds_since_monday: seconds.rem_euclid(SECONDS_PER_WEEK),
+        }
+    }
+
+    pub fn week_seconds(&self) -> f64 {
+        self.seconds_since_monday
+    }
+
+    /// The time `seconds` after this one.
+    pub fn after(self, seconds: f64) -> Self {
+        Self::from_week_seconds(self.seconds_since_monday + seconds)
+    }
+
+    /// Monday = 0 to Sunday = 6.
+    pub fn weekday(&self) -> u8 {
+        (self.seconds_since_monday / SECONDS_PER_DAY) as u8
+    }
+
+    pub fn minute_of_day(&self) -> u16 {
+        ((self.seconds_since_monday % SECONDS_PER_DAY) / 60.0) as u16
+    }
+}
+
+impl FromStr for DepartureTime {
+    type Err = OsmGraphError;
+
+    /// Parse `"<day> HH:MM"` with an opening-hours weekday, e.g. `"Tu 07:30"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            OsmGraphError::InvalidInput(format!(
+                "departure time must look like \"Mo 08:00\", got {s:?}"
+            ))
+        };
+        let (day, time) = s
+            .trim()
+            .split_once(char::is_whitespace)
+            .ok_or_else(invalid)?;
+        let weekday = parse_day(day).ok_or_else(invalid)?;
+        let minute = parse_clock(time.trim()).ok_or_else(invalid)?;
+        if minute >= MINUTES_PER_DAY {
+            return Err(invalid());
+        }
+        DepartureTime::new(weekday, (minute / 60) as u8, (minute % 60) as u8).ok_or_else(invalid)
+    }
+}
+
+/// A weekly recurring interval: the given weekdays from `start_minute` to
+/// `end_minute`. Windows whose end is not after their start run overnight into
+/// the following day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimeWindow {
+    /// Bit `d` set means the window starts on weekday `d` (Monday = 0).
+    days: u8,
+    start_minute: u16,
+    end_minute: u16,
+}
+
+impl TimeWindow {
+    /// `(days, start_minute, end_minute)`, for serialization.
+    pub(crate) fn to_parts(self) -> (u8, u16, u16) {
+        (self.days, self.start_minute, self.end_minute)
+    }
+
+    /// Inverse of [`TimeWindow::to_parts`]; `None` for out of range values.
+    pub(crate) fn from_parts(days: u8, start_minute: u16, end_minute: u16) -> Option<Self> {
+        (days < 1 << 7 && start_minute < MINUTES_PER_DAY && end_minute <= MINUTES_PER_DAY)
+            .then_some(Self {
+                days,
+                start_minute,
+                end_minute,
+            })
+    }
+
+    pub fn contains(&self, time: DepartureTime) -> bool {
+        let day = time.weekday();
+        let minute = time.minute_of_day();
+        let starts_on = |d: u8| self.days & (1 << d) != 0;
+        if self.start_minute < self.end_minute {
+            starts_on(day) && (self.start_minute..self.end_minute).contains(&minute)
+        } else {
+            (starts_on(day) && minute >= self.start_minute)
+                || (starts_on((day + 6) % 7) && minute < self.end_minute)
+        }
+    }
+}
+
+/// Parse a `*:conditional` tag value into `(value, windows)` pairs, one per
+/// `;`-separated condition whose time condition is understood.
+pub fn parse_conditional(value: &str) -> Vec<(String, Vec<TimeWindow>)> {
+    split_outside_parens(value)
+        .into_iter()
+        .filter_map(|part| {
+            let (restriction, condition) = part.split_once('@')?;
+            let condition = condition.trim();
+            let condition = condition
+                .strip_prefix('(')
+                .and_then(|c| c.strip_suffix(')'))
+                .unwrap_or(condition);
+            let windows = parse_opening_hours(condition)?;
+            Some((restriction.trim().to_ascii_lowercase(), windows))
+        })
+        .collect()
+}
+
+/// Whether a `*:conditional` tag value grants access under some condition,
+/// e.g. `yes @ (Mo-Fr 07:00-19:00)`, understood or not. Road filters keep a
+/// way their plain access tags exclude when this holds, leaving it to
+/// [`way_closures`] to close it outside those times.
+pub(crate) fn grants_access(value: &str) -> bool {
+    split_outside_parens(value).into_iter().any(|part| {
+        part.split_once('@').is_some_and(|(restriction, _)| {
+            PERMISSIVE_VALUES.contains(&restriction.trim().to_ascii_lowercase().as_str())
+        })
+    })
+}
+
+/// Parse the weekday/clock subset of the opening-hours syntax, e.g.
+/// `Mo-Fr 07:00-09:00,16:00-18:00; Sa 08:00-12:00`. Returns `None` for any
+/// rule outside that subset.
+fn parse_opening_hours(rules: &str) -> Option<Vec<TimeWindow>> {
+    let mut windows = Vec::new();
+    for rule in rules.split(';').map(str::trim).filter(|r| !r.is_empty()) {
+        if rule == "24/7" {
+            windows.push(TimeWindow {
+                days: 0x7f,
+                start_minute: 0,
+                end_minute: MINUTES_PER_DAY,
+            });
+            continue;
+        }
+        let split = rule
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rule.len());
+        let (day_part, time_part) = rule.split_at(split);
+        let days = parse_day_selector(day_part)?;
+
+        let time_part: String = time_part.chars().filter(|c| !c.is_whitespace()).collect();
+        if time_part.is_empty() {
+            windows.push(TimeWindow {
+                days,
+                start_minute: 0,
+                end_minute: MINUTES_PER_DAY,
+            });
+            continue;
+        }
+        for range in time_part.split(',') {
+            let (start, end) = range.split_once('-')?;
+            let start_minute = parse_clock(start)?;
+            let end_minute = parse_clock(end)?;
+            if start_minute >= MINUTES_PER_DAY || end_minute == start_minute {
+                return None;
+            }
+            windows.push(TimeWindow {
+                days,
+                start_minute,
+                end_minute,
+            });
+        }
+    }
+    (!windows.is_empty()).then_some(windows)
+}
+
+pub(crate) fn parse_day_selector(selector: &str) -> Option<u8> {
+    let selector: String = selector.chars().filter(|c| !c.is_whitespace()).collect();
+    if selector.is_empty() {
+        return Some(0x7f);
+    }
+    let mut days = 0u8;
+    for item in selector.split(',') {
+        match item.split_once('-') {
+            Some((from, to)) => {
+                let (from, to) = (parse_day(from)?, parse_day(to)?);
+                let mut day = from;
+                loop {
+                    days |= 1 << day;
+                    if day == to {
+                        break;
+                    }
+                    day = (day + 1) % 7;
+                }
+            }
+            None => days |= 1 << parse_day(item)?,
+        }
+    }
+    Some(days)
+}
+
+fn parse_day(day: &str) -> Option<u8> {
+    let day = day.to_ascii_lowercase();
+    DAY_NAMES.iter().position(|&d| d == day).map(|d| d as u8)
+}
+
+/// Minutes since midnight for `HH:MM`; `24:00` is accepted as end of day.
+fn parse_clock(clock: &str) -> Option<u16> {
+    let (hours, minutes) = clock.split_once(':')?;
+    let hours: u16 = hours.parse().ok()?;
+    let minutes: u16 = minutes.parse().ok()?;
+    let total = hours * 60 + minutes;
+    (minutes < 60 && total <= MINUTES_PER_DAY).then_some(total)
+}
+
+fn split_outside_parens(value: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut start = 0;
+    for (i, c) in value.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ';' if depth == 0 => {
+                parts.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+/// Access keys consulted for `network_type`, most general first.
+pub(crate) fn access_keys(network_type: NetworkType) -> &'static [&'static str] {
+    match network_type {
+        NetworkType::Walk => &["access", "foot"],
+        NetworkType::Bike => &["access", "vehicle", "bicycle"],
+        NetworkType::Drive
+        | NetworkType::DriveService
+        | NetworkType::All
+        | NetworkType::AllPrivate => &["access", "vehicle", "motor_vehicle", "motorcar"],
+    }
+}
+
+/// Conditional closures of one way, from [`way_closures`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct WayClosures {
+    /// Windows during which travel along the way's node order is closed.
+    pub forward: Vec<TimeWindow>,
+    /// Windows during which travel against the way's node order is closed.
+    pub backward: Vec<TimeWindow>,
+    /// The way's base access tag closes it and a conditional tag only opens
+    /// it at times, outside of which the windows above close it.
+    pub closed_by_default: bool,
+}
+
+/// Windows during which travel along (`forward`) and against the way's node
+/// order is closed.
+///
+/// Among `access_keys`, the most specific one carrying a `:conditional` tag
+/// decides; its restrictive values (`no`, `private`, ...) close both
+/// directions. Its permissive values (`yes`, `destination`, ...) open the
+/// way when the most specific plain access tag closes it, so the way is
+/// closed by default and at every time outside their windows, or at all
+/// times when none of their conditions is understood. When
+/// `oneway_applies`, `oneway:conditional=yes` closes the backward direction
+/// and `-1` the forward direction.
+pub(crate) fn way_closures(
+    tags: &[XmlTag],
+    access_keys: &[&str],
+    oneway_applies: bool,
+) -> WayClosures {
+    let tag = |key: &str| {
+        tags.iter()
+            .find(|tag| tag.key == key)
+            .map(|tag| tag.value.as_str())
+    };
+    let mut closures = WayClosures::default();
+
+    let access = access_keys
+        .iter()
+        .rev()
+        .find_map(|key| tag(&format!("{key}:conditional")));
+    let opens = access.is_some_and(grants_access)
+        && access_keys
+            .iter()
+            .rev()
+            .find_map(|key| tag(key))
+            .is_some_and(|value| RESTRICTIVE_VALUES.contains(&value));
+    let mut open_during = Vec::new();
+    for (value, windows) in access.map(parse_conditional).unwrap_or_default() {
+        if RESTRICTIVE_VALUES.contains(&value.as_str()) {
+            closures.forward.extend(&windows);
+            closures.backward.extend(&windows);
+        } else if opens && PERMISSIVE_VALUES.contains(&value.as_str()) {
+            open_during.extend(windows);
+        }
+    }
+    if opens {
+        let closed = closed_outside(&open_during);
+        closures.forward.extend(&closed);
+        closures.backward.extend(&closed);
+        closures.closed_by_default = true;
+    }
+
+    if oneway_applies {
+        for (value, windows) in tag("oneway:conditional")
+            .map(parse_conditional)
+            .unwrap_or_default()
+        {
+            match value.as_str() {
+                "yes" | "true" | "1" => closures.backward.extend(&windows),
+                "-1" | "reverse" => closures.forward.extend(&windows),
+                _ => {}
+            }
+        }
+    }
+
+    closures
+}
+
+/// Closures for travel against a one-way street that `oneway:conditional=no`
+/// opens at times: every time outside its windows. `None` when the tag never
+/// lifts the one-way restriction.
+pub(crate) fn contraflow_closures(tags: &[XmlTag]) -> Option<Vec<TimeWindow>> {
+    let value = tags
+        .iter()
+        .find(|tag| tag.key == "oneway:conditional")?
+        .value
+        .as_str();
+    let open_during: Vec<TimeWindow> = parse_conditional(value)
+        .into_iter()
+        .filter(|(value, _)| matches!(value.as_str(), "no" | "false" | "0"))
+        .flat_map(|(_, windows)| windows)
+        .collect();
+    (!open_during.is_empty()).then(|| closed_outside(&open_during))
+}
+
+/// Windows covering every minute of the week that none of `windows` covers.
+fn closed_outside(windows: &[TimeWindow]) -> Vec<TimeWindow> {
+    let mut gaps: Vec<TimeWindow> = Vec::new();
+    let mut add_gap = |day: u8, start_minute: u16, end_minute: u16| match gaps
+        .iter_mut()
+        .find(|gap| (gap.start_minute, gap.end_minute) == (start_minute, end_minute))
+    {
+        Some(gap) => gap.days |= 1 << day,
+        None => gaps.push(TimeWindow {
+            days: 1 << day,
+            start_minute,
+            end_minute,
+        }),
+    };
+    for day in 0..7u8 {
+        let mut gap_start = None;
+        for minute in 0..=MINUTES_PER_DAY {
+            let covered = minute == MINUTES_PER_DAY || {
+                let time = DepartureTime::from_week_seconds(
+                    day as f64 * SECONDS_PER_DAY + minute as f64 * 60.0,
+                );
+                windows.iter().any(|window| window.contains(time))
+            };
+            if !covered {
+                gap_start.get_or_insert(minute);
+            } else if let Some(start_minute) = gap_start.take() {
+                add_gap(day, start_minute, minute);
+            }
+        }
+    }
+    gaps
+}
+
+/// Travel time over `way` for a search entering it `elapsed_s` after
+/// `departure`: infinite while one of its closures applies, and at the
+/// hourly speed for that time on drive networks.
+pub(crate) fn timed_travel_time(
+    way: &XmlWay,
+    network_type: NetworkType,
+    departure: Option<DepartureTime>,
+    elapsed_s: f64,
+) -> f64 {
+    let Some(departure) = departure else {
+        return way.travel_time(network_type);
+    };
+    let time = departure.after(elapsed_s);
+    if way.is_closed_at(time) {
+        return f64::INFINITY;
+    }
+    match network_type {
+        NetworkType::Walk | NetworkType::Bike => way.open_travel_time(network_type),
+        _ => way.drive_travel_time_at(time),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::SpatialGraph;
+
+    fn at(s: &str) -> DepartureTime {
+        s.parse().unwrap()
+    }
+
+    fn tags(pairs: &[(&str, &str)]) -> Vec<XmlTag> {
+        pairs
+            .iter()
+            .map(|(k, v)| XmlTag {
+                key: (*k).into(),
+                value: (*v).into(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn departure_time_parses_and_wraps() {
+        let time = at("Tu 07:30");
+        assert_eq!(time.weekday(), 1);
+        assert_eq!(time.minute_of_day(), 7 * 60 + 30);
+        assert_eq!(at("Su 23:30").after(3600.0), at("Mo 00:30"));
+        assert!("Tuesday".parse::<DepartureTime>().is_err());
+        assert!("Xx 07:00".parse::<DepartureTime>().is_err());
+        assert!("Mo 24:00".parse::<DepartureTime>().is_err());
+    }
+
+    #[test]
+    fn parses_day_and_clock_windows() {
+        let parsed = parse_conditional("no @ (Mo-Fr 07:00-09:00, 16:00-18:00); yes @ Sa");
+        assert_eq!(parsed.len(), 2);
+        let (value, windows) = &parsed[0];
+        assert_eq!(value, "no");
+        assert!(windows.iter().any(|w| w.contains(at("We 08:15"))));
+        assert!(windows.iter().any(|w| w.contains(at("Fr 17:00"))));
+        assert!(!windows.iter().any(|w| w.contains(at("We 09:00"))));
+        assert!(!windows.iter().any(|w| w.contains(at("Sa 08:15"))));
+        assert!(parsed[1].1.iter().any(|w| w.contains(at("Sa 23:59"))));
+    }
+
+    #[test]
+    fn overnight_and_wrapping_day_ranges() {
+        let (_, windows) = &parse_conditional("no @ (Fr-Mo 22:00-06:00)")[0];
+        assert!(windows[0].contains(at("Fr 23:00")));
+        assert!(windows[0].contains(at("Sa 05:00")));
+        assert!(windows[0].contains(at("Tu 05:00")));
+        assert!(!windows[0].contains(at("Tu 23:00")));
+        assert!(!windows[0].contains(at("Fr 05:00")));
+    }
+
+    #[test]
+    fn unsupported_conditions_are_skipped() {
+        assert!(parse_conditional("no @ (weight>7.5)").is_empty());
+        assert!(parse_conditional("no @ (PH 08:00-12:00)").is_empty());
+        assert!(parse_conditional("no @ (Mo-Fr 07:00-09:00 AND wet)").is_empty());
+        assert_eq!(
+            parse_conditional("no @ (wet); no @ (Su 10:00-12:00)").len(),
+            1
+        );
+    }
+
+    #[test]
+    fn way_closures_pick_most_specific_mode_and_oneway_direction() {
+        let school_street = tags(&[
+            ("access:conditional", "no @ (Mo-Fr 07:30-08:30)"),
+            ("foot:conditional", "yes @ (Mo-Fr 07:30-08:30)"),
+        ]);
+        let closures = way_closures(&school_street, &["access", "foot"], false);
+        assert!(closures.forward.is_empty() && closures.backward.is_empty());
+        let drive = access_keys(NetworkType::Drive);
+        let closures = way_closures(&school_street, drive, true);
+        assert_eq!(closures.forward.len(), 1);
+        assert_eq!(closures.backward.len(), 1);
+        assert!(!closures.closed_by_default);
+
+        let tidal = tags(&[("oneway:conditional", "yes @ (Mo-Fr 06:00-10:00)")]);
+        let closures = way_closures(&tidal, drive, true);
+        assert!(closures.forward.is_empty());
+        assert!(closures.backward[0].contains(at("Mo 07:00")));
+        let closures = way_closures(&tidal, drive, false);
+        assert!(closures.forward.is_empty() && closures.backward.is_empty());
+    }
+
+    #[test]
+    fn permissive_conditionals_open_closed_ways() {
+        let closed_at = |windows: &[TimeWindow], time: &str| {
+            windows.iter().any(|window| window.contains(at(time)))
+        };
+        let drive = access_keys(NetworkType::Drive);
+
+        let market_lane = tags(&[
+            ("access", "no"),
+            ("access:conditional", "yes @ (Sa 22:00-06:00)"),
+        ]);
+        let closures = way_closures(&market_lane, drive, true);
+        assert!(closures.closed_by_default);
+        for windows in [&closures.forward, &closures.backward] {
+            assert!(!closed_at(windows, "Sa 23:00"));
+            assert!(!closed_at(windows, "Su 05:59"));
+            assert!(closed_at(windows, "Su 06:00"));
+            assert!(closed_at(windows, "Sa 21:59"));
+            assert!(closed_at(windows, "We 23:00"));
+        }
+        // A more specific plain tag already lets cars in.
+        let mut open_to_cars = market_lane.clone();
+        open_to_cars.extend(tags(&[("motor_vehicle", "yes")]));
+        let closures = way_closures(&open_to_cars, drive, true);
+        assert!(!closures.closed_by_default && closures.forward.is_empty());
+
+        let tidal = tags(&[
+            ("oneway", "yes"),
+            ("oneway:conditional", "no @ (Mo-Fr 16:00-19:00)"),
+        ]);
+        let closed = contraflow_closures(&tidal).unwrap();
+        assert!(!closed_at(&closed, "Tu 17:00"));
+        assert!(closed_at(&closed, "Tu 12:00"));
+        assert!(closed_at(&closed, "Sa 17:00"));
+        assert!(contraflow_closures(&tags(&[("oneway", "yes")])).is_none());
+        assert!(
+            contraflow_closures(&tags(&[("oneway:conditional", "yes @ (Mo 06:00-10:00)")]))
+                .is_none()
+        );
+    }
+
+    // A(0) is a ~11 km lead-in south of W. From W, way 20 runs straight east
+    // to E; way 21 detours north through N.
+    const A: (f64, f64) = (47.9, 11.0);
+    const E: (f64, f64) = (48.0, 11.002);
+    const N_ID: i64 = 4;
+
+    fn school_street_graph(conditional_tags: &str) -> SpatialGraph {
+        let xml = format!(
+            r#"<osm>
+              <node id="0" lat="47.900" lon="11.000" />
+              <node id="1" lat="48.000" lon="11.000" />
+              <node id="2" lat="48.000" lon="11.001" />
+              <node id="3" lat="48.000" lon="11.002" />
+              <node id="4" lat="48.001" lon="11.001" />
+              <way id="19"><nd ref="0" /><nd ref="1" /><tag k="highway" v="residential" /></way>
+              <way id="20"><nd ref="1" /><nd ref="2" /><nd ref="3" />
+                <tag k="highway" v="residential" />{conditional_tags}</way>
+              <way id="21"><nd ref="1" /><nd ref="4" /><nd ref="3" /><tag k="highway" v="residential" /></way>
+            </osm>"#
+        );
+        SpatialGraph::from_osm(&xml, NetworkType::Drive, Some(true), None).unwrap()
+    }
+
+    fn passes_north(graph: &SpatialGraph, route: &crate::routing::Route) -> bool {
+        let north = graph
+            .graph
+            .node_indices()
+            .find(|&i| graph.graph[i].id == N_ID)
+            .unwrap();
+        let north = (graph.graph[north].lat, graph.graph[north].lon);
+        route.coordinates.contains(&north)
+    }
+
+    #[test]
+    fn route_at_avoids_edges_closed_when_reached() {
+        let graph = school_street_graph(
+            r#"<tag k="motor_vehicle:conditional" v="no @ (Mo-Fr 07:00-09:00)" />"#,
+        );
+        let route = |departure: Option<&str>| match departure {
+            Some(departure) => graph
+                .route_at(A.0, A.1, E.0, E.1, NetworkType::Drive, None, at(departure))
+                .unwrap(),
+            None => graph
+                .route(A.0, A.1, E.0, E.1, NetworkType::Drive, None)
+                .unwrap(),
+        };
+
+        assert!(!passes_north(&graph, &route(None)));
+        assert!(!passes_north(&graph, &route(Some("Mo 06:30"))));
+        assert!(!passes_north(&graph, &route(Some("Sa 08:00"))));
+        // Leaving before the window opens but reaching the school street inside it.
+        assert!(passes_north(&graph, &route(Some("Mo 06:50"))));
+    }
+
+    #[test]
+    fn oneway_conditional_closes_reverse_direction() {
+        let graph =
+            school_street_graph(r#"<tag k="oneway:conditional" v="-1 @ (Mo-Su 00:00-24:00)" />"#);
+        let route = graph
+            .route_at(A.0, A.1, E.0, E.1, NetworkType::Drive, None, at("We 12:00"))
+            .unwrap();
+        assert!(passes_north(&graph, &route));
+    }
+
+    #[test]
+    fn conditional_openings_close_edges_outside_their_windows() {
+        // A route from E back to A takes the school street against its
+        // node order, which only opens at weekends.
+        let route_back = |graph: &SpatialGraph, departure: Option<&str>| match departure {
+            Some(departure) => graph
+                .route_at(E.0, E.1, A.0, A.1, NetworkType::Drive, None, at(departure))
+                .unwrap(),
+            None => graph
+                .route(E.0, E.1, A.0, A.1, NetworkType::Drive, None)
+                .unwrap(),
+        };
+
+        let graph = school_street_graph(
+            r#"<tag k="oneway" v="yes" /><tag k="oneway:conditional" v="no @ (Sa,Su)" />"#,
+        );
+        assert!(passes_north(&graph, &route_back(&graph, None)));
+        assert!(passes_north(&graph, &route_back(&graph, Some("We 12:00"))));
+        assert!(!passes_north(&graph, &route_back(&graph, Some("Sa 12:00"))));
+
+        let graph = school_street_graph(
+            r#"<tag k="access" v="no" /><tag k="access:conditional" v="yes @ (Sa,Su)" />"#,
+        );
+        assert!(passes_north(&graph, &route_back(&graph, None)));
+        assert!(passes_north(&graph, &route_back(&graph, Some("We 12:00"))));
+        assert!(!passes_north(&graph, &route_back(&graph, Some("Sa 12:00"))));
+    }
+
+    #[test]
+    fn reachability_and_isochrones_honour_departure_time() {
+        let graph =
+            school_street_graph(r#"<tag k="access:conditional" v="no @ (Mo-Fr 07:00-09:00)" />"#);
+        let middle = graph
+            .graph
+            .node_indices()
+            .find(|&i| graph.graph[i].id == 2)
+            .unwrap();
+        let open = graph
+            .reachability_at(A.0, A.1, 3600.0, NetworkType::Drive, None, at("Mo 12:00"))
+            .unwrap();
+        let closed = graph
+            .reachability_at(A.0, A.1, 3600.0, NetworkType::Drive, None, at("Mo 07:00"))
+            .unwrap();
+        assert!(open.distances.contains_key(&middle));
+        assert!(!closed.distances.contains_key(&middle));
+
+        let isochrones = graph
+            .isochrones_at(
+                A.0,
+                A.1,
+                vec![3600.0],
+                NetworkType::Drive,
+                None,
+                at("Mo 07:00"),
+            )
+            .unwrap();
+        assert_eq!(isochrones.len(), 1);
+    }
+}
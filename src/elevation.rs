@@ -0,0 +1,696 @@
+//! Ground elevation from local DEM tiles and grade-aware travel times.
+//!
+//! A [`Dem`] is a set of elevation grids loaded from SRTM `.hgt` files or
+//! single-band GeoTIFFs in geographic (WGS84 lat/lon) coordinates.
+//! [`add_elevation`] samples it at every node and edge shape point, then
+//! rescales walk and bike travel times by the grade of each shape segment:
+//! walking follows Tobler's hiking function, cycling a climb penalty and a
+//! capped descent bonus. Drive times are left unchanged.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use petgraph::graph::DiGraph;
+
+use crate::error::OsmGraphError;
+use crate::graph::{directed_edge_geometry, XmlNode, XmlWay};
+use crate::storage::pack_edge_geometry;
+use crate::utils::calculate_distance;
+
+/// Bike speed drops as `1 / (1 + BIKE_CLIMB_PENALTY * grade)` uphill.
+const BIKE_CLIMB_PENALTY: f64 = 15.0;
+/// Bike speed rises as `1 + BIKE_DESCENT_BONUS * |grade|` downhill...
+const BIKE_DESCENT_BONUS: f64 = 5.0;
+/// ...up to this multiple of the flat speed.
+const BIKE_MAX_DESCENT_FACTOR: f64 = 1.5;
+
+// GeoTIFF keys, see the OGC GeoTIFF standard.
+const GT_MODEL_TYPE_GEO_KEY: u16 = 1024;
+const GT_RASTER_TYPE_GEO_KEY: u16 = 1025;
+const MODEL_TYPE_GEOGRAPHIC: u16 = 2;
+const RASTER_PIXEL_IS_POINT: u16 = 2;
+
+/// A regular grid of elevation samples.
+///
+/// Row 0 is the northernmost row; samples are spaced `lat_step` degrees
+/// southwards and `lon_step` degrees eastwards from `(north, west)`, which is
+/// the position of the first sample. Voids are stored as NaN.
+#[derive(Debug, Clone)]
+pub struct DemTile {
+    north: f64,
+    west: f64,
+    lat_step: f64,
+    lon_step: f64,
+    rows: usize,
+    cols: usize,
+    heights: Vec<f32>,
+}
+
+impl DemTile {
+    /// Build a tile from row-major `heights` (north to south, west to east).
+    pub fn new(
+        north: f64,
+        west: f64,
+        lat_step: f64,
+        lon_step: f64,
+        cols: usize,
+        heights: Vec<f32>,
+    ) -> Result<Self, OsmGraphError> {
+        if !(lat_step > 0.0 && lon_step > 0.0) {
+            return Err(OsmGraphError::DemError(
+                "sample spacing must be positive".into(),
+            ));
+        }
+        if cols < 2 || !heights.len().is_multiple_of(cols) || heights.len() / cols < 2 {
+            return Err(OsmGraphError::DemError(format!(
+                "{} samples do not form a grid at least 2x2 with {} columns",
+                heights.len(),
+                cols
+            )));
+        }
+        Ok(Self {
+            north,
+            west,
+            lat_step,
+            lon_step,
+            rows: heights.len() / cols,
+            cols,
+            heights,
+        })
+    }
+
+    /// Read an SRTM `.hgt` tile. The south-west corner comes from the file
+    /// name (e.g. `N37W122.hgt`) and the resolution from the file size, so
+    /// both 3 (1201x1201) and 1 arc-second (3601x3601) tiles work.
+    pub fn from_hgt(path: impl AsRef<Path>) -> Result<Self, OsmGraphError> {
+        let path = path.as_ref();
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+        let (south, west) = parse_hgt_name(name).ok_or_else(|| {
+            OsmGraphError::DemError(format!(
+                "cannot read tile position from file name '{}'",
+                name
+            ))
+        })?;
+
+        let bytes = std::fs::read(path)?;
+        let size = ((bytes.len() / 2) as f64).sqrt() as usize;
+        if size < 2 || size * size * 2 != bytes.len() {
+            return Err(OsmGraphError::DemError(format!(
+                "'{}' is not a square grid of 16-bit samples",
+                name
+            )));
+        }
+        let heights = bytes
+            .chunks_exact(2)
+            .map(|pair| match i16::from_be_bytes([pair[0], pair[1]]) {
+                i16::MIN => f32::NAN,
+                height => height as f32,
+            })
+            .collect();
+        let step = 1.0 / (size - 1) as f64;
+        Self::new(south + 1.0, west, step, step, size, heights)
+    }
+
+    /// Read a single-band GeoTIFF in geographic coordinates. The grid
+    /// position comes from the model tie point and pixel scale tags, and
+    /// `GDAL_NODATA` values become voids. Projected rasters are rejected;
+    /// reproject them to EPSG:4326 first.
+    pub fn from_geotiff(path: impl AsRef<Path>) -> Result<Self, OsmGraphError> {
+        use tiff::decoder::{Decoder, DecodingResult};
+        use tiff::tags::Tag;
+
+        let tiff_error = |e: tiff::TiffError| OsmGraphError::DemError(e.to_string());
+        let file = BufReader::new(File::open(path.as_ref())?);
+        let mut decoder = Decoder::new(file).map_err(tiff_error)?;
+        let (cols, rows) = decoder.dimensions().map_err(tiff_error)?;
+        let bands = decoder
+            .find_tag_unsigned::<u16>(Tag::SamplesPerPixel)
+            .map_err(tiff_error)?
+            .unwrap_or(1);
+        if bands != 1 {
+            return Err(OsmGraphError::DemError(format!(
+                "only single-band GeoTIFFs are supported, got {bands} bands"
+            )));
+        }
+        let scale = decoder
+            .get_tag_f64_vec(Tag::ModelPixelScaleTag)
+            .map_err(tiff_error)?;
+        let tie_point = decoder
+            .get_tag_f64_vec(Tag::ModelTiepointTag)
+            .map_err(tiff_error)?;
+        if scale.len() < 2 || tie_point.len() < 6 {
+            return Err(OsmGraphError::DemError(
+                "GeoTIFF lacks a pixel scale or tie point".into(),
+            ));
+        }
+
+        let geo_keys = decoder
+            .find_tag_unsigned_vec::<u16>(Tag::GeoKeyDirectoryTag)
+            .map_err(tiff_error)?
+            .unwrap_or_default();
+        let geo_key = |id: u16| {
+            geo_keys
+                .get(4..)
+                .unwrap_or_default()
+                .chunks_exact(4)
+                .find(|entry| entry[0] == id && entry[1] == 0)
+                .map(|entry| entry[3])
+        };
+        if geo_key(GT_MODEL_TYPE_GEO_KEY).is_some_and(|model| model != MODEL_TYPE_GEOGRAPHIC) {
+            return Err(OsmGraphError::DemError(
+                "only GeoTIFFs in geographic lat/lon coordinates are supported".into(),
+            ));
+        }
+        // Pixel-is-area rasters locate the tie point at a pixel corner;
+        // samples represent the pixel centres.
+        let centre_offset = if geo_key(GT_RASTER_TYPE_GEO_KEY) == Some(RASTER_PIXEL_IS_POINT) {
+            0.0
+        } else {
+            0.5
+        };
+
+        let nodata = decoder
+            .find_tag(Tag::GdalNodata)
+            .map_err(tiff_error)?
+            .and_then(|value| value.into_string().ok())
+            .and_then(|value| value.trim_end_matches('\0').trim().parse::<f64>().ok());
+        let heights: Vec<f64> = match decoder.read_image().map_err(tiff_error)? {
+            DecodingResult::U8(v) => v.into_iter().map(f64::from).collect(),
+            DecodingResult::U16(v) => v.into_iter().map(f64::from).collect(),
+            DecodingResult::U32(v) => v.into_iter().map(f64::from).collect(),
+            DecodingResult::U64(v) => v.into_iter().map(|h| h as f64).collect(),
+            DecodingResult::I8(v) => v.into_iter().map(f64::from).collect(),
+            DecodingResult::I16(v) => v.into_iter().map(f64::from).collect(),
+            DecodingResult::I32(v) => v.into_iter().map(f64::from).collect(),
+            DecodingResult::I64(v) => v.into_iter().map(|h| h as f64).collect(),
+            DecodingResult::F32(v) => v.into_iter().map(f64::from).collect(),
+            DecodingResult::F64(v) => v,
+        };
+        if heights.len() != cols as usize * rows as usize {
+            return Err(OsmGraphError::DemError(format!(
+                "GeoTIFF holds {} samples for a {cols}x{rows} grid",
+                heights.len()
+            )));
+        }
+        let heights = heights
+            .into_iter()
+            .map(|height| match nodata {
+                Some(nodata) if height == nodata => f32::NAN,
+                _ => height as f32,
+            })
+            .collect();
+
+        let (pixel_col, pixel_row, x, y) = (tie_point[0], tie_point[1], tie_point[3], tie_point[4]);
+        let west = x + (centre_offset - pixel_col) * scale[0];
+        let north = y - (centre_offset - pixel_row) * scale[1];
+        Self::new(north, west, scale[1], scale[0], cols as usize, heights)
+    }
+
+    /// Bilinearly interpolated elevation in metres, or `None` outside the
+    /// tile or next to a void.
+    pub fn elevation_at(&self, lat: f64, lon: f64) -> Option<f64> {
+        let row = (self.north - lat) / self.lat_step;
+        let col = (lon - self.west) / self.lon_step;
+        let (max_row, max_col) = ((self.rows - 1) as f64, (self.cols - 1) as f64);
+        if !(0.0..=max_row).contains(&row) || !(0.0..=max_col).contains(&col) {
+            return None;
+        }
+
+        let r0 = (row.floor() as usize).min(self.rows - 2);
+        let c0 = (col.floor() as usize).min(self.cols - 2);
+        let (dr, dc) = (row - r0 as f64, col - c0 as f64);
+        // Samples with zero weight are skipped so that points exactly on a
+        // sample next to a void still resolve.
+        let corners = [
+            (r0, c0, (1.0 - dr) * (1.0 - dc)),
+            (r0, c0 + 1, (1.0 - dr) * dc),
+            (r0 + 1, c0, dr * (1.0 - dc)),
+            (r0 + 1, c0 + 1, dr * dc),
+        ];
+        let height: f64 = corners
+            .into_iter()
+            .filter(|&(_, _, weight)| weight > 0.0)
+            .map(|(r, c, weight)| self.heights[r * self.cols + c] as f64 * weight)
+            .sum();
+        height.is_finite().then_some(height)
+    }
+}
+
+/// Elevation model made of one or more [`DemTile`]s.
+#[derive(Debug, Clone, Default)]
+pub struct Dem {
+    tiles: Vec<DemTile>,
+}
+
+impl Dem {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load tiles from `.hgt`, `.tif` or `.tiff` files.
+    pub fn from_paths<P: AsRef<Path>>(paths: &[P]) -> Result<Self, OsmGraphError> {
+        let mut dem = Self::new();
+        for path in paths {
+            let path = path.as_ref();
+            let extension = path
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .map(str::to_ascii_lowercase);
+            let tile = match extension.as_deref() {
+                Some("hgt") => DemTile::from_hgt(path)?,
+                Some("tif" | "tiff") => DemTile::from_geotiff(path)?,
+                _ => {
+                    return Err(OsmGraphError::DemError(format!(
+                        "unsupported DEM file '{}'; expected .hgt or GeoTIFF",
+                        path.display()
+                    )))
+                }
+            };
+            dem.add_tile(tile);
+        }
+        Ok(dem)
+    }
+
+    pub fn add_tile(&mut self, tile: DemTile) {
+        self.tiles.push(tile);
+    }
+
+    pub fn tiles(&self) -> &[DemTile] {
+        &self.tiles
+    }
+
+    /// Elevation in metres from the first tile that covers the point.
+    pub fn elevation_at(&self, lat: f64, lon: f64) -> Option<f64> {
+        self.tiles
+            .iter()
+            .find_map(|tile| tile.elevation_at(lat, lon))
+    }
+}
+
+/// Walking speed on `grade` (rise over run) relative to the flat, from
+/// Tobler's hiking function. Peaks at about 1.19 on a 5% descent.
+pub fn tobler_speed_factor(grade: f64) -> f64 {
+    (-3.5 * ((grade + 0.05).abs() - 0.05)).exp()
+}
+
+/// Cycling speed on `grade` (rise over run) relative to the flat.
+pub fn bike_speed_factor(grade: f64) -> f64 {
+    if grade >= 0.0 {
+        1.0 / (1.0 + BIKE_CLIMB_PENALTY * grade)
+    } else {
+        (1.0 - BIKE_DESCENT_BONUS * grade).min(BIKE_MAX_DESCENT_FACTOR)
+    }
+}
+
+/// Attach DEM elevations to `graph` and make walk and bike times grade-aware.
+///
+/// Nodes get `elevation_m`; edges get `elevations_m` for each geometry point
+/// (the geometry is filled with the endpoints when empty) and have their walk
+/// and bike travel times, less any [`XmlWay::node_delays`], rescaled segment
+/// by segment with [`tobler_speed_factor`] and [`bike_speed_factor`]. The
+/// [`XmlWay::bike_push_m`] a cyclist walks beside the bike scale with
+/// [`tobler_speed_factor`], and ferry crossings keep their times. Edges
+/// without any DEM coverage, and edges that already carry elevations, are
+/// left unchanged, so calling this again with another DEM only fills gaps.
+pub fn add_elevation(graph: &mut DiGraph<XmlNode, XmlWay>, dem: &Dem) {
+    for node in graph.node_weights_mut() {
+        if node.elevation_m.is_none() {
+            node.elevation_m = dem.elevation_at(node.lat, node.lon);
+        }
+    }
+
+    for edge in graph.edge_indices() {
+        if !graph[edge].elevations_m.is_empty() {
+            continue;
+        }
+        let points = directed_edge_geometry(graph, edge);
+        let Some(elevations) = fill_gaps(
+            points
+                .iter()
+                .map(|&(lat, lon)| dem.elevation_at(lat, lon))
+                .collect(),
+        ) else {
+            continue;
+        };
+
+        let mut length = 0.0;
+        let mut walk_length = 0.0;
+        let mut bike_length = 0.0;
+        for (pair, heights) in points.windows(2).zip(elevations.windows(2)) {
+            let segment = calculate_distance(pair[0].0, pair[0].1, pair[1].0, pair[1].1);
+            if segment <= 0.0 {
+                continue;
+            }
+            let grade = (heights[1] - heights[0]) / segment;
+            length += segment;
+            walk_length += segment / tobler_speed_factor(grade);
+            bike_length += segment / bike_speed_factor(grade);
+        }
+
+        let way = &mut graph[edge];
+        // A ferry sails as fast whatever the ground beneath the route does.
+        if length > 0.0 && !way.is_ferry() {
+            // Pushed metres go at walking pace, so they slow like walking.
+            let pushed = if way.length > 0.0 {
+                (way.bike_push_m / way.length).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let bike_length = pushed * walk_length + (1.0 - pushed) * bike_length;
+            // Waiting at the end node takes as long on a hill as on the flat.
+            let delays = way.node_delays;
+            way.walk_travel_time =
+                (way.walk_travel_time - delays.walk_s) * walk_length / length + delays.walk_s;
+            way.bike_travel_time =
+                (way.bike_travel_time - delays.bike_s) * bike_length / length + delays.bike_s;
+        }
+        way.geometry = points.into();
+        way.elevations_m = elevations;
+    }
+    pack_edge_geometry(graph);
+}
+
+/// Replace missing samples with the nearest preceding one (or the first
+/// present one for a leading gap). `None` when no sample is present.
+fn fill_gaps(samples: Vec<Option<f64>>) -> Option<Vec<f64>> {
+    let first = samples.iter().find_map(|&sample| sample)?;
+    let mut last = first;
+    Some(
+        samples
+            .into_iter()
+            .map(|sample| {
+                last = sample.unwrap_or(last);
+                last
+            })
+            .collect(),
+    )
+}
+
+/// South-west corner of an SRTM tile named like `N37W122` or `s05e120`.
+fn parse_hgt_name(name: &str) -> Option<(f64, f64)> {
+    let name = name.get(..7)?.to_ascii_uppercase();
+    let lat: f64 = name.get(1..3)?.parse().ok()?;
+    let lon: f64 = name.get(4..7)?.parse().ok()?;
+    let lat = match &name[..1] {
+        "N" => lat,
+        "S" => -lat,
+        _ => return None,
+    };
+    let lon = match &name[3..4] {
+        "E" => lon,
+        "W" => -lon,
+        _ => return None,
+    };
+    Some((lat, lon))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::SpatialGraph;
+    use crate::overpass::NetworkType;
+    use crate::speed::SpeedProfile;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "graphways_dem_{}",
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .subsec_nanos()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join(name)
+    }
+
+    /// Plane rising 1 m for every 0.0001 degrees north of 48.0.
+    fn north_slope_tile() -> DemTile {
+        let cols = 11;
+        let heights = (0..11)
+            .flat_map(|row| std::iter::repeat_n((100 - row * 10) as f32, cols))
+            .collect();
+        DemTile::new(48.01, 10.99, 0.001, 0.002, cols, heights).unwrap()
+    }
+
+    #[test]
+    fn grade_factors_penalise_climbs() {
+        assert!((tobler_speed_factor(0.0) - 1.0).abs() < 1e-12);
+        assert!(tobler_speed_factor(-0.05) > tobler_speed_factor(-0.2));
+        assert!(tobler_speed_factor(0.12) < 0.7);
+        assert!((bike_speed_factor(0.0) - 1.0).abs() < 1e-12);
+        assert!(bike_speed_factor(0.12) < 0.4);
+        assert_eq!(bike_speed_factor(-0.5), BIKE_MAX_DESCENT_FACTOR);
+    }
+
+    #[test]
+    fn tile_interpolates_between_samples() {
+        let tile = north_slope_tile();
+        assert!((tile.elevation_at(48.01, 11.0).unwrap() - 100.0).abs() < 1e-9);
+        assert!((tile.elevation_at(48.0005, 11.0).unwrap() - 5.0).abs() < 1e-6);
+        assert!(tile.elevation_at(48.02, 11.0).is_none());
+        assert!(tile.elevation_at(48.0, 11.05).is_none());
+    }
+
+    #[test]
+    fn reads_hgt_tiles() {
+        let path = temp_path("N48E011.hgt");
+        let mut bytes = Vec::new();
+        for height in [10i16, 20, 30, i16::MIN] {
+            bytes.extend_from_slice(&height.to_be_bytes());
+        }
+        std::fs::write(&path, bytes).unwrap();
+
+        let tile = DemTile::from_hgt(&path).unwrap();
+        assert_eq!(tile.elevation_at(49.0, 11.0), Some(10.0));
+        assert_eq!(tile.elevation_at(49.0, 12.0), Some(20.0));
+        assert_eq!(tile.elevation_at(48.0, 11.0), Some(30.0));
+        assert!(tile.elevation_at(48.5, 11.5).is_none());
+        assert!(DemTile::from_hgt(path.with_file_name("tile.hgt")).is_err());
+
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn reads_geotiff_tiles() {
+        use tiff::encoder::{colortype, TiffEncoder};
+        use tiff::tags::Tag;
+
+        let path = temp_path("dem.tif");
+        {
+            let file = File::create(&path).unwrap();
+            let mut encoder = TiffEncoder::new(file).unwrap();
+            let mut image = encoder.new_image::<colortype::Gray32Float>(2, 2).unwrap();
+            image
+                .encoder()
+                .write_tag(Tag::ModelPixelScaleTag, &[0.5f64, 0.5, 0.0][..])
+                .unwrap();
+            image
+                .encoder()
+                .write_tag(
+                    Tag::ModelTiepointTag,
+                    &[0.0f64, 0.0, 0.0, 11.0, 49.0, 0.0][..],
+                )
+                .unwrap();
+            image.encoder().write_tag(Tag::GdalNodata, "-9999").unwrap();
+            image.write_data(&[10.0f32, 20.0, 30.0, -9999.0]).unwrap();
+        }
+
+        // Pixel-is-area: the first sample sits at the centre of the corner pixel.
+        let tile = Dem::from_paths(&[&path]).unwrap();
+        assert_eq!(tile.elevation_at(48.75, 11.25), Some(10.0));
+        assert_eq!(tile.elevation_at(48.75, 11.75), Some(20.0));
+        assert_eq!(tile.elevation_at(48.25, 11.25), Some(30.0));
+        assert!(tile.elevation_at(48.25, 11.75).is_none());
+
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn rejects_multi_band_geotiffs() {
+        use tiff::encoder::{colortype::ColorType, TiffEncoder};
+        use tiff::tags::{PhotometricInterpretation, SampleFormat, Tag};
+
+        struct TwoBandFloat;
+        impl ColorType for TwoBandFloat {
+            type Inner = f32;
+            const TIFF_VALUE: PhotometricInterpretation = PhotometricInterpretation::BlackIsZero;
+            const BITS_PER_SAMPLE: &'static [u16] = &[32, 32];
+            const SAMPLE_FORMAT: &'static [SampleFormat] =
+                &[SampleFormat::IEEEFP, SampleFormat::IEEEFP];
+        }
+
+        let path = temp_path("two_band.tif");
+        {
+            let file = File::create(&path).unwrap();
+            let mut encoder = TiffEncoder::new(file).unwrap();
+            let mut image = encoder.new_image::<TwoBandFloat>(2, 2).unwrap();
+            image
+                .encoder()
+                .write_tag(Tag::ModelPixelScaleTag, &[0.5f64, 0.5, 0.0][..])
+                .unwrap();
+            image
+                .encoder()
+                .write_tag(
+                    Tag::ModelTiepointTag,
+                    &[0.0f64, 0.0, 0.0, 11.0, 49.0, 0.0][..],
+                )
+                .unwrap();
+            image
+                .write_data(&[10.0f32, 1.0, 20.0, 1.0, 30.0, 1.0, 40.0, 1.0])
+                .unwrap();
+        }
+
+        let error = DemTile::from_geotiff(&path).unwrap_err();
+        assert!(error.to_string().contains("single-band"), "{error}");
+
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn elevation_slows_walking_and_cycling_uphill() {
+        let xml = r#"<osm>
+              <node id="1" lat="48.000" lon="11.000" />
+              <node id="2" lat="48.002" lon="11.000" />
+              <way id="10"><nd ref="1" /><nd ref="2" /><tag k="highway" v="residential" /></way>
+            </osm>"#;
+        let flat = SpatialGraph::from_osm(xml, NetworkType::Walk, Some(true), None).unwrap();
+        let mut dem = Dem::new();
+        dem.add_tile(north_slope_tile());
+        let hilly = flat.clone().with_elevation(&dem);
+
+        let edge = |graph: &SpatialGraph, from: i64| {
+            graph
+                .graph
+                .edge_indices()
+                .find(|&e| graph.graph[graph.graph.edge_endpoints(e).unwrap().0].id == from)
+                .map(|e| graph.graph[e].clone())
+                .unwrap()
+        };
+        let (flat_up, up, down) = (edge(&flat, 1), edge(&hilly, 1), edge(&hilly, 2));
+
+        assert!((up.ascent_m() - 20.0).abs() < 1e-6);
+        assert!((down.descent_m() - 20.0).abs() < 1e-6);
+        assert!((up.grade().unwrap() - 20.0 / up.length).abs() < 1e-9);
+        assert!(up.walk_travel_time > flat_up.walk_travel_time);
+        assert!(down.walk_travel_time < up.walk_travel_time);
+        assert!(up.bike_travel_time > flat_up.bike_travel_time);
+        assert_eq!(up.drive_travel_time, flat_up.drive_travel_time);
+        let top = hilly.graph.node_weights().find(|n| n.id == 2).unwrap();
+        assert!((top.elevation_m.unwrap() - 20.0).abs() < 1e-6);
+
+        let route = hilly
+            .route(48.0, 11.0, 48.002, 11.0, NetworkType::Walk, None)
+            .unwrap();
+        assert_eq!(route.elevations_m.len(), route.coordinates.len());
+        assert!((route.ascent_m - 20.0).abs() < 1e-6);
+        assert_eq!(route.descent_m, 0.0);
+        let profile = route.elevation_profile();
+        assert_eq!(profile.first().unwrap().0, 0.0);
+        assert!((profile.last().unwrap().1 - 20.0).abs() < 1e-6);
+
+        let flat_route = flat
+            .route(48.0, 11.0, 48.002, 11.0, NetworkType::Walk, None)
+            .unwrap();
+        assert!(flat_route.elevations_m.is_empty());
+        assert!(route.duration_s > flat_route.duration_s);
+    }
+
+    #[test]
+    fn elevation_leaves_node_delays_unscaled() {
+        // Node 2, at the top of the slope, is a signalled crossing.
+        let xml = r#"<osm>
+              <node id="1" lat="48.000" lon="11.000" />
+              <node id="2" lat="48.002" lon="11.000">
+                <tag k="highway" v="crossing" /><tag k="crossing" v="traffic_signals" />
+              </node>
+              <way id="10"><nd ref="1" /><nd ref="2" /><tag k="highway" v="residential" /></way>
+            </osm>"#;
+        let plain = xml.replace(r#"<tag k="crossing" v="traffic_signals" />"#, "");
+        let plain = plain.replace(r#"<tag k="highway" v="crossing" />"#, "");
+        let mut dem = Dem::new();
+        dem.add_tile(north_slope_tile());
+        let up = |xml: &str| {
+            let graph = SpatialGraph::from_osm(xml, NetworkType::Walk, Some(true), None)
+                .unwrap()
+                .with_elevation(&dem);
+            let edge = graph
+                .graph
+                .edge_indices()
+                .find(|&e| graph.graph[graph.graph.edge_endpoints(e).unwrap().0].id == 1)
+                .unwrap();
+            graph.graph[edge].clone()
+        };
+
+        let (signalled, plain) = (up(xml), up(&plain));
+        let delay_s = SpeedProfile::default().traffic_signal_delay_s;
+        assert_eq!(signalled.node_delays.walk_s, delay_s);
+        assert!((signalled.walk_travel_time - plain.walk_travel_time - delay_s).abs() < 1e-9);
+        assert!((signalled.bike_travel_time - plain.bike_travel_time).abs() < 1e-9);
+    }
+
+    /// The directed edge leaving the node with OSM id `from`.
+    fn edge_from(graph: &SpatialGraph, from: i64) -> XmlWay {
+        graph
+            .graph
+            .edge_indices()
+            .find(|&e| graph.graph[graph.graph.edge_endpoints(e).unwrap().0].id == from)
+            .map(|e| graph.graph[e].clone())
+            .unwrap()
+    }
+
+    #[test]
+    fn elevation_leaves_ferry_times_alone() {
+        let xml = r#"<osm>
+              <node id="1" lat="48.000" lon="11.000" />
+              <node id="2" lat="48.002" lon="11.000" />
+              <way id="10"><nd ref="1" /><nd ref="2" />
+                <tag k="route" v="ferry" /><tag k="duration" v="00:10" /></way>
+            </osm>"#;
+        let flat = SpatialGraph::from_osm(xml, NetworkType::Walk, Some(true), None).unwrap();
+        let mut dem = Dem::new();
+        dem.add_tile(north_slope_tile());
+        let hilly = flat.clone().with_elevation(&dem);
+
+        let (flat_up, up) = (edge_from(&flat, 1), edge_from(&hilly, 1));
+        assert!((up.ascent_m() - 20.0).abs() < 1e-6);
+        assert_eq!(up.walk_travel_time, flat_up.walk_travel_time);
+        assert_eq!(up.bike_travel_time, flat_up.bike_travel_time);
+    }
+
+    #[test]
+    fn elevation_slows_pushed_bikes_like_walking() {
+        // One-way downhill from 2 to 1; cyclists push their bikes up.
+        let xml = r#"<osm>
+              <node id="1" lat="48.000" lon="11.000" />
+              <node id="2" lat="48.002" lon="11.000" />
+              <way id="10"><nd ref="2" /><nd ref="1" />
+                <tag k="highway" v="residential" /><tag k="oneway" v="yes" /></way>
+            </osm>"#;
+        let profile = SpeedProfile {
+            bike_push_speed_kph: Some(4.0),
+            ..SpeedProfile::default()
+        };
+        let flat =
+            SpatialGraph::from_osm(xml, NetworkType::Bike, Some(true), Some(&profile)).unwrap();
+        let mut dem = Dem::new();
+        dem.add_tile(north_slope_tile());
+        let hilly = flat.clone().with_elevation(&dem);
+
+        let (flat_up, up) = (edge_from(&flat, 1), edge_from(&hilly, 1));
+        assert_eq!(up.bike_push_m, up.length);
+        let walk_ratio = up.walk_travel_time / flat_up.walk_travel_time;
+        let push_ratio = up.bike_travel_time / flat_up.bike_travel_time;
+        assert!(walk_ratio > 1.0);
+        assert!((push_ratio - walk_ratio).abs() < 1e-9);
+
+        let (flat_down, down) = (edge_from(&flat, 2), edge_from(&hilly, 2));
+        assert_eq!(down.bike_push_m, 0.0);
+        let ride_ratio = down.bike_travel_time / flat_down.bike_travel_time;
+        let expected = 1.0 / bike_speed_factor(-20.0 / down.length);
+        assert!((ride_ratio - expected).abs() < 1e-6);
+    }
+}
@@ -13,11 +13,19 @@ pub enum OsmGraphError {
         max_distance_m: f64,
     },
     PathNotFound,
+    /// Leg `leg` (from point `leg` to point `leg + 1`, counting from 0) of a
+    /// multi-stop route failed.
+    LegFailed {
+        leg: usize,
+        error: Box<OsmGraphError>,
+    },
     LockPoisoned,
     GeocodingFailed(String),
     InvalidInput(String),
     Io(std::io::Error),
     PbfError(String),
+    DemError(String),
+    GraphFile(String),
 }
 
 impl std::fmt::Display for OsmGraphError {
@@ -46,11 +54,21 @@ impl std::fmt::Display for OsmGraphError {
                 f,
                 "No path found between the snapped origin and destination nodes"
             ),
+            OsmGraphError::LegFailed { leg, error } => write!(
+                f,
+                "Leg {} (point {} to point {}) failed: {}",
+                leg,
+                leg,
+                leg + 1,
+                error
+            ),
             OsmGraphError::LockPoisoned => write!(f, "Internal cache lock was poisoned"),
             OsmGraphError::GeocodingFailed(p) => write!(f, "Could not geocode '{}'", p),
             OsmGraphError::InvalidInput(msg) => write!(f, "Invalid input: {}", msg),
             OsmGraphError::Io(e) => write!(f, "IO error: {}", e),
             OsmGraphError::PbfError(msg) => write!(f, "PBF error: {}", msg),
+            OsmGraphError::DemError(msg) => write!(f, "DEM error: {}", msg),
+            OsmGraphError::GraphFile(msg) => write!(f, "Graph file error: {}", msg),
         }
     }
 }
@@ -61,6 +79,7 @@ impl std::error::Error for OsmGraphError {
             OsmGraphError::Network(e) => Some(e),
             OsmGraphError::XmlParse(e) => Some(e),
             OsmGraphError::Io(e) => Some(e),
+            OsmGraphError::LegFailed { error, .. } => Some(error.as_ref()),
             _ => None,
         }
     }
@@ -88,7 +107,13 @@ impl From<std::io::Error> for OsmGraphError {
 #[cfg(feature = "extension-module")]
 impl From<OsmGraphError> for pyo3::PyErr {
     fn from(e: OsmGraphError) -> Self {
-        match e {
+        // A failed leg raises the exception its underlying error would, with
+        // the leg named in the message.
+        let mut kind = &e;
+        while let OsmGraphError::LegFailed { error, .. } = kind {
+            kind = error;
+        }
+        match kind {
             OsmGraphError::Network(_) => {
                 pyo3::exceptions::PyConnectionError::new_err(e.to_string())
             }
@@ -96,12 +121,15 @@ impl From<OsmGraphError> for pyo3::PyErr {
             OsmGraphError::XmlParse(_)
             | OsmGraphError::InvalidInput(_)
             | OsmGraphError::PbfError(_)
+            | OsmGraphError::DemError(_)
+            | OsmGraphError::GraphFile(_)
             | OsmGraphError::EmptyGraph => pyo3::exceptions::PyValueError::new_err(e.to_string()),
             OsmGraphError::NodeNotFound
             | OsmGraphError::OriginNodeNotFound
             | OsmGraphError::DestinationNodeNotFound
             | OsmGraphError::SnapDistanceExceeded { .. }
             | OsmGraphError::PathNotFound
+            | OsmGraphError::LegFailed { .. }
             | OsmGraphError::GeocodingFailed(_) => {
                 pyo3::exceptions::PyLookupError::new_err(e.to_string())
             }
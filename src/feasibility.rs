@@ -41,7 +41,9 @@ use petgraph::visit::EdgeRef;
 
 use crate::graph::{node_to_latlon, SpatialGraph, XmlNode, XmlWay};
 use crate::overpass::NetworkType;
+use crate::reachability::reachability_search;
 use crate::reachability::EdgeInfo;
+use crate::search::{edge_based_search, same_edge_path, Anchor, SearchDirection};
 
 // ---------------------------------------------------------------------------
 // Public types
@@ -211,10 +213,28 @@ where
         })
     });
 
-    // Intersect: keep only nodes present in both searches whose combined cost
-    // fits within the budget.
+    Ok(intersect_searches(
+        origin,
+        destination,
+        available_time,
+        direct_time,
+        &forward,
+        &backward,
+    ))
+}
+
+/// Intersect forward and backward search labels: keep only nodes present in
+/// both searches whose combined cost fits within the budget.
+fn intersect_searches(
+    origin: NodeIndex,
+    destination: NodeIndex,
+    available_time: f64,
+    direct_time: f64,
+    forward: &HashMap<NodeIndex, f64>,
+    backward: &HashMap<NodeIndex, f64>,
+) -> FeasibilityResult {
     let mut feasible = HashMap::new();
-    for (&node, &inbound) in &forward {
+    for (&node, &inbound) in forward {
         if inbound > available_time {
             continue;
         }
@@ -233,13 +253,13 @@ where
         }
     }
 
-    Ok(FeasibilityResult {
+    FeasibilityResult {
         origin,
         destination,
         available_time,
         direct_time,
         feasible,
-    })
+    }
 }
 
 /// Compute two-sided feasibility using the precomputed walk/bike/drive travel
@@ -317,7 +337,7 @@ fn prism_subgraph(sg: &SpatialGraph, result: &FeasibilityResult) -> SpatialGraph
         subgraph.add_edge(source, target, edge.weight().clone());
     }
 
-    SpatialGraph::new(subgraph)
+    sg.derive(subgraph)
 }
 
 impl PrismGraph {
@@ -337,17 +357,14 @@ impl PrismGraph {
     }
 
     pub fn contains_node_id(&self, node_id: i64) -> bool {
-        self.result
-            .feasible
-            .keys()
-            .any(|&idx| self.graph.graph[idx].id == node_id)
+        self.graph
+            .node_index_for_osm_id(node_id)
+            .is_some_and(|idx| self.result.feasible.contains_key(&idx))
     }
 
     pub fn slack_at_node_id(&self, node_id: i64) -> Option<f64> {
-        self.result
-            .feasible
-            .iter()
-            .find_map(|(&idx, node)| (self.graph.graph[idx].id == node_id).then_some(node.slack))
+        let idx = self.graph.node_index_for_osm_id(node_id)?;
+        self.result.feasible.get(&idx).map(|node| node.slack)
     }
 
     pub fn materialize(&self) -> SpatialGraph {
@@ -389,7 +406,9 @@ impl SpatialGraph {
     ///
     /// The result is a lightweight graph view over every node `v` satisfying
     /// `origin -> v -> destination <= available_time`, with each node labeled
-    /// by inbound time, outbound time, and slack.
+    /// by inbound time, outbound time, and slack. Both coordinates are
+    /// snapped to the closest point on an edge, so either end may lie
+    /// part-way along one.
     #[allow(clippy::too_many_arguments)]
     pub fn prism(
         &self,
@@ -401,21 +420,93 @@ impl SpatialGraph {
         network_type: NetworkType,
         max_snap_m: Option<f64>,
     ) -> Option<Result<PrismGraph, InfeasibleReason>> {
-        let origin = self.nearest_node_within(origin_lat, origin_lon, max_snap_m)?;
-        let destination = self.nearest_node_within(dest_lat, dest_lon, max_snap_m)?;
-        let result = compute_feasibility(
-            &self.graph,
-            origin,
-            destination,
-            available_time,
-            network_type,
-        );
+        let origin = self.anchor_within(origin_lat, origin_lon, max_snap_m)?;
+        let destination = self.anchor_within(dest_lat, dest_lon, max_snap_m)?;
+        let result = self.anchored_feasibility(&origin, &destination, available_time, network_type);
         Some(result.map(|result| PrismGraph {
             graph: self.clone(),
             result,
             network_type,
         }))
     }
+
+    /// Two-sided feasibility between two search anchors. Both legs use the
+    /// edge-based search when the graph has turn restrictions or penalties
+    /// for `network_type`, so that they respect them.
+    fn anchored_feasibility(
+        &self,
+        origin: &Anchor,
+        destination: &Anchor,
+        available_time: f64,
+        network_type: NetworkType,
+    ) -> Result<FeasibilityResult, InfeasibleReason> {
+        let cost = |e: EdgeInfo<'_>, _| self.search_cost(e.id, network_type, None, 0.0);
+        let turns = self.turn_model(network_type);
+        let search = |start: &Anchor, direction| match turns {
+            Some(turns) => {
+                edge_based_search(
+                    &self.graph,
+                    turns,
+                    start,
+                    direction,
+                    &[],
+                    f64::INFINITY,
+                    cost,
+                    |_| 0.0,
+                )
+                .distances
+            }
+            None => reachability_search(&self.graph, start, direction, f64::INFINITY, cost),
+        };
+
+        let forward = search(origin, SearchDirection::Forward);
+        let mut backward = None;
+        let direct_time = if destination.is_node() {
+            forward.get(&destination.node).copied()
+        } else if let Some(turns) = turns {
+            // Joining the two legs at a node would skip the turn made there.
+            edge_based_search(
+                &self.graph,
+                turns,
+                origin,
+                SearchDirection::Forward,
+                &[destination],
+                f64::INFINITY,
+                cost,
+                |_| 0.0,
+            )
+            .paths
+            .pop()
+            .flatten()
+            .map(|path| path.cost)
+        } else {
+            let backward = backward.insert(search(destination, SearchDirection::Backward));
+            forward
+                .iter()
+                .filter_map(|(node, inbound)| backward.get(node).map(|outbound| inbound + outbound))
+                .chain(same_edge_path(&self.graph, origin, destination, cost).map(|path| path.cost))
+                .min_by(f64::total_cmp)
+        };
+        let Some(direct_time) = direct_time else {
+            return Err(InfeasibleReason::NoPathExists);
+        };
+        if direct_time > available_time {
+            return Err(InfeasibleReason::BudgetTooTight {
+                direct_time,
+                available_time,
+            });
+        }
+
+        let backward = backward.unwrap_or_else(|| search(destination, SearchDirection::Backward));
+        Ok(intersect_searches(
+            origin.node,
+            destination.node,
+            available_time,
+            direct_time,
+            &forward,
+            &backward,
+        ))
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -427,6 +518,8 @@ mod tests {
     use super::*;
     use crate::graph::{create_graph, XmlNode, XmlNodeRef, XmlTag, XmlWay};
     use crate::overpass::NetworkType;
+    use crate::storage::EdgeGeometry;
+    use std::sync::Arc;
 
     // ------------------------------------------------------------------
     // Helpers
@@ -438,6 +531,7 @@ mod tests {
             lat,
             lon,
             tags: vec![],
+            elevation_m: None,
         }
     }
 
@@ -448,16 +542,23 @@ mod tests {
                 .into_iter()
                 .map(|id| XmlNodeRef { node_id: id })
                 .collect(),
-            tags: vec![XmlTag {
+            tags: Arc::from([XmlTag {
                 key: "highway".into(),
                 value: "residential".into(),
-            }],
+            }]),
             length: 0.0,
             speed_kph: 0.0,
             walk_travel_time: 0.0,
             bike_travel_time: 0.0,
             drive_travel_time: 0.0,
-            geometry: Vec::new(),
+            geometry: EdgeGeometry::default(),
+            way_ids: Vec::new(),
+            closed_during: Vec::new(),
+            closed_by_default: false,
+            node_delays: Default::default(),
+            bike_push_m: 0.0,
+            elevations_m: Vec::new(),
+            hourly_speeds: None,
         }
     }
 
@@ -560,6 +661,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn prism_between_points_part_way_along_edges() {
+        let sg = SpatialGraph::new(linear_graph());
+        let edge_time = sg.graph.edge_weights().next().unwrap().drive_travel_time;
+
+        // Halfway between A and B to halfway between C and D.
+        let prism = sg
+            .prism(0.0005, 0.0, 0.0025, 0.0, 10_000.0, NetworkType::Drive, None)
+            .unwrap()
+            .unwrap();
+
+        assert!((prism.result.direct_time - 2.0 * edge_time).abs() < 1e-3);
+        let b = &prism.result.feasible[&find_node(&sg.graph, 2)];
+        assert!((b.inbound_time - 0.5 * edge_time).abs() < 1e-3);
+        assert!((b.outbound_time - 1.5 * edge_time).abs() < 1e-3);
+    }
+
     #[test]
     fn slack_equals_budget_minus_travel_times() {
         let g = linear_graph();
@@ -2,6 +2,7 @@
 //! the PBF pipeline. A single source of truth means road-filter rule changes
 //! and POI category additions only need to happen in one place.
 
+use crate::conditional::grants_access;
 use crate::overpass::NetworkType;
 
 // ---------------------------------------------------------------------------
@@ -11,13 +12,16 @@ use crate::overpass::NetworkType;
 /// Return `true` if a way with the given tags should be included in the road
 /// network for `network_type`.
 ///
-/// The rules mirror `overpass::get_osm_filter` exactly — if you change one,
-/// change the other. The Overpass filter is a string passed to the API; this
-/// function is the equivalent predicate applied to already-fetched data (PBF
-/// or cached XML).
+/// The rules mirror `overpass::get_osm_filter_alternatives` exactly — if you
+/// change one, change the other. The Overpass filters are strings passed to
+/// the API; this function is the equivalent predicate applied to
+/// already-fetched data (PBF or cached XML).
 pub fn way_passes_road_filter(tags: &[(String, String)], network_type: NetworkType) -> bool {
     let get = |k: &str| tags.iter().find(|(tk, _)| tk == k).map(|(_, v)| v.as_str());
 
+    if get("route") == Some("ferry") {
+        return ferry_passes_filter(tags, network_type);
+    }
     let highway = match get("highway") {
         Some(v) => v,
         None => return false,
@@ -53,10 +57,10 @@ pub fn way_passes_road_filter(tags: &[(String, String)], network_type: NetworkTy
             if EXCLUDE_HIGHWAY.contains(&highway) {
                 return false;
             }
-            if get("motor_vehicle") == Some("no") {
+            if denied(tags, "motor_vehicle") {
                 return false;
             }
-            if get("motorcar") == Some("no") {
+            if denied(tags, "motorcar") {
                 return false;
             }
             const EXCLUDE_SERVICE: &[&str] = &[
@@ -98,10 +102,10 @@ pub fn way_passes_road_filter(tags: &[(String, String)], network_type: NetworkTy
             if EXCLUDE_HIGHWAY.contains(&highway) {
                 return false;
             }
-            if get("motor_vehicle") == Some("no") {
+            if denied(tags, "motor_vehicle") {
                 return false;
             }
-            if get("motorcar") == Some("no") {
+            if denied(tags, "motorcar") {
                 return false;
             }
             const EXCLUDE_SERVICE: &[&str] =
@@ -132,7 +136,7 @@ pub fn way_passes_road_filter(tags: &[(String, String)], network_type: NetworkTy
             if EXCLUDE_HIGHWAY.contains(&highway) || highway.starts_with("motor") {
                 return false;
             }
-            if get("foot") == Some("no") {
+            if denied(tags, "foot") {
                 return false;
             }
             if get("service") == Some("private") {
@@ -159,7 +163,7 @@ pub fn way_passes_road_filter(tags: &[(String, String)], network_type: NetworkTy
             if EXCLUDE_HIGHWAY.contains(&highway) || highway.starts_with("motor") {
                 return false;
             }
-            if get("bicycle") == Some("no") {
+            if denied(tags, "bicycle") {
                 return false;
             }
             if get("service") == Some("private") {
@@ -203,6 +207,34 @@ pub fn way_passes_road_filter(tags: &[(String, String)], network_type: NetworkTy
     true
 }
 
+/// Return `true` if `key=no` bars the way outright. A `<key>:conditional`
+/// tag granting access at times, such as
+/// `motor_vehicle:conditional=yes @ (Mo-Fr 07:00-19:00)`, keeps it in the
+/// network; graph construction closes it outside those times.
+fn denied(tags: &[(String, String)], key: &str) -> bool {
+    let get = |k: &str| tags.iter().find(|(tk, _)| tk == k).map(|(_, v)| v.as_str());
+    get(key) == Some("no") && !get(&format!("{key}:conditional")).is_some_and(grants_access)
+}
+
+/// Return `true` if a `route=ferry` way with the given tags carries traffic
+/// of `network_type`. Cars need an explicit `motor_vehicle=yes` or
+/// `motorcar=yes`, since most ferries are passenger-only; walkers and
+/// cyclists are allowed unless `foot=no` / `bicycle=no`.
+///
+/// Mirrors `overpass::get_ferry_filter`.
+pub fn ferry_passes_filter(tags: &[(String, String)], network_type: NetworkType) -> bool {
+    let get = |k: &str| tags.iter().find(|(tk, _)| tk == k).map(|(_, v)| v.as_str());
+
+    match network_type {
+        NetworkType::Drive | NetworkType::DriveService => {
+            get("motor_vehicle") == Some("yes") || get("motorcar") == Some("yes")
+        }
+        NetworkType::Walk => get("foot") != Some("no"),
+        NetworkType::Bike => get("bicycle") != Some("no"),
+        NetworkType::All | NetworkType::AllPrivate => true,
+    }
+}
+
 // ---------------------------------------------------------------------------
 // POI filter
 // ---------------------------------------------------------------------------
@@ -366,6 +398,33 @@ mod tests {
         assert!(!way_passes_road_filter(&tags, NetworkType::Drive));
     }
 
+    #[test]
+    fn road_keeps_ways_a_conditional_tag_opens() {
+        let delivery_hours = tags(&[
+            ("highway", "pedestrian"),
+            ("motor_vehicle", "no"),
+            ("motor_vehicle:conditional", "yes @ (Mo-Fr 07:00-19:00)"),
+        ]);
+        let school_street = tags(&[
+            ("highway", "residential"),
+            ("motor_vehicle", "no"),
+            ("motor_vehicle:conditional", "yes @ (Mo-Fr 07:00-19:00)"),
+        ]);
+        let closed_at_times = tags(&[
+            ("highway", "residential"),
+            ("foot", "no"),
+            ("foot:conditional", "no @ (Sa,Su)"),
+        ]);
+
+        assert!(!way_passes_road_filter(&delivery_hours, NetworkType::Drive));
+        assert!(way_passes_road_filter(&school_street, NetworkType::Drive));
+        assert!(way_passes_road_filter(
+            &school_street,
+            NetworkType::DriveService
+        ));
+        assert!(!way_passes_road_filter(&closed_at_times, NetworkType::Walk));
+    }
+
     #[test]
     fn road_drive_service_keeps_service_driveway_but_drive_rejects_it() {
         let tags = tags(&[("highway", "service"), ("service", "driveway")]);
@@ -392,6 +451,24 @@ mod tests {
         assert!(way_passes_road_filter(&tags, NetworkType::Bike));
     }
 
+    #[test]
+    fn ferries_pass_for_the_modes_they_carry() {
+        let foot_ferry = tags(&[("route", "ferry"), ("duration", "00:20")]);
+        let car_ferry = tags(&[("route", "ferry"), ("motor_vehicle", "yes")]);
+        let no_bikes = tags(&[("route", "ferry"), ("bicycle", "no")]);
+
+        assert!(way_passes_road_filter(&foot_ferry, NetworkType::Walk));
+        assert!(way_passes_road_filter(&foot_ferry, NetworkType::Bike));
+        assert!(!way_passes_road_filter(&foot_ferry, NetworkType::Drive));
+        assert!(way_passes_road_filter(&car_ferry, NetworkType::Drive));
+        assert!(way_passes_road_filter(
+            &car_ferry,
+            NetworkType::DriveService
+        ));
+        assert!(!way_passes_road_filter(&no_bikes, NetworkType::Bike));
+        assert!(way_passes_road_filter(&no_bikes, NetworkType::All));
+    }
+
     #[test]
     fn road_bike_rejects_bicycle_no() {
         let tags = tags(&[("highway", "residential"), ("bicycle", "no")]);
@@ -1,9 +1,25 @@
+use crate::closures::ClosureOverlay;
+use crate::components::ComponentSizes;
+use crate::conditional::{
+    access_keys, contraflow_closures, way_closures, DepartureTime, TimeWindow,
+};
+use crate::elevation::{add_elevation, Dem};
+use crate::hierarchy::ContractionHierarchy;
+use crate::node_costs::{apply_node_costs, node_has_cost};
+use crate::search::Anchor;
 use crate::simplify::simplify_graph;
+use crate::speed::{parse_maxspeed, SpeedProfile};
+use crate::storage::{intern_graph_tags, pack_edge_geometry, EdgeGeometry, TagStr};
+use crate::traffic::WeekSpeeds;
+use crate::turns::{
+    protected_node_ids, TurnGeometry, TurnModel, TurnPenalties, TurnRestriction, TurnRestrictions,
+};
 use crate::utils::{calculate_distance, calculate_travel_time};
-use petgraph::graph::{DiGraph, NodeIndex};
+use petgraph::graph::{DiGraph, EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
 use rstar::{PointDistance, RTree, RTreeObject, AABB};
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 #[derive(Debug, Deserialize)]
@@ -12,6 +28,10 @@ pub struct XmlData {
     pub nodes: Vec<XmlNode>,
     #[serde(rename = "way", default)]
     pub ways: Vec<XmlWay>,
+    /// Relations kept for graph construction. Only `type=restriction`
+    /// relations are retained by the PBF reader and requested from Overpass.
+    #[serde(rename = "relation", default)]
+    pub relations: Vec<XmlRelation>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -24,6 +44,10 @@ pub struct XmlNode {
     pub lon: f64,
     #[serde(rename = "tag", default)]
     pub tags: Vec<XmlTag>,
+    /// Ground elevation in metres, sampled from a DEM by
+    /// [`SpatialGraph::with_elevation`]. `None` when no DEM covers the node.
+    #[serde(skip)]
+    pub elevation_m: Option<f64>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -32,8 +56,10 @@ pub struct XmlWay {
     pub id: i64,
     #[serde(rename = "nd", default)]
     pub nodes: Vec<XmlNodeRef>,
+    /// Tags of the source way. Every edge cut from one way shares this
+    /// record, and graph builds intern records across ways.
     #[serde(rename = "tag", default)]
-    pub tags: Vec<XmlTag>,
+    pub tags: Arc<[XmlTag]>,
     #[serde(default)]
     pub length: f64,
     #[serde(default)]
@@ -44,35 +70,188 @@ pub struct XmlWay {
     pub bike_travel_time: f64,
     #[serde(default)]
     pub drive_travel_time: f64,
+    /// The part of each travel time spent waiting at the edge's target node
+    /// (traffic signals, stop signs, crossings), which grades do not scale.
+    #[serde(skip)]
+    pub node_delays: NodeDelays,
+    /// Metres of the edge along which cyclists push their bike against a
+    /// one-way, at [`SpeedProfile::bike_push_speed_kph`].
+    #[serde(skip)]
+    pub bike_push_m: f64,
     /// Ordered route geometry as `(lat, lon)` points for this directed edge.
     ///
-    /// Unsimplified edges contain their source and target coordinates.
-    /// Simplified edges retain the intermediate shape points from the collapsed
-    /// chain so route rendering follows the original road geometry.
+    /// Empty for edges of a single straight segment, whose geometry is their
+    /// source and target coordinates. Simplified edges retain the
+    /// intermediate shape points from the collapsed chain so route rendering
+    /// follows the original road geometry. Graph builds pack all shape points
+    /// into one shared buffer.
+    #[serde(default)]
+    pub geometry: EdgeGeometry,
+    /// OSM way ids traversed by this directed edge, in travel order.
+    ///
+    /// Empty for unsimplified edges, which cover only `id`; use
+    /// [`XmlWay::osm_way_ids`]. Simplified edges list every way in the
+    /// collapsed chain, which lets turn restrictions match the first and last
+    /// way of a collapsed edge.
     #[serde(default)]
-    pub geometry: Vec<(f64, f64)>,
+    pub way_ids: Vec<i64>,
+    /// Weekly windows during which this directed edge is closed, from
+    /// `*:conditional` access and `oneway:conditional` tags. Only consulted
+    /// by queries that carry a departure time.
+    #[serde(skip)]
+    pub closed_during: Vec<TimeWindow>,
+    /// Whether the edge's base tags close it and a conditional tag only
+    /// opens it at times (e.g. `oneway:conditional=no` against a one-way
+    /// street). Queries without a departure time treat it as closed; the
+    /// others rely on `closed_during`, which covers every time outside the
+    /// opening.
+    #[serde(skip)]
+    pub closed_by_default: bool,
+    /// Ground elevation in metres at each `geometry` point, filled by
+    /// [`SpatialGraph::with_elevation`]. Empty when the edge has no DEM
+    /// coverage.
+    #[serde(default)]
+    pub elevations_m: Vec<f64>,
+    /// Drive speeds by hour of the week for this directed edge, from
+    /// [`SpeedProfile::hourly_speeds`]. Only consulted by queries that carry
+    /// a departure time.
+    #[serde(skip)]
+    pub hourly_speeds: Option<Arc<WeekSpeeds>>,
 }
 
 impl XmlWay {
     pub fn filter_useful_tags(mut self) -> Self {
-        const USEFUL_TAGS: &[&str] = &["highway", "name", "ref", "bridge", "tunnel", "service"];
-        // Linear search on 15-element static slice — no HashSet allocation needed.
-        self.tags
-            .retain(|tag| USEFUL_TAGS.contains(&tag.key.as_str()));
+        const USEFUL_TAGS: &[&str] = &[
+            "highway",
+            "name",
+            "ref",
+            "bridge",
+            "tunnel",
+            "service",
+            "surface",
+            "smoothness",
+            "tracktype",
+            "mtb:scale",
+            "route",
+            "junction",
+        ];
+        // Linear search on 12-element static slice — no HashSet allocation needed.
+        self.tags = self
+            .tags
+            .iter()
+            .filter(|tag| USEFUL_TAGS.contains(&tag.key.as_str()))
+            .cloned()
+            .collect();
         self
     }
 
     /// Return the travel time (seconds) for the given network type.
     /// Centralises the walk / bike / drive dispatch so call sites don't
     /// repeat the same match expression.
+    ///
+    /// Infinite for edges that are [`XmlWay::closed_by_default`].
     #[inline]
     pub fn travel_time(&self, network_type: crate::overpass::NetworkType) -> f64 {
+        if self.closed_by_default {
+            return f64::INFINITY;
+        }
+        self.open_travel_time(network_type)
+    }
+
+    /// Travel time (seconds) for the given network type while the edge is
+    /// open, ignoring [`XmlWay::closed_by_default`].
+    #[inline]
+    pub(crate) fn open_travel_time(&self, network_type: crate::overpass::NetworkType) -> f64 {
         match network_type {
             crate::overpass::NetworkType::Walk => self.walk_travel_time,
             crate::overpass::NetworkType::Bike => self.bike_travel_time,
             _ => self.drive_travel_time,
         }
     }
+
+    /// Whether the edge is part of a `route=ferry` crossing.
+    pub fn is_ferry(&self) -> bool {
+        self.tags
+            .iter()
+            .any(|tag| tag.key == "route" && tag.value == "ferry")
+    }
+
+    /// Whether one of the edge's conditional closures applies at `time`.
+    pub fn is_closed_at(&self, time: DepartureTime) -> bool {
+        self.closed_during
+            .iter()
+            .any(|window| window.contains(time))
+    }
+
+    /// Drive travel time for a vehicle entering the edge at `time`: at the
+    /// hourly speed for that time when one is known, plus any fixed delay
+    /// such as a traffic signal, otherwise `drive_travel_time`.
+    pub fn drive_travel_time_at(&self, time: DepartureTime) -> f64 {
+        let speed_kph = self
+            .hourly_speeds
+            .as_ref()
+            .and_then(|speeds| speeds.speed_kph_at(time));
+        match speed_kph {
+            Some(speed_kph) if self.drive_travel_time.is_finite() => {
+                let delay_s =
+                    self.drive_travel_time - calculate_travel_time(self.length, self.speed_kph);
+                calculate_travel_time(self.length, speed_kph) + delay_s.max(0.0)
+            }
+            _ => self.drive_travel_time,
+        }
+    }
+
+    /// Total climb in metres along the edge, from `elevations_m`.
+    pub fn ascent_m(&self) -> f64 {
+        self.elevations_m
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]).max(0.0))
+            .sum()
+    }
+
+    /// Total drop in metres along the edge, from `elevations_m`.
+    pub fn descent_m(&self) -> f64 {
+        self.elevations_m
+            .windows(2)
+            .map(|pair| (pair[0] - pair[1]).max(0.0))
+            .sum()
+    }
+
+    /// Average grade (rise over run) from the first to the last geometry
+    /// point, or `None` when the edge has no elevations.
+    pub fn grade(&self) -> Option<f64> {
+        let (first, last) = (self.elevations_m.first()?, self.elevations_m.last()?);
+        (self.length > 0.0).then(|| (last - first) / self.length)
+    }
+
+    /// OSM way ids covered by this edge. Falls back to `id` for edges cut
+    /// from a single way, which leave `way_ids` empty.
+    pub fn osm_way_ids(&self) -> &[i64] {
+        if self.way_ids.is_empty() {
+            std::slice::from_ref(&self.id)
+        } else {
+            &self.way_ids
+        }
+    }
+
+    /// OSM way id at the start of this directed edge.
+    pub fn first_way_id(&self) -> i64 {
+        self.osm_way_ids()[0]
+    }
+
+    /// OSM way id at the end of this directed edge.
+    pub fn last_way_id(&self) -> i64 {
+        *self.osm_way_ids().last().unwrap()
+    }
+}
+
+/// Seconds of node delay included in an edge's walk, bike and drive travel
+/// times.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NodeDelays {
+    pub walk_s: f64,
+    pub bike_s: f64,
+    pub drive_s: f64,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -81,12 +260,33 @@ pub struct XmlNodeRef {
     pub node_id: i64,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub struct XmlTag {
     #[serde(rename = "@k")]
-    pub key: String,
+    pub key: TagStr,
     #[serde(rename = "@v")]
-    pub value: String,
+    pub value: TagStr,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct XmlRelation {
+    #[serde(rename = "@id")]
+    pub id: i64,
+    #[serde(rename = "member", default)]
+    pub members: Vec<XmlMember>,
+    #[serde(rename = "tag", default)]
+    pub tags: Vec<XmlTag>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct XmlMember {
+    /// `node`, `way` or `relation`.
+    #[serde(rename = "@type")]
+    pub member_type: String,
+    #[serde(rename = "@ref")]
+    pub member_ref: i64,
+    #[serde(rename = "@role", default)]
+    pub role: String,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -106,6 +306,27 @@ fn find_tag<'a>(tags: &'a [XmlTag], key: &str) -> Option<&'a XmlTag> {
     tags.iter().find(|tag| tag.key == key)
 }
 
+/// Which directionality tags a graph honours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DirectionRules {
+    /// `oneway` and `junction=roundabout`.
+    Vehicle,
+    /// Vehicle rules, overridden by `oneway:bicycle` and contraflow cycleways.
+    Bicycle,
+    /// Every way is walkable in both directions.
+    Pedestrian,
+}
+
+impl DirectionRules {
+    fn for_network(network_type: crate::overpass::NetworkType) -> Self {
+        match network_type {
+            crate::overpass::NetworkType::Walk => DirectionRules::Pedestrian,
+            crate::overpass::NetworkType::Bike => DirectionRules::Bicycle,
+            _ => DirectionRules::Vehicle,
+        }
+    }
+}
+
 fn assess_path_directionality(path: &XmlWay) -> Direction {
     let oneway_tag = find_tag(&path.tags, "oneway");
     let junction_tag = find_tag(&path.tags, "junction");
@@ -127,58 +348,98 @@ fn assess_path_directionality(path: &XmlWay) -> Direction {
     }
 }
 
-fn highway_speed_kph(highway: &str) -> Option<f64> {
-    match highway {
-        "motorway" => Some(110.0),
-        "motorway_link" => Some(60.0),
-        "trunk" => Some(90.0),
-        "trunk_link" => Some(45.0),
-        "primary" => Some(65.0),
-        "primary_link" => Some(45.0),
-        "secondary" => Some(55.0),
-        "secondary_link" => Some(40.0),
-        "tertiary" => Some(45.0),
-        "tertiary_link" => Some(35.0),
-        "unclassified" => Some(45.0),
-        "residential" => Some(30.0),
-        "living_street" => Some(10.0),
-        "service" => Some(20.0),
-        "track" => Some(20.0),
-        "road" => Some(50.0),
-        _ => None,
+/// Directionality for cyclists: `oneway:bicycle` wins outright; otherwise a
+/// oneway street with a contraflow cycleway (`cycleway*=opposite*`, or a
+/// `cycleway:<side>:oneway` of `no`/`-1`) is rideable both ways.
+fn assess_bicycle_directionality(path: &XmlWay) -> Direction {
+    match find_tag(&path.tags, "oneway:bicycle").map(|tag| tag.value.as_str()) {
+        Some("no") => return Direction::Bidirectional,
+        Some("yes" | "true" | "1") => return Direction::OneWayForward,
+        Some("-1" | "reverse") => return Direction::OneWayReverse,
+        _ => {}
     }
-}
-
-fn way_speed_kph(way: &XmlWay) -> f64 {
-    const FALLBACK_SPEED_KPH: f64 = 50.0;
 
-    if let Some(maxspeed) =
-        find_tag(&way.tags, "maxspeed").and_then(|tag| clean_maxspeed(&tag.value))
-    {
-        return maxspeed;
+    let direction = assess_path_directionality(path);
+    let has_contraflow = [
+        "cycleway",
+        "cycleway:left",
+        "cycleway:right",
+        "cycleway:both",
+    ]
+    .iter()
+    .filter_map(|key| find_tag(&path.tags, key))
+    .any(|tag| tag.value.starts_with("opposite"))
+        || [
+            "cycleway:left:oneway",
+            "cycleway:right:oneway",
+            "cycleway:both:oneway",
+        ]
+        .iter()
+        .filter_map(|key| find_tag(&path.tags, key))
+        .any(|tag| matches!(tag.value.as_str(), "no" | "-1"));
+    if has_contraflow {
+        Direction::Bidirectional
+    } else {
+        direction
     }
+}
 
-    find_tag(&way.tags, "highway")
-        .and_then(|tag| highway_speed_kph(&tag.value))
-        .unwrap_or(FALLBACK_SPEED_KPH)
+/// Drive speed for travel along (`forward`) or against the way's node order.
+///
+/// The first parseable value among the directional `maxspeed`, `maxspeed`,
+/// `maxspeed:type`, `zone:maxspeed` and `source:maxspeed` tags wins; the
+/// highway-class default applies when none resolves to a speed.
+fn way_speed_kph(way: &XmlWay, profile: &SpeedProfile, forward: bool) -> f64 {
+    let directional_key = if forward {
+        "maxspeed:forward"
+    } else {
+        "maxspeed:backward"
+    };
+    let maxspeed = [
+        directional_key,
+        "maxspeed",
+        "maxspeed:type",
+        "zone:maxspeed",
+        "source:maxspeed",
+    ]
+    .into_iter()
+    .find_map(|key| find_tag(&way.tags, key).and_then(|tag| parse_maxspeed(&tag.value)));
+    let highway = find_tag(&way.tags, "highway").map(|tag| tag.value.as_str());
+    profile.drive_speed_kph(maxspeed, highway)
 }
 
 fn edge_way_from_template(
     template: &XmlWay,
     length: f64,
     speed_kph: f64,
-    geometry: Vec<(f64, f64)>,
+    profile: &SpeedProfile,
+    closed_during: &[TimeWindow],
 ) -> XmlWay {
+    let bike_speed_kph = profile.bike_way_speed_kph(
+        template
+            .tags
+            .iter()
+            .map(|tag| (tag.key.as_str(), tag.value.as_str())),
+    );
     XmlWay {
         id: template.id,
         nodes: Vec::new(),
         tags: template.tags.clone(),
         length,
         speed_kph,
-        walk_travel_time: calculate_travel_time(length, 5.0),
-        bike_travel_time: calculate_travel_time(length, 15.0),
+        walk_travel_time: calculate_travel_time(length, profile.walk_speed_kph),
+        // Surfaces excluded by the profile are never cycled.
+        bike_travel_time: bike_speed_kph
+            .map_or(f64::INFINITY, |speed| calculate_travel_time(length, speed)),
         drive_travel_time: calculate_travel_time(length, speed_kph),
-        geometry,
+        node_delays: NodeDelays::default(),
+        bike_push_m: 0.0,
+        geometry: EdgeGeometry::default(),
+        way_ids: Vec::new(),
+        closed_during: closed_during.to_vec(),
+        closed_by_default: false,
+        elevations_m: Vec::new(),
+        hourly_speeds: None,
     }
 }
 
@@ -188,9 +449,68 @@ pub fn create_graph(
     ways: Vec<XmlWay>,
     retain_all: bool,
     bidirectional: bool,
+) -> DiGraph<XmlNode, XmlWay> {
+    create_graph_with_speeds(
+        nodes,
+        ways,
+        retain_all,
+        bidirectional,
+        &SpeedProfile::default(),
+    )
+}
+
+/// Like [`create_graph`], but derives edge speeds and travel times from
+/// `profile` instead of the built-in defaults.
+pub fn create_graph_with_speeds(
+    nodes: Vec<XmlNode>,
+    ways: Vec<XmlWay>,
+    retain_all: bool,
+    bidirectional: bool,
+    profile: &SpeedProfile,
+) -> DiGraph<XmlNode, XmlWay> {
+    let rules = if bidirectional {
+        DirectionRules::Pedestrian
+    } else {
+        DirectionRules::Vehicle
+    };
+    build_graph(
+        nodes,
+        ways,
+        retain_all,
+        rules,
+        profile,
+        &["access"],
+        &HashSet::new(),
+    )
+}
+
+/// Shared graph builder. `access_keys` selects the `*:conditional` access
+/// tags that apply (see [`crate::conditional::access_keys`]); `protected`
+/// lists OSM node ids that simplification must keep as distinct endpoints
+/// (e.g. turn-restriction via nodes).
+fn build_graph(
+    nodes: Vec<XmlNode>,
+    ways: Vec<XmlWay>,
+    retain_all: bool,
+    rules: DirectionRules,
+    profile: &SpeedProfile,
+    access_keys: &[&str],
+    protected: &HashSet<i64>,
 ) -> DiGraph<XmlNode, XmlWay> {
     let mut graph = DiGraph::<XmlNode, XmlWay>::new();
     let mut node_index_map = HashMap::new();
+    // Barriers, signals and crossings keep their own node so that the costs
+    // charged around them stay put.
+    let protected: HashSet<i64> = protected
+        .iter()
+        .copied()
+        .chain(
+            nodes
+                .iter()
+                .filter(|node| node_has_cost(&node.tags, profile))
+                .map(|node| node.id),
+        )
+        .collect();
 
     // Add nodes to the graph and keep track of their indices
     for node in nodes {
@@ -204,131 +524,219 @@ pub fn create_graph(
         // Extract node refs before consuming `way` so that edge weights are stored
         // without the construction-only node list (saves memory for every edge in the graph).
         let node_refs = std::mem::take(&mut way.nodes);
-        let path_direction = assess_path_directionality(&way);
-        let speed_kph = way_speed_kph(&way);
+        let path_direction = match rules {
+            DirectionRules::Bicycle => assess_bicycle_directionality(&way),
+            DirectionRules::Vehicle | DirectionRules::Pedestrian => {
+                assess_path_directionality(&way)
+            }
+        };
+        let forward_speed_kph = way_speed_kph(&way, profile, true);
+        let backward_speed_kph = way_speed_kph(&way, profile, false);
+        let closures = way_closures(&way.tags, access_keys, rules != DirectionRules::Pedestrian);
+        // Pedestrians are not bound by `oneway`, so there is nothing to lift.
+        let contraflow = match rules {
+            DirectionRules::Pedestrian => None,
+            DirectionRules::Bicycle | DirectionRules::Vehicle => contraflow_closures(&way.tags),
+        };
+        let segment_length = |graph: &DiGraph<XmlNode, XmlWay>, start: i64, end: i64| {
+            let start_node = &graph[node_index_map[&start]];
+            let end_node = &graph[node_index_map[&end]];
+            calculate_distance(start_node.lat, start_node.lon, end_node.lat, end_node.lon)
+        };
+        // A ferry crossing takes the same time for every mode; it is spread
+        // over the way's segments by length. Returns (seconds, metres).
+        let ferry = way.is_ferry().then(|| {
+            let length: f64 = node_refs
+                .windows(2)
+                .map(|pair| segment_length(&graph, pair[0].node_id, pair[1].node_id))
+                .sum();
+            let duration = find_tag(&way.tags, "duration").map(|tag| tag.value.as_str());
+            (profile.ferry_time_s(duration, length), length)
+        });
+        let hourly_speeds = |forward: bool| {
+            profile
+                .hourly_speeds
+                .as_ref()
+                .and_then(|speeds| speeds.get(way.id, forward))
+                .map(|speeds| speeds.capped(profile.max_speed_kph))
+        };
+        let (forward_hourly, backward_hourly) = (hourly_speeds(true), hourly_speeds(false));
         let filtered_way = way.filter_useful_tags();
+        // An edge along (`forward`) or against the way's node order.
+        let make_edge = |length: f64, forward: bool| {
+            let (speed_kph, closed_during, hourly) = if forward {
+                (forward_speed_kph, &closures.forward, &forward_hourly)
+            } else {
+                (backward_speed_kph, &closures.backward, &backward_hourly)
+            };
+            let mut edge_way =
+                edge_way_from_template(&filtered_way, length, speed_kph, profile, closed_during);
+            edge_way.closed_by_default = closures.closed_by_default;
+            edge_way.hourly_speeds = hourly.clone();
+            if let Some((crossing_s, way_length)) = ferry {
+                let time_s = if way_length > 0.0 {
+                    crossing_s * length / way_length
+                } else {
+                    crossing_s
+                };
+                edge_way.walk_travel_time = time_s;
+                edge_way.bike_travel_time = time_s;
+                edge_way.drive_travel_time = time_s;
+                edge_way.speed_kph = if time_s > 0.0 {
+                    length / time_s * 3.6
+                } else {
+                    profile.ferry_speed_kph
+                };
+            }
+            edge_way
+        };
 
         for window in node_refs.windows(2) {
             if let [start_ref, end_ref] = window {
                 let start_index = node_index_map[&start_ref.node_id];
                 let end_index = node_index_map[&end_ref.node_id];
-                let (length, forward_geometry, reverse_geometry) = {
-                    let start_node = &graph[start_index];
-                    let end_node = &graph[end_index];
-                    let length = calculate_distance(
-                        start_node.lat,
-                        start_node.lon,
-                        end_node.lat,
-                        end_node.lon,
-                    );
-                    (
-                        length,
-                        vec![
-                            (start_node.lat, start_node.lon),
-                            (end_node.lat, end_node.lon),
-                        ],
-                        vec![
-                            (end_node.lat, end_node.lon),
-                            (start_node.lat, start_node.lon),
-                        ],
-                    )
-                };
+                let length = segment_length(&graph, start_ref.node_id, end_ref.node_id);
                 match path_direction {
                     Direction::OneWayForward => {
-                        let edge_way = edge_way_from_template(
-                            &filtered_way,
-                            length,
-                            speed_kph,
-                            forward_geometry.clone(),
-                        );
+                        let edge_way = make_edge(length, true);
                         graph.add_edge(start_index, end_index, edge_way);
                     }
                     Direction::OneWayReverse => {
-                        let reverse_way = edge_way_from_template(
-                            &filtered_way,
-                            length,
-                            speed_kph,
-                            reverse_geometry.clone(),
-                        );
+                        let reverse_way = make_edge(length, false);
                         graph.add_edge(end_index, start_index, reverse_way);
                     }
                     Direction::Bidirectional => {
-                        let edge_way = edge_way_from_template(
-                            &filtered_way,
-                            length,
-                            speed_kph,
-                            forward_geometry.clone(),
-                        );
-                        let reverse_way = edge_way_from_template(
-                            &filtered_way,
-                            length,
-                            speed_kph,
-                            reverse_geometry.clone(),
-                        );
+                        let edge_way = make_edge(length, true);
+                        let reverse_way = make_edge(length, false);
                         graph.add_edge(start_index, end_index, edge_way);
                         graph.add_edge(end_index, start_index, reverse_way);
                     }
                 }
 
-                if bidirectional && path_direction != Direction::Bidirectional {
-                    match path_direction {
+                // A one-way street that `oneway:conditional` opens both ways
+                // at times gets an against-the-flow edge closed otherwise.
+                if let Some(contraflow) = &contraflow {
+                    let (mut contraflow_way, source, target) = match path_direction {
                         Direction::OneWayForward => {
-                            let reverse_way = edge_way_from_template(
-                                &filtered_way,
-                                length,
-                                speed_kph,
-                                reverse_geometry.clone(),
-                            );
-                            graph.add_edge(end_index, start_index, reverse_way);
+                            (make_edge(length, false), end_index, start_index)
                         }
                         Direction::OneWayReverse => {
-                            let reverse_way = edge_way_from_template(
-                                &filtered_way,
-                                length,
-                                speed_kph,
-                                forward_geometry.clone(),
-                            );
-                            graph.add_edge(start_index, end_index, reverse_way);
+                            (make_edge(length, true), start_index, end_index)
                         }
-                        Direction::Bidirectional => {}
+                        Direction::Bidirectional => continue,
+                    };
+                    contraflow_way.closed_during.extend(contraflow);
+                    contraflow_way.closed_by_default = true;
+                    graph.add_edge(source, target, contraflow_way);
+                }
+
+                // Against-the-flow edges: always walkable for pedestrians;
+                // for cyclists only when the profile allows pushing the bike.
+                // A oneway ferry only sails one way, whatever the mode.
+                if ferry.is_some() {
+                    continue;
+                }
+                let push_speed_kph = match rules {
+                    DirectionRules::Pedestrian => None,
+                    DirectionRules::Bicycle => match profile.bike_push_speed_kph {
+                        Some(speed) => Some(speed),
+                        None => continue,
+                    },
+                    DirectionRules::Vehicle => continue,
+                };
+                let (mut reverse_way, reverse_source, reverse_target) = match path_direction {
+                    Direction::OneWayForward => (make_edge(length, false), end_index, start_index),
+                    Direction::OneWayReverse => (make_edge(length, true), start_index, end_index),
+                    Direction::Bidirectional => continue,
+                };
+                if let Some(push_speed_kph) = push_speed_kph {
+                    // Pushing is only offered where the surface can be cycled.
+                    if reverse_way.bike_travel_time.is_finite() {
+                        reverse_way.bike_travel_time =
+                            calculate_travel_time(length, push_speed_kph);
+                        reverse_way.bike_push_m = length;
                     }
+                    reverse_way.speed_kph = push_speed_kph;
+                    reverse_way.drive_travel_time = f64::INFINITY;
+                    reverse_way.hourly_speeds = None;
                 }
+                graph.add_edge(reverse_source, reverse_target, reverse_way);
             }
         }
     }
 
+    apply_node_costs(&mut graph, profile);
+
     // Simplify graph topology for faster downstream calculations
     // Consolidates distance and speed from
     if !retain_all {
-        graph = simplify_graph(&graph)
+        graph = simplify_graph(&graph, &protected)
     }
-    // ... other future logic
+    intern_graph_tags(&mut graph);
+    pack_edge_geometry(&mut graph);
 
     graph
 }
 
-fn clean_maxspeed(maxspeed: &str) -> Option<f64> {
-    let mph_to_kph = 1.60934;
-    let trimmed = maxspeed.trim();
-    let numeric_prefix: String = trimmed
-        .chars()
-        .take_while(|c| c.is_ascii_digit() || *c == '.')
-        .collect();
-    let speed = numeric_prefix.parse::<f64>().ok()?;
-    if speed <= 0.0 {
-        return None;
-    }
+pub fn node_to_latlon(graph: &DiGraph<XmlNode, XmlWay>, node_index: NodeIndex) -> (f64, f64) {
+    let node = &graph[node_index];
+    (node.lat, node.lon)
+}
 
-    if trimmed.to_ascii_lowercase().contains("mph") {
-        Some(speed * mph_to_kph)
+/// Geometry of `edge` as `(lat, lon)` points ordered from its source to its
+/// target. Falls back to the endpoint coordinates when the edge carries no
+/// shape points.
+pub(crate) fn directed_edge_geometry(
+    graph: &DiGraph<XmlNode, XmlWay>,
+    edge: EdgeIndex,
+) -> Vec<(f64, f64)> {
+    let (source, target) = graph.edge_endpoints(edge).unwrap();
+    let way = graph.edge_weight(edge).unwrap();
+    let mut points = if way.geometry.len() >= 2 {
+        way.geometry.to_vec()
     } else {
-        Some(speed)
+        vec![
+            (graph[source].lat, graph[source].lon),
+            (graph[target].lat, graph[target].lon),
+        ]
+    };
+    if !stored_geometry_is_forward(graph, edge, &points) {
+        points.reverse();
     }
+    points
 }
 
-pub fn node_to_latlon(graph: &DiGraph<XmlNode, XmlWay>, node_index: NodeIndex) -> (f64, f64) {
-    let node = &graph[node_index];
-    (node.lat, node.lon)
+/// Elevations of `edge` ordered like [`directed_edge_geometry`], or an empty
+/// vector when the edge carries no elevation samples.
+pub(crate) fn directed_edge_elevations(
+    graph: &DiGraph<XmlNode, XmlWay>,
+    edge: EdgeIndex,
+) -> Vec<f64> {
+    let way = graph.edge_weight(edge).unwrap();
+    if way.elevations_m.len() < 2 || way.elevations_m.len() != way.geometry.len() {
+        return Vec::new();
+    }
+    let mut elevations = way.elevations_m.clone();
+    if !stored_geometry_is_forward(graph, edge, &way.geometry) {
+        elevations.reverse();
+    }
+    elevations
+}
+
+fn stored_geometry_is_forward(
+    graph: &DiGraph<XmlNode, XmlWay>,
+    edge: EdgeIndex,
+    points: &[(f64, f64)],
+) -> bool {
+    let (source, target) = graph.edge_endpoints(edge).unwrap();
+    let source_point = (graph[source].lat, graph[source].lon);
+    let target_point = (graph[target].lat, graph[target].lon);
+    let first = *points.first().unwrap();
+    let last = *points.last().unwrap();
+    calculate_distance(first.0, first.1, source_point.0, source_point.1)
+        + calculate_distance(last.0, last.1, target_point.0, target_point.1)
+        <= calculate_distance(first.0, first.1, target_point.0, target_point.1)
+            + calculate_distance(last.0, last.1, source_point.0, source_point.1)
 }
 
 /// R-tree entry pairing a node's coordinates with its NodeIndex.
@@ -361,6 +769,103 @@ impl PointDistance for NodeEntry {
     }
 }
 
+/// R-tree entry for one straight segment of an edge's geometry, in the
+/// projected coordinates of [`spatial_index_point`].
+#[derive(Clone)]
+struct EdgeSegmentEntry {
+    from: [f64; 2],
+    to: [f64; 2],
+    edge: EdgeIndex,
+    /// Position of the segment within the edge's directed geometry.
+    segment: u32,
+}
+
+impl EdgeSegmentEntry {
+    /// Fraction of the way along the segment of its point closest to `point`.
+    fn projection(&self, point: &[f64; 2]) -> f64 {
+        let (dx, dy) = (self.to[0] - self.from[0], self.to[1] - self.from[1]);
+        let length_2 = dx * dx + dy * dy;
+        if length_2 == 0.0 {
+            return 0.0;
+        }
+        (((point[0] - self.from[0]) * dx + (point[1] - self.from[1]) * dy) / length_2)
+            .clamp(0.0, 1.0)
+    }
+}
+
+impl RTreeObject for EdgeSegmentEntry {
+    type Envelope = AABB<[f64; 2]>;
+    fn envelope(&self) -> Self::Envelope {
+        AABB::from_corners(self.from, self.to)
+    }
+}
+
+impl PointDistance for EdgeSegmentEntry {
+    fn distance_2(&self, point: &[f64; 2]) -> f64 {
+        let t = self.projection(point);
+        let dx = self.from[0] + (self.to[0] - self.from[0]) * t - point[0];
+        let dy = self.from[1] + (self.to[1] - self.from[1]) * t - point[1];
+        dx * dx + dy * dy
+    }
+}
+
+/// Segments of every edge's geometry. Of two edges running opposite ways
+/// over the same geometry only the one with the lower `(source, target)` OSM
+/// node ids is indexed, so snaps do not depend on edge insertion order.
+fn edge_segment_entries(graph: &DiGraph<XmlNode, XmlWay>) -> Vec<EdgeSegmentEntry> {
+    let osm_key = |edge: EdgeIndex| {
+        let (source, target) = graph.edge_endpoints(edge).unwrap();
+        (graph[source].id, graph[target].id, edge)
+    };
+    let mut entries = Vec::new();
+    for edge in graph.edge_indices() {
+        if reverse_twin(graph, edge).is_some_and(|twin| osm_key(twin) < osm_key(edge)) {
+            continue;
+        }
+        let points = directed_edge_geometry(graph, edge);
+        for (segment, pair) in points.windows(2).enumerate() {
+            entries.push(EdgeSegmentEntry {
+                from: spatial_index_point(pair[0].0, pair[0].1),
+                to: spatial_index_point(pair[1].0, pair[1].1),
+                edge,
+                segment: segment as u32,
+            });
+        }
+    }
+    entries
+}
+
+/// The edge running the opposite way over the same geometry as `edge`, as
+/// on a two-way street.
+pub(crate) fn reverse_twin(graph: &DiGraph<XmlNode, XmlWay>, edge: EdgeIndex) -> Option<EdgeIndex> {
+    let (source, target) = graph.edge_endpoints(edge)?;
+    let geometry = graph[edge].geometry.as_slice();
+    graph
+        .edges_connecting(target, source)
+        .map(|candidate| candidate.id())
+        .find(|&candidate| {
+            let other = graph[candidate].geometry.as_slice();
+            candidate != edge && (other == geometry || other.iter().eq(geometry.iter().rev()))
+        })
+}
+
+/// Length in metres of a `(lat, lon)` polyline.
+pub(crate) fn polyline_length(points: &[(f64, f64)]) -> f64 {
+    points
+        .windows(2)
+        .map(|pair| calculate_distance(pair[0].0, pair[0].1, pair[1].0, pair[1].1))
+        .sum()
+}
+
+/// Snaps within this many metres of an edge end start or end searches at
+/// the end node itself.
+const NODE_SNAP_TOLERANCE_M: f64 = 0.1;
+
+/// Where a coordinate meets the network.
+///
+/// Points are snapped to the closest point on any edge's geometry. The
+/// `node_*` fields describe the nearer end of that edge; on a graph without
+/// edges they describe the nearest node, and `edge_index` is `None`.
 #[derive(Debug, Clone, Copy)]
 pub struct SnapResult {
     pub input_lat: f64,
@@ -369,7 +874,19 @@ pub struct SnapResult {
     pub node_id: i64,
     pub node_lat: f64,
     pub node_lon: f64,
+    /// Distance in metres from the input coordinate to the snapped point.
     pub distance_m: f64,
+    /// The snapped point on the network.
+    pub snapped_lat: f64,
+    pub snapped_lon: f64,
+    /// The directed edge the point was snapped to. On a two-way street the
+    /// point equally lies on the edge running the other way.
+    pub edge_index: Option<EdgeIndex>,
+    /// OSM ids of the source and target nodes of `edge_index`.
+    pub edge_node_ids: Option<(i64, i64)>,
+    /// Distance in metres along the edge geometry from its source to the
+    /// snapped point.
+    pub edge_offset_m: f64,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -387,54 +904,266 @@ pub struct SnappedPoi {
 pub struct SpatialGraph {
     pub graph: Arc<DiGraph<XmlNode, XmlWay>>,
     tree: Arc<RTree<NodeEntry>>,
+    /// Edge geometry segments, for snapping to the closest point on an edge.
+    edge_tree: Arc<RTree<EdgeSegmentEntry>>,
     /// POI OSM node id → snapped graph node diagnostics, computed once at startup via
     /// `snap_pois`. `None` until called; `Some` map used by POI filtering
     /// for O(1) lookup instead of an R-tree query on every request.
     pub poi_snaps: Option<Arc<HashMap<i64, SnappedPoi>>>,
+    /// Turn restrictions resolved against `graph`. `None` when the graph has
+    /// none, in which case searches use the cheaper node-based variants.
+    turn_restrictions: Option<Arc<TurnRestrictions>>,
+    /// Turn penalties per network type plus the edge bearings they are
+    /// classified from. `None` until `with_turn_penalties` is called.
+    turn_penalties: Option<Arc<TurnPenaltyTable>>,
+    /// OSM node id and way id → graph index lookups, built with the spatial
+    /// index. Node ids include the synthetic ids of merged nodes.
+    id_index: Arc<IdIndex>,
+    /// Per-node component sizes, computed on first use.
+    pub(crate) component_sizes: Arc<ComponentSizes>,
+    /// Edges closed by `with_closures`. `None` when nothing is closed.
+    pub(crate) closures: Option<Arc<ClosureOverlay>>,
+    /// Contraction hierarchies per network type, built by
+    /// `with_contraction_hierarchy`.
+    pub(crate) hierarchies: Arc<HashMap<crate::overpass::NetworkType, Arc<ContractionHierarchy>>>,
+}
+
+#[derive(Debug, Default)]
+struct IdIndex {
+    nodes: HashMap<i64, NodeIndex>,
+    ways: HashMap<i64, Vec<EdgeIndex>>,
+}
+
+impl IdIndex {
+    fn build(graph: &DiGraph<XmlNode, XmlWay>) -> Self {
+        let nodes = graph
+            .node_indices()
+            .map(|index| (graph[index].id, index))
+            .collect();
+        let mut ways: HashMap<i64, Vec<EdgeIndex>> = HashMap::new();
+        for index in graph.edge_indices() {
+            let way = &graph[index];
+            // Collapsed edges are also found by their own synthetic id.
+            let ids = way.osm_way_ids().iter().chain(
+                (!way.way_ids.is_empty() && !way.way_ids.contains(&way.id)).then_some(&way.id),
+            );
+            for &way_id in ids {
+                let edges = ways.entry(way_id).or_default();
+                if edges.last() != Some(&index) {
+                    edges.push(index);
+                }
+            }
+        }
+        Self { nodes, ways }
+    }
+}
+
+#[derive(Clone)]
+struct TurnPenaltyTable {
+    by_network: HashMap<crate::overpass::NetworkType, TurnPenalties>,
+    geometry: TurnGeometry,
 }
 
 impl SpatialGraph {
     pub fn new(graph: DiGraph<XmlNode, XmlWay>) -> Self {
-        let entries: Vec<NodeEntry> = graph
+        let entries = graph
             .node_indices()
-            .map(|i| NodeEntry {
-                point: spatial_index_point(graph[i].lat, graph[i].lon),
-                index: i,
-            })
+            .map(|i| (spatial_index_point(graph[i].lat, graph[i].lon), i))
+            .collect();
+        Self::with_spatial_index(graph, entries)
+    }
+
+    /// Build from `graph` and precomputed spatial-index entries, as returned
+    /// by [`SpatialGraph::spatial_index_entries`].
+    pub(crate) fn with_spatial_index(
+        graph: DiGraph<XmlNode, XmlWay>,
+        entries: Vec<([f64; 2], NodeIndex)>,
+    ) -> Self {
+        let entries: Vec<NodeEntry> = entries
+            .into_iter()
+            .map(|(point, index)| NodeEntry { point, index })
             .collect();
         let tree = Arc::new(RTree::bulk_load(entries));
+        let edge_tree = Arc::new(RTree::bulk_load(edge_segment_entries(&graph)));
+        let id_index = Arc::new(IdIndex::build(&graph));
         let graph = Arc::new(graph);
         Self {
             graph,
             tree,
+            edge_tree,
             poi_snaps: None,
+            turn_restrictions: None,
+            turn_penalties: None,
+            id_index,
+            component_sizes: Arc::default(),
+            closures: None,
+            hierarchies: Arc::default(),
         }
     }
 
+    /// Contents of the spatial index as `(point, node)` pairs.
+    pub(crate) fn spatial_index_entries(&self) -> Vec<([f64; 2], NodeIndex)> {
+        self.tree
+            .iter()
+            .map(|entry| (entry.point, entry.index))
+            .collect()
+    }
+
     pub(crate) fn from_parsed_osm(
         data: XmlData,
         network_type: crate::overpass::NetworkType,
         retain_all: bool,
+        speed_profile: &SpeedProfile,
     ) -> Self {
-        let bidirectional = matches!(network_type, crate::overpass::NetworkType::Walk);
-        let graph = create_graph(data.nodes, data.ways, retain_all, bidirectional);
-        Self::new(graph)
+        let rules = DirectionRules::for_network(network_type);
+        let restrictions: Vec<TurnRestriction> = data
+            .relations
+            .iter()
+            .flat_map(|relation| TurnRestriction::from_relation(relation, network_type))
+            .collect();
+        let protected = protected_node_ids(&restrictions, &data.ways);
+        let graph = build_graph(
+            data.nodes,
+            data.ways,
+            retain_all,
+            rules,
+            speed_profile,
+            access_keys(network_type),
+            &protected,
+        );
+        Self::new(graph).with_turn_restrictions(restrictions)
+    }
+
+    /// Attach OSM turn restrictions to this graph.
+    ///
+    /// Restrictions are resolved against the current edges; ones whose ways or
+    /// via nodes are not part of the graph are kept but have no effect. Once
+    /// attached, `route`, `reachability`, `isochrones` and `prism` switch to an
+    /// edge-based search so that restricted turns are never taken. Each query
+    /// obeys only the restrictions whose [`RestrictionModes`] apply to its
+    /// network type, so walking ignores them all.
+    ///
+    /// [`RestrictionModes`]: crate::turns::RestrictionModes
+    pub fn with_turn_restrictions(mut self, restrictions: Vec<TurnRestriction>) -> Self {
+        self.turn_restrictions = if restrictions.is_empty() {
+            None
+        } else {
+            Some(Arc::new(TurnRestrictions::resolve(
+                &self.graph,
+                restrictions,
+            )))
+        };
+        self
+    }
+
+    /// The OSM turn restrictions attached to this graph.
+    pub fn turn_restrictions(&self) -> &[TurnRestriction] {
+        self.turn_restrictions
+            .as_deref()
+            .map(TurnRestrictions::restrictions)
+            .unwrap_or_default()
+    }
+
+    /// Charge `penalties` on every turn made by `network_type` searches.
+    ///
+    /// Once set, `route`, `reachability`, `isochrones` and `prism` for that
+    /// network type run an edge-based search that adds the penalty for each
+    /// left turn, right turn or U-turn to the travel time. Route durations and
+    /// cumulative times include the penalties. Use
+    /// [`TurnPenalties::for_network`] for suggested values.
+    pub fn with_turn_penalties(
+        mut self,
+        network_type: crate::overpass::NetworkType,
+        penalties: TurnPenalties,
+    ) -> Self {
+        let table = self.turn_penalties.get_or_insert_with(|| {
+            Arc::new(TurnPenaltyTable {
+                by_network: HashMap::new(),
+                geometry: TurnGeometry::new(&self.graph),
+            })
+        });
+        Arc::make_mut(table)
+            .by_network
+            .insert(network_type, penalties);
+        self
+    }
+
+    /// The turn penalties configured for `network_type`, if any.
+    pub fn turn_penalties(
+        &self,
+        network_type: crate::overpass::NetworkType,
+    ) -> Option<&TurnPenalties> {
+        self.turn_penalties
+            .as_deref()
+            .and_then(|table| table.by_network.get(&network_type))
+    }
+
+    /// Turn handling for a `network_type` search, or `None` when the graph
+    /// has neither effective restrictions nor penalties for it and the
+    /// cheaper node-based searches apply.
+    pub(crate) fn turn_model(
+        &self,
+        network_type: crate::overpass::NetworkType,
+    ) -> Option<TurnModel<'_>> {
+        let restrictions = self
+            .turn_restrictions
+            .as_deref()
+            .and_then(|restrictions| restrictions.for_network(network_type));
+        let penalties = self.turn_penalties.as_deref().and_then(|table| {
+            table
+                .by_network
+                .get(&network_type)
+                .map(|penalties| (penalties, &table.geometry))
+        });
+        (restrictions.is_some() || penalties.is_some()).then_some(TurnModel {
+            restrictions,
+            penalties,
+        })
+    }
+
+    /// Build a new graph over `subgraph` that keeps this graph's turn
+    /// restrictions, penalties and closures, re-resolved against the
+    /// subgraph's edges.
+    pub(crate) fn derive(&self, subgraph: DiGraph<XmlNode, XmlWay>) -> Self {
+        let mut derived =
+            Self::new(subgraph).with_turn_restrictions(self.turn_restrictions().to_vec());
+        if let Some(table) = &self.turn_penalties {
+            for (&network_type, &penalties) in &table.by_network {
+                derived = derived.with_turn_penalties(network_type, penalties);
+            }
+        }
+        derived.with_closures(&self.closures())
     }
 
     /// Parse an OSM XML response and build a [`SpatialGraph`].
+    ///
+    /// `speed_profile` sets the speeds used for edge travel times; `None`
+    /// uses [`SpeedProfile::default`].
     pub fn from_osm(
         xml: &str,
         network_type: crate::overpass::NetworkType,
         retain_all: Option<bool>,
+        speed_profile: Option<&SpeedProfile>,
     ) -> Result<Self, quick_xml::DeError> {
         let data = parse_xml(xml)?;
         Ok(Self::from_parsed_osm(
             data,
             network_type,
             retain_all.unwrap_or(false),
+            speed_profile.unwrap_or(&SpeedProfile::default()),
         ))
     }
 
+    /// Sample `dem` at every node and edge shape point and make walk and
+    /// bike travel times grade-aware; see [`crate::elevation::add_elevation`].
+    /// Routes, reachability and isochrones on the returned graph use the
+    /// adjusted times, and routes report an elevation profile.
+    pub fn with_elevation(mut self, dem: &Dem) -> Self {
+        add_elevation(Arc::make_mut(&mut self.graph), dem);
+        self.hierarchies = Arc::default();
+        self
+    }
+
     /// Pre-snap a set of POI nodes to their nearest graph nodes, storing the
     /// result for O(1) lookup at request time.
     ///
@@ -458,13 +1187,53 @@ impl SpatialGraph {
         self.poi_snaps = Some(Arc::new(snaps));
     }
 
+    /// Graph node with OSM id `node_id`, or the synthetic id of a merged
+    /// node. Constant time.
+    pub fn node_index_for_osm_id(&self, node_id: i64) -> Option<NodeIndex> {
+        self.id_index.nodes.get(&node_id).copied()
+    }
+
+    /// [`SpatialGraph::node_index_for_osm_id`] for each id in `node_ids`, in
+    /// order.
+    pub fn node_indices_for_osm_ids(&self, node_ids: &[i64]) -> Vec<Option<NodeIndex>> {
+        node_ids
+            .iter()
+            .map(|&node_id| self.node_index_for_osm_id(node_id))
+            .collect()
+    }
+
+    /// Directed edges covering OSM way `way_id`, in edge index order. Edges
+    /// collapsed by simplification are listed under every way they cover and
+    /// under their own synthetic id. Empty when the way is not in the graph.
+    pub fn edges_for_osm_way_id(&self, way_id: i64) -> &[EdgeIndex] {
+        self.id_index
+            .ways
+            .get(&way_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// [`SpatialGraph::edges_for_osm_way_id`] for each id in `way_ids`, in
+    /// order.
+    pub fn edges_for_osm_way_ids(&self, way_ids: &[i64]) -> Vec<&[EdgeIndex]> {
+        way_ids
+            .iter()
+            .map(|&way_id| self.edges_for_osm_way_id(way_id))
+            .collect()
+    }
+
     pub fn nearest_node(&self, lat: f64, lon: f64) -> Option<NodeIndex> {
         self.tree
             .nearest_neighbor(&spatial_index_point(lat, lon))
             .map(|e| e.index)
     }
 
+    /// Snap a coordinate to the closest point on an edge of the graph, or to
+    /// the nearest node when the graph has no edges.
     pub fn snap_point(&self, lat: f64, lon: f64) -> Option<SnapResult> {
+        if let Some((snap, _)) = self.edge_snaps(lat, lon).next() {
+            return Some(snap);
+        }
         self.nearest_node(lat, lon).map(|node_index| {
             let node = &self.graph[node_index];
             SnapResult {
@@ -475,11 +1244,143 @@ impl SpatialGraph {
                 node_lat: node.lat,
                 node_lon: node.lon,
                 distance_m: calculate_distance(lat, lon, node.lat, node.lon),
+                snapped_lat: node.lat,
+                snapped_lon: node.lon,
+                edge_index: None,
+                edge_node_ids: None,
+                edge_offset_m: 0.0,
             }
         })
     }
 
-    /// Snap a coordinate to the nearest graph node, optionally rejecting snaps
+    /// Snaps of `(lat, lon)` onto every indexed edge segment that is not
+    /// closed, closest first, each with its distance in the spatial index's
+    /// planar metres. An edge appears once per segment of its geometry.
+    pub(crate) fn edge_snaps(
+        &self,
+        lat: f64,
+        lon: f64,
+    ) -> impl Iterator<Item = (SnapResult, f64)> + '_ {
+        let query = spatial_index_point(lat, lon);
+        self.edge_tree
+            .nearest_neighbor_iter_with_distance_2(&query)
+            .filter(|(entry, _)| !self.is_edge_closed(entry.edge))
+            .map(move |(entry, distance_2)| {
+                (
+                    self.snap_to_segment(lat, lon, &query, entry),
+                    distance_2.sqrt(),
+                )
+            })
+    }
+
+    /// Indexed edges with a segment inside the `(lat, lon)` box from `min`
+    /// to `max`, each once. Of two edges running opposite ways over the same
+    /// street only one is indexed.
+    pub(crate) fn indexed_edges_within(
+        &self,
+        min: geo::Coord<f64>,
+        max: geo::Coord<f64>,
+    ) -> HashSet<EdgeIndex> {
+        // Planar longitudes shrink away from the equator, so the box's
+        // extremes lie at its corners or, when it spans the equator, on it.
+        let lats = [min.x, max.x, 0.0_f64.clamp(min.x, max.x)];
+        let corners: Vec<[f64; 2]> = lats
+            .iter()
+            .flat_map(|&lat| {
+                [
+                    spatial_index_point(lat, min.y),
+                    spatial_index_point(lat, max.y),
+                ]
+            })
+            .collect();
+        let lower = corners
+            .iter()
+            .fold([f64::INFINITY; 2], |a, c| [a[0].min(c[0]), a[1].min(c[1])]);
+        let upper = corners.iter().fold([f64::NEG_INFINITY; 2], |a, c| {
+            [a[0].max(c[0]), a[1].max(c[1])]
+        });
+        self.edge_tree
+            .locate_in_envelope_intersecting(&AABB::from_corners(lower, upper))
+            .map(|entry| entry.edge)
+            .collect()
+    }
+
+    fn snap_to_segment(
+        &self,
+        lat: f64,
+        lon: f64,
+        query: &[f64; 2],
+        entry: &EdgeSegmentEntry,
+    ) -> SnapResult {
+        let points = directed_edge_geometry(&self.graph, entry.edge);
+        let segment = entry.segment as usize;
+        let (from, to) = (points[segment], points[segment + 1]);
+        let t = entry.projection(query);
+        let (snapped_lat, snapped_lon) =
+            (from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t);
+        let edge_offset_m = polyline_length(&points[..=segment])
+            + t * calculate_distance(from.0, from.1, to.0, to.1);
+        let (source, target) = self.graph.edge_endpoints(entry.edge).unwrap();
+        let node_index = if edge_offset_m * 2.0 <= polyline_length(&points) {
+            source
+        } else {
+            target
+        };
+        let node = &self.graph[node_index];
+        SnapResult {
+            input_lat: lat,
+            input_lon: lon,
+            node_index,
+            node_id: node.id,
+            node_lat: node.lat,
+            node_lon: node.lon,
+            distance_m: calculate_distance(lat, lon, snapped_lat, snapped_lon),
+            snapped_lat,
+            snapped_lon,
+            edge_index: Some(entry.edge),
+            edge_node_ids: Some((self.graph[source].id, self.graph[target].id)),
+            edge_offset_m,
+        }
+    }
+
+    /// Search anchor for `snap`: its edge, and the edge running the other
+    /// way, split at the snapped point. Points at an edge end anchor at the
+    /// end node itself.
+    pub(crate) fn anchor(&self, snap: &SnapResult) -> Anchor {
+        let Some(edge) = snap.edge_index else {
+            return Anchor::node(&self.graph, snap.node_index);
+        };
+        let length = polyline_length(&directed_edge_geometry(&self.graph, edge));
+        if snap.edge_offset_m <= NODE_SNAP_TOLERANCE_M
+            || length - snap.edge_offset_m <= NODE_SNAP_TOLERANCE_M
+        {
+            return Anchor::node(&self.graph, snap.node_index);
+        }
+        let fraction = snap.edge_offset_m / length;
+        let mut edges = vec![(edge, fraction)];
+        if let Some(twin) = reverse_twin(&self.graph, edge) {
+            edges.push((twin, 1.0 - fraction));
+        }
+        Anchor {
+            node: snap.node_index,
+            lat: snap.snapped_lat,
+            lon: snap.snapped_lon,
+            edges,
+        }
+    }
+
+    /// [`SpatialGraph::snap_point_within`] as a search anchor.
+    pub(crate) fn anchor_within(
+        &self,
+        lat: f64,
+        lon: f64,
+        max_distance_m: Option<f64>,
+    ) -> Option<Anchor> {
+        self.snap_point_within(lat, lon, max_distance_m)
+            .map(|snap| self.anchor(&snap))
+    }
+
+    /// Snap a coordinate to the network, optionally rejecting snaps
     /// farther than `max_distance_m`.
     pub fn snap_point_within(
         &self,
@@ -516,6 +1417,7 @@ mod tests {
             lat,
             lon,
             tags: vec![],
+            elevation_m: None,
         }
     }
 
@@ -538,7 +1440,14 @@ mod tests {
             walk_travel_time: 0.0,
             bike_travel_time: 0.0,
             drive_travel_time: 0.0,
-            geometry: Vec::new(),
+            geometry: EdgeGeometry::default(),
+            way_ids: Vec::new(),
+            closed_during: Vec::new(),
+            closed_by_default: false,
+            node_delays: Default::default(),
+            bike_push_m: 0.0,
+            elevations_m: Vec::new(),
+            hourly_speeds: None,
         }
     }
 
@@ -600,6 +1509,109 @@ mod tests {
         assert_eq!(graph.edge_weights().next().unwrap().speed_kph, 30.0);
     }
 
+    #[test]
+    fn test_graph_resolves_implicit_maxspeed_tags() {
+        let cases = [
+            (vec![("maxspeed", "DE:rural")], 100.0),
+            (vec![("maxspeed", "RU:living_street")], 20.0),
+            (vec![("maxspeed:type", "FR:urban")], 50.0),
+            (vec![("zone:maxspeed", "DE:30")], 30.0),
+            (
+                vec![("maxspeed", "signals"), ("source:maxspeed", "DE:urban")],
+                50.0,
+            ),
+            (vec![("source:maxspeed", "sign")], 30.0),
+        ];
+        for (tags, expected) in cases {
+            let nodes = vec![make_node(1, 0.0, 0.0), make_node(2, 0.001, 0.0)];
+            let mut all_tags = vec![("highway", "residential"), ("oneway", "yes")];
+            all_tags.extend(tags.iter().copied());
+            let way = make_way_raw(vec![1, 2], all_tags);
+
+            let graph = create_graph(nodes, vec![way], true, false);
+
+            let speed = graph.edge_weights().next().unwrap().speed_kph;
+            assert_eq!(speed, expected, "tags={tags:?}");
+        }
+    }
+
+    #[test]
+    fn test_graph_applies_directional_maxspeed() {
+        let nodes = vec![make_node(1, 0.0, 0.0), make_node(2, 0.001, 0.0)];
+        let way = make_way_raw(
+            vec![1, 2],
+            vec![
+                ("highway", "primary"),
+                ("maxspeed", "70"),
+                ("maxspeed:backward", "50"),
+            ],
+        );
+
+        let graph = create_graph(nodes, vec![way], true, false);
+
+        for edge in graph.edge_references() {
+            let expected = if graph[edge.source()].id == 1 {
+                70.0
+            } else {
+                50.0
+            };
+            assert_eq!(edge.weight().speed_kph, expected);
+        }
+    }
+
+    #[test]
+    fn test_oneway_reverse_uses_backward_maxspeed() {
+        let nodes = vec![make_node(1, 0.0, 0.0), make_node(2, 0.001, 0.0)];
+        let way = make_way_raw(
+            vec![1, 2],
+            vec![
+                ("highway", "primary"),
+                ("oneway", "-1"),
+                ("maxspeed:forward", "90"),
+                ("maxspeed:backward", "40"),
+            ],
+        );
+
+        let graph = create_graph(nodes, vec![way], true, false);
+
+        assert_eq!(graph.edge_weights().next().unwrap().speed_kph, 40.0);
+    }
+
+    #[test]
+    fn test_graph_applies_speed_profile() {
+        let nodes = vec![make_node(1, 0.0, 0.0), make_node(2, 0.001, 0.0)];
+        let ways = vec![
+            make_way_raw(vec![1, 2], vec![("highway", "residential")]),
+            make_way_raw(
+                vec![2, 1],
+                vec![
+                    ("highway", "primary"),
+                    ("maxspeed", "100"),
+                    ("oneway", "yes"),
+                ],
+            ),
+        ];
+        let mut profile = SpeedProfile {
+            walk_speed_kph: 4.0,
+            bike_speed_kph: 20.0,
+            max_speed_kph: Some(80.0),
+            ..SpeedProfile::default()
+        };
+        profile
+            .highway_speeds_kph
+            .insert("residential".to_string(), 15.0);
+
+        let graph = create_graph_with_speeds(nodes, ways, true, false, &profile);
+
+        let mut speeds: Vec<f64> = graph.edge_weights().map(|way| way.speed_kph).collect();
+        speeds.sort_by(f64::total_cmp);
+        assert_eq!(speeds, vec![15.0, 15.0, 80.0]);
+        for way in graph.edge_weights() {
+            assert!((way.walk_travel_time - way.length / (4.0 / 3.6)).abs() < 1e-9);
+            assert!((way.bike_travel_time - way.length / (20.0 / 3.6)).abs() < 1e-9);
+        }
+    }
+
     #[test]
     fn test_oneway_produces_single_edge() {
         let nodes = vec![make_node(1, 0.0, 0.0), make_node(2, 0.001, 0.0)];
@@ -698,6 +1710,128 @@ mod tests {
         assert_eq!(graph.edge_count(), 2);
     }
 
+    fn bike_graph(tags: Vec<(&str, &str)>, profile: &SpeedProfile) -> DiGraph<XmlNode, XmlWay> {
+        let nodes = vec![make_node(1, 0.0, 0.0), make_node(2, 0.001, 0.0)];
+        let way = make_way_raw(vec![1, 2], tags);
+        build_graph(
+            nodes,
+            vec![way],
+            true,
+            DirectionRules::Bicycle,
+            profile,
+            &["access"],
+            &HashSet::new(),
+        )
+    }
+
+    #[test]
+    fn test_bicycle_contraflow_tags_open_reverse_direction() {
+        let profile = SpeedProfile::default();
+        for extra in [
+            ("oneway:bicycle", "no"),
+            ("cycleway", "opposite"),
+            ("cycleway:left", "opposite_lane"),
+            ("cycleway:right", "opposite_track"),
+            ("cycleway:left:oneway", "-1"),
+        ] {
+            let graph = bike_graph(
+                vec![("highway", "residential"), ("oneway", "yes"), extra],
+                &profile,
+            );
+            assert_eq!(edge_id_pairs(&graph), vec![(1, 2), (2, 1)], "{extra:?}");
+        }
+
+        let graph = bike_graph(
+            vec![("highway", "residential"), ("oneway", "yes")],
+            &profile,
+        );
+        assert_eq!(edge_id_pairs(&graph), vec![(1, 2)]);
+    }
+
+    #[test]
+    fn test_oneway_bicycle_restricts_cyclists_only() {
+        let tags = vec![("highway", "cycleway"), ("oneway:bicycle", "-1")];
+        let graph = bike_graph(tags.clone(), &SpeedProfile::default());
+        assert_eq!(edge_id_pairs(&graph), vec![(2, 1)]);
+
+        let nodes = vec![make_node(1, 0.0, 0.0), make_node(2, 0.001, 0.0)];
+        let graph = create_graph(nodes, vec![make_way_raw(vec![1, 2], tags)], true, false);
+        assert_eq!(edge_id_pairs(&graph), vec![(1, 2), (2, 1)]);
+
+        let nodes = vec![make_node(1, 0.0, 0.0), make_node(2, 0.001, 0.0)];
+        let way = make_way_raw(
+            vec![1, 2],
+            vec![
+                ("highway", "residential"),
+                ("oneway", "yes"),
+                ("cycleway", "opposite_lane"),
+            ],
+        );
+        let graph = create_graph(nodes, vec![way], true, false);
+        assert_eq!(edge_id_pairs(&graph), vec![(1, 2)]);
+    }
+
+    #[test]
+    fn test_bike_push_edges_run_at_push_speed() {
+        let profile = SpeedProfile {
+            bike_push_speed_kph: Some(4.0),
+            ..SpeedProfile::default()
+        };
+        let graph = bike_graph(
+            vec![("highway", "residential"), ("oneway", "yes")],
+            &profile,
+        );
+        assert_eq!(edge_id_pairs(&graph), vec![(1, 2), (2, 1)]);
+
+        for edge in graph.edge_references() {
+            let way = edge.weight();
+            if graph[edge.source()].id == 1 {
+                assert!((way.bike_travel_time - way.length / (15.0 / 3.6)).abs() < 1e-9);
+                assert!(way.drive_travel_time.is_finite());
+            } else {
+                assert!((way.bike_travel_time - way.length / (4.0 / 3.6)).abs() < 1e-9);
+                assert_eq!(way.drive_travel_time, f64::INFINITY);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bike_times_follow_surface_factors() {
+        let bike_time = |tags: Vec<(&str, &str)>, profile: &SpeedProfile| {
+            let graph = bike_graph(tags, profile);
+            let way = graph.edge_weights().next().unwrap();
+            (way.length, way.bike_travel_time)
+        };
+        let profile = SpeedProfile::default();
+
+        let (length, asphalt) =
+            bike_time(vec![("highway", "track"), ("surface", "asphalt")], &profile);
+        assert!((asphalt - length / (15.0 / 3.6)).abs() < 1e-9);
+        let (_, gravel) = bike_time(vec![("highway", "track"), ("surface", "gravel")], &profile);
+        assert!((gravel - length / (15.0 * 0.7 / 3.6)).abs() < 1e-9);
+        let (_, rough) = bike_time(
+            vec![
+                ("highway", "track"),
+                ("tracktype", "grade2"),
+                ("smoothness", "very_bad"),
+            ],
+            &profile,
+        );
+        assert!((rough - length / (15.0 * 0.5 / 3.6)).abs() < 1e-9);
+
+        let road_bike = SpeedProfile::road_bike();
+        let (_, gravel) = bike_time(
+            vec![("highway", "track"), ("surface", "gravel")],
+            &road_bike,
+        );
+        assert_eq!(gravel, f64::INFINITY);
+        let (_, compacted) = bike_time(
+            vec![("highway", "track"), ("surface", "compacted")],
+            &road_bike,
+        );
+        assert!(compacted.is_finite());
+    }
+
     #[test]
     fn test_nearest_node_finds_closest() {
         let mut graph = DiGraph::new();
@@ -732,6 +1866,98 @@ mod tests {
         assert!(sg.snap_point_within(48.001, 11.001, Some(1.0)).is_none());
     }
 
+    #[test]
+    fn test_snap_point_projects_onto_edge_geometry() {
+        // One two-way edge bending through (48.001, 11.001).
+        let mut graph = DiGraph::new();
+        let a = graph.add_node(make_node(1, 48.0, 11.0));
+        let b = graph.add_node(make_node(2, 48.0, 11.002));
+        let geometry = vec![(48.0, 11.0), (48.001, 11.001), (48.0, 11.002)];
+        let mut reversed = geometry.clone();
+        reversed.reverse();
+        let mut way = make_way_raw(vec![1, 2], vec![("highway", "residential")]);
+        way.geometry = geometry.into();
+        let ab = graph.add_edge(a, b, way.clone());
+        way.geometry = reversed.into();
+        let ba = graph.add_edge(b, a, way);
+        let sg = SpatialGraph::new(graph);
+
+        let snap = sg.snap_point(48.0011, 11.001).unwrap();
+
+        assert_eq!(snap.edge_index, Some(ab));
+        assert_eq!(snap.edge_node_ids, Some((1, 2)));
+        assert!((snap.snapped_lat - 48.001).abs() < 1e-9);
+        assert!((snap.snapped_lon - 11.001).abs() < 1e-9);
+        assert!((snap.distance_m - 11.1).abs() < 0.1);
+        let half = calculate_distance(48.0, 11.0, 48.001, 11.001);
+        assert!((snap.edge_offset_m - half).abs() < 1e-6);
+        assert_eq!(reverse_twin(&sg.graph, ab), Some(ba));
+
+        let anchor = sg.anchor(&snap);
+        assert_eq!(anchor.edges.len(), 2);
+        assert!((anchor.fraction_on(ba).unwrap() - 0.5).abs() < 1e-9);
+        // Snapping onto an edge end anchors at that node.
+        let end = sg.snap_point(47.999, 10.999).unwrap();
+        assert_eq!(end.node_id, 1);
+        assert!(sg.anchor(&end).is_node());
+    }
+
+    #[test]
+    fn test_snap_point_indexes_twin_independent_of_insertion_order() {
+        // The edge from node 2 to node 1 is added first.
+        let mut graph = DiGraph::new();
+        let a = graph.add_node(make_node(1, 48.0, 11.0));
+        let b = graph.add_node(make_node(2, 48.0, 11.002));
+        let way = make_way_raw(vec![1, 2], vec![("highway", "residential")]);
+        graph.add_edge(b, a, way.clone());
+        let ab = graph.add_edge(a, b, way);
+        let sg = SpatialGraph::new(graph);
+
+        let snap = sg.snap_point(48.0, 11.0).unwrap();
+
+        assert_eq!(snap.edge_index, Some(ab));
+        assert_eq!(snap.edge_offset_m, 0.0);
+    }
+
+    #[test]
+    fn test_id_index_looks_up_nodes_and_ways() {
+        // Way 10 runs 1-2-3 and is collapsed into one edge per direction;
+        // way 11 continues 3-4.
+        let xml = r#"<osm>
+              <node id="1" lat="48.000" lon="11.000" />
+              <node id="2" lat="48.000" lon="11.001" />
+              <node id="3" lat="48.000" lon="11.002" />
+              <node id="4" lat="48.001" lon="11.002" />
+              <node id="5" lat="48.000" lon="11.003" />
+              <way id="10"><nd ref="1" /><nd ref="2" /><nd ref="3" />
+                <tag k="highway" v="residential" /></way>
+              <way id="11"><nd ref="3" /><nd ref="4" /><tag k="highway" v="residential" /></way>
+              <way id="12"><nd ref="3" /><nd ref="5" /><tag k="highway" v="residential" /></way>
+            </osm>"#;
+        let sg =
+            SpatialGraph::from_osm(xml, crate::overpass::NetworkType::Drive, None, None).unwrap();
+
+        let one = sg.node_index_for_osm_id(1).unwrap();
+        assert_eq!(sg.graph[one].id, 1);
+        assert_eq!(
+            sg.node_indices_for_osm_ids(&[3, 2, 99]),
+            vec![sg.node_index_for_osm_id(3), None, None]
+        );
+        for index in sg.graph.node_indices() {
+            assert_eq!(sg.node_index_for_osm_id(sg.graph[index].id), Some(index));
+        }
+
+        let edges = sg.edges_for_osm_way_ids(&[10, 11, 99]);
+        assert_eq!(
+            edges.iter().map(|e| e.len()).collect::<Vec<_>>(),
+            vec![2, 2, 0]
+        );
+        for &edge in edges[0] {
+            assert!(sg.graph[edge].osm_way_ids().contains(&10));
+            assert_eq!(sg.edges_for_osm_way_id(sg.graph[edge].id), &[edge]);
+        }
+    }
+
     #[test]
     fn test_parse_xml_minimal_osm_fixture() {
         let xml = r#"
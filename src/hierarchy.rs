@@ -0,0 +1,810 @@
+//! Contraction hierarchies for fast point-to-point routes.
+//!
+//! A* settles a large share of a regional graph before it reaches a distant
+//! destination. [`SpatialGraph::with_contraction_hierarchy`] pays that cost
+//! once: nodes are contracted one by one, least important first, and a
+//! shortcut is added wherever the only shortest path between two neighbours
+//! ran through the contracted node. A query then searches upward from both
+//! ends and meets after settling a few hundred nodes, and its shortcuts are
+//! unpacked into the original edges, so the route is the same as plain A*.
+//! A travel time matrix runs the upward search of each destination once and
+//! leaves its costs in buckets at the nodes it settles, so each origin needs
+//! just one upward search that scans them.
+//!
+//! The hierarchy is built from the static travel times of one network type
+//! and ignores turns. `route` falls back to A* when the graph has closures or
+//! turn penalties for that network type, when the query has a departure time,
+//! and when the hierarchy's route takes a restricted turn.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::Arc;
+
+use petgraph::graph::{DiGraph, EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
+
+use crate::graph::{SpatialGraph, XmlNode, XmlWay};
+use crate::overpass::NetworkType;
+use crate::reachability::EdgeInfo;
+use crate::search::{edge_info, same_edge_path, Anchor, SearchDirection, SearchPath};
+
+/// Nodes a witness search may settle before it gives up and keeps the
+/// shortcut. Extra shortcuts cost memory, never correctness.
+const WITNESS_SETTLE_LIMIT: usize = 500;
+
+/// A directed link of the hierarchy: an original edge, or a shortcut
+/// standing for two consecutive links through a contracted node.
+#[derive(Debug, Clone, Copy)]
+struct Link {
+    from: NodeIndex,
+    to: NodeIndex,
+    cost: f64,
+    via: Via,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Via {
+    Edge(EdgeIndex),
+    Shortcut(u32, u32),
+}
+
+/// A contraction hierarchy over one graph for one network type.
+#[derive(Debug)]
+pub struct ContractionHierarchy {
+    links: Vec<Link>,
+    /// Links from each node to higher-ranked nodes, for the forward search.
+    up: Vec<Vec<u32>>,
+    /// Links into each node from higher-ranked nodes, for the backward search.
+    down: Vec<Vec<u32>>,
+}
+
+impl ContractionHierarchy {
+    /// Contract every node of `graph`, with edge costs from `cost`. Edges
+    /// whose cost is infinite or negative are left out.
+    fn build<F>(graph: &DiGraph<XmlNode, XmlWay>, mut cost: F) -> Self
+    where
+        F: FnMut(EdgeInfo<'_>) -> f64,
+    {
+        let node_count = graph.node_count();
+        let mut links: Vec<Link> = Vec::new();
+        // Remaining graph: neighbour → cheapest link, per direction.
+        let mut outgoing: Vec<HashMap<NodeIndex, u32>> = vec![HashMap::new(); node_count];
+        let mut incoming: Vec<HashMap<NodeIndex, u32>> = vec![HashMap::new(); node_count];
+        for edge in graph.edge_references() {
+            let (from, to) = (edge.source(), edge.target());
+            let edge_cost = cost(edge_info(graph, edge.id()));
+            if from == to || !edge_cost.is_finite() || edge_cost < 0.0 {
+                continue;
+            }
+            add_link(
+                &mut links,
+                &mut outgoing,
+                &mut incoming,
+                Link {
+                    from,
+                    to,
+                    cost: edge_cost,
+                    via: Via::Edge(edge.id()),
+                },
+            );
+        }
+
+        let mut witness = WitnessSearch::new(node_count);
+        let mut contracted = vec![false; node_count];
+        let mut deleted_neighbours = vec![0i64; node_count];
+        let mut up = vec![Vec::new(); node_count];
+        let mut down = vec![Vec::new(); node_count];
+        let priority = |node: NodeIndex,
+                        shortcuts: usize,
+                        outgoing: &[HashMap<NodeIndex, u32>],
+                        incoming: &[HashMap<NodeIndex, u32>],
+                        deleted_neighbours: &[i64]| {
+            shortcuts as i64 - (outgoing[node.index()].len() + incoming[node.index()].len()) as i64
+                + deleted_neighbours[node.index()]
+        };
+
+        let mut queue: BinaryHeap<Reverse<(i64, usize)>> = graph
+            .node_indices()
+            .map(|node| {
+                let shortcuts = shortcuts_for(node, &mut witness, &links, &outgoing, &incoming);
+                Reverse((
+                    priority(
+                        node,
+                        shortcuts.len(),
+                        &outgoing,
+                        &incoming,
+                        &deleted_neighbours,
+                    ),
+                    node.index(),
+                ))
+            })
+            .collect();
+
+        while let Some(Reverse((_, index))) = queue.pop() {
+            if contracted[index] {
+                continue;
+            }
+            let node = NodeIndex::new(index);
+            // Priorities go stale as neighbours are contracted; recheck
+            // before committing to this node.
+            let shortcuts = shortcuts_for(node, &mut witness, &links, &outgoing, &incoming);
+            let current = priority(
+                node,
+                shortcuts.len(),
+                &outgoing,
+                &incoming,
+                &deleted_neighbours,
+            );
+            if queue
+                .peek()
+                .is_some_and(|&Reverse((next, _))| current > next)
+            {
+                queue.push(Reverse((current, index)));
+                continue;
+            }
+
+            contracted[index] = true;
+            up[index] = outgoing[index].values().copied().collect();
+            down[index] = incoming[index].values().copied().collect();
+            for neighbour in outgoing[index].keys().chain(incoming[index].keys()) {
+                deleted_neighbours[neighbour.index()] += 1;
+            }
+            for &link in &up[index] {
+                incoming[links[link as usize].to.index()].remove(&node);
+            }
+            for &link in &down[index] {
+                outgoing[links[link as usize].from.index()].remove(&node);
+            }
+            for shortcut in shortcuts {
+                add_link(&mut links, &mut outgoing, &mut incoming, shortcut);
+            }
+        }
+
+        Self { links, up, down }
+    }
+
+    /// The cheapest path from `origin` to `destination`, or `None` when
+    /// there is none. `cost` must be the cost the hierarchy was built with;
+    /// it prices the partial edges of anchors part-way along an edge.
+    pub(crate) fn shortest_path<F>(
+        &self,
+        graph: &DiGraph<XmlNode, XmlWay>,
+        origin: &Anchor,
+        destination: &Anchor,
+        mut cost: F,
+    ) -> Option<SearchPath>
+    where
+        F: FnMut(EdgeInfo<'_>, f64) -> f64,
+    {
+        let same_edge = same_edge_path(graph, origin, destination, &mut cost);
+        let mut forward = Search::from_anchor(graph, origin, SearchDirection::Forward, &mut cost);
+        let mut backward =
+            Search::from_anchor(graph, destination, SearchDirection::Backward, &mut cost);
+
+        // Cheapest meeting found so far: (cost, node).
+        let mut best: Option<(f64, NodeIndex)> = None;
+        loop {
+            // Each direction stops once it cannot improve on the best
+            // meeting; until then the cheaper one goes next.
+            let bound = best.map_or(f64::INFINITY, |(cost, _)| cost);
+            let next_forward = forward.peek().filter(|&cost| cost < bound);
+            let next_backward = backward.peek().filter(|&cost| cost < bound);
+            let go_forward = match (next_forward, next_backward) {
+                (None, None) => break,
+                (Some(forward), Some(backward)) => forward <= backward,
+                (forward, _) => forward.is_some(),
+            };
+            let (search, other, direction) = if go_forward {
+                (&mut forward, &backward, SearchDirection::Forward)
+            } else {
+                (&mut backward, &forward, SearchDirection::Backward)
+            };
+            let Some((node, node_cost)) = self.settle_next(search, direction) else {
+                continue;
+            };
+            if let Some(&(other_cost, _)) = other.labels.get(&node) {
+                if best.is_none_or(|(cost, _)| node_cost + other_cost < cost) {
+                    best = Some((node_cost + other_cost, node));
+                }
+            }
+        }
+
+        match best {
+            Some((cost, meeting)) if same_edge.as_ref().is_none_or(|path| path.cost > cost) => {
+                Some(self.join(&forward, &backward, meeting, cost, origin, destination))
+            }
+            _ => same_edge,
+        }
+    }
+
+    /// Search upward from every destination ahead of a many-to-many query;
+    /// see [`DestinationBuckets::paths_from`]. `cost` must be the cost the
+    /// hierarchy was built with.
+    pub(crate) fn destination_buckets<'a, F>(
+        &'a self,
+        graph: &DiGraph<XmlNode, XmlWay>,
+        destinations: &'a [Option<Anchor>],
+        mut cost: F,
+    ) -> DestinationBuckets<'a>
+    where
+        F: FnMut(EdgeInfo<'_>, f64) -> f64,
+    {
+        let mut buckets: HashMap<NodeIndex, Vec<(usize, f64)>> = HashMap::new();
+        let searches = destinations
+            .iter()
+            .enumerate()
+            .map(|(index, destination)| {
+                let destination = destination.as_ref()?;
+                let mut search =
+                    Search::from_anchor(graph, destination, SearchDirection::Backward, &mut cost);
+                while let Some((node, node_cost)) =
+                    self.settle_next(&mut search, SearchDirection::Backward)
+                {
+                    buckets.entry(node).or_default().push((index, node_cost));
+                }
+                Some((destination, search))
+            })
+            .collect();
+        DestinationBuckets {
+            hierarchy: self,
+            searches,
+            buckets,
+        }
+    }
+
+    /// Settle the next node of `search` and relax its links: up the
+    /// hierarchy searching forward, down into it searching backward.
+    fn settle_next(
+        &self,
+        search: &mut Search,
+        direction: SearchDirection,
+    ) -> Option<(NodeIndex, f64)> {
+        let (node, node_cost) = search.pop()?;
+        let links_of = match direction {
+            SearchDirection::Forward => &self.up,
+            SearchDirection::Backward => &self.down,
+        };
+        for &link in &links_of[node.index()] {
+            let Link { from, to, cost, .. } = self.links[link as usize];
+            let next = match direction {
+                SearchDirection::Forward => to,
+                SearchDirection::Backward => from,
+            };
+            search.relax(next, node_cost + cost, link);
+        }
+        Some((node, node_cost))
+    }
+
+    /// The path of `cost` from `origin` to `destination` through `meeting`,
+    /// a node both searches settled.
+    fn join(
+        &self,
+        forward: &Search,
+        backward: &Search,
+        meeting: NodeIndex,
+        cost: f64,
+        origin: &Anchor,
+        destination: &Anchor,
+    ) -> SearchPath {
+        let (first_edge, mut forward_links) = forward.trace(meeting, |link| self.links[link].from);
+        forward_links.reverse();
+        let (last_edge, backward_links) = backward.trace(meeting, |link| self.links[link].to);
+        let mut edges: Vec<EdgeIndex> = first_edge.into_iter().collect();
+        for link in forward_links.into_iter().chain(backward_links) {
+            self.unpack(link, &mut edges);
+        }
+        edges.extend(last_edge);
+        SearchPath {
+            cost,
+            start_fraction: first_edge
+                .and_then(|edge| origin.fraction_on(edge))
+                .unwrap_or(0.0),
+            end_fraction: last_edge
+                .and_then(|edge| destination.fraction_on(edge))
+                .unwrap_or(1.0),
+            edges,
+        }
+    }
+
+    /// Append the original edges behind `link` to `edges`, in travel order.
+    fn unpack(&self, link: u32, edges: &mut Vec<EdgeIndex>) {
+        let mut stack = vec![link];
+        while let Some(link) = stack.pop() {
+            match self.links[link as usize].via {
+                Via::Edge(edge) => edges.push(edge),
+                Via::Shortcut(first, second) => {
+                    stack.push(second);
+                    stack.push(first);
+                }
+            }
+        }
+    }
+}
+
+/// Add `link` to the remaining graph unless a link at most as cheap already
+/// joins the same nodes.
+fn add_link(
+    links: &mut Vec<Link>,
+    outgoing: &mut [HashMap<NodeIndex, u32>],
+    incoming: &mut [HashMap<NodeIndex, u32>],
+    link: Link,
+) {
+    if let Some(&existing) = outgoing[link.from.index()].get(&link.to) {
+        if links[existing as usize].cost <= link.cost {
+            return;
+        }
+    }
+    let id = links.len() as u32;
+    links.push(link);
+    outgoing[link.from.index()].insert(link.to, id);
+    incoming[link.to.index()].insert(link.from, id);
+}
+
+/// Shortcuts needed to contract `node`: one for each pair of neighbours
+/// whose cheapest connection runs through it.
+fn shortcuts_for(
+    node: NodeIndex,
+    witness: &mut WitnessSearch,
+    links: &[Link],
+    outgoing: &[HashMap<NodeIndex, u32>],
+    incoming: &[HashMap<NodeIndex, u32>],
+) -> Vec<Link> {
+    let mut shortcuts = Vec::new();
+    let exits = &outgoing[node.index()];
+    if exits.is_empty() {
+        return shortcuts;
+    }
+    let max_exit = exits
+        .values()
+        .map(|&link| links[link as usize].cost)
+        .fold(0.0, f64::max);
+    for (&source, &entry) in &incoming[node.index()] {
+        let entry_cost = links[entry as usize].cost;
+        witness.run(source, node, exits, entry_cost + max_exit, links, outgoing);
+        for (&target, &exit) in exits {
+            if target == source {
+                continue;
+            }
+            let via_cost = entry_cost + links[exit as usize].cost;
+            if witness.cost(target) > via_cost {
+                shortcuts.push(Link {
+                    from: source,
+                    to: target,
+                    cost: via_cost,
+                    via: Via::Shortcut(entry, exit),
+                });
+            }
+        }
+    }
+    shortcuts
+}
+
+/// Dijkstra over the uncontracted graph, with buffers reused between the
+/// many small searches of a contraction.
+struct WitnessSearch {
+    costs: Vec<f64>,
+    touched: Vec<NodeIndex>,
+    heap: BinaryHeap<State>,
+}
+
+impl WitnessSearch {
+    fn new(node_count: usize) -> Self {
+        Self {
+            costs: vec![f64::INFINITY; node_count],
+            touched: Vec::new(),
+            heap: BinaryHeap::new(),
+        }
+    }
+
+    /// Costs from `source` within `max_cost` without passing `avoid`. Stops
+    /// once every node of `targets` is settled, or after
+    /// [`WITNESS_SETTLE_LIMIT`] nodes.
+    fn run(
+        &mut self,
+        source: NodeIndex,
+        avoid: NodeIndex,
+        targets: &HashMap<NodeIndex, u32>,
+        max_cost: f64,
+        links: &[Link],
+        outgoing: &[HashMap<NodeIndex, u32>],
+    ) {
+        for node in self.touched.drain(..) {
+            self.costs[node.index()] = f64::INFINITY;
+        }
+        self.heap.clear();
+        self.costs[source.index()] = 0.0;
+        self.touched.push(source);
+        self.heap.push(State {
+            cost: 0.0,
+            node: source,
+        });
+
+        let mut settled = 0;
+        let mut targets_left = targets.len() - usize::from(targets.contains_key(&source));
+        while let Some(State { cost, node }) = self.heap.pop() {
+            if cost > self.costs[node.index()] {
+                continue;
+            }
+            settled += 1;
+            if node != source && targets.contains_key(&node) {
+                targets_left -= 1;
+            }
+            if cost > max_cost || settled > WITNESS_SETTLE_LIMIT || targets_left == 0 {
+                break;
+            }
+            for (&next, &link) in &outgoing[node.index()] {
+                let next_cost = cost + links[link as usize].cost;
+                if next != avoid && next_cost < self.costs[next.index()] {
+                    if self.costs[next.index()].is_infinite() {
+                        self.touched.push(next);
+                    }
+                    self.costs[next.index()] = next_cost;
+                    self.heap.push(State {
+                        cost: next_cost,
+                        node: next,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Cheapest cost found to `node` by the last run, settled or not.
+    fn cost(&self, node: NodeIndex) -> f64 {
+        self.costs[node.index()]
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct State {
+    cost: f64,
+    node: NodeIndex,
+}
+
+impl PartialEq for State {
+    fn eq(&self, other: &Self) -> bool {
+        self.cost == other.cost && self.node == other.node
+    }
+}
+
+impl Eq for State {}
+
+impl PartialOrd for State {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for State {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .partial_cmp(&self.cost)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+/// The upward searches from the destinations of a many-to-many query,
+/// shared by all of its origins: each node a destination's search settled
+/// holds a bucket entry with the cost from the node to that destination.
+pub(crate) struct DestinationBuckets<'a> {
+    hierarchy: &'a ContractionHierarchy,
+    /// Each destination with its search, or `None` for a missing one.
+    searches: Vec<Option<(&'a Anchor, Search)>>,
+    /// Node → (destination, cost from the node to the destination).
+    buckets: HashMap<NodeIndex, Vec<(usize, f64)>>,
+}
+
+impl DestinationBuckets<'_> {
+    /// The cheapest path from `origin` to each destination, or `None` where
+    /// there is none. A single upward search from `origin` scans the bucket
+    /// of every node it settles, so the whole row costs one search instead
+    /// of one per destination. `cost` is as for
+    /// [`ContractionHierarchy::destination_buckets`].
+    pub(crate) fn paths_from<F>(
+        &self,
+        graph: &DiGraph<XmlNode, XmlWay>,
+        origin: &Anchor,
+        mut cost: F,
+    ) -> Vec<Option<SearchPath>>
+    where
+        F: FnMut(EdgeInfo<'_>, f64) -> f64,
+    {
+        let hierarchy = self.hierarchy;
+        let mut forward = Search::from_anchor(graph, origin, SearchDirection::Forward, &mut cost);
+        // Cheapest meeting per destination: (cost, node).
+        let mut best: Vec<Option<(f64, NodeIndex)>> = vec![None; self.searches.len()];
+        while let Some((node, node_cost)) =
+            hierarchy.settle_next(&mut forward, SearchDirection::Forward)
+        {
+            for &(index, to_destination) in self.buckets.get(&node).into_iter().flatten() {
+                let total = node_cost + to_destination;
+                if best[index].is_none_or(|(cost, _)| total < cost) {
+                    best[index] = Some((total, node));
+                }
+            }
+        }
+
+        self.searches
+            .iter()
+            .zip(best)
+            .map(|(search, best)| {
+                let (destination, backward) = search.as_ref()?;
+                let same_edge = same_edge_path(graph, origin, destination, &mut cost);
+                match best {
+                    Some((cost, meeting))
+                        if same_edge.as_ref().is_none_or(|path| path.cost > cost) =>
+                    {
+                        Some(hierarchy.join(&forward, backward, meeting, cost, origin, destination))
+                    }
+                    _ => same_edge,
+                }
+            })
+            .collect()
+    }
+}
+
+/// How a query search reached a node.
+#[derive(Clone, Copy, Debug)]
+enum Parent {
+    /// A start of the search, entered along the anchor's partial edge if any.
+    Seed(Option<EdgeIndex>),
+    Link(u32),
+}
+
+/// One direction of a bidirectional hierarchy query.
+#[derive(Default)]
+struct Search {
+    labels: HashMap<NodeIndex, (f64, Parent)>,
+    heap: BinaryHeap<State>,
+}
+
+impl Search {
+    /// A search starting at `anchor`: at its node, or where its partial
+    /// edges lead to searching in `direction`.
+    fn from_anchor<F>(
+        graph: &DiGraph<XmlNode, XmlWay>,
+        anchor: &Anchor,
+        direction: SearchDirection,
+        cost: F,
+    ) -> Self
+    where
+        F: FnMut(EdgeInfo<'_>, f64) -> f64,
+    {
+        let mut search = Self::default();
+        if anchor.is_node() {
+            search.seed(anchor.node, 0.0, None);
+        }
+        for (edge, edge_cost) in anchor.partial_edges(graph, direction, cost) {
+            let (source, target) = graph.edge_endpoints(edge).unwrap();
+            let node = match direction {
+                SearchDirection::Forward => target,
+                SearchDirection::Backward => source,
+            };
+            search.seed(node, edge_cost, Some(edge));
+        }
+        search
+    }
+
+    fn seed(&mut self, node: NodeIndex, cost: f64, edge: Option<EdgeIndex>) {
+        self.update(node, cost, Parent::Seed(edge));
+    }
+
+    fn relax(&mut self, node: NodeIndex, cost: f64, link: u32) {
+        self.update(node, cost, Parent::Link(link));
+    }
+
+    fn update(&mut self, node: NodeIndex, cost: f64, parent: Parent) {
+        if self
+            .labels
+            .get(&node)
+            .is_none_or(|&(known, _)| cost < known)
+        {
+            self.labels.insert(node, (cost, parent));
+            self.heap.push(State { cost, node });
+        }
+    }
+
+    /// Cost of the next node to settle.
+    fn peek(&mut self) -> Option<f64> {
+        while let Some(&State { cost, node }) = self.heap.peek() {
+            if cost > self.labels[&node].0 {
+                self.heap.pop();
+            } else {
+                return Some(cost);
+            }
+        }
+        None
+    }
+
+    fn pop(&mut self) -> Option<(NodeIndex, f64)> {
+        self.peek()?;
+        self.heap.pop().map(|State { cost, node }| (node, cost))
+    }
+
+    /// Links from `node` back to the seed, nearest first, and the seed's
+    /// partial edge. `previous` gives the node a link was relaxed from.
+    fn trace(
+        &self,
+        mut node: NodeIndex,
+        previous: impl Fn(usize) -> NodeIndex,
+    ) -> (Option<EdgeIndex>, Vec<u32>) {
+        let mut links = Vec::new();
+        loop {
+            match self.labels[&node].1 {
+                Parent::Seed(edge) => return (edge, links),
+                Parent::Link(link) => {
+                    links.push(link);
+                    node = previous(link as usize);
+                }
+            }
+        }
+    }
+}
+
+impl SpatialGraph {
+    /// This graph with a contraction hierarchy for `network_type` searches.
+    ///
+    /// Building it contracts the whole graph once; afterwards `route` for
+    /// that network type answers from the hierarchy and returns the same
+    /// routes as A*. Graphs with closures or turn penalties for
+    /// `network_type`, queries with a departure time and routes that would
+    /// take a restricted turn keep using A*. The hierarchy is dropped by
+    /// changes to travel times such as [`SpatialGraph::with_elevation`].
+    pub fn with_contraction_hierarchy(mut self, network_type: NetworkType) -> Self {
+        let hierarchy =
+            ContractionHierarchy::build(&self.graph, |edge| edge.weight.travel_time(network_type));
+        Arc::make_mut(&mut self.hierarchies).insert(network_type, Arc::new(hierarchy));
+        self
+    }
+
+    /// Whether [`SpatialGraph::with_contraction_hierarchy`] was called for
+    /// `network_type`.
+    pub fn has_contraction_hierarchy(&self, network_type: NetworkType) -> bool {
+        self.hierarchies.contains_key(&network_type)
+    }
+
+    /// The hierarchy that can answer a `network_type` query without a
+    /// departure time, if any. Its routes still need checking against turn
+    /// restrictions.
+    pub(crate) fn contraction_hierarchy(
+        &self,
+        network_type: NetworkType,
+    ) -> Option<&ContractionHierarchy> {
+        if self.closures.is_some() || self.turn_penalties(network_type).is_some() {
+            return None;
+        }
+        self.hierarchies.get(&network_type).map(Arc::as_ref)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A grid of two-way streets of mixed classes, with a one-way row.
+    fn grid_xml(size: i64) -> String {
+        let mut xml = String::from("<osm>");
+        for row in 0..size {
+            for col in 0..size {
+                xml.push_str(&format!(
+                    r#"<node id="{}" lat="{}" lon="{}" />"#,
+                    row * size + col + 1,
+                    48.0 + row as f64 * 0.002,
+                    11.0 + col as f64 * 0.003 + (row * col % 3) as f64 * 0.0004,
+                ));
+            }
+        }
+        let highways = ["residential", "primary", "tertiary", "service"];
+        let mut way_id = 100;
+        let mut way = |a: i64, b: i64, highway: &str, oneway: bool| {
+            way_id += 1;
+            let oneway = if oneway {
+                r#"<tag k="oneway" v="yes" />"#
+            } else {
+                ""
+            };
+            format!(
+                r#"<way id="{way_id}"><nd ref="{a}" /><nd ref="{b}" /><tag k="highway" v="{highway}" />{oneway}</way>"#
+            )
+        };
+        for row in 0..size {
+            for col in 0..size {
+                let node = row * size + col + 1;
+                let highway = highways[((row + 2 * col) % 4) as usize];
+                if col + 1 < size {
+                    xml.push_str(&way(node, node + 1, highway, row == 2));
+                }
+                if row + 1 < size {
+                    xml.push_str(&way(node, node + size, highways[(col % 4) as usize], false));
+                }
+            }
+        }
+        xml.push_str("</osm>");
+        xml
+    }
+
+    #[test]
+    fn hierarchy_routes_match_a_star() {
+        let plain =
+            SpatialGraph::from_osm(&grid_xml(7), NetworkType::Drive, Some(true), None).unwrap();
+        let fast = plain.clone().with_contraction_hierarchy(NetworkType::Drive);
+        assert!(fast.has_contraction_hierarchy(NetworkType::Drive));
+        assert!(!fast.has_contraction_hierarchy(NetworkType::Walk));
+
+        let points = [
+            (48.0, 11.0),
+            (48.012, 11.018),
+            (48.0051, 11.0072),
+            (48.004, 11.0151),
+            (48.0101, 11.0004),
+            (48.004, 11.009),
+        ];
+        for &(from_lat, from_lon) in &points {
+            for &(to_lat, to_lon) in &points {
+                let route = |sg: &SpatialGraph| {
+                    sg.route(from_lat, from_lon, to_lat, to_lon, NetworkType::Drive, None)
+                        .map(|route| (route.duration_s, route.distance_m))
+                        .ok()
+                };
+                let (expected, actual) = (route(&plain), route(&fast));
+                match (expected, actual) {
+                    (Some(expected), Some(actual)) => {
+                        assert!(
+                            (expected.0 - actual.0).abs() < 1e-6,
+                            "{expected:?} {actual:?}"
+                        );
+                    }
+                    (expected, actual) => assert_eq!(expected.is_some(), actual.is_some()),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bucket_rows_match_point_to_point_queries() {
+        let sg = SpatialGraph::from_osm(&grid_xml(7), NetworkType::Drive, Some(true), None)
+            .unwrap()
+            .with_contraction_hierarchy(NetworkType::Drive);
+        let hierarchy = sg.contraction_hierarchy(NetworkType::Drive).unwrap();
+        let cost = |e: EdgeInfo<'_>, _| e.weight.travel_time(NetworkType::Drive);
+        let anchors: Vec<Option<Anchor>> = [(48.0, 11.0), (48.012, 11.018), (48.0051, 11.0072)]
+            .iter()
+            .map(|&(lat, lon)| sg.snap_point(lat, lon).map(|snap| sg.anchor(&snap)))
+            .chain([None])
+            .collect();
+
+        let buckets = hierarchy.destination_buckets(&sg.graph, &anchors, cost);
+        for origin in anchors.iter().flatten() {
+            let row = buckets.paths_from(&sg.graph, origin, cost);
+            assert_eq!(row.len(), anchors.len());
+            assert!(row[3].is_none());
+            for (path, destination) in row.iter().zip(anchors.iter().flatten()) {
+                let expected = hierarchy
+                    .shortest_path(&sg.graph, origin, destination, cost)
+                    .unwrap();
+                let path = path.as_ref().unwrap();
+                assert!((path.cost - expected.cost).abs() < 1e-9);
+                assert_eq!(path.edges, expected.edges);
+            }
+        }
+    }
+
+    #[test]
+    fn hierarchy_is_bypassed_by_closures_and_elevation() {
+        let sg = SpatialGraph::from_osm(&grid_xml(4), NetworkType::Drive, Some(true), None)
+            .unwrap()
+            .with_contraction_hierarchy(NetworkType::Drive);
+        assert!(sg.contraction_hierarchy(NetworkType::Drive).is_some());
+
+        let first_way = sg.graph.edge_weights().next().unwrap().id;
+        let closed = sg
+            .clone()
+            .with_closures(&crate::closures::EdgeClosures::new().with_way(first_way));
+        assert!(closed.contraction_hierarchy(NetworkType::Drive).is_none());
+
+        let dem = crate::elevation::Dem::default();
+        assert!(!sg
+            .with_elevation(&dem)
+            .has_contraction_hierarchy(NetworkType::Drive));
+    }
+}
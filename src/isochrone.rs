@@ -1,3 +1,4 @@
+use crate::conditional::DepartureTime;
 #[cfg(feature = "extension-module")]
 use crate::error::OsmGraphError;
 use crate::graph::{self, SpatialGraph};
@@ -5,11 +6,11 @@ use crate::graph::{self, SpatialGraph};
 use crate::overpass;
 use crate::overpass::NetworkType;
 use crate::reachability::{compute_reachability, ReachabilityResult};
+use crate::search::Anchor;
 use geo::{ConvexHull, LineString, MultiPoint, Polygon};
 use petgraph::prelude::*;
 use spade::{DelaunayTriangulation, HasPosition, Point2, Triangulation};
 use std::collections::{HashMap, HashSet};
-use std::sync::Arc;
 
 const SATURATED_REUSE_RATIO: f64 = 0.99;
 const CONTOUR_KEY_SCALE: f64 = 10.0;
@@ -350,11 +351,124 @@ fn projected_ring_area(ring: &[ContourPoint]) -> f64 {
         * 0.5
 }
 
+pub fn calculate_isochrones_concurrently(
+    graph: std::sync::Arc<DiGraph<graph::XmlNode, graph::XmlWay>>,
+    start_node: NodeIndex,
+    time_limits: Vec<f64>,
+    network_type: NetworkType,
+) -> Vec<Polygon> {
+    let max_cost = time_limits.iter().cloned().fold(0.0_f64, f64::max);
+    let result = compute_reachability(&graph, start_node, max_cost, network_type);
+    build_isochrone_polygons(&graph, &result, &time_limits)
+}
+
+impl SpatialGraph {
+    /// Build isochrone polygons for one or more time limits from a lat/lon origin.
+    ///
+    /// Each polygon encloses all nodes reachable within the corresponding time
+    /// limit. The returned `Vec` is in the same order as `time_limits`. The
+    /// search starts from the point snapped on the closest edge.
+    ///
+    /// Returns `None` if no graph node is found near `(lat, lon)`.
+    pub fn isochrones(
+        &self,
+        lat: f64,
+        lon: f64,
+        time_limits: Vec<f64>,
+        network_type: NetworkType,
+        max_snap_m: Option<f64>,
+    ) -> Option<Vec<Polygon>> {
+        let start = self.anchor_within(lat, lon, max_snap_m)?;
+        Some(self.isochrones_from(&start, time_limits, network_type, None))
+    }
+
+    /// Like [`SpatialGraph::isochrones`], leaving at `departure`: edges are
+    /// unusable while a conditional closure applies at the time the search
+    /// reaches them.
+    pub fn isochrones_at(
+        &self,
+        lat: f64,
+        lon: f64,
+        time_limits: Vec<f64>,
+        network_type: NetworkType,
+        max_snap_m: Option<f64>,
+        departure: DepartureTime,
+    ) -> Option<Vec<Polygon>> {
+        let start = self.anchor_within(lat, lon, max_snap_m)?;
+        Some(self.isochrones_from(&start, time_limits, network_type, Some(departure)))
+    }
+
+    fn isochrones_from(
+        &self,
+        start: &Anchor,
+        time_limits: Vec<f64>,
+        network_type: NetworkType,
+        departure: Option<DepartureTime>,
+    ) -> Vec<Polygon> {
+        let max_cost = time_limits.iter().cloned().fold(0.0_f64, f64::max);
+        let result = self.reachability_from(start, max_cost, network_type, departure);
+        build_isochrone_polygons(&self.graph, &result, &time_limits)
+    }
+}
+
+#[cfg(feature = "extension-module")]
+pub(crate) async fn calculate_isochrones_from_point(
+    lat: f64,
+    lon: f64,
+    max_dist: Option<f64>,
+    time_limits: Vec<f64>,
+    network_type: overpass::NetworkType,
+    retain_all: bool,
+    speed_profile: &crate::speed::SpeedProfile,
+) -> Result<(Vec<Polygon>, SpatialGraph), OsmGraphError> {
+    use crate::cache;
+
+    // Auto-size bounding box if not provided.
+    // Use max time limit * a generous speed + 20% buffer to ensure the
+    // isochrone never saturates into a square at the bbox boundary.
+    let max_speed_m_per_s = match network_type {
+        NetworkType::Walk => speed_profile.walk_speed_kph.max(5.0) / 3.6,
+        NetworkType::Bike => speed_profile.bike_speed_kph.max(25.0) / 3.6,
+        NetworkType::Drive
+        | NetworkType::DriveService
+        | NetworkType::All
+        | NetworkType::AllPrivate => 120.0 / 3.6,
+    };
+    let max_time = time_limits.iter().cloned().fold(0.0_f64, f64::max);
+    let computed_dist = max_dist.unwrap_or(max_time * max_speed_m_per_s * 1.2);
+
+    let polygon_coord_str = overpass::bbox_from_point(lat, lon, computed_dist);
+    let query = overpass::create_overpass_query(&polygon_coord_str, network_type);
+
+    let xml = if let Some(cached_xml) = cache::check_xml_cache(&query)? {
+        cached_xml // in-memory hit
+    } else if let Some(disk_xml) = cache::check_disk_xml_cache(&query) {
+        cache::insert_into_xml_cache(query.clone(), disk_xml.clone())?; // promote to memory
+        disk_xml // disk hit
+    } else {
+        let fetched = overpass::make_request(&overpass::overpass_url(), &query).await?;
+        cache::write_disk_xml_cache(&query, &fetched); // persist to disk (best-effort)
+        cache::insert_into_xml_cache(query.clone(), fetched.clone())?;
+        fetched // network fetch
+    };
+    let parsed = graph::parse_xml(&xml)?;
+    if parsed.nodes.is_empty() {
+        return Err(OsmGraphError::EmptyGraph);
+    }
+    let sg = SpatialGraph::from_parsed_osm(parsed, network_type, retain_all, speed_profile);
+
+    let snap = sg.snap_point(lat, lon).ok_or(OsmGraphError::NodeNotFound)?;
+    let isochrones = sg.isochrones_from(&sg.anchor(&snap), time_limits, network_type, None);
+
+    Ok((isochrones, sg))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::graph::{XmlNode, XmlWay};
     use crate::reachability::compute_reachability;
+    use crate::storage::EdgeGeometry;
     use geo::Area;
     use petgraph::graph::DiGraph;
     use std::sync::Arc;
@@ -400,6 +514,7 @@ mod tests {
             lat,
             lon,
             tags: Vec::new(),
+            elevation_m: None,
         }
     }
 
@@ -407,13 +522,20 @@ mod tests {
         XmlWay {
             id: 1,
             nodes: Vec::new(),
-            tags: Vec::new(),
+            tags: Arc::from([]),
             length: seconds,
             speed_kph: 50.0,
             walk_travel_time: seconds,
             bike_travel_time: seconds,
             drive_travel_time: seconds,
-            geometry: Vec::new(),
+            geometry: EdgeGeometry::default(),
+            way_ids: Vec::new(),
+            closed_during: Vec::new(),
+            closed_by_default: false,
+            node_delays: Default::default(),
+            bike_push_m: 0.0,
+            elevations_m: Vec::new(),
+            hourly_speeds: None,
         }
     }
 
@@ -490,96 +612,3 @@ mod tests {
         assert!(polygons[0].unsigned_area() <= polygons[1].unsigned_area());
     }
 }
-
-pub fn calculate_isochrones_concurrently(
-    graph: std::sync::Arc<DiGraph<graph::XmlNode, graph::XmlWay>>,
-    start_node: NodeIndex,
-    time_limits: Vec<f64>,
-    network_type: NetworkType,
-) -> Vec<Polygon> {
-    let max_cost = time_limits.iter().cloned().fold(0.0_f64, f64::max);
-    let result = compute_reachability(&graph, start_node, max_cost, network_type);
-    build_isochrone_polygons(&graph, &result, &time_limits)
-}
-
-impl SpatialGraph {
-    /// Build isochrone polygons for one or more time limits from a lat/lon origin.
-    ///
-    /// Each polygon encloses all nodes reachable within the corresponding time
-    /// limit. The returned `Vec` is in the same order as `time_limits`.
-    ///
-    /// Returns `None` if no graph node is found near `(lat, lon)`.
-    pub fn isochrones(
-        &self,
-        lat: f64,
-        lon: f64,
-        time_limits: Vec<f64>,
-        network_type: NetworkType,
-        max_snap_m: Option<f64>,
-    ) -> Option<Vec<Polygon>> {
-        let start_node = self.nearest_node_within(lat, lon, max_snap_m)?;
-        Some(calculate_isochrones_concurrently(
-            Arc::clone(&self.graph),
-            start_node,
-            time_limits,
-            network_type,
-        ))
-    }
-}
-
-#[cfg(feature = "extension-module")]
-pub(crate) async fn calculate_isochrones_from_point(
-    lat: f64,
-    lon: f64,
-    max_dist: Option<f64>,
-    time_limits: Vec<f64>,
-    network_type: overpass::NetworkType,
-    retain_all: bool,
-) -> Result<(Vec<Polygon>, SpatialGraph), OsmGraphError> {
-    use crate::cache;
-
-    // Auto-size bounding box if not provided.
-    // Use max time limit * a generous speed + 20% buffer to ensure the
-    // isochrone never saturates into a square at the bbox boundary.
-    let max_speed_m_per_s = match network_type {
-        NetworkType::Walk => 5.0 / 3.6,
-        NetworkType::Bike => 25.0 / 3.6,
-        NetworkType::Drive
-        | NetworkType::DriveService
-        | NetworkType::All
-        | NetworkType::AllPrivate => 120.0 / 3.6,
-    };
-    let max_time = time_limits.iter().cloned().fold(0.0_f64, f64::max);
-    let computed_dist = max_dist.unwrap_or(max_time * max_speed_m_per_s * 1.2);
-
-    let polygon_coord_str = overpass::bbox_from_point(lat, lon, computed_dist);
-    let query = overpass::create_overpass_query(&polygon_coord_str, network_type);
-
-    let xml = if let Some(cached_xml) = cache::check_xml_cache(&query)? {
-        cached_xml // in-memory hit
-    } else if let Some(disk_xml) = cache::check_disk_xml_cache(&query) {
-        cache::insert_into_xml_cache(query.clone(), disk_xml.clone())?; // promote to memory
-        disk_xml // disk hit
-    } else {
-        let fetched = overpass::make_request(&overpass::overpass_url(), &query).await?;
-        cache::write_disk_xml_cache(&query, &fetched); // persist to disk (best-effort)
-        cache::insert_into_xml_cache(query.clone(), fetched.clone())?;
-        fetched // network fetch
-    };
-    let parsed = graph::parse_xml(&xml)?;
-    if parsed.nodes.is_empty() {
-        return Err(OsmGraphError::EmptyGraph);
-    }
-    let bidirectional = matches!(network_type, NetworkType::Walk);
-    let g = graph::create_graph(parsed.nodes, parsed.ways, retain_all, bidirectional);
-    let sg = SpatialGraph::new(g);
-
-    let node_index = sg
-        .nearest_node(lat, lon)
-        .ok_or(OsmGraphError::NodeNotFound)?;
-    let shared_graph = Arc::clone(&sg.graph); // O(1) refcount bump — no graph copy
-    let isochrones =
-        calculate_isochrones_concurrently(shared_graph, node_index, time_limits, network_type);
-
-    Ok((isochrones, sg))
-}
@@ -1,20 +1,40 @@
+// pyo3 0.20's `#[new]` expansion trips this lint on newer compilers.
+#![cfg_attr(feature = "extension-module", allow(non_local_definitions))]
+
 // Public modules — available to any Rust crate that depends on this library.
 // None of these import pyo3, so they compile cleanly without the extension-module feature.
+pub mod alternatives;
+pub mod closures;
+pub mod components;
+pub mod conditional;
+pub mod elevation;
 pub mod error;
 pub mod feasibility;
 pub mod filters;
 pub mod geocoding;
 pub mod graph;
+pub mod hierarchy;
 pub mod isochrone;
+pub mod maneuvers;
+pub mod matrix;
 pub mod overpass;
 pub mod pbf;
+pub mod persist;
 pub mod poi;
 pub mod reachability;
 pub mod routing;
+pub mod snapping;
+pub mod speed;
+pub mod storage;
+pub mod tour;
+pub mod traffic;
+pub mod turns;
 pub mod utils;
 
 // Internal implementation details; not part of the public Rust API.
 mod cache;
+mod node_costs;
+mod search;
 mod simplify;
 
 // ---------------------------------------------------------------------------
@@ -48,6 +68,56 @@ fn tokio_rt() -> &'static tokio::runtime::Runtime {
 // Shared helpers (Python-binding layer only)
 // ---------------------------------------------------------------------------
 
+#[cfg(feature = "extension-module")]
+fn parse_departure(departure: Option<String>) -> PyResult<Option<conditional::DepartureTime>> {
+    Ok(departure.map(|d| d.parse()).transpose()?)
+}
+
+/// Parse the `largest_component` constructor option: `"strong"`, `"weak"` or
+/// `None` to keep every component.
+#[cfg(feature = "extension-module")]
+fn parse_connectivity(connectivity: Option<&str>) -> PyResult<Option<components::Connectivity>> {
+    match connectivity
+        .map(|c| c.trim().to_ascii_lowercase())
+        .as_deref()
+    {
+        None => Ok(None),
+        Some("strong") => Ok(Some(components::Connectivity::Strong)),
+        Some("weak") => Ok(Some(components::Connectivity::Weak)),
+        Some(other) => Err(pyo3::exceptions::PyValueError::new_err(format!(
+            "Invalid connectivity '{}'. Expected 'strong' or 'weak'",
+            other
+        ))),
+    }
+}
+
+/// Build a [`snapping::SnapFilter`] from the keyword arguments shared by
+/// `snap_point` and `snap_candidates`. Components are measured for
+/// `network`, or the graph's `network_type` when no network is given.
+#[cfg(feature = "extension-module")]
+fn snap_filter(
+    network_type: overpass::NetworkType,
+    network: Option<&str>,
+    highways: Option<Vec<String>>,
+    min_component_size: Option<usize>,
+    connectivity: &str,
+) -> PyResult<snapping::SnapFilter> {
+    let mut filter = snapping::SnapFilter::new();
+    let mut component_network_type = network_type;
+    if let Some(network) = network {
+        component_network_type = parse_network_type(network)?;
+        filter = filter.with_network_type(component_network_type);
+    }
+    if let Some(highways) = highways {
+        filter = filter.with_highways(highways);
+    }
+    if let Some(nodes) = min_component_size {
+        let connectivity = parse_connectivity(Some(connectivity))?.unwrap();
+        filter = filter.with_min_component_size(nodes, component_network_type, connectivity);
+    }
+    Ok(filter)
+}
+
 #[cfg(feature = "extension-module")]
 fn parse_network_type(s: &str) -> PyResult<overpass::NetworkType> {
     match s.trim().to_ascii_lowercase().as_str() {
@@ -73,7 +143,7 @@ fn edge_geojson_coords(
     way: &graph::XmlWay,
 ) -> Vec<Vec<f64>> {
     let mut points = if way.geometry.len() >= 2 {
-        way.geometry.clone()
+        way.geometry.to_vec()
     } else {
         vec![(source.lat, source.lon), (target.lat, target.lon)]
     };
@@ -103,6 +173,66 @@ fn snap_json(snap: graph::SnapResult) -> geojson::JsonValue {
     obj.insert("node_lat".into(), snap.node_lat.into());
     obj.insert("node_lon".into(), snap.node_lon.into());
     obj.insert("distance_m".into(), snap.distance_m.into());
+    obj.insert("snapped_lat".into(), snap.snapped_lat.into());
+    obj.insert("snapped_lon".into(), snap.snapped_lon.into());
+    let edge = snap
+        .edge_node_ids
+        .map(|(from_id, to_id)| vec![from_id, to_id].into())
+        .unwrap_or(geojson::JsonValue::Null);
+    obj.insert("edge".into(), edge);
+    obj.insert("edge_offset_m".into(), snap.edge_offset_m.into());
+    geojson::JsonValue::Object(obj)
+}
+
+#[cfg(feature = "extension-module")]
+fn ferry_leg_json(leg: &routing::FerryLeg) -> geojson::JsonValue {
+    let mut obj = geojson::JsonObject::new();
+    obj.insert("start_index".into(), leg.start_index.into());
+    obj.insert("end_index".into(), leg.end_index.into());
+    obj.insert("distance_m".into(), leg.distance_m.into());
+    obj.insert("duration_s".into(), leg.duration_s.into());
+    geojson::JsonValue::Object(obj)
+}
+
+#[cfg(feature = "extension-module")]
+fn route_leg_json(leg: &routing::RouteLeg) -> geojson::JsonValue {
+    let mut obj = geojson::JsonObject::new();
+    obj.insert("start_index".into(), leg.start_index.into());
+    obj.insert("end_index".into(), leg.end_index.into());
+    obj.insert("distance_m".into(), leg.distance_m.into());
+    obj.insert("duration_s".into(), leg.duration_s.into());
+    obj.insert("origin_snap".into(), snap_json(leg.origin_snap));
+    obj.insert("destination_snap".into(), snap_json(leg.destination_snap));
+    geojson::JsonValue::Object(obj)
+}
+
+#[cfg(feature = "extension-module")]
+fn maneuver_json(r: &routing::Route, maneuver: &maneuvers::Maneuver) -> geojson::JsonValue {
+    let (lat, lon) = r.coordinates[maneuver.coordinate_index];
+    let mut obj = geojson::JsonObject::new();
+    obj.insert("type".into(), maneuver.kind.as_str().into());
+    match maneuver.kind {
+        maneuvers::ManeuverKind::Roundabout { exit } => {
+            obj.insert("exit".into(), exit.into());
+        }
+        maneuvers::ManeuverKind::Waypoint { index } => {
+            obj.insert("waypoint".into(), index.into());
+        }
+        _ => {}
+    }
+    obj.insert("instruction".into(), maneuver.instruction().into());
+    obj.insert(
+        "street".into(),
+        maneuver
+            .street
+            .clone()
+            .map_or(geojson::JsonValue::Null, Into::into),
+    );
+    obj.insert("coordinate_index".into(), maneuver.coordinate_index.into());
+    obj.insert("location".into(), vec![lon, lat].into());
+    obj.insert("bearing_deg".into(), maneuver.bearing_deg.into());
+    obj.insert("distance_m".into(), maneuver.distance_m.into());
+    obj.insert("duration_s".into(), maneuver.duration_s.into());
     geojson::JsonValue::Object(obj)
 }
 
@@ -117,6 +247,28 @@ fn route_to_geojson(r: &routing::Route) -> String {
     let mut props = geojson::JsonObject::new();
     props.insert("distance_m".into(), r.distance_m.into());
     props.insert("duration_s".into(), r.duration_s.into());
+    if !r.elevations_m.is_empty() {
+        props.insert("ascent_m".into(), r.ascent_m.into());
+        props.insert("descent_m".into(), r.descent_m.into());
+        props.insert(
+            "elevations_m".into(),
+            geojson::JsonValue::Array(r.elevations_m.iter().map(|&e| e.into()).collect()),
+        );
+    }
+    if !r.ferry_legs.is_empty() {
+        props.insert(
+            "ferry_legs".into(),
+            geojson::JsonValue::Array(r.ferry_legs.iter().map(ferry_leg_json).collect()),
+        );
+    }
+    props.insert(
+        "maneuvers".into(),
+        geojson::JsonValue::Array(r.maneuvers.iter().map(|m| maneuver_json(r, m)).collect()),
+    );
+    props.insert(
+        "legs".into(),
+        geojson::JsonValue::Array(r.legs.iter().map(route_leg_json).collect()),
+    );
     props.insert("origin_snap".into(), snap_json(r.origin_snap));
     props.insert("destination_snap".into(), snap_json(r.destination_snap));
     props.insert(
@@ -140,6 +292,10 @@ fn snap_to_dict<'py>(py: Python<'py>, snap: graph::SnapResult) -> PyResult<&'py
     dict.set_item("node_lat", snap.node_lat)?;
     dict.set_item("node_lon", snap.node_lon)?;
     dict.set_item("distance_m", snap.distance_m)?;
+    dict.set_item("snapped_lat", snap.snapped_lat)?;
+    dict.set_item("snapped_lon", snap.snapped_lon)?;
+    dict.set_item("edge", snap.edge_node_ids)?;
+    dict.set_item("edge_offset_m", snap.edge_offset_m)?;
     Ok(dict)
 }
 
@@ -183,6 +339,26 @@ impl PySnapResult {
         self.snap.distance_m
     }
 
+    #[getter]
+    fn snapped_lat(&self) -> f64 {
+        self.snap.snapped_lat
+    }
+
+    #[getter]
+    fn snapped_lon(&self) -> f64 {
+        self.snap.snapped_lon
+    }
+
+    #[getter]
+    fn edge(&self) -> Option<(i64, i64)> {
+        self.snap.edge_node_ids
+    }
+
+    #[getter]
+    fn edge_offset_m(&self) -> f64 {
+        self.snap.edge_offset_m
+    }
+
     fn as_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
         snap_to_dict(py, self.snap)
     }
@@ -195,6 +371,128 @@ impl PySnapResult {
     }
 }
 
+#[cfg(feature = "extension-module")]
+#[pyclass(name = "TravelTimeMatrix")]
+#[derive(Clone)]
+struct PyTravelTimeMatrix {
+    matrix: matrix::TravelTimeMatrix,
+    // Converted once, so reading a property does not copy the whole matrix.
+    durations_s: Py<PyList>,
+    distances_m: Py<PyList>,
+}
+
+#[cfg(feature = "extension-module")]
+impl PyTravelTimeMatrix {
+    fn new(py: Python<'_>, matrix: matrix::TravelTimeMatrix) -> Self {
+        let rows = |cells: &[Vec<Option<f64>>]| -> Py<PyList> {
+            PyList::new(py, cells.iter().map(|row| PyList::new(py, row))).into()
+        };
+        Self {
+            durations_s: rows(&matrix.durations_s),
+            distances_m: rows(&matrix.distances_m),
+            matrix,
+        }
+    }
+}
+
+#[cfg(feature = "extension-module")]
+#[pymethods]
+impl PyTravelTimeMatrix {
+    /// Seconds per origin row and destination column; `None` where there is
+    /// no path. Every access returns the same list.
+    #[getter]
+    fn durations_s(&self, py: Python<'_>) -> Py<PyList> {
+        self.durations_s.clone_ref(py)
+    }
+
+    /// Metres along the same paths; `None` where there is no path.
+    #[getter]
+    fn distances_m(&self, py: Python<'_>) -> Py<PyList> {
+        self.distances_m.clone_ref(py)
+    }
+
+    #[getter]
+    fn origin_snaps(&self) -> Vec<Option<PySnapResult>> {
+        self.matrix
+            .origin_snaps
+            .iter()
+            .map(|snap| snap.map(|snap| PySnapResult { snap }))
+            .collect()
+    }
+
+    #[getter]
+    fn destination_snaps(&self) -> Vec<Option<PySnapResult>> {
+        self.matrix
+            .destination_snaps
+            .iter()
+            .map(|snap| snap.map(|snap| PySnapResult { snap }))
+            .collect()
+    }
+
+    #[getter]
+    fn shape(&self) -> (usize, usize) {
+        (
+            self.matrix.origin_snaps.len(),
+            self.matrix.destination_snaps.len(),
+        )
+    }
+
+    fn __repr__(&self) -> String {
+        let (origins, destinations) = self.shape();
+        format!("TravelTimeMatrix(origins={origins}, destinations={destinations})")
+    }
+}
+
+#[cfg(feature = "extension-module")]
+#[pyclass(name = "TourResult")]
+#[derive(Clone)]
+struct PyTourResult {
+    tour: tour::Tour,
+}
+
+#[cfg(feature = "extension-module")]
+#[pymethods]
+impl PyTourResult {
+    /// Indices into the stops, in visiting order.
+    #[getter]
+    fn order(&self) -> Vec<usize> {
+        self.tour.order.clone()
+    }
+
+    /// Seconds after leaving the depot at which each stop in `order` is
+    /// reached.
+    #[getter]
+    fn arrival_times_s(&self) -> Vec<f64> {
+        self.tour.arrival_times_s.clone()
+    }
+
+    #[getter]
+    fn duration_s(&self) -> f64 {
+        self.tour.duration_s
+    }
+
+    #[getter]
+    fn lateness_s(&self) -> f64 {
+        self.tour.lateness_s
+    }
+
+    #[getter]
+    fn route(&self) -> PyRouteResult {
+        PyRouteResult {
+            route: self.tour.route.clone(),
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "TourResult(stops={}, duration_s={:.0}, lateness_s={:.0})",
+            self.tour.order.len(),
+            self.tour.duration_s,
+            self.tour.lateness_s
+        )
+    }
+}
+
 #[cfg(feature = "extension-module")]
 #[pyclass(name = "RouteResult")]
 #[derive(Clone)]
@@ -225,6 +523,86 @@ impl PyRouteResult {
         self.route.duration_s
     }
 
+    #[getter]
+    fn elevations_m(&self) -> Vec<f64> {
+        self.route.elevations_m.clone()
+    }
+
+    #[getter]
+    fn ascent_m(&self) -> f64 {
+        self.route.ascent_m
+    }
+
+    #[getter]
+    fn descent_m(&self) -> f64 {
+        self.route.descent_m
+    }
+
+    fn elevation_profile(&self) -> Vec<(f64, f64)> {
+        self.route.elevation_profile()
+    }
+
+    /// Ferry crossings as dicts with `start_index`, `end_index` (into
+    /// `coordinates`), `distance_m` and `duration_s`.
+    #[getter]
+    fn ferry_legs<'py>(&self, py: Python<'py>) -> PyResult<&'py PyList> {
+        let legs = PyList::empty(py);
+        for leg in &self.route.ferry_legs {
+            let dict = PyDict::new(py);
+            dict.set_item("start_index", leg.start_index)?;
+            dict.set_item("end_index", leg.end_index)?;
+            dict.set_item("distance_m", leg.distance_m)?;
+            dict.set_item("duration_s", leg.duration_s)?;
+            legs.append(dict)?;
+        }
+        Ok(legs)
+    }
+
+    /// Turn-by-turn steps as dicts, in the same shape as the `maneuvers`
+    /// of `to_geojson`.
+    #[getter]
+    fn maneuvers<'py>(&self, py: Python<'py>) -> PyResult<&'py PyList> {
+        let steps = PyList::empty(py);
+        for maneuver in &self.route.maneuvers {
+            let (lat, lon) = self.route.coordinates[maneuver.coordinate_index];
+            let dict = PyDict::new(py);
+            dict.set_item("type", maneuver.kind.as_str())?;
+            match maneuver.kind {
+                maneuvers::ManeuverKind::Roundabout { exit } => dict.set_item("exit", exit)?,
+                maneuvers::ManeuverKind::Waypoint { index } => dict.set_item("waypoint", index)?,
+                _ => {}
+            }
+            dict.set_item("instruction", maneuver.instruction())?;
+            dict.set_item("street", maneuver.street.clone())?;
+            dict.set_item("coordinate_index", maneuver.coordinate_index)?;
+            dict.set_item("location", (lat, lon))?;
+            dict.set_item("bearing_deg", maneuver.bearing_deg)?;
+            dict.set_item("distance_m", maneuver.distance_m)?;
+            dict.set_item("duration_s", maneuver.duration_s)?;
+            steps.append(dict)?;
+        }
+        Ok(steps)
+    }
+
+    /// Waypoint-to-waypoint legs as dicts with `start_index`, `end_index`
+    /// (into `coordinates`), `distance_m`, `duration_s`, `origin_snap` and
+    /// `destination_snap`.
+    #[getter]
+    fn legs<'py>(&self, py: Python<'py>) -> PyResult<&'py PyList> {
+        let legs = PyList::empty(py);
+        for leg in &self.route.legs {
+            let dict = PyDict::new(py);
+            dict.set_item("start_index", leg.start_index)?;
+            dict.set_item("end_index", leg.end_index)?;
+            dict.set_item("distance_m", leg.distance_m)?;
+            dict.set_item("duration_s", leg.duration_s)?;
+            dict.set_item("origin_snap", snap_to_dict(py, leg.origin_snap)?)?;
+            dict.set_item("destination_snap", snap_to_dict(py, leg.destination_snap)?)?;
+            legs.append(dict)?;
+        }
+        Ok(legs)
+    }
+
     #[getter]
     fn origin_snap(&self) -> PySnapResult {
         PySnapResult {
@@ -249,6 +627,12 @@ impl PyRouteResult {
         dict.set_item("cumulative_times_s", self.cumulative_times_s())?;
         dict.set_item("distance_m", self.route.distance_m)?;
         dict.set_item("duration_s", self.route.duration_s)?;
+        dict.set_item("elevations_m", self.elevations_m())?;
+        dict.set_item("ascent_m", self.route.ascent_m)?;
+        dict.set_item("descent_m", self.route.descent_m)?;
+        dict.set_item("ferry_legs", self.ferry_legs(py)?)?;
+        dict.set_item("maneuvers", self.maneuvers(py)?)?;
+        dict.set_item("legs", self.legs(py)?)?;
         dict.set_item("origin_snap", self.origin_snap().as_dict(py)?)?;
         dict.set_item("destination_snap", self.destination_snap().as_dict(py)?)?;
         Ok(dict)
@@ -395,6 +779,220 @@ impl PyPoiCollection {
     }
 }
 
+// ---------------------------------------------------------------------------
+// PySpeedProfile — speed assumptions used when building a graph
+// ---------------------------------------------------------------------------
+
+/// Speeds used to derive edge travel times at graph construction.
+///
+/// `highway_speeds` entries override the default per-highway drive speeds;
+/// classes not listed keep their defaults.
+#[cfg(feature = "extension-module")]
+#[pyclass(name = "SpeedProfile")]
+#[derive(Clone)]
+struct PySpeedProfile {
+    profile: speed::SpeedProfile,
+}
+
+#[cfg(feature = "extension-module")]
+#[pymethods]
+impl PySpeedProfile {
+    #[new]
+    #[pyo3(signature = (
+        highway_speeds = None,
+        fallback_speed_kph = None,
+        walk_speed_kph = None,
+        bike_speed_kph = None,
+        maxspeed_factor = None,
+        max_speed_kph = None,
+        bike_push_speed_kph = None,
+        bike_surface_factors = None,
+        bike_min_surface_factor = None,
+        ferry_speed_kph = None,
+        ferry_boarding_s = None,
+        traffic_signal_delay_s = None,
+        stop_delay_s = None,
+        crossing_delay_s = None,
+        hourly_speeds_csv = None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        highway_speeds: Option<std::collections::HashMap<String, f64>>,
+        fallback_speed_kph: Option<f64>,
+        walk_speed_kph: Option<f64>,
+        bike_speed_kph: Option<f64>,
+        maxspeed_factor: Option<f64>,
+        max_speed_kph: Option<f64>,
+        bike_push_speed_kph: Option<f64>,
+        bike_surface_factors: Option<std::collections::HashMap<String, f64>>,
+        bike_min_surface_factor: Option<f64>,
+        ferry_speed_kph: Option<f64>,
+        ferry_boarding_s: Option<f64>,
+        traffic_signal_delay_s: Option<f64>,
+        stop_delay_s: Option<f64>,
+        crossing_delay_s: Option<f64>,
+        hourly_speeds_csv: Option<String>,
+    ) -> PyResult<Self> {
+        let mut profile = speed::SpeedProfile::default();
+        let speeds = highway_speeds
+            .iter()
+            .flat_map(|speeds| speeds.values().copied())
+            .chain(fallback_speed_kph)
+            .chain(walk_speed_kph)
+            .chain(bike_speed_kph)
+            .chain(maxspeed_factor)
+            .chain(max_speed_kph)
+            .chain(bike_push_speed_kph)
+            .chain(
+                bike_surface_factors
+                    .iter()
+                    .flat_map(|factors| factors.values().copied()),
+            )
+            .chain(bike_min_surface_factor)
+            .chain(ferry_speed_kph);
+        for value in speeds {
+            if !value.is_finite() || value <= 0.0 {
+                return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                    "speeds and factors must be positive, got {value}"
+                )));
+            }
+        }
+        if let Some(highway_speeds) = highway_speeds {
+            profile.highway_speeds_kph.extend(highway_speeds);
+        }
+        if let Some(speed) = fallback_speed_kph {
+            profile.fallback_speed_kph = speed;
+        }
+        if let Some(speed) = walk_speed_kph {
+            profile.walk_speed_kph = speed;
+        }
+        if let Some(speed) = bike_speed_kph {
+            profile.bike_speed_kph = speed;
+        }
+        if let Some(factor) = maxspeed_factor {
+            profile.maxspeed_factor = factor;
+        }
+        profile.max_speed_kph = max_speed_kph;
+        profile.bike_push_speed_kph = bike_push_speed_kph;
+        if let Some(factors) = bike_surface_factors {
+            profile.bike_surface_factors.extend(factors);
+        }
+        profile.bike_min_surface_factor = bike_min_surface_factor;
+        if let Some(speed) = ferry_speed_kph {
+            profile.ferry_speed_kph = speed;
+        }
+        let delays = [
+            (ferry_boarding_s, &mut profile.ferry_boarding_s),
+            (traffic_signal_delay_s, &mut profile.traffic_signal_delay_s),
+            (stop_delay_s, &mut profile.stop_delay_s),
+            (crossing_delay_s, &mut profile.crossing_delay_s),
+        ];
+        for (seconds, field) in delays {
+            if let Some(seconds) = seconds {
+                if !seconds.is_finite() || seconds < 0.0 {
+                    return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                        "delays must be non-negative, got {seconds}"
+                    )));
+                }
+                *field = seconds;
+            }
+        }
+        if let Some(path) = hourly_speeds_csv {
+            profile.hourly_speeds =
+                Some(std::sync::Arc::new(traffic::HourlySpeeds::from_csv(path)?));
+        }
+        Ok(Self { profile })
+    }
+
+    /// Default speeds with cycling limited to surfaces fit for road bikes.
+    #[staticmethod]
+    fn road_bike() -> Self {
+        Self {
+            profile: speed::SpeedProfile::road_bike(),
+        }
+    }
+
+    #[getter]
+    fn highway_speeds(&self) -> std::collections::HashMap<String, f64> {
+        self.profile.highway_speeds_kph.clone()
+    }
+
+    #[getter]
+    fn fallback_speed_kph(&self) -> f64 {
+        self.profile.fallback_speed_kph
+    }
+
+    #[getter]
+    fn walk_speed_kph(&self) -> f64 {
+        self.profile.walk_speed_kph
+    }
+
+    #[getter]
+    fn bike_speed_kph(&self) -> f64 {
+        self.profile.bike_speed_kph
+    }
+
+    #[getter]
+    fn maxspeed_factor(&self) -> f64 {
+        self.profile.maxspeed_factor
+    }
+
+    #[getter]
+    fn max_speed_kph(&self) -> Option<f64> {
+        self.profile.max_speed_kph
+    }
+
+    #[getter]
+    fn bike_push_speed_kph(&self) -> Option<f64> {
+        self.profile.bike_push_speed_kph
+    }
+
+    #[getter]
+    fn bike_surface_factors(&self) -> std::collections::HashMap<String, f64> {
+        self.profile.bike_surface_factors.clone()
+    }
+
+    #[getter]
+    fn bike_min_surface_factor(&self) -> Option<f64> {
+        self.profile.bike_min_surface_factor
+    }
+
+    #[getter]
+    fn ferry_speed_kph(&self) -> f64 {
+        self.profile.ferry_speed_kph
+    }
+
+    #[getter]
+    fn ferry_boarding_s(&self) -> f64 {
+        self.profile.ferry_boarding_s
+    }
+
+    #[getter]
+    fn traffic_signal_delay_s(&self) -> f64 {
+        self.profile.traffic_signal_delay_s
+    }
+
+    #[getter]
+    fn stop_delay_s(&self) -> f64 {
+        self.profile.stop_delay_s
+    }
+
+    #[getter]
+    fn crossing_delay_s(&self) -> f64 {
+        self.profile.crossing_delay_s
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "SpeedProfile(walk_speed_kph={}, bike_speed_kph={}, maxspeed_factor={}, max_speed_kph={:?})",
+            self.profile.walk_speed_kph,
+            self.profile.bike_speed_kph,
+            self.profile.maxspeed_factor,
+            self.profile.max_speed_kph
+        )
+    }
+}
+
 // ---------------------------------------------------------------------------
 // PyGraph — exposes a loaded SpatialGraph to Python
 // ---------------------------------------------------------------------------
@@ -429,41 +1027,75 @@ struct PyPrismGraph {
     buffer_s: f64,
 }
 
+#[cfg(feature = "extension-module")]
+impl PyGraph {
+    fn new(
+        sg: graph::SpatialGraph,
+        network_type: overpass::NetworkType,
+        largest_component: Option<components::Connectivity>,
+    ) -> Self {
+        let sg = match largest_component {
+            Some(connectivity) => sg.retain_largest_component(network_type, connectivity),
+            None => sg,
+        };
+        Self { sg, network_type }
+    }
+}
+
 #[cfg(feature = "extension-module")]
 #[pymethods]
 impl PyGraph {
     #[staticmethod]
-    #[pyo3(signature = (path, network, retain_all = false))]
-    fn from_pbf(path: String, network: String, retain_all: bool) -> PyResult<Self> {
+    #[pyo3(signature = (path, network, retain_all = false, speed_profile = None, largest_component = None))]
+    fn from_pbf(
+        path: &PyAny,
+        network: String,
+        retain_all: bool,
+        speed_profile: Option<PySpeedProfile>,
+        largest_component: Option<&str>,
+    ) -> PyResult<Self> {
+        // One path, or a list of neighbouring extracts merged into one graph.
+        let paths: Vec<String> = match path.extract::<String>() {
+            Ok(path) => vec![path],
+            Err(_) => path.extract()?,
+        };
         let nt = parse_network_type(&network)?;
-        let sg = graph::SpatialGraph::from_pbf(path, nt, Some(retain_all))?;
-        Ok(Self {
-            sg,
-            network_type: nt,
-        })
+        let connectivity = parse_connectivity(largest_component)?;
+        let profile = speed_profile.map(|p| p.profile);
+        let sg = graph::SpatialGraph::from_pbfs(&paths, nt, Some(retain_all), profile.as_ref())?;
+        Ok(Self::new(sg, nt, connectivity))
     }
 
     #[staticmethod]
-    #[pyo3(signature = (xml, network, retain_all = false))]
-    fn from_osm(xml: String, network: String, retain_all: bool) -> PyResult<Self> {
+    #[pyo3(signature = (xml, network, retain_all = false, speed_profile = None, largest_component = None))]
+    fn from_osm(
+        xml: String,
+        network: String,
+        retain_all: bool,
+        speed_profile: Option<PySpeedProfile>,
+        largest_component: Option<&str>,
+    ) -> PyResult<Self> {
         let nt = parse_network_type(&network)?;
-        let sg = graph::SpatialGraph::from_osm(&xml, nt, Some(retain_all))
+        let connectivity = parse_connectivity(largest_component)?;
+        let profile = speed_profile.map(|p| p.profile);
+        let sg = graph::SpatialGraph::from_osm(&xml, nt, Some(retain_all), profile.as_ref())
             .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))?;
-        Ok(Self {
-            sg,
-            network_type: nt,
-        })
+        Ok(Self::new(sg, nt, connectivity))
     }
 
     #[staticmethod]
-    #[pyo3(signature = (place, network, max_dist = None, retain_all = false))]
+    #[pyo3(signature = (place, network, max_dist = None, retain_all = false, speed_profile = None, largest_component = None))]
     fn from_place(
         place: String,
         network: String,
         max_dist: Option<f64>,
         retain_all: bool,
+        speed_profile: Option<PySpeedProfile>,
+        largest_component: Option<&str>,
     ) -> PyResult<Self> {
+        let profile = speed_profile.map(|p| p.profile).unwrap_or_default();
         let nt = parse_network_type(&network)?;
+        let connectivity = parse_connectivity(largest_component)?;
         let (lat, lon) = tokio_rt().block_on(geocoding::geocode(&place))?;
         let (_, sg) = tokio_rt().block_on(isochrone::calculate_isochrones_from_point(
             lat,
@@ -472,13 +1104,66 @@ impl PyGraph {
             vec![],
             nt,
             retain_all,
+            &profile,
         ))?;
+        Ok(Self::new(sg, nt, connectivity))
+    }
+
+    #[staticmethod]
+    fn load(path: String, network: String) -> PyResult<Self> {
+        let nt = parse_network_type(&network)?;
+        let sg = graph::SpatialGraph::load(path)?;
         Ok(Self {
             sg,
             network_type: nt,
         })
     }
 
+    fn save(&self, path: String) -> PyResult<()> {
+        Ok(self.sg.save(path)?)
+    }
+
+    fn with_elevation(&self, dem_paths: Vec<String>) -> PyResult<Self> {
+        let dem = elevation::Dem::from_paths(&dem_paths)?;
+        Ok(Self {
+            sg: self.sg.clone().with_elevation(&dem),
+            network_type: self.network_type,
+        })
+    }
+
+    #[pyo3(signature = (way_ids = None, node_pairs = None, areas = None))]
+    fn with_closures(
+        &self,
+        way_ids: Option<Vec<i64>>,
+        node_pairs: Option<Vec<(i64, i64)>>,
+        areas: Option<Vec<String>>,
+    ) -> PyResult<Self> {
+        let mut closures = closures::EdgeClosures::new().with_ways(way_ids.unwrap_or_default());
+        for (a, b) in node_pairs.unwrap_or_default() {
+            closures = closures.with_node_pair(a, b);
+        }
+        for area in areas.unwrap_or_default() {
+            closures = closures.with_geojson(&area)?;
+        }
+        Ok(Self {
+            sg: self.sg.clone().with_closures(&closures),
+            network_type: self.network_type,
+        })
+    }
+
+    fn with_contraction_hierarchy(&self) -> Self {
+        let sg = self.sg.clone();
+        let network_type = self.network_type;
+        Self {
+            sg: sg.with_contraction_hierarchy(network_type),
+            network_type,
+        }
+    }
+
+    fn has_contraction_hierarchy(&self) -> bool {
+        self.sg.has_contraction_hierarchy(self.network_type)
+    }
+
     fn node_count(&self) -> usize {
         self.sg.graph.node_count()
     }
@@ -487,6 +1172,21 @@ impl PyGraph {
         self.sg.graph.edge_count()
     }
 
+    #[pyo3(signature = (connectivity = "strong"))]
+    fn components<'py>(&self, py: Python<'py>, connectivity: &str) -> PyResult<&'py PyList> {
+        let connectivity =
+            parse_connectivity(Some(connectivity))?.unwrap_or(components::Connectivity::Strong);
+        let list = PyList::empty(py);
+        for component in self.sg.components(self.network_type, connectivity) {
+            let dict = PyDict::new(py);
+            dict.set_item("size", component.len())?;
+            let node_ids: Vec<i64> = component.iter().map(|&i| self.sg.graph[i].id).collect();
+            dict.set_item("node_ids", node_ids)?;
+            list.append(dict)?;
+        }
+        Ok(list)
+    }
+
     fn nearest_node(&self, lat: f64, lon: f64) -> PyResult<Option<(i64, f64, f64)>> {
         Ok(self.sg.nearest_node(lat, lon).map(|idx| {
             let n = &self.sg.graph[idx];
@@ -494,36 +1194,138 @@ impl PyGraph {
         }))
     }
 
-    fn snap_point(&self, lat: f64, lon: f64) -> PyResult<Option<PySnapResult>> {
-        let Some(snap) = self.sg.snap_point(lat, lon) else {
-            return Ok(None);
+    fn nodes_by_id(&self, node_ids: Vec<i64>) -> Vec<Option<(i64, f64, f64)>> {
+        self.sg
+            .node_indices_for_osm_ids(&node_ids)
+            .into_iter()
+            .map(|idx| {
+                idx.map(|idx| {
+                    let n = &self.sg.graph[idx];
+                    (n.id, n.lat, n.lon)
+                })
+            })
+            .collect()
+    }
+
+    fn edges_by_way_id(&self, way_ids: Vec<i64>) -> Vec<Vec<(i64, i64)>> {
+        self.sg
+            .edges_for_osm_way_ids(&way_ids)
+            .into_iter()
+            .map(|edges| {
+                edges
+                    .iter()
+                    .filter_map(|&edge| self.sg.graph.edge_endpoints(edge))
+                    .map(|(u, v)| (self.sg.graph[u].id, self.sg.graph[v].id))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[pyo3(signature = (
+        lat,
+        lon,
+        max_snap_m = None,
+        network = None,
+        highways = None,
+        min_component_size = None,
+        connectivity = "strong"
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn snap_point(
+        &self,
+        lat: f64,
+        lon: f64,
+        max_snap_m: Option<f64>,
+        network: Option<&str>,
+        highways: Option<Vec<String>>,
+        min_component_size: Option<usize>,
+        connectivity: &str,
+    ) -> PyResult<Option<PySnapResult>> {
+        let filtered = network.is_some() || highways.is_some() || min_component_size.is_some();
+        let snap = if filtered {
+            let filter = snap_filter(
+                self.network_type,
+                network,
+                highways,
+                min_component_size,
+                connectivity,
+            )?;
+            self.sg.snap_point_filtered(lat, lon, max_snap_m, &filter)
+        } else {
+            self.sg.snap_point_within(lat, lon, max_snap_m)
         };
-        Ok(Some(PySnapResult { snap }))
+        Ok(snap.map(|snap| PySnapResult { snap }))
     }
 
-    #[pyo3(signature = (origin, minutes, max_snap_m = Some(100.0)))]
+    #[pyo3(signature = (
+        lat,
+        lon,
+        k = 5,
+        max_snap_m = None,
+        network = None,
+        highways = None,
+        min_component_size = None,
+        connectivity = "strong"
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn snap_candidates(
+        &self,
+        lat: f64,
+        lon: f64,
+        k: usize,
+        max_snap_m: Option<f64>,
+        network: Option<&str>,
+        highways: Option<Vec<String>>,
+        min_component_size: Option<usize>,
+        connectivity: &str,
+    ) -> PyResult<Vec<PySnapResult>> {
+        let filter = snap_filter(
+            self.network_type,
+            network,
+            highways,
+            min_component_size,
+            connectivity,
+        )?;
+        Ok(self
+            .sg
+            .snap_candidates(lat, lon, k, max_snap_m, &filter)
+            .into_iter()
+            .map(|snap| PySnapResult { snap })
+            .collect())
+    }
+
+    #[pyo3(signature = (origin, minutes, max_snap_m = Some(100.0), departure = None))]
     fn isochrone(
         &self,
         origin: (f64, f64),
         minutes: Vec<f64>,
         max_snap_m: Option<f64>,
+        departure: Option<String>,
     ) -> PyResult<Vec<PyIsochroneResult>> {
         let output_minutes = minutes.clone();
         let time_limits = minutes.into_iter().map(|m| m * 60.0).collect();
-        let isos = self
-            .sg
-            .isochrones(
+        let isos = match parse_departure(departure)? {
+            Some(departure) => self.sg.isochrones_at(
+                origin.0,
+                origin.1,
+                time_limits,
+                self.network_type,
+                max_snap_m,
+                departure,
+            ),
+            None => self.sg.isochrones(
                 origin.0,
                 origin.1,
                 time_limits,
                 self.network_type,
                 max_snap_m,
+            ),
+        }
+        .ok_or_else(|| {
+            pyo3::exceptions::PyValueError::new_err(
+                "No graph node found within max_snap_m of the origin coordinates",
             )
-            .ok_or_else(|| {
-                pyo3::exceptions::PyValueError::new_err(
-                    "No graph node found within max_snap_m of the origin coordinates",
-                )
-            })?;
+        })?;
         Ok(output_minutes
             .into_iter()
             .zip(isos)
@@ -531,22 +1333,135 @@ impl PyGraph {
             .collect())
     }
 
-    #[pyo3(signature = (origin, destination, max_snap_m = Some(100.0)))]
+    #[pyo3(signature = (origin, destination, max_snap_m = Some(100.0), departure = None))]
     fn route(
         &self,
         origin: (f64, f64),
         destination: (f64, f64),
         max_snap_m: Option<f64>,
+        departure: Option<String>,
+    ) -> PyResult<PyRouteResult> {
+        let r = match parse_departure(departure)? {
+            Some(departure) => self.sg.route_at(
+                origin.0,
+                origin.1,
+                destination.0,
+                destination.1,
+                self.network_type,
+                max_snap_m,
+                departure,
+            ),
+            None => self.sg.route(
+                origin.0,
+                origin.1,
+                destination.0,
+                destination.1,
+                self.network_type,
+                max_snap_m,
+            ),
+        }?;
+        Ok(PyRouteResult { route: r })
+    }
+
+    #[pyo3(signature = (
+        depot,
+        stops,
+        return_to_depot = true,
+        time_windows = None,
+        max_snap_m = Some(100.0),
+        max_evaluations = tour::DEFAULT_MAX_EVALUATIONS
+    ))]
+    fn optimize_tour(
+        &self,
+        depot: (f64, f64),
+        stops: Vec<(f64, f64)>,
+        return_to_depot: bool,
+        time_windows: Option<Vec<Option<(f64, f64)>>>,
+        max_snap_m: Option<f64>,
+        max_evaluations: usize,
+    ) -> PyResult<PyTourResult> {
+        let options = tour::TourOptions {
+            return_to_depot,
+            time_windows: time_windows
+                .unwrap_or_default()
+                .into_iter()
+                .map(|window| {
+                    window.map(|(earliest_s, latest_s)| tour::TimeWindow {
+                        earliest_s,
+                        latest_s,
+                    })
+                })
+                .collect(),
+            max_evaluations,
+        };
+        let tour = self
+            .sg
+            .optimize_tour(depot, &stops, self.network_type, max_snap_m, &options)?;
+        Ok(PyTourResult { tour })
+    }
+
+    #[pyo3(signature = (points, max_snap_m = Some(100.0)))]
+    fn route_via(
+        &self,
+        points: Vec<(f64, f64)>,
+        max_snap_m: Option<f64>,
     ) -> PyResult<PyRouteResult> {
-        let r = self.sg.route(
+        let route = self.sg.route_via(&points, self.network_type, max_snap_m)?;
+        Ok(PyRouteResult { route })
+    }
+
+    #[pyo3(signature = (
+        origin,
+        destination,
+        k = 3,
+        max_shared = 0.5,
+        max_stretch = 1.4,
+        max_snap_m = Some(100.0)
+    ))]
+    fn route_alternatives(
+        &self,
+        origin: (f64, f64),
+        destination: (f64, f64),
+        k: usize,
+        max_shared: f64,
+        max_stretch: f64,
+        max_snap_m: Option<f64>,
+    ) -> PyResult<Vec<PyRouteResult>> {
+        let options = alternatives::AlternativeOptions {
+            max_alternatives: k,
+            max_shared_fraction: max_shared,
+            max_stretch,
+            ..Default::default()
+        };
+        let routes = self.sg.route_alternatives(
             origin.0,
             origin.1,
             destination.0,
             destination.1,
             self.network_type,
             max_snap_m,
+            &options,
         )?;
-        Ok(PyRouteResult { route: r })
+        Ok(routes
+            .into_iter()
+            .map(|route| PyRouteResult { route })
+            .collect())
+    }
+
+    #[pyo3(signature = (origins, destinations, max_snap_m = Some(100.0)))]
+    fn travel_time_matrix(
+        &self,
+        py: Python<'_>,
+        origins: Vec<(f64, f64)>,
+        destinations: Vec<(f64, f64)>,
+        max_snap_m: Option<f64>,
+    ) -> PyResult<PyTravelTimeMatrix> {
+        // Other Python threads keep running while the searches do.
+        let matrix = py.allow_threads(|| {
+            self.sg
+                .travel_time_matrix(&origins, &destinations, self.network_type, max_snap_m)
+        })?;
+        Ok(PyTravelTimeMatrix::new(py, matrix))
     }
 
     fn fetch_pois(&self, isochrone: &PyAny) -> PyResult<PyPoiCollection> {
@@ -564,27 +1479,36 @@ impl PyGraph {
         Ok(PyPoiCollection { pois })
     }
 
-    #[pyo3(signature = (origin, minutes, max_snap_m = Some(100.0)))]
+    #[pyo3(signature = (origin, minutes, max_snap_m = Some(100.0), departure = None))]
     fn reachable(
         &self,
         origin: (f64, f64),
         minutes: f64,
         max_snap_m: Option<f64>,
+        departure: Option<String>,
     ) -> PyResult<PyReachableGraph> {
-        let reachable = self
-            .sg
-            .reachable_graph(
+        let reachable = match parse_departure(departure)? {
+            Some(departure) => self.sg.reachable_graph_at(
+                origin.0,
+                origin.1,
+                minutes * 60.0,
+                self.network_type,
+                max_snap_m,
+                departure,
+            ),
+            None => self.sg.reachable_graph(
                 origin.0,
                 origin.1,
                 minutes * 60.0,
                 self.network_type,
                 max_snap_m,
+            ),
+        }
+        .ok_or_else(|| {
+            pyo3::exceptions::PyValueError::new_err(
+                "No graph node found within max_snap_m of the origin coordinates",
             )
-            .ok_or_else(|| {
-                pyo3::exceptions::PyValueError::new_err(
-                    "No graph node found within max_snap_m of the origin coordinates",
-                )
-            })?;
+        })?;
         Ok(PyReachableGraph {
             sg: reachable.graph,
             result: reachable.result,
@@ -666,6 +1590,9 @@ impl PyGraph {
                 props.insert("id".into(), n.id.into());
                 props.insert("lat".into(), n.lat.into());
                 props.insert("lon".into(), n.lon.into());
+                if let Some(elevation_m) = n.elevation_m {
+                    props.insert("elevation_m".into(), elevation_m.into());
+                }
                 geojson::Feature {
                     geometry: Some(geom),
                     properties: Some(props),
@@ -749,10 +1676,9 @@ impl PyReachableGraph {
     }
 
     fn contains_node(&self, node_id: i64) -> bool {
-        self.result
-            .distances
-            .keys()
-            .any(|&idx| self.sg.graph[idx].id == node_id)
+        self.sg
+            .node_index_for_osm_id(node_id)
+            .is_some_and(|idx| self.result.distances.contains_key(&idx))
     }
 
     fn nearest_node(&self, lat: f64, lon: f64) -> PyResult<Option<(i64, f64, f64)>> {
@@ -774,10 +1700,8 @@ impl PyReachableGraph {
     }
 
     fn travel_time_to_node_id(&self, node_id: i64) -> Option<f64> {
-        self.result
-            .distances
-            .iter()
-            .find_map(|(&idx, &time)| (self.sg.graph[idx].id == node_id).then_some(time))
+        let idx = self.sg.node_index_for_osm_id(node_id)?;
+        self.result.distances.get(&idx).copied()
     }
 
     fn nodes<'py>(&self, py: Python<'py>) -> PyResult<&'py PyList> {
@@ -1032,10 +1956,9 @@ impl PyPrismGraph {
     }
 
     fn contains_node(&self, node_id: i64) -> bool {
-        self.result
-            .feasible
-            .keys()
-            .any(|&idx| self.sg.graph[idx].id == node_id)
+        self.sg
+            .node_index_for_osm_id(node_id)
+            .is_some_and(|idx| self.result.feasible.contains_key(&idx))
     }
 
     fn nearest_node(&self, lat: f64, lon: f64) -> PyResult<Option<(i64, f64, f64)>> {
@@ -1057,10 +1980,8 @@ impl PyPrismGraph {
     }
 
     fn slack_at_node_id(&self, node_id: i64) -> Option<f64> {
-        self.result
-            .feasible
-            .iter()
-            .find_map(|(&idx, node)| (self.sg.graph[idx].id == node_id).then_some(node.slack))
+        let idx = self.sg.node_index_for_osm_id(node_id)?;
+        self.result.feasible.get(&idx).map(|node| node.slack)
     }
 
     fn nodes<'py>(&self, py: Python<'py>) -> PyResult<&'py PyList> {
@@ -1324,10 +2245,13 @@ fn cache_dir() -> PyResult<String> {
 #[pymodule]
 fn graphways(_py: Python, m: &PyModule) -> pyo3::PyResult<()> {
     m.add_class::<PyGraph>()?;
+    m.add_class::<PySpeedProfile>()?;
     m.add_class::<PyReachableGraph>()?;
     m.add_class::<PyPrismGraph>()?;
     m.add_class::<PySnapResult>()?;
     m.add_class::<PyRouteResult>()?;
+    m.add_class::<PyTravelTimeMatrix>()?;
+    m.add_class::<PyTourResult>()?;
     m.add_class::<PyIsochroneResult>()?;
     m.add_class::<PyPoi>()?;
     m.add_class::<PyPoiCollection>()?;
@@ -0,0 +1,441 @@
+//! Turn-by-turn maneuvers along a route.
+//!
+//! A [`Route`](crate::routing::Route) is split into steps, each starting with
+//! a [`Maneuver`]: departing, turning or continuing onto another street,
+//! taking a roundabout exit, and arriving. Turn directions come from the
+//! bearings of the route geometry on either side of a junction; street names
+//! come from the `name` tag, or `ref` for unnamed roads.
+
+use petgraph::graph::{DiGraph, NodeIndex};
+
+use crate::graph::{XmlNode, XmlWay};
+use crate::overpass::NetworkType;
+use crate::search::SearchPath;
+use crate::utils::calculate_bearing;
+
+/// What a traveller does at the start of a [`Maneuver`]'s step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManeuverKind {
+    /// Leave the origin.
+    Depart,
+    /// Carry straight on, onto a street with another name.
+    Continue,
+    SlightRight,
+    Right,
+    SharpRight,
+    SlightLeft,
+    Left,
+    SharpLeft,
+    UTurn,
+    /// Enter a roundabout and leave it at the given exit, counting from 1.
+    Roundabout {
+        exit: usize,
+    },
+    /// Pass through an intermediate waypoint of a multi-stop route,
+    /// numbered from 1; the step is empty.
+    Waypoint {
+        index: usize,
+    },
+    /// Reach the destination; the step is empty.
+    Arrive,
+}
+
+impl ManeuverKind {
+    /// Classify a signed turn angle in degrees (negative = left).
+    fn from_angle(angle_deg: f64) -> Self {
+        let magnitude = angle_deg.abs();
+        let right = angle_deg > 0.0;
+        if magnitude <= 15.0 {
+            ManeuverKind::Continue
+        } else if magnitude <= 45.0 {
+            if right {
+                ManeuverKind::SlightRight
+            } else {
+                ManeuverKind::SlightLeft
+            }
+        } else if magnitude <= 120.0 {
+            if right {
+                ManeuverKind::Right
+            } else {
+                ManeuverKind::Left
+            }
+        } else if magnitude < 150.0 {
+            if right {
+                ManeuverKind::SharpRight
+            } else {
+                ManeuverKind::SharpLeft
+            }
+        } else {
+            ManeuverKind::UTurn
+        }
+    }
+
+    /// Short identifier such as `"turn-left"`, as used in GeoJSON output.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ManeuverKind::Depart => "depart",
+            ManeuverKind::Continue => "continue",
+            ManeuverKind::SlightRight => "turn-slight-right",
+            ManeuverKind::Right => "turn-right",
+            ManeuverKind::SharpRight => "turn-sharp-right",
+            ManeuverKind::SlightLeft => "turn-slight-left",
+            ManeuverKind::Left => "turn-left",
+            ManeuverKind::SharpLeft => "turn-sharp-left",
+            ManeuverKind::UTurn => "u-turn",
+            ManeuverKind::Roundabout { .. } => "roundabout",
+            ManeuverKind::Waypoint { .. } => "waypoint",
+            ManeuverKind::Arrive => "arrive",
+        }
+    }
+}
+
+/// One step of a route's turn-by-turn directions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Maneuver {
+    pub kind: ManeuverKind,
+    /// Index into [`Route::coordinates`](crate::routing::Route::coordinates)
+    /// where the maneuver takes place.
+    pub coordinate_index: usize,
+    /// Street followed after the maneuver, from its `name` or else `ref`
+    /// tag; `None` for unnamed roads.
+    pub street: Option<String>,
+    /// Compass bearing in degrees when leaving the maneuver point; `0` on
+    /// arrival.
+    pub bearing_deg: f64,
+    /// Metres from this maneuver to the next one.
+    pub distance_m: f64,
+    /// Seconds from this maneuver to the next one.
+    pub duration_s: f64,
+}
+
+impl Maneuver {
+    /// English instruction such as "Turn left onto Main Street".
+    pub fn instruction(&self) -> String {
+        let onto = |text: &str| match &self.street {
+            Some(street) => format!("{text} onto {street}"),
+            None => text.to_string(),
+        };
+        match self.kind {
+            ManeuverKind::Depart => match &self.street {
+                Some(street) => format!("Head {} on {street}", compass(self.bearing_deg)),
+                None => format!("Head {}", compass(self.bearing_deg)),
+            },
+            ManeuverKind::Continue => onto("Continue"),
+            ManeuverKind::SlightRight => onto("Turn slightly right"),
+            ManeuverKind::Right => onto("Turn right"),
+            ManeuverKind::SharpRight => onto("Turn sharp right"),
+            ManeuverKind::SlightLeft => onto("Turn slightly left"),
+            ManeuverKind::Left => onto("Turn left"),
+            ManeuverKind::SharpLeft => onto("Turn sharp left"),
+            ManeuverKind::UTurn => onto("Make a U-turn"),
+            ManeuverKind::Roundabout { exit } => onto(&format!(
+                "At the roundabout, take the {} exit",
+                ordinal(exit)
+            )),
+            ManeuverKind::Waypoint { index } => format!("Arrive at waypoint {index}"),
+            ManeuverKind::Arrive => "Arrive at your destination".to_string(),
+        }
+    }
+}
+
+fn compass(bearing_deg: f64) -> &'static str {
+    const POINTS: [&str; 8] = [
+        "north",
+        "northeast",
+        "east",
+        "southeast",
+        "south",
+        "southwest",
+        "west",
+        "northwest",
+    ];
+    POINTS[((bearing_deg.rem_euclid(360.0) + 22.5) / 45.0) as usize % 8]
+}
+
+fn ordinal(n: usize) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{n}{suffix}")
+}
+
+fn tag<'a>(way: &'a XmlWay, key: &str) -> Option<&'a str> {
+    way.tags
+        .iter()
+        .find(|tag| tag.key == key)
+        .map(|tag| tag.value.as_str())
+}
+
+fn street(way: &XmlWay) -> Option<&str> {
+    tag(way, "name").or_else(|| tag(way, "ref"))
+}
+
+fn is_roundabout(way: &XmlWay) -> bool {
+    matches!(tag(way, "junction"), Some("roundabout" | "circular"))
+}
+
+/// Whether more than two roads meet at `node`, so a change of direction
+/// there is a turn rather than a bend.
+fn is_junction(graph: &DiGraph<XmlNode, XmlWay>, node: NodeIndex) -> bool {
+    let mut neighbors: Vec<NodeIndex> = graph
+        .neighbors_undirected(node)
+        .filter(|&n| n != node)
+        .collect();
+    neighbors.sort_unstable();
+    neighbors.dedup();
+    neighbors.len() > 2
+}
+
+/// Bearing of the route geometry arriving at `coordinates[index]`, skipping
+/// repeated points; `None` at the start of the route.
+fn bearing_into(coordinates: &[(f64, f64)], index: usize) -> Option<f64> {
+    let (lat, lon) = coordinates[index];
+    coordinates[..index]
+        .iter()
+        .rev()
+        .find(|&&point| point != (lat, lon))
+        .map(|&(from_lat, from_lon)| calculate_bearing(from_lat, from_lon, lat, lon))
+}
+
+/// Bearing of the route geometry leaving `coordinates[index]`, skipping
+/// repeated points; `None` at the end of the route.
+fn bearing_out_of(coordinates: &[(f64, f64)], index: usize) -> Option<f64> {
+    let (lat, lon) = coordinates[index];
+    coordinates[index + 1..]
+        .iter()
+        .find(|&&point| point != (lat, lon))
+        .map(|&(to_lat, to_lon)| calculate_bearing(lat, lon, to_lat, to_lon))
+}
+
+/// Maneuvers along `path`, whose edge `i` starts at
+/// `coordinates[edge_starts[i]]`.
+///
+/// A maneuver is emitted where the street name changes, at every real turn
+/// at a junction, on entering a roundabout and for U-turns. Bends along one
+/// street and slight forks that keep the name are folded into the step.
+pub(crate) fn route_maneuvers(
+    graph: &DiGraph<XmlNode, XmlWay>,
+    path: &SearchPath,
+    edge_starts: &[usize],
+    coordinates: &[(f64, f64)],
+    cumulative_times_s: &[f64],
+    network_type: NetworkType,
+) -> Vec<Maneuver> {
+    let maneuver = |kind, coordinate_index: usize, street: Option<&str>| Maneuver {
+        kind,
+        coordinate_index,
+        street: street.map(str::to_string),
+        bearing_deg: bearing_out_of(coordinates, coordinate_index).unwrap_or(0.0),
+        distance_m: 0.0,
+        duration_s: 0.0,
+    };
+    let ways: Vec<&XmlWay> = path.edges.iter().map(|&edge| &graph[edge]).collect();
+    let edge_length = |i: usize| {
+        let (from, to) = path.edge_span(i);
+        ways[i].length * (to - from)
+    };
+
+    let mut maneuvers = vec![maneuver(
+        ManeuverKind::Depart,
+        0,
+        ways.first().and_then(|way| street(way)),
+    )];
+    let mut i = 0;
+    while i < ways.len() {
+        let start_index = edge_starts[i];
+        if i > 0 && is_roundabout(ways[i]) && !is_roundabout(ways[i - 1]) {
+            // Count the exits passed on the way round: every roundabout node
+            // where a road leaves the ring, up to the one the route takes.
+            let mut end = i;
+            let mut exit = 1;
+            while end + 1 < ways.len() && is_roundabout(ways[end + 1]) {
+                let (_, node) = graph.edge_endpoints(path.edges[end]).unwrap();
+                if leaves_ring(graph, node, network_type) {
+                    exit += 1;
+                }
+                end += 1;
+            }
+            let exit_street = ways
+                .get(end + 1)
+                .copied()
+                .or(Some(ways[i]))
+                .and_then(street);
+            maneuvers.push(maneuver(
+                ManeuverKind::Roundabout { exit },
+                start_index,
+                exit_street,
+            ));
+            for j in i..=(end + 1).min(ways.len() - 1) {
+                maneuvers.last_mut().unwrap().distance_m += edge_length(j);
+            }
+            i = end + 2;
+            continue;
+        }
+        if i > 0 {
+            let kind = match (
+                bearing_into(coordinates, start_index),
+                bearing_out_of(coordinates, start_index),
+            ) {
+                (Some(incoming), Some(outgoing)) => ManeuverKind::from_angle(
+                    (outgoing - incoming + 180.0).rem_euclid(360.0) - 180.0,
+                ),
+                _ => ManeuverKind::Continue,
+            };
+            let (_, node) = graph.edge_endpoints(path.edges[i - 1]).unwrap();
+            let renamed = street(ways[i]) != street(ways[i - 1]);
+            let turned = !matches!(
+                kind,
+                ManeuverKind::Continue | ManeuverKind::SlightLeft | ManeuverKind::SlightRight
+            ) && is_junction(graph, node);
+            if renamed || turned || kind == ManeuverKind::UTurn {
+                maneuvers.push(maneuver(kind, start_index, street(ways[i])));
+            }
+        }
+        maneuvers.last_mut().unwrap().distance_m += edge_length(i);
+        i += 1;
+    }
+    let last_index = coordinates.len() - 1;
+    maneuvers.push(maneuver(ManeuverKind::Arrive, last_index, None));
+
+    for k in 0..maneuvers.len() - 1 {
+        let (from, to) = (
+            maneuvers[k].coordinate_index,
+            maneuvers[k + 1].coordinate_index,
+        );
+        maneuvers[k].duration_s = cumulative_times_s[to] - cumulative_times_s[from];
+    }
+    maneuvers
+}
+
+/// Whether a road usable by `network_type` leaves the roundabout at `node`.
+fn leaves_ring(
+    graph: &DiGraph<XmlNode, XmlWay>,
+    node: NodeIndex,
+    network_type: NetworkType,
+) -> bool {
+    graph.edges(node).any(|edge| {
+        let way = edge.weight();
+        !is_roundabout(way) && way.travel_time(network_type).is_finite()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::SpatialGraph;
+    use crate::routing::Route;
+
+    // Main Street runs east from 1 through 2 to 4 and becomes High Street
+    // at 6; Oak Avenue crosses it at 2 from 5 in the south to 3 in the north.
+    // A roundabout D → C → B → A → D has arms west (W), south (S), east (E)
+    // and north (N).
+    const XML: &str = r#"<osm>
+          <node id="1" lat="48.000" lon="11.000" />
+          <node id="2" lat="48.000" lon="11.002" />
+          <node id="3" lat="48.002" lon="11.002" />
+          <node id="4" lat="48.000" lon="11.004" />
+          <node id="5" lat="47.998" lon="11.002" />
+          <node id="6" lat="48.000" lon="11.006" />
+          <node id="20" lat="48.010" lon="11.010" />
+          <node id="21" lat="48.009" lon="11.011" />
+          <node id="22" lat="48.008" lon="11.010" />
+          <node id="23" lat="48.009" lon="11.009" />
+          <node id="24" lat="48.009" lon="11.006" />
+          <node id="25" lat="48.005" lon="11.010" />
+          <node id="26" lat="48.009" lon="11.014" />
+          <node id="27" lat="48.013" lon="11.010" />
+          <way id="10"><nd ref="1" /><nd ref="2" /><nd ref="4" />
+            <tag k="highway" v="residential" /><tag k="name" v="Main Street" /></way>
+          <way id="11"><nd ref="4" /><nd ref="6" />
+            <tag k="highway" v="residential" /><tag k="name" v="High Street" /></way>
+          <way id="12"><nd ref="5" /><nd ref="2" /><nd ref="3" />
+            <tag k="highway" v="residential" /><tag k="name" v="Oak Avenue" /></way>
+
+          <way id="30"><nd ref="23" /><nd ref="22" /><nd ref="21" /><nd ref="20" /><nd ref="23" />
+            <tag k="highway" v="primary" /><tag k="junction" v="roundabout" /></way>
+          <way id="31"><nd ref="24" /><nd ref="23" />
+            <tag k="highway" v="primary" /><tag k="name" v="West Road" /></way>
+          <way id="32"><nd ref="22" /><nd ref="25" />
+            <tag k="highway" v="primary" /><tag k="name" v="South Road" /></way>
+          <way id="33"><nd ref="21" /><nd ref="26" />
+            <tag k="highway" v="primary" /><tag k="ref" v="B 12" /></way>
+          <way id="34"><nd ref="20" /><nd ref="27" />
+            <tag k="highway" v="primary" /><tag k="name" v="North Road" /></way>
+        </osm>"#;
+
+    fn route_between(from: (f64, f64), to: (f64, f64)) -> Route {
+        SpatialGraph::from_osm(XML, NetworkType::Drive, Some(true), None)
+            .unwrap()
+            .route(from.0, from.1, to.0, to.1, NetworkType::Drive, None)
+            .unwrap()
+    }
+
+    fn instructions(route: &Route) -> Vec<String> {
+        route.maneuvers.iter().map(Maneuver::instruction).collect()
+    }
+
+    #[test]
+    fn turns_and_name_changes_start_new_steps() {
+        let route = route_between((48.0, 11.0), (48.002, 11.002));
+        assert_eq!(
+            instructions(&route),
+            [
+                "Head east on Main Street",
+                "Turn left onto Oak Avenue",
+                "Arrive at your destination",
+            ]
+        );
+        assert_eq!(
+            route.coordinates[route.maneuvers[1].coordinate_index],
+            (48.0, 11.002)
+        );
+
+        // Straight through the junction at 2 is not a maneuver; the change
+        // of name at 4 is.
+        let route = route_between((48.0, 11.0), (48.0, 11.006));
+        assert_eq!(
+            instructions(&route),
+            [
+                "Head east on Main Street",
+                "Continue onto High Street",
+                "Arrive at your destination",
+            ]
+        );
+    }
+
+    #[test]
+    fn steps_add_up_to_the_route() {
+        let route = route_between((48.0, 11.0005), (48.0015, 11.002));
+        let distance: f64 = route.maneuvers.iter().map(|m| m.distance_m).sum();
+        let duration: f64 = route.maneuvers.iter().map(|m| m.duration_s).sum();
+        assert!((distance - route.distance_m).abs() < 1e-6);
+        assert!((duration - route.duration_s).abs() < 1e-6);
+        let arrive = route.maneuvers.last().unwrap();
+        assert_eq!(arrive.kind, ManeuverKind::Arrive);
+        assert_eq!(arrive.coordinate_index, route.coordinates.len() - 1);
+        assert_eq!(arrive.distance_m, 0.0);
+    }
+
+    #[test]
+    fn roundabout_exits_are_counted() {
+        let route = route_between((48.009, 11.006), (48.009, 11.014));
+        assert_eq!(
+            instructions(&route),
+            [
+                "Head east on West Road",
+                "At the roundabout, take the 2nd exit onto B 12",
+                "Arrive at your destination",
+            ]
+        );
+        let route = route_between((48.009, 11.006), (48.013, 11.010));
+        assert_eq!(
+            route.maneuvers[1].kind,
+            ManeuverKind::Roundabout { exit: 3 }
+        );
+        assert_eq!(route.maneuvers[1].street.as_deref(), Some("North Road"));
+    }
+}
@@ -0,0 +1,441 @@
+//! Many-to-many travel times.
+//!
+//! [`SpatialGraph::travel_time_matrix`] snaps every origin and destination
+//! once, then runs one search per origin that stops as soon as every
+//! destination is settled, spreading the origins over all available cores.
+//! On graphs with turn restrictions or turn penalties for the network type
+//! that search is edge-based, and on graphs with a contraction hierarchy the
+//! destinations' upward searches are run once and shared by every origin, so
+//! every cell matches the duration of the equivalent `route`.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::thread;
+
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
+
+use crate::error::OsmGraphError;
+use crate::graph::{SnapResult, SpatialGraph};
+use crate::hierarchy::DestinationBuckets;
+use crate::overpass::NetworkType;
+use crate::reachability::EdgeInfo;
+use crate::search::{edge_based_search, same_edge_path, Anchor, SearchDirection, SearchPath};
+
+/// Travel times, and distances along the same paths, between every origin
+/// and every destination.
+#[derive(Debug, Clone)]
+pub struct TravelTimeMatrix {
+    /// Where each origin met the network; `None` when it could not be
+    /// snapped within `max_snap_m`, which leaves its row unreachable.
+    pub origin_snaps: Vec<Option<SnapResult>>,
+    /// Where each destination met the network, as for `origin_snaps`.
+    pub destination_snaps: Vec<Option<SnapResult>>,
+    /// `durations_s[i][j]`: seconds from origin `i` to destination `j` for
+    /// the network type, or `None` when there is no path.
+    pub durations_s: Vec<Vec<Option<f64>>>,
+    /// `distances_m[i][j]`: metres along the fastest path, or `None` when
+    /// there is no path.
+    pub distances_m: Vec<Vec<Option<f64>>>,
+}
+
+/// Duration and distance of one cell.
+type Cell = Option<(f64, f64)>;
+
+impl SpatialGraph {
+    /// Travel times from every `(lat, lon)` origin to every destination.
+    ///
+    /// Points farther than `max_snap_m` from the network are left unsnapped
+    /// and their row or column is unreachable instead of failing the whole
+    /// matrix. Returns [`OsmGraphError::EmptyGraph`] for a graph without
+    /// nodes.
+    pub fn travel_time_matrix(
+        &self,
+        origins: &[(f64, f64)],
+        destinations: &[(f64, f64)],
+        network_type: NetworkType,
+        max_snap_m: Option<f64>,
+    ) -> Result<TravelTimeMatrix, OsmGraphError> {
+        if self.graph.node_count() == 0 {
+            return Err(OsmGraphError::EmptyGraph);
+        }
+        let snap = |&(lat, lon): &(f64, f64)| self.snap_point_within(lat, lon, max_snap_m);
+        let origin_snaps: Vec<Option<SnapResult>> = origins.iter().map(snap).collect();
+        let destination_snaps: Vec<Option<SnapResult>> = destinations.iter().map(snap).collect();
+        let origin_anchors: Vec<Option<Anchor>> = origin_snaps
+            .iter()
+            .map(|snap| snap.as_ref().map(|snap| self.anchor(snap)))
+            .collect();
+        let destination_anchors: Vec<Option<Anchor>> = destination_snaps
+            .iter()
+            .map(|snap| snap.as_ref().map(|snap| self.anchor(snap)))
+            .collect();
+
+        // Turn-aware searches cannot use the hierarchy, which ignores turns.
+        let buckets = self
+            .contraction_hierarchy(network_type)
+            .filter(|_| self.turn_model(network_type).is_none())
+            .map(|hierarchy| {
+                hierarchy.destination_buckets(&self.graph, &destination_anchors, |e, _| {
+                    self.search_cost(e.id, network_type, None, 0.0)
+                })
+            });
+        let row = |origin: &Option<Anchor>| match origin {
+            None => vec![None; destination_anchors.len()],
+            Some(origin) => {
+                self.matrix_row(origin, &destination_anchors, network_type, buckets.as_ref())
+            }
+        };
+        let threads = thread::available_parallelism()
+            .map_or(1, usize::from)
+            .min(origin_anchors.len())
+            .max(1);
+        let rows_per_thread = origin_anchors.len().div_ceil(threads).max(1);
+        let rows: Vec<Vec<Cell>> = thread::scope(|scope| {
+            let workers: Vec<_> = origin_anchors
+                .chunks(rows_per_thread)
+                .map(|chunk| scope.spawn(move || chunk.iter().map(row).collect::<Vec<_>>()))
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().expect("matrix worker panicked"))
+                .collect()
+        });
+
+        let column = |pick: fn((f64, f64)) -> f64| -> Vec<Vec<Option<f64>>> {
+            rows.iter()
+                .map(|row| row.iter().map(|cell| cell.map(pick)).collect())
+                .collect()
+        };
+        Ok(TravelTimeMatrix {
+            durations_s: column(|(duration, _)| duration),
+            distances_m: column(|(_, distance)| distance),
+            origin_snaps,
+            destination_snaps,
+        })
+    }
+
+    /// Cells from `origin` to each of `destinations`, from one search:
+    /// edge-based when the graph has a turn model for `network_type`, over
+    /// the hierarchy's `buckets` when it has a contraction hierarchy.
+    fn matrix_row(
+        &self,
+        origin: &Anchor,
+        destinations: &[Option<Anchor>],
+        network_type: NetworkType,
+        buckets: Option<&DestinationBuckets<'_>>,
+    ) -> Vec<Cell> {
+        let cost = |e: EdgeInfo<'_>, _| self.search_cost(e.id, network_type, None, 0.0);
+        let paths = if let Some(turns) = self.turn_model(network_type) {
+            let targets: Vec<&Anchor> = destinations.iter().flatten().collect();
+            let mut paths = edge_based_search(
+                &self.graph,
+                turns,
+                origin,
+                SearchDirection::Forward,
+                &targets,
+                f64::INFINITY,
+                cost,
+                |_| 0.0,
+            )
+            .paths
+            .into_iter();
+            destinations
+                .iter()
+                .map(|destination| destination.as_ref().and_then(|_| paths.next().flatten()))
+                .collect()
+        } else if let Some(buckets) = buckets {
+            buckets.paths_from(&self.graph, origin, cost)
+        } else {
+            return self.one_to_many(origin, destinations, network_type);
+        };
+        paths
+            .into_iter()
+            .map(|path| path.map(|path| (path.cost, self.path_distance(&path))))
+            .collect()
+    }
+
+    /// Metres travelled along `path`.
+    fn path_distance(&self, path: &SearchPath) -> f64 {
+        path.edges
+            .iter()
+            .enumerate()
+            .map(|(i, &edge)| {
+                let (from, to) = path.edge_span(i);
+                self.graph[edge].length * (to - from)
+            })
+            .sum()
+    }
+
+    /// Dijkstra from `origin` until every destination is settled, tracking
+    /// the distance along each cheapest path.
+    fn one_to_many(
+        &self,
+        origin: &Anchor,
+        destinations: &[Option<Anchor>],
+        network_type: NetworkType,
+    ) -> Vec<Cell> {
+        let cost = |e: EdgeInfo<'_>, _| self.search_cost(e.id, network_type, None, 0.0);
+        let mut cells: Vec<Cell> = destinations
+            .iter()
+            .map(|destination| {
+                let path = same_edge_path(&self.graph, origin, destination.as_ref()?, cost)?;
+                let (from, to) = path.edge_span(0);
+                Some((path.cost, self.graph[path.edges[0]].length * (to - from)))
+            })
+            .collect();
+
+        // Destinations at a node, and destination edges by the node they
+        // leave from: (destination, edge, fraction of the edge before it).
+        let mut at_node: HashMap<NodeIndex, Vec<usize>> = HashMap::new();
+        let mut exits: HashMap<NodeIndex, Vec<(usize, EdgeIndex, f64)>> = HashMap::new();
+        for (index, destination) in destinations.iter().enumerate() {
+            let Some(destination) = destination else {
+                continue;
+            };
+            if destination.is_node() {
+                at_node.entry(destination.node).or_default().push(index);
+            }
+            for &(edge, fraction) in &destination.edges {
+                let (source, _) = self.graph.edge_endpoints(edge).unwrap();
+                exits
+                    .entry(source)
+                    .or_default()
+                    .push((index, edge, fraction));
+            }
+        }
+        // Once every destination has a cost, the search can stop at the
+        // largest of them; costs only fall, so it stays an upper bound.
+        let mut unreached = destinations
+            .iter()
+            .zip(&cells)
+            .filter(|(destination, cell)| destination.is_some() && cell.is_none())
+            .count();
+        let mut bound = f64::INFINITY;
+
+        let mut labels: HashMap<NodeIndex, (f64, f64)> = HashMap::new();
+        let mut heap = BinaryHeap::new();
+        let seeds: Vec<(NodeIndex, f64, f64)> = if origin.is_node() {
+            vec![(origin.node, 0.0, 0.0)]
+        } else {
+            origin
+                .partial_edges(&self.graph, SearchDirection::Forward, cost)
+                .into_iter()
+                .map(|(edge, edge_cost)| {
+                    let fraction = origin.fraction_on(edge).unwrap_or(0.0);
+                    (
+                        self.graph.edge_endpoints(edge).unwrap().1,
+                        edge_cost,
+                        self.graph[edge].length * (1.0 - fraction),
+                    )
+                })
+                .collect()
+        };
+        for (node, node_cost, distance) in seeds {
+            if labels
+                .get(&node)
+                .is_none_or(|&(known, _)| node_cost < known)
+            {
+                labels.insert(node, (node_cost, distance));
+                heap.push(State {
+                    cost: node_cost,
+                    node,
+                });
+            }
+        }
+
+        while let Some(State {
+            cost: node_cost,
+            node,
+        }) = heap.pop()
+        {
+            let (known, distance) = labels[&node];
+            if node_cost > known {
+                continue;
+            }
+            if node_cost >= bound {
+                break;
+            }
+
+            let mut reach = |index: usize, total: f64, distance: f64| match &mut cells[index] {
+                Some(cell) if total < cell.0 => *cell = (total, distance),
+                Some(_) => {}
+                cell @ None => {
+                    *cell = Some((total, distance));
+                    unreached -= 1;
+                }
+            };
+            for &index in at_node.get(&node).into_iter().flatten() {
+                reach(index, node_cost, distance);
+            }
+            for &(index, edge, fraction) in exits.get(&node).into_iter().flatten() {
+                let edge_cost = self.search_cost(edge, network_type, None, node_cost);
+                if edge_cost.is_finite() && edge_cost >= 0.0 {
+                    reach(
+                        index,
+                        node_cost + fraction * edge_cost,
+                        distance + fraction * self.graph[edge].length,
+                    );
+                }
+            }
+            if unreached == 0 && bound.is_infinite() {
+                bound = cells
+                    .iter()
+                    .flatten()
+                    .map(|&(cost, _)| cost)
+                    .fold(0.0, f64::max);
+            }
+            for edge in self.graph.edges(node) {
+                let edge_cost = self.search_cost(edge.id(), network_type, None, node_cost);
+                if !edge_cost.is_finite() || edge_cost < 0.0 {
+                    continue;
+                }
+                let next = edge.target();
+                let next_cost = node_cost + edge_cost;
+                if labels
+                    .get(&next)
+                    .is_none_or(|&(known, _)| next_cost < known)
+                {
+                    labels.insert(next, (next_cost, distance + edge.weight().length));
+                    heap.push(State {
+                        cost: next_cost,
+                        node: next,
+                    });
+                }
+            }
+        }
+        cells
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct State {
+    cost: f64,
+    node: NodeIndex,
+}
+
+impl PartialEq for State {
+    fn eq(&self, other: &Self) -> bool {
+        self.cost == other.cost && self.node == other.node
+    }
+}
+
+impl Eq for State {}
+
+impl PartialOrd for State {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for State {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .partial_cmp(&self.cost)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::turns::{
+        RestrictionKind, RestrictionModes, RestrictionVia, TurnPenalties, TurnRestriction,
+    };
+
+    // A one-way loop 1 → 2 → 3 → 1 beside a two-way street 3 - 4, and node
+    // 5 on an island.
+    const XML: &str = r#"<osm>
+          <node id="1" lat="48.000" lon="11.000" />
+          <node id="2" lat="48.000" lon="11.002" />
+          <node id="3" lat="48.002" lon="11.001" />
+          <node id="4" lat="48.004" lon="11.001" />
+          <node id="5" lat="48.010" lon="11.010" />
+          <node id="6" lat="48.010" lon="11.011" />
+          <way id="10"><nd ref="1" /><nd ref="2" /><nd ref="3" /><nd ref="1" />
+            <tag k="highway" v="residential" /><tag k="oneway" v="yes" /></way>
+          <way id="11"><nd ref="3" /><nd ref="4" /><tag k="highway" v="primary" /></way>
+          <way id="12"><nd ref="5" /><nd ref="6" /><tag k="highway" v="residential" /></way>
+        </osm>"#;
+
+    fn graph() -> SpatialGraph {
+        SpatialGraph::from_osm(XML, NetworkType::Drive, Some(true), None).unwrap()
+    }
+
+    #[test]
+    fn matrix_cells_match_routes() {
+        let points = [
+            (48.0, 11.0),
+            (48.0, 11.0013),
+            (48.002, 11.001),
+            (48.003, 11.001),
+            (48.0041, 11.001),
+        ];
+        // No turning from the primary street onto the loop at node 3.
+        let no_left = TurnRestriction {
+            relation_id: 20,
+            kind: RestrictionKind::No,
+            from_way: 11,
+            via: RestrictionVia::Node(3),
+            to_way: 10,
+            modes: RestrictionModes::ALL,
+        };
+        for sg in [
+            graph(),
+            graph().with_contraction_hierarchy(NetworkType::Drive),
+            graph().with_turn_penalties(
+                NetworkType::Drive,
+                TurnPenalties::for_network(NetworkType::Drive),
+            ),
+            graph()
+                .with_turn_restrictions(vec![no_left])
+                .with_contraction_hierarchy(NetworkType::Drive),
+        ] {
+            let matrix = sg
+                .travel_time_matrix(&points, &points, NetworkType::Drive, Some(50.0))
+                .unwrap();
+            for (i, &(from_lat, from_lon)) in points.iter().enumerate() {
+                for (j, &(to_lat, to_lon)) in points.iter().enumerate() {
+                    let route = sg
+                        .route(from_lat, from_lon, to_lat, to_lon, NetworkType::Drive, None)
+                        .ok();
+                    let duration = matrix.durations_s[i][j];
+                    let distance = matrix.distances_m[i][j];
+                    assert_eq!(duration.is_some(), route.is_some(), "{i} -> {j}");
+                    let Some(route) = route else {
+                        continue;
+                    };
+                    assert!(
+                        (duration.unwrap() - route.duration_s).abs() < 1e-6,
+                        "{i} -> {j}"
+                    );
+                    assert!(
+                        (distance.unwrap() - route.distance_m).abs() < 1e-3,
+                        "{i} -> {j}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unreachable_and_unsnapped_cells_are_none() {
+        let sg = graph();
+        let matrix = sg
+            .travel_time_matrix(
+                &[(48.0, 11.0), (49.0, 12.0)],
+                &[(48.002, 11.001), (48.010, 11.010), (49.0, 12.0)],
+                NetworkType::Drive,
+                Some(50.0),
+            )
+            .unwrap();
+
+        assert!(matrix.origin_snaps[1].is_none());
+        assert!(matrix.destination_snaps[2].is_none());
+        assert!(matrix.durations_s[0][0].is_some());
+        assert_eq!(matrix.durations_s[0][1..], [None, None]);
+        assert_eq!(matrix.durations_s[1], [None, None, None]);
+        assert_eq!(matrix.distances_m[1], [None, None, None]);
+    }
+}
@@ -0,0 +1,243 @@
+//! Costs of passing through tagged nodes.
+//!
+//! Barriers (`barrier=bollard`, `barrier=gate`, ...) and node `access` tags
+//! block the modes they apply to; traffic signals, stop signs and crossings
+//! delay them. Both are applied at graph construction to the edges around the
+//! node, which then survives simplification as an edge endpoint.
+
+use petgraph::graph::DiGraph;
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+
+use crate::conditional::{access_keys, RESTRICTIVE_VALUES};
+use crate::graph::{XmlNode, XmlTag, XmlWay};
+use crate::overpass::NetworkType;
+use crate::speed::SpeedProfile;
+
+/// The modes whose travel times every edge carries.
+const MODES: [NetworkType; 3] = [NetworkType::Walk, NetworkType::Bike, NetworkType::Drive];
+
+/// Barriers that stop motor vehicles unless the node's access tags allow
+/// them. Gates and lift gates stop nobody unless access tags say so.
+const MOTOR_BARRIERS: &[&str] = &[
+    "block",
+    "bollard",
+    "bus_trap",
+    "chain",
+    "cycle_barrier",
+    "full-height_turnstile",
+    "kissing_gate",
+    "stile",
+    "turnstile",
+];
+
+/// Barriers that also stop cyclists.
+const BIKE_BARRIERS: &[&str] = &[
+    "full-height_turnstile",
+    "kissing_gate",
+    "stile",
+    "turnstile",
+];
+
+fn tag<'a>(tags: &'a [XmlTag], key: &str) -> Option<&'a str> {
+    tags.iter()
+        .find(|tag| tag.key == key)
+        .map(|tag| tag.value.as_str())
+}
+
+/// Whether a node with `tags` cannot be passed by `mode` (`Walk`, `Bike` or
+/// `Drive`).
+///
+/// On barriers and nodes tagged `access=*`, the most specific access key for
+/// the mode decides; otherwise the barrier type's default applies.
+pub(crate) fn node_blocks(tags: &[XmlTag], mode: NetworkType) -> bool {
+    let barrier = tag(tags, "barrier");
+    if barrier.is_none() && tag(tags, "access").is_none() {
+        return false;
+    }
+    if let Some(value) = access_keys(mode)
+        .iter()
+        .rev()
+        .find_map(|key| tag(tags, key))
+    {
+        return RESTRICTIVE_VALUES.contains(&value);
+    }
+    match (barrier, mode) {
+        (None, _) | (Some(_), NetworkType::Walk) => false,
+        (Some(barrier), NetworkType::Bike) => BIKE_BARRIERS.contains(&barrier),
+        (Some(barrier), _) => MOTOR_BARRIERS.contains(&barrier),
+    }
+}
+
+/// Seconds `mode` loses passing a node with `tags`: traffic signals and stop
+/// signs delay vehicles, crossings delay walkers.
+pub(crate) fn node_delay_s(tags: &[XmlTag], mode: NetworkType, profile: &SpeedProfile) -> f64 {
+    let highway = tag(tags, "highway");
+    let crossing = tag(tags, "crossing");
+    match mode {
+        NetworkType::Walk => match crossing {
+            Some("traffic_signals") => profile.traffic_signal_delay_s,
+            Some("no") => 0.0,
+            Some(_) => profile.crossing_delay_s,
+            None if highway == Some("crossing") => profile.crossing_delay_s,
+            None => 0.0,
+        },
+        _ => match highway {
+            Some("traffic_signals") => profile.traffic_signal_delay_s,
+            Some("stop") => profile.stop_delay_s,
+            _ => 0.0,
+        },
+    }
+}
+
+/// Whether a node with `tags` blocks or delays any mode.
+pub(crate) fn node_has_cost(tags: &[XmlTag], profile: &SpeedProfile) -> bool {
+    !tags.is_empty()
+        && MODES
+            .iter()
+            .any(|&mode| node_blocks(tags, mode) || node_delay_s(tags, mode, profile) > 0.0)
+}
+
+/// Charge node delays on the edges arriving at each tagged node, and make
+/// the edges leaving a barrier impassable for the modes it blocks. Blocked
+/// modes can still travel up to the barrier, just not through it.
+pub(crate) fn apply_node_costs(graph: &mut DiGraph<XmlNode, XmlWay>, profile: &SpeedProfile) {
+    for node in graph.node_indices() {
+        if !node_has_cost(&graph[node].tags, profile) {
+            continue;
+        }
+        for mode in MODES {
+            let tags = &graph[node].tags;
+            let blocked = node_blocks(tags, mode);
+            let delay_s = node_delay_s(tags, mode, profile);
+            for direction in [Direction::Incoming, Direction::Outgoing] {
+                let edges: Vec<_> = graph
+                    .edges_directed(node, direction)
+                    .map(|edge| edge.id())
+                    .collect();
+                for edge in edges {
+                    let way = &mut graph[edge];
+                    match direction {
+                        Direction::Incoming => {
+                            *travel_time_mut(way, mode) += delay_s;
+                            *node_delay_mut(way, mode) += delay_s;
+                        }
+                        Direction::Outgoing if blocked => {
+                            *travel_time_mut(way, mode) = f64::INFINITY
+                        }
+                        Direction::Outgoing => {}
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn travel_time_mut(way: &mut XmlWay, mode: NetworkType) -> &mut f64 {
+    match mode {
+        NetworkType::Walk => &mut way.walk_travel_time,
+        NetworkType::Bike => &mut way.bike_travel_time,
+        _ => &mut way.drive_travel_time,
+    }
+}
+
+fn node_delay_mut(way: &mut XmlWay, mode: NetworkType) -> &mut f64 {
+    match mode {
+        NetworkType::Walk => &mut way.node_delays.walk_s,
+        NetworkType::Bike => &mut way.node_delays.bike_s,
+        _ => &mut way.node_delays.drive_s,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::SpatialGraph;
+
+    fn tags(pairs: &[(&str, &str)]) -> Vec<XmlTag> {
+        pairs
+            .iter()
+            .map(|(key, value)| XmlTag {
+                key: (*key).into(),
+                value: (*value).into(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn barriers_block_the_modes_they_apply_to() {
+        let bollard = tags(&[("barrier", "bollard")]);
+        let gate = tags(&[("barrier", "gate")]);
+        let private_gate = tags(&[("barrier", "lift_gate"), ("access", "private")]);
+        let bus_bollard = tags(&[("barrier", "bollard"), ("motor_vehicle", "yes")]);
+        let closed = tags(&[("access", "no"), ("foot", "yes")]);
+
+        assert!(!node_blocks(&bollard, NetworkType::Walk));
+        assert!(!node_blocks(&bollard, NetworkType::Bike));
+        assert!(node_blocks(&bollard, NetworkType::Drive));
+        assert!(!node_blocks(&gate, NetworkType::Drive));
+        assert!(node_blocks(&private_gate, NetworkType::Drive));
+        assert!(!node_blocks(&bus_bollard, NetworkType::Drive));
+        assert!(!node_blocks(&closed, NetworkType::Walk));
+        assert!(node_blocks(&closed, NetworkType::Bike));
+        assert!(!node_blocks(
+            &tags(&[("highway", "crossing"), ("bicycle", "no")]),
+            NetworkType::Bike
+        ));
+    }
+
+    #[test]
+    fn signals_stops_and_crossings_delay_by_mode() {
+        let profile = SpeedProfile::default();
+        let signals = tags(&[("highway", "traffic_signals")]);
+        let signalled_crossing = tags(&[("highway", "crossing"), ("crossing", "traffic_signals")]);
+        let zebra = tags(&[("highway", "crossing"), ("crossing", "uncontrolled")]);
+
+        assert_eq!(node_delay_s(&signals, NetworkType::Drive, &profile), 15.0);
+        assert_eq!(node_delay_s(&signals, NetworkType::Walk, &profile), 0.0);
+        assert_eq!(
+            node_delay_s(&tags(&[("highway", "stop")]), NetworkType::Bike, &profile),
+            5.0
+        );
+        assert_eq!(
+            node_delay_s(&signalled_crossing, NetworkType::Walk, &profile),
+            15.0
+        );
+        assert_eq!(node_delay_s(&zebra, NetworkType::Walk, &profile), 5.0);
+        assert_eq!(node_delay_s(&zebra, NetworkType::Drive, &profile), 0.0);
+    }
+
+    #[test]
+    fn node_costs_apply_to_routes_and_survive_simplification() {
+        let xml = r#"<osm>
+          <node id="1" lat="48.000" lon="11.000" />
+          <node id="2" lat="48.001" lon="11.000"><tag k="highway" v="traffic_signals" /></node>
+          <node id="3" lat="48.002" lon="11.000"><tag k="barrier" v="bollard" /></node>
+          <node id="4" lat="48.003" lon="11.000" />
+          <way id="10"><nd ref="1" /><nd ref="2" /><nd ref="3" /><nd ref="4" />
+            <tag k="highway" v="residential" /></way>
+        </osm>"#;
+        let drive = SpatialGraph::from_osm(xml, NetworkType::Drive, None, None).unwrap();
+        let walk = SpatialGraph::from_osm(xml, NetworkType::Walk, None, None).unwrap();
+        let profile = SpeedProfile {
+            traffic_signal_delay_s: 0.0,
+            ..SpeedProfile::default()
+        };
+        let no_signals =
+            SpatialGraph::from_osm(xml, NetworkType::Drive, None, Some(&profile)).unwrap();
+
+        assert_eq!(drive.graph.node_count(), 4);
+        let to_bollard = |sg: &SpatialGraph| {
+            sg.route(48.000, 11.000, 48.0015, 11.000, NetworkType::Drive, None)
+                .unwrap()
+                .duration_s
+        };
+        assert!((to_bollard(&drive) - to_bollard(&no_signals) - 15.0).abs() < 1e-6);
+        assert!(drive
+            .route(48.000, 11.000, 48.003, 11.000, NetworkType::Drive, None)
+            .is_err());
+        assert!(walk
+            .route(48.000, 11.000, 48.003, 11.000, NetworkType::Walk, None)
+            .is_ok());
+    }
+}
@@ -58,12 +58,72 @@ pub fn get_osm_filter(network_type: NetworkType) -> Result<&'static str, Overpas
     }
 }
 
-// Function to create the Overpass query string
-pub fn create_overpass_query(polygon_coord_str: &str, network_type: NetworkType) -> String {
+/// Matches a `*:conditional` value granting access under some condition.
+/// Mirrors `conditional::grants_access`.
+const GRANTS_ACCESS_REGEX: &str = "(^|;) *(yes|permissive|designated|destination) *@";
+
+/// Keys whose `no` value the road filter of `network_type` excludes unless
+/// the matching `:conditional` tag grants access at times.
+fn conditionally_denied_keys(network_type: NetworkType) -> &'static [&'static str] {
+    match network_type {
+        NetworkType::Drive | NetworkType::DriveService => &["motor_vehicle", "motorcar"],
+        NetworkType::Walk => &["foot"],
+        NetworkType::Bike => &["bicycle"],
+        NetworkType::All | NetworkType::AllPrivate => &[],
+    }
+}
+
+/// The road filter of `network_type` as alternatives to union in one query.
+///
+/// Overpass tag filters cannot express "`key` is not `no`, or
+/// `key:conditional` grants access", so besides [`get_osm_filter`] there is
+/// one alternative for each non-empty set of its `["key"!~"no"]` clauses,
+/// with those clauses replaced by a match on the `:conditional` tag. Mirrors
+/// `filters::way_passes_road_filter`.
+pub fn get_osm_filter_alternatives(network_type: NetworkType) -> Vec<String> {
     let filter = get_osm_filter(network_type).unwrap_or("");
+    let keys = conditionally_denied_keys(network_type);
+    (0..1usize << keys.len())
+        .map(|subset| {
+            keys.iter()
+                .enumerate()
+                .filter(|(i, _)| subset & (1 << i) != 0)
+                .fold(filter.to_string(), |filter, (_, key)| {
+                    filter.replace(
+                        &format!("[\"{key}\"!~\"no\"]"),
+                        &format!("[\"{key}:conditional\"~\"{GRANTS_ACCESS_REGEX}\",i]"),
+                    )
+                })
+        })
+        .collect()
+}
+
+/// Overpass filter selecting the `route=ferry` ways that carry traffic of
+/// `network_type`. Mirrors `filters::ferry_passes_filter`.
+pub fn get_ferry_filter(network_type: NetworkType) -> &'static str {
+    match network_type {
+        NetworkType::Drive | NetworkType::DriveService => {
+            "[\"route\"=\"ferry\"][~\"^(motor_vehicle|motorcar)$\"~\"^yes$\"]"
+        }
+        NetworkType::Walk => "[\"route\"=\"ferry\"][\"foot\"!~\"no\"]",
+        NetworkType::Bike => "[\"route\"=\"ferry\"][\"bicycle\"!~\"no\"]",
+        NetworkType::All | NetworkType::AllPrivate => "[\"route\"=\"ferry\"]",
+    }
+}
+
+// Function to create the Overpass query string. Besides the filtered roads
+// (including those a `:conditional` tag opens at times), the ferry routes and
+// their nodes, it fetches the turn-restriction relations that reference them.
+pub fn create_overpass_query(polygon_coord_str: &str, network_type: NetworkType) -> String {
+    let roads: String = get_osm_filter_alternatives(network_type)
+        .iter()
+        .map(|filter| format!("way{filter}({polygon_coord_str});"))
+        .collect();
+    let ferry_filter = get_ferry_filter(network_type);
     format!(
-        "[out:xml][timeout:50];(way{}({});>;);out;",
-        filter, polygon_coord_str
+        "[out:xml][timeout:50];({roads}way{ferry_filter}({poly}););\
+         (._;>;)->.roads;(.roads;rel(bw.roads)[\"type\"=\"restriction\"];);out;",
+        poly = polygon_coord_str
     )
 }
 
@@ -227,6 +287,21 @@ mod tests {
         assert!(large_parts[2] > small_parts[2]);
     }
 
+    #[test]
+    fn road_queries_also_fetch_ways_conditional_tags_open() {
+        let drive = get_osm_filter_alternatives(NetworkType::Drive);
+        assert_eq!(drive.len(), 4);
+        assert_eq!(drive[0], get_osm_filter(NetworkType::Drive).unwrap());
+        assert!(drive[1].contains("[\"motor_vehicle:conditional\"~"));
+        assert!(drive[1].contains("[\"motorcar\"!~\"no\"]"));
+        assert!(!drive[3].contains("!~\"no\"]"));
+        assert_eq!(get_osm_filter_alternatives(NetworkType::All).len(), 1);
+
+        let query = create_overpass_query("48 11 48.1 11 48.1 11.1", NetworkType::Walk);
+        assert!(query.contains("[\"foot\"!~\"no\"]"));
+        assert!(query.contains("[\"foot:conditional\"~"));
+    }
+
     #[test]
     fn service_urls_can_be_overridden_by_environment() {
         let _guard = ENV_LOCK.lock().unwrap();
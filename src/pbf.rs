@@ -6,28 +6,51 @@
 use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
-use osmpbf::{Element, ElementReader};
+use osmpbf::{Element, ElementReader, RelMemberType, Relation};
 
 use crate::error::OsmGraphError;
 use crate::filters::{is_poi_node, way_passes_road_filter};
-use crate::graph::{SpatialGraph, XmlData, XmlNode, XmlNodeRef, XmlTag, XmlWay};
+use crate::graph::{
+    SpatialGraph, XmlData, XmlMember, XmlNode, XmlNodeRef, XmlRelation, XmlTag, XmlWay,
+};
 use crate::overpass::NetworkType;
 use crate::poi::Poi;
+use crate::speed::SpeedProfile;
+use crate::storage::EdgeGeometry;
 
 impl SpatialGraph {
     /// Build a routable [`SpatialGraph`] directly from a local OSM PBF file.
     ///
     /// POIs are parsed separately from road-network nodes and pre-snapped onto
     /// the graph. Use [`read_pbf`] when you need access to the intermediate
-    /// [`XmlData`] or raw [`Poi`] list.
+    /// [`XmlData`] or raw [`Poi`] list. `speed_profile` sets the speeds used
+    /// for edge travel times; `None` uses [`SpeedProfile::default`].
     pub fn from_pbf(
         path: impl AsRef<Path>,
         network_type: NetworkType,
         retain_all: Option<bool>,
+        speed_profile: Option<&SpeedProfile>,
     ) -> Result<Self, OsmGraphError> {
-        let (data, pois) = read_pbf(path, network_type)?;
-        let mut spatial_graph =
-            SpatialGraph::from_parsed_osm(data, network_type, retain_all.unwrap_or(false));
+        Self::from_pbfs(&[path], network_type, retain_all, speed_profile)
+    }
+
+    /// Build one [`SpatialGraph`] from several neighbouring PBF extracts.
+    ///
+    /// Nodes, ways and POIs shared by the files are merged by OSM id, so
+    /// roads crossing an extract boundary stay connected. See [`read_pbfs`].
+    pub fn from_pbfs<P: AsRef<Path>>(
+        paths: &[P],
+        network_type: NetworkType,
+        retain_all: Option<bool>,
+        speed_profile: Option<&SpeedProfile>,
+    ) -> Result<Self, OsmGraphError> {
+        let (data, pois) = read_pbfs(paths, network_type)?;
+        let mut spatial_graph = SpatialGraph::from_parsed_osm(
+            data,
+            network_type,
+            retain_all.unwrap_or(false),
+            speed_profile.unwrap_or(&SpeedProfile::default()),
+        );
         spatial_graph.snap_pois(&pois);
         Ok(spatial_graph)
     }
@@ -42,129 +65,9 @@ pub fn read_pbf_multi(
     path: impl AsRef<Path>,
     network_types: &[NetworkType],
 ) -> Result<(HashMap<NetworkType, XmlData>, Vec<Poi>), OsmGraphError> {
-    let mut all_nodes: HashMap<i64, RawNode> = HashMap::new();
-    let mut roads_by_type: HashMap<NetworkType, Vec<RawWay>> =
-        network_types.iter().map(|nt| (*nt, Vec::new())).collect();
-    let mut poi_ids: HashSet<i64> = HashSet::new();
-
-    let reader = ElementReader::from_path(path.as_ref())
-        .map_err(|e| OsmGraphError::PbfError(e.to_string()))?;
-
-    reader
-        .for_each(|element| match element {
-            Element::Node(node) => {
-                let tags: Vec<(String, String)> = node
-                    .tags()
-                    .map(|(k, v)| (k.to_string(), v.to_string()))
-                    .collect();
-                let id = node.id();
-                if is_poi_node(&tags) {
-                    poi_ids.insert(id);
-                }
-                all_nodes.insert(
-                    id,
-                    RawNode {
-                        lat: node.lat(),
-                        lon: node.lon(),
-                        tags,
-                    },
-                );
-            }
-            Element::DenseNode(node) => {
-                let tags: Vec<(String, String)> = node
-                    .tags()
-                    .map(|(k, v)| (k.to_string(), v.to_string()))
-                    .collect();
-                let id = node.id();
-                if is_poi_node(&tags) {
-                    poi_ids.insert(id);
-                }
-                all_nodes.insert(
-                    id,
-                    RawNode {
-                        lat: node.lat(),
-                        lon: node.lon(),
-                        tags,
-                    },
-                );
-            }
-            Element::Way(way) => {
-                let tags: Vec<(String, String)> = way
-                    .tags()
-                    .map(|(k, v)| (k.to_string(), v.to_string()))
-                    .collect();
-                // Quick reject: ways without a highway tag aren't roads for any mode.
-                if !tags.iter().any(|(k, _)| k == "highway") {
-                    return;
-                }
-                let refs: Vec<i64> = way.refs().collect();
-                for &nt in network_types {
-                    if way_passes_road_filter(&tags, nt) {
-                        roads_by_type.get_mut(&nt).unwrap().push(RawWay {
-                            id: way.id(),
-                            refs: refs.clone(),
-                            tags: tags.clone(),
-                        });
-                    }
-                }
-            }
-            Element::Relation(_) => {}
-        })
-        .map_err(|e| OsmGraphError::PbfError(e.to_string()))?;
-
-    let pois = pois_from_nodes(&all_nodes, &poi_ids);
-
-    // Per-network-type, emit only the road nodes referenced by that type's ways.
-    // POIs are returned separately so POI-only nodes do not enter the routable graph.
-    let mut out: HashMap<NetworkType, XmlData> = HashMap::new();
-    for (nt, roads) in roads_by_type {
-        let mut needed: HashSet<i64> = HashSet::new();
-        for w in &roads {
-            for r in &w.refs {
-                needed.insert(*r);
-            }
-        }
-        let nodes: Vec<XmlNode> = all_nodes
-            .iter()
-            .filter(|(id, _)| needed.contains(id))
-            .map(|(id, n)| XmlNode {
-                id: *id,
-                lat: n.lat,
-                lon: n.lon,
-                tags: n
-                    .tags
-                    .iter()
-                    .cloned()
-                    .map(|(k, v)| XmlTag { key: k, value: v })
-                    .collect(),
-            })
-            .collect();
-        let ways: Vec<XmlWay> = roads
-            .into_iter()
-            .map(|w| XmlWay {
-                id: w.id,
-                nodes: w
-                    .refs
-                    .into_iter()
-                    .map(|node_id| XmlNodeRef { node_id })
-                    .collect(),
-                tags: w
-                    .tags
-                    .into_iter()
-                    .map(|(k, v)| XmlTag { key: k, value: v })
-                    .collect(),
-                length: 0.0,
-                speed_kph: 0.0,
-                walk_travel_time: 0.0,
-                bike_travel_time: 0.0,
-                drive_travel_time: 0.0,
-                geometry: Vec::new(),
-            })
-            .collect();
-        out.insert(nt, XmlData { nodes, ways });
-    }
-
-    Ok((out, pois))
+    let mut collector = PbfCollector::new(network_types);
+    collector.read(path.as_ref())?;
+    Ok(collector.finish())
 }
 
 /// Read a PBF file and produce an `XmlData` (the canonical intermediate shape
@@ -182,119 +85,63 @@ pub fn read_pbf(
     path: impl AsRef<Path>,
     network_type: NetworkType,
 ) -> Result<(XmlData, Vec<Poi>), OsmGraphError> {
-    let mut all_nodes: HashMap<i64, RawNode> = HashMap::new();
-    let mut roads: Vec<RawWay> = Vec::new();
-    let mut poi_ids: HashSet<i64> = HashSet::new();
-
-    let reader = ElementReader::from_path(path.as_ref())
-        .map_err(|e| OsmGraphError::PbfError(e.to_string()))?;
-
-    reader
-        .for_each(|element| match element {
-            Element::Node(node) => {
-                let tags: Vec<(String, String)> = node
-                    .tags()
-                    .map(|(k, v)| (k.to_string(), v.to_string()))
-                    .collect();
-                let id = node.id();
-                if is_poi_node(&tags) {
-                    poi_ids.insert(id);
-                }
-                all_nodes.insert(
-                    id,
-                    RawNode {
-                        lat: node.lat(),
-                        lon: node.lon(),
-                        tags,
-                    },
-                );
-            }
-            Element::DenseNode(node) => {
-                let tags: Vec<(String, String)> = node
-                    .tags()
-                    .map(|(k, v)| (k.to_string(), v.to_string()))
-                    .collect();
-                let id = node.id();
-                if is_poi_node(&tags) {
-                    poi_ids.insert(id);
-                }
-                all_nodes.insert(
-                    id,
-                    RawNode {
-                        lat: node.lat(),
-                        lon: node.lon(),
-                        tags,
-                    },
-                );
-            }
-            Element::Way(way) => {
-                let tags: Vec<(String, String)> = way
-                    .tags()
-                    .map(|(k, v)| (k.to_string(), v.to_string()))
-                    .collect();
-                if !way_passes_road_filter(&tags, network_type) {
-                    return;
-                }
-                let refs: Vec<i64> = way.refs().collect();
-                roads.push(RawWay {
-                    id: way.id(),
-                    refs,
-                    tags,
-                });
-            }
-            Element::Relation(_) => {}
-        })
-        .map_err(|e| OsmGraphError::PbfError(e.to_string()))?;
-
-    let pois = pois_from_nodes(&all_nodes, &poi_ids);
+    read_pbfs(&[path], network_type)
+}
 
-    // Build the set of road nodes we actually need to keep.
-    let mut needed: HashSet<i64> = HashSet::new();
-    for w in &roads {
-        for r in &w.refs {
-            needed.insert(*r);
-        }
+/// Read several PBF files into one `XmlData` and one POI list, as if they
+/// were a single extract.
+///
+/// Neighbouring extracts (e.g. two Geofabrik regions) both contain the nodes
+/// and ways along their shared boundary. Every element is kept once by OSM
+/// id, so the ways of both files meet at the same seam nodes and the graph
+/// built from the result is connected across the boundary. When one file
+/// holds a clipped copy of a way, the copy with the most nodes wins.
+pub fn read_pbfs<P: AsRef<Path>>(
+    paths: &[P],
+    network_type: NetworkType,
+) -> Result<(XmlData, Vec<Poi>), OsmGraphError> {
+    let mut collector = PbfCollector::new(&[network_type]);
+    for path in paths {
+        collector.read(path.as_ref())?;
     }
+    let (mut data, pois) = collector.finish();
+    Ok((data.remove(&network_type).unwrap(), pois))
+}
 
-    let nodes: Vec<XmlNode> = all_nodes
-        .into_iter()
-        .filter(|(id, _)| needed.contains(id))
-        .map(|(id, n)| XmlNode {
-            id,
-            lat: n.lat,
-            lon: n.lon,
-            tags: n
-                .tags
-                .into_iter()
-                .map(|(k, v)| XmlTag { key: k, value: v })
-                .collect(),
+/// Convert a PBF relation into an [`XmlRelation`] if it is a turn restriction.
+/// Every other relation type is irrelevant to the routable graph.
+fn restriction_relation(relation: &Relation) -> Option<XmlRelation> {
+    if !relation
+        .tags()
+        .any(|(k, v)| k == "type" && v == "restriction")
+    {
+        return None;
+    }
+    let members = relation
+        .members()
+        .map(|member| XmlMember {
+            member_type: match member.member_type {
+                RelMemberType::Node => "node",
+                RelMemberType::Way => "way",
+                RelMemberType::Relation => "relation",
+            }
+            .to_string(),
+            member_ref: member.member_id,
+            role: member.role().unwrap_or_default().to_string(),
         })
         .collect();
-
-    let ways: Vec<XmlWay> = roads
-        .into_iter()
-        .map(|w| XmlWay {
-            id: w.id,
-            nodes: w
-                .refs
-                .into_iter()
-                .map(|node_id| XmlNodeRef { node_id })
-                .collect(),
-            tags: w
-                .tags
-                .into_iter()
-                .map(|(k, v)| XmlTag { key: k, value: v })
-                .collect(),
-            length: 0.0,
-            speed_kph: 0.0,
-            walk_travel_time: 0.0,
-            bike_travel_time: 0.0,
-            drive_travel_time: 0.0,
-            geometry: Vec::new(),
+    let tags = relation
+        .tags()
+        .map(|(k, v)| XmlTag {
+            key: k.into(),
+            value: v.into(),
         })
         .collect();
-
-    Ok((XmlData { nodes, ways }, pois))
+    Some(XmlRelation {
+        id: relation.id(),
+        members,
+        tags,
+    })
 }
 
 fn pois_from_nodes(all_nodes: &HashMap<i64, RawNode>, poi_ids: &HashSet<i64>) -> Vec<Poi> {
@@ -312,6 +159,189 @@ fn pois_from_nodes(all_nodes: &HashMap<i64, RawNode>, poi_ids: &HashSet<i64>) ->
         .collect()
 }
 
+/// Elements gathered from one or more PBF files, de-duplicated by OSM id.
+struct PbfCollector {
+    network_types: Vec<NetworkType>,
+    all_nodes: HashMap<i64, RawNode>,
+    poi_ids: HashSet<i64>,
+    /// Road ways passing the filter of at least one network type.
+    roads: Vec<RawWay>,
+    road_positions: HashMap<i64, usize>,
+    relations: Vec<XmlRelation>,
+    relation_ids: HashSet<i64>,
+}
+
+impl PbfCollector {
+    fn new(network_types: &[NetworkType]) -> Self {
+        Self {
+            network_types: network_types.to_vec(),
+            all_nodes: HashMap::new(),
+            poi_ids: HashSet::new(),
+            roads: Vec::new(),
+            road_positions: HashMap::new(),
+            relations: Vec::new(),
+            relation_ids: HashSet::new(),
+        }
+    }
+
+    fn read(&mut self, path: &Path) -> Result<(), OsmGraphError> {
+        let reader =
+            ElementReader::from_path(path).map_err(|e| OsmGraphError::PbfError(e.to_string()))?;
+
+        reader
+            .for_each(|element| match element {
+                Element::Node(node) => {
+                    let tags = node
+                        .tags()
+                        .map(|(k, v)| (k.to_string(), v.to_string()))
+                        .collect();
+                    self.add_node(node.id(), node.lat(), node.lon(), tags);
+                }
+                Element::DenseNode(node) => {
+                    let tags = node
+                        .tags()
+                        .map(|(k, v)| (k.to_string(), v.to_string()))
+                        .collect();
+                    self.add_node(node.id(), node.lat(), node.lon(), tags);
+                }
+                Element::Way(way) => {
+                    let tags: Vec<(String, String)> = way
+                        .tags()
+                        .map(|(k, v)| (k.to_string(), v.to_string()))
+                        .collect();
+                    self.add_way(way.id(), way.refs().collect(), tags);
+                }
+                Element::Relation(relation) => {
+                    if let Some(relation) = restriction_relation(&relation) {
+                        if self.relation_ids.insert(relation.id) {
+                            self.relations.push(relation);
+                        }
+                    }
+                }
+            })
+            .map_err(|e| OsmGraphError::PbfError(e.to_string()))
+    }
+
+    fn add_node(&mut self, id: i64, lat: f64, lon: f64, tags: Vec<(String, String)>) {
+        if is_poi_node(&tags) {
+            self.poi_ids.insert(id);
+        }
+        self.all_nodes.insert(id, RawNode { lat, lon, tags });
+    }
+
+    /// Keep a way that passes the road filter of any requested network type.
+    fn add_way(&mut self, id: i64, refs: Vec<i64>, tags: Vec<(String, String)>) {
+        // Quick reject: ways that are neither highways nor ferry routes
+        // aren't part of any network.
+        if !tags
+            .iter()
+            .any(|(k, v)| k == "highway" || (k == "route" && v == "ferry"))
+        {
+            return;
+        }
+        if !self
+            .network_types
+            .iter()
+            .any(|&nt| way_passes_road_filter(&tags, nt))
+        {
+            return;
+        }
+        self.add_road(RawWay { id, refs, tags });
+    }
+
+    fn add_road(&mut self, way: RawWay) {
+        match self.road_positions.get(&way.id) {
+            Some(&i) => {
+                if way.refs.len() > self.roads[i].refs.len() {
+                    self.roads[i] = way;
+                }
+            }
+            None => {
+                self.road_positions.insert(way.id, self.roads.len());
+                self.roads.push(way);
+            }
+        }
+    }
+
+    /// Per-network-type, emit only the road nodes referenced by that type's
+    /// ways. POIs are returned separately so POI-only nodes do not enter the
+    /// routable graph.
+    fn finish(self) -> (HashMap<NetworkType, XmlData>, Vec<Poi>) {
+        let pois = pois_from_nodes(&self.all_nodes, &self.poi_ids);
+
+        let mut out: HashMap<NetworkType, XmlData> = HashMap::new();
+        for &nt in &self.network_types {
+            let roads: Vec<&RawWay> = self
+                .roads
+                .iter()
+                .filter(|w| way_passes_road_filter(&w.tags, nt))
+                .collect();
+            let mut needed: HashSet<i64> = HashSet::new();
+            for w in &roads {
+                for r in &w.refs {
+                    needed.insert(*r);
+                }
+            }
+            let nodes: Vec<XmlNode> = self
+                .all_nodes
+                .iter()
+                .filter(|(id, _)| needed.contains(id))
+                .map(|(id, n)| XmlNode {
+                    id: *id,
+                    lat: n.lat,
+                    lon: n.lon,
+                    tags: xml_tags(&n.tags),
+                    elevation_m: None,
+                })
+                .collect();
+            let ways: Vec<XmlWay> = roads
+                .into_iter()
+                .map(|w| XmlWay {
+                    id: w.id,
+                    nodes: w
+                        .refs
+                        .iter()
+                        .map(|&node_id| XmlNodeRef { node_id })
+                        .collect(),
+                    tags: xml_tags(&w.tags),
+                    length: 0.0,
+                    speed_kph: 0.0,
+                    walk_travel_time: 0.0,
+                    bike_travel_time: 0.0,
+                    drive_travel_time: 0.0,
+                    geometry: EdgeGeometry::default(),
+                    way_ids: Vec::new(),
+                    closed_during: Vec::new(),
+                    closed_by_default: false,
+                    node_delays: Default::default(),
+                    bike_push_m: 0.0,
+                    elevations_m: Vec::new(),
+                    hourly_speeds: None,
+                })
+                .collect();
+            out.insert(
+                nt,
+                XmlData {
+                    nodes,
+                    ways,
+                    relations: self.relations.clone(),
+                },
+            );
+        }
+
+        (out, pois)
+    }
+}
+
+fn xml_tags<T: FromIterator<XmlTag>>(tags: &[(String, String)]) -> T {
+    tags.iter()
+        .map(|(k, v)| XmlTag {
+            key: k.as_str().into(),
+            value: v.as_str().into(),
+        })
+        .collect()
+}
+
 struct RawNode {
     lat: f64,
     lon: f64,
@@ -327,9 +357,13 @@ struct RawWay {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::components::Connectivity;
+    use crate::conditional::DepartureTime;
     use crate::graph::parse_xml;
 
     const TINY_PBF: &str = "tests/fixtures/tiny_map.osm.pbf";
+    const TINY_SOUTH_PBF: &str = "tests/fixtures/tiny_map_south.osm.pbf";
+    const TINY_NORTH_PBF: &str = "tests/fixtures/tiny_map_north.osm.pbf";
     const TINY_DRIVE_XML: &str = include_str!("../tests/fixtures/tiny_drive_overpass.osm");
 
     fn sorted_ids<T, F>(items: &[T], mut id: F) -> Vec<i64>
@@ -345,7 +379,7 @@ mod tests {
         way.tags
             .iter()
             .find(|tag| tag.key == key)
-            .map(|tag| tag.value.clone())
+            .map(|tag| tag.value.to_string())
     }
 
     #[test]
@@ -440,6 +474,30 @@ mod tests {
         }
     }
 
+    #[test]
+    fn tiny_pbf_restriction_relations_match_overpass_xml() {
+        let (pbf_data, _) = read_pbf(TINY_PBF, NetworkType::Drive).unwrap();
+        let xml_data = parse_xml(TINY_DRIVE_XML).unwrap();
+
+        assert_eq!(pbf_data.relations.len(), 1);
+        assert_eq!(xml_data.relations.len(), 1);
+        let (actual, expected) = (&pbf_data.relations[0], &xml_data.relations[0]);
+        assert_eq!(actual.id, expected.id);
+        let members = |relation: &XmlRelation| -> Vec<(String, i64, String)> {
+            relation
+                .members
+                .iter()
+                .map(|m| (m.member_type.clone(), m.member_ref, m.role.clone()))
+                .collect()
+        };
+        assert_eq!(members(actual), members(expected));
+
+        let graph = SpatialGraph::from_pbf(TINY_PBF, NetworkType::Drive, None, None).unwrap();
+        assert_eq!(graph.turn_restrictions().len(), 1);
+        // The via node survives simplification even though it is a chain node.
+        assert!(graph.graph.node_weights().any(|node| node.id == 3));
+    }
+
     #[test]
     fn tiny_pbf_multi_matches_single_profile_reads() {
         let (multi, pois) =
@@ -460,4 +518,101 @@ mod tests {
             sorted_ids(&walk.ways, |way| way.id)
         );
     }
+
+    #[test]
+    fn neighbouring_extracts_merge_at_their_seam() {
+        let (merged, pois) =
+            read_pbfs(&[TINY_SOUTH_PBF, TINY_NORTH_PBF], NetworkType::Walk).unwrap();
+        let (whole, whole_pois) = read_pbf(TINY_PBF, NetworkType::Walk).unwrap();
+
+        assert_eq!(
+            sorted_ids(&merged.nodes, |node| node.id),
+            sorted_ids(&whole.nodes, |node| node.id)
+        );
+        assert_eq!(sorted_ids(&merged.ways, |way| way.id), vec![10, 20, 30, 40]);
+        assert_eq!(merged.relations.len(), 1);
+        assert_eq!(
+            sorted_ids(&pois, |poi| poi.id),
+            sorted_ids(&whole_pois, |poi| poi.id)
+        );
+
+        let graph = SpatialGraph::from_pbfs(
+            &[TINY_SOUTH_PBF, TINY_NORTH_PBF],
+            NetworkType::Walk,
+            None,
+            None,
+        )
+        .unwrap();
+        let whole = SpatialGraph::from_pbf(TINY_PBF, NetworkType::Walk, None, None).unwrap();
+        assert_eq!(graph.graph.node_count(), whole.graph.node_count());
+        assert_eq!(graph.graph.edge_count(), whole.graph.edge_count());
+        assert_eq!(
+            graph
+                .components(NetworkType::Walk, Connectivity::Weak)
+                .len(),
+            1
+        );
+        assert_eq!(graph.poi_snaps.as_ref().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn ways_a_conditional_tag_opens_are_kept_and_closed_outside_its_windows() {
+        let tags = |pairs: &[(&str, &str)]| -> Vec<(String, String)> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        let mut collector = PbfCollector::new(&[NetworkType::Drive]);
+        for (id, lat, lon) in [
+            (1, 48.0, 11.0),
+            (2, 48.0, 11.001),
+            (3, 48.0, 11.002),
+            (4, 48.001, 11.001),
+            (5, 48.0, 11.003),
+        ] {
+            collector.add_node(id, lat, lon, Vec::new());
+        }
+        // A weekend-only school street 1-2-3, a detour 1-4-3 and a street
+        // closed to cars at all times.
+        collector.add_way(
+            20,
+            vec![1, 2, 3],
+            tags(&[
+                ("highway", "residential"),
+                ("motor_vehicle", "no"),
+                ("motor_vehicle:conditional", "yes @ (Sa,Su)"),
+            ]),
+        );
+        collector.add_way(21, vec![1, 4, 3], tags(&[("highway", "residential")]));
+        collector.add_way(
+            22,
+            vec![3, 5],
+            tags(&[("highway", "residential"), ("motor_vehicle", "no")]),
+        );
+        let (mut data, _) = collector.finish();
+        let data = data.remove(&NetworkType::Drive).unwrap();
+        assert_eq!(sorted_ids(&data.ways, |way| way.id), vec![20, 21]);
+
+        let graph =
+            SpatialGraph::from_parsed_osm(data, NetworkType::Drive, true, &SpeedProfile::default());
+        let via_school_street = |departure: Option<DepartureTime>| {
+            let route = match departure {
+                Some(departure) => graph.route_at(
+                    48.0,
+                    11.0,
+                    48.0,
+                    11.002,
+                    NetworkType::Drive,
+                    None,
+                    departure,
+                ),
+                None => graph.route(48.0, 11.0, 48.0, 11.002, NetworkType::Drive, None),
+            };
+            route.unwrap().coordinates.contains(&(48.0, 11.001))
+        };
+        assert!(!via_school_street(None));
+        assert!(!via_school_street(DepartureTime::new(2, 12, 0)));
+        assert!(via_school_street(DepartureTime::new(5, 12, 0)));
+    }
 }
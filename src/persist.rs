@@ -0,0 +1,777 @@
+//! Versioned binary file format for [`SpatialGraph`].
+//!
+//! A saved graph holds everything needed to answer queries without touching
+//! the original PBF: nodes, edges with their geometry, travel times,
+//! closures, hourly speeds and elevations, the spatial-index entries,
+//! pre-snapped POIs, turn restrictions and turn penalties. Tag strings and
+//! hourly speed tables are interned in tables, so repeated values are stored
+//! once. All numbers are little-endian.
+//!
+//! Files start with a magic marker and [`FORMAT_VERSION`]. A file with any
+//! other version is rejected rather than misread; rebuild it from the source
+//! data after upgrading.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use petgraph::graph::{DiGraph, EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
+
+use crate::conditional::TimeWindow;
+use crate::error::OsmGraphError;
+use crate::graph::{NodeDelays, SnapResult, SnappedPoi, SpatialGraph, XmlNode, XmlTag, XmlWay};
+use crate::overpass::NetworkType;
+use crate::storage::{intern_graph_tags, pack_edge_geometry, TagStr};
+use crate::traffic::{WeekSpeeds, HOURS_PER_WEEK};
+use crate::turns::{
+    RestrictionKind, RestrictionModes, RestrictionVia, TurnPenalties, TurnRestriction,
+};
+
+const MAGIC: &[u8; 8] = b"GWGRAPH\0";
+/// Bumped whenever the layout changes.
+pub const FORMAT_VERSION: u32 = 7;
+
+/// Network types with stored turn penalties, in their on-disk encoding.
+const NETWORK_TYPES: [NetworkType; 6] = [
+    NetworkType::Drive,
+    NetworkType::DriveService,
+    NetworkType::Walk,
+    NetworkType::Bike,
+    NetworkType::All,
+    NetworkType::AllPrivate,
+];
+
+impl SpatialGraph {
+    /// Write this graph to `path` in the binary format described in
+    /// [`crate::persist`].
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), OsmGraphError> {
+        let mut out = Writer::new(BufWriter::new(File::create(path)?));
+        out.bytes(MAGIC)?;
+        out.u32(FORMAT_VERSION)?;
+        out.write_graph(self)?;
+        out.inner.flush()?;
+        Ok(())
+    }
+
+    /// Read a graph written by [`SpatialGraph::save`].
+    ///
+    /// Returns [`OsmGraphError::GraphFile`] when the file is not a graph
+    /// file, was written with a different format version, or is truncated.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, OsmGraphError> {
+        let mut input = Reader {
+            inner: BufReader::new(File::open(path)?),
+            strings: Vec::new(),
+            speeds: Vec::new(),
+        };
+        let mut magic = [0u8; 8];
+        input.fill(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(OsmGraphError::GraphFile(
+                "not a graphways graph file".into(),
+            ));
+        }
+        let version = input.u32()?;
+        if version != FORMAT_VERSION {
+            return Err(OsmGraphError::GraphFile(format!(
+                "file has format version {version}, but graphways {} reads version {FORMAT_VERSION}; rebuild it",
+                env!("CARGO_PKG_VERSION")
+            )));
+        }
+        input.read_graph()
+    }
+}
+
+struct Writer<W: Write> {
+    inner: W,
+    strings: HashMap<String, u32>,
+    speeds: HashMap<*const WeekSpeeds, u32>,
+}
+
+impl<W: Write> Writer<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            strings: HashMap::new(),
+            speeds: HashMap::new(),
+        }
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        self.inner.write_all(bytes)
+    }
+
+    fn u8(&mut self, value: u8) -> std::io::Result<()> {
+        self.bytes(&[value])
+    }
+
+    fn u16(&mut self, value: u16) -> std::io::Result<()> {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    fn u32(&mut self, value: u32) -> std::io::Result<()> {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    fn i64(&mut self, value: i64) -> std::io::Result<()> {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    fn f32(&mut self, value: f32) -> std::io::Result<()> {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    fn f64(&mut self, value: f64) -> std::io::Result<()> {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    fn len(&mut self, len: usize) -> Result<(), OsmGraphError> {
+        let len = u32::try_from(len).map_err(|_| {
+            OsmGraphError::GraphFile(format!("{len} items exceed the format's limit"))
+        })?;
+        Ok(self.u32(len)?)
+    }
+
+    fn write_graph(&mut self, sg: &SpatialGraph) -> Result<(), OsmGraphError> {
+        let graph = &sg.graph;
+        let mut table: Vec<&str> = Vec::new();
+        let tags = graph
+            .node_weights()
+            .flat_map(|node| &node.tags)
+            .chain(graph.edge_weights().flat_map(|way| way.tags.iter()));
+        for tag in tags {
+            for text in [tag.key.as_str(), tag.value.as_str()] {
+                if !self.strings.contains_key(text) {
+                    self.strings.insert(text.to_string(), table.len() as u32);
+                    table.push(text);
+                }
+            }
+        }
+        self.len(table.len())?;
+        for text in table {
+            self.len(text.len())?;
+            self.bytes(text.as_bytes())?;
+        }
+
+        // Hours without a speed are stored as NaN.
+        let mut speed_tables: Vec<&WeekSpeeds> = Vec::new();
+        for speeds in graph
+            .edge_weights()
+            .filter_map(|way| way.hourly_speeds.as_ref())
+        {
+            if let Entry::Vacant(entry) = self.speeds.entry(Arc::as_ptr(speeds)) {
+                entry.insert(speed_tables.len() as u32);
+                speed_tables.push(speeds);
+            }
+        }
+        self.len(speed_tables.len())?;
+        for speeds in speed_tables {
+            for &kph in speeds.hours() {
+                self.f32(kph.unwrap_or(f32::NAN))?;
+            }
+        }
+
+        self.len(graph.node_count())?;
+        for node in graph.node_weights() {
+            self.i64(node.id)?;
+            self.f64(node.lat)?;
+            self.f64(node.lon)?;
+            self.tags(&node.tags)?;
+            match node.elevation_m {
+                Some(elevation) => {
+                    self.u8(1)?;
+                    self.f64(elevation)?;
+                }
+                None => self.u8(0)?,
+            }
+        }
+
+        self.len(graph.edge_count())?;
+        for edge in graph.edge_references() {
+            self.u32(edge.source().index() as u32)?;
+            self.u32(edge.target().index() as u32)?;
+            self.way(edge.weight())?;
+        }
+
+        let entries = sg.spatial_index_entries();
+        self.len(entries.len())?;
+        for ([x, y], index) in entries {
+            self.f64(x)?;
+            self.f64(y)?;
+            self.u32(index.index() as u32)?;
+        }
+
+        match &sg.poi_snaps {
+            Some(snaps) => {
+                self.u8(1)?;
+                self.len(snaps.len())?;
+                for snapped in snaps.values() {
+                    self.i64(snapped.poi_id)?;
+                    self.snap(&snapped.snap)?;
+                }
+            }
+            None => self.u8(0)?,
+        }
+
+        let restrictions = sg.turn_restrictions();
+        self.len(restrictions.len())?;
+        for restriction in restrictions {
+            self.i64(restriction.relation_id)?;
+            self.u8(match restriction.kind {
+                RestrictionKind::No => 0,
+                RestrictionKind::Only => 1,
+            })?;
+            self.i64(restriction.from_way)?;
+            match &restriction.via {
+                RestrictionVia::Node(node) => {
+                    self.u8(0)?;
+                    self.i64(*node)?;
+                }
+                RestrictionVia::Ways(ways) => {
+                    self.u8(1)?;
+                    self.len(ways.len())?;
+                    for &way in ways {
+                        self.i64(way)?;
+                    }
+                }
+            }
+            self.i64(restriction.to_way)?;
+            self.u8(restriction.modes.bicycle as u8 | (restriction.modes.motor_vehicle as u8) << 1)?;
+        }
+
+        let penalties: Vec<(u8, &TurnPenalties)> = NETWORK_TYPES
+            .iter()
+            .enumerate()
+            .filter_map(|(code, &nt)| sg.turn_penalties(nt).map(|p| (code as u8, p)))
+            .collect();
+        self.len(penalties.len())?;
+        for (code, penalties) in penalties {
+            self.u8(code)?;
+            self.f64(penalties.left_s)?;
+            self.f64(penalties.right_s)?;
+            self.f64(penalties.u_turn_s)?;
+            self.f64(penalties.straight_tolerance_deg)?;
+            self.f64(penalties.u_turn_threshold_deg)?;
+        }
+        Ok(())
+    }
+
+    fn tags(&mut self, tags: &[XmlTag]) -> Result<(), OsmGraphError> {
+        self.len(tags.len())?;
+        for tag in tags {
+            self.u32(self.strings[tag.key.as_str()])?;
+            self.u32(self.strings[tag.value.as_str()])?;
+        }
+        Ok(())
+    }
+
+    fn way(&mut self, way: &XmlWay) -> Result<(), OsmGraphError> {
+        self.i64(way.id)?;
+        self.tags(&way.tags)?;
+        for value in [
+            way.length,
+            way.speed_kph,
+            way.walk_travel_time,
+            way.bike_travel_time,
+            way.drive_travel_time,
+            way.node_delays.walk_s,
+            way.node_delays.bike_s,
+            way.node_delays.drive_s,
+            way.bike_push_m,
+        ] {
+            self.f64(value)?;
+        }
+        self.len(way.geometry.len())?;
+        for &(lat, lon) in way.geometry.iter() {
+            self.f64(lat)?;
+            self.f64(lon)?;
+        }
+        self.len(way.way_ids.len())?;
+        for &way_id in &way.way_ids {
+            self.i64(way_id)?;
+        }
+        self.len(way.closed_during.len())?;
+        for window in &way.closed_during {
+            let (days, start_minute, end_minute) = window.to_parts();
+            self.u8(days)?;
+            self.u16(start_minute)?;
+            self.u16(end_minute)?;
+        }
+        self.u8(way.closed_by_default as u8)?;
+        self.len(way.elevations_m.len())?;
+        for &elevation in &way.elevations_m {
+            self.f64(elevation)?;
+        }
+        // 0 for none, otherwise one more than the speed table index.
+        let speeds = way
+            .hourly_speeds
+            .as_ref()
+            .map_or(0, |speeds| self.speeds[&Arc::as_ptr(speeds)] + 1);
+        self.u32(speeds)?;
+        Ok(())
+    }
+
+    fn snap(&mut self, snap: &SnapResult) -> Result<(), OsmGraphError> {
+        self.f64(snap.input_lat)?;
+        self.f64(snap.input_lon)?;
+        self.u32(snap.node_index.index() as u32)?;
+        self.i64(snap.node_id)?;
+        self.f64(snap.node_lat)?;
+        self.f64(snap.node_lon)?;
+        self.f64(snap.distance_m)?;
+        self.f64(snap.snapped_lat)?;
+        self.f64(snap.snapped_lon)?;
+        match (snap.edge_index, snap.edge_node_ids) {
+            (Some(edge), Some((source_id, target_id))) => {
+                self.u8(1)?;
+                self.u32(edge.index() as u32)?;
+                self.i64(source_id)?;
+                self.i64(target_id)?;
+                self.f64(snap.edge_offset_m)?;
+            }
+            _ => self.u8(0)?,
+        }
+        Ok(())
+    }
+}
+
+struct Reader<R: Read> {
+    inner: R,
+    strings: Vec<TagStr>,
+    speeds: Vec<Arc<WeekSpeeds>>,
+}
+
+impl<R: Read> Reader<R> {
+    fn fill(&mut self, buf: &mut [u8]) -> Result<(), OsmGraphError> {
+        self.inner.read_exact(buf).map_err(|e| match e.kind() {
+            std::io::ErrorKind::UnexpectedEof => {
+                OsmGraphError::GraphFile("file is truncated".into())
+            }
+            _ => OsmGraphError::Io(e),
+        })
+    }
+
+    fn u8(&mut self) -> Result<u8, OsmGraphError> {
+        let mut buf = [0u8; 1];
+        self.fill(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, OsmGraphError> {
+        let mut buf = [0u8; 2];
+        self.fill(&mut buf)?;
+        Ok(u16::from_le_bytes(buf))
+    }
+
+    fn u32(&mut self) -> Result<u32, OsmGraphError> {
+        let mut buf = [0u8; 4];
+        self.fill(&mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    fn i64(&mut self) -> Result<i64, OsmGraphError> {
+        let mut buf = [0u8; 8];
+        self.fill(&mut buf)?;
+        Ok(i64::from_le_bytes(buf))
+    }
+
+    fn f32(&mut self) -> Result<f32, OsmGraphError> {
+        let mut buf = [0u8; 4];
+        self.fill(&mut buf)?;
+        Ok(f32::from_le_bytes(buf))
+    }
+
+    fn f64(&mut self) -> Result<f64, OsmGraphError> {
+        let mut buf = [0u8; 8];
+        self.fill(&mut buf)?;
+        Ok(f64::from_le_bytes(buf))
+    }
+
+    fn len(&mut self) -> Result<usize, OsmGraphError> {
+        Ok(self.u32()? as usize)
+    }
+
+    /// A node index, checked against the `node_count` nodes read so far.
+    fn node_index(&mut self, node_count: usize) -> Result<NodeIndex, OsmGraphError> {
+        let index = self.len()?;
+        if index >= node_count {
+            return Err(corrupt("node index out of range"));
+        }
+        Ok(NodeIndex::new(index))
+    }
+
+    fn read_graph(&mut self) -> Result<SpatialGraph, OsmGraphError> {
+        let string_count = self.len()?;
+        for _ in 0..string_count {
+            let mut buf = vec![0u8; self.len()?];
+            self.fill(&mut buf)?;
+            let text = String::from_utf8(buf).map_err(|_| corrupt("invalid UTF-8 in tags"))?;
+            self.strings.push(text.into());
+        }
+
+        let speed_count = self.len()?;
+        for _ in 0..speed_count {
+            let mut hours = [None; HOURS_PER_WEEK];
+            for hour in hours.iter_mut() {
+                let kph = self.f32()?;
+                *hour = (!kph.is_nan()).then_some(kph);
+            }
+            self.speeds.push(Arc::new(WeekSpeeds::from_hours(hours)));
+        }
+
+        let mut graph = DiGraph::new();
+        let node_count = self.len()?;
+        for _ in 0..node_count {
+            let id = self.i64()?;
+            let lat = self.f64()?;
+            let lon = self.f64()?;
+            let tags = self.tags()?;
+            let elevation_m = match self.u8()? {
+                0 => None,
+                _ => Some(self.f64()?),
+            };
+            graph.add_node(XmlNode {
+                id,
+                lat,
+                lon,
+                tags,
+                elevation_m,
+            });
+        }
+
+        let edge_count = self.len()?;
+        for _ in 0..edge_count {
+            let source = self.node_index(node_count)?;
+            let target = self.node_index(node_count)?;
+            let way = self.way()?;
+            graph.add_edge(source, target, way);
+        }
+        intern_graph_tags(&mut graph);
+        pack_edge_geometry(&mut graph);
+
+        let entry_count = self.len()?;
+        let mut entries = Vec::with_capacity(entry_count.min(node_count));
+        for _ in 0..entry_count {
+            let x = self.f64()?;
+            let y = self.f64()?;
+            entries.push(([x, y], self.node_index(node_count)?));
+        }
+
+        let poi_snaps = match self.u8()? {
+            0 => None,
+            _ => {
+                let count = self.len()?;
+                let mut snaps = HashMap::with_capacity(count.min(node_count));
+                for _ in 0..count {
+                    let poi_id = self.i64()?;
+                    let snap = self.snap(node_count, edge_count)?;
+                    snaps.insert(poi_id, SnappedPoi { poi_id, snap });
+                }
+                Some(Arc::new(snaps))
+            }
+        };
+
+        let restriction_count = self.len()?;
+        let mut restrictions = Vec::new();
+        for _ in 0..restriction_count {
+            let relation_id = self.i64()?;
+            let kind = match self.u8()? {
+                0 => RestrictionKind::No,
+                1 => RestrictionKind::Only,
+                _ => return Err(corrupt("unknown restriction kind")),
+            };
+            let from_way = self.i64()?;
+            let via = match self.u8()? {
+                0 => RestrictionVia::Node(self.i64()?),
+                1 => {
+                    let count = self.len()?;
+                    let ways = (0..count).map(|_| self.i64()).collect::<Result<_, _>>()?;
+                    RestrictionVia::Ways(ways)
+                }
+                _ => return Err(corrupt("unknown restriction via")),
+            };
+            let to_way = self.i64()?;
+            let modes = self.u8()?;
+            restrictions.push(TurnRestriction {
+                relation_id,
+                kind,
+                from_way,
+                via,
+                to_way,
+                modes: RestrictionModes {
+                    bicycle: modes & 1 != 0,
+                    motor_vehicle: modes & 2 != 0,
+                },
+            });
+        }
+
+        let mut sg =
+            SpatialGraph::with_spatial_index(graph, entries).with_turn_restrictions(restrictions);
+        sg.poi_snaps = poi_snaps;
+
+        let penalty_count = self.len()?;
+        for _ in 0..penalty_count {
+            let network_type = *NETWORK_TYPES
+                .get(self.u8()? as usize)
+                .ok_or_else(|| corrupt("unknown network type"))?;
+            let penalties = TurnPenalties {
+                left_s: self.f64()?,
+                right_s: self.f64()?,
+                u_turn_s: self.f64()?,
+                straight_tolerance_deg: self.f64()?,
+                u_turn_threshold_deg: self.f64()?,
+            };
+            sg = sg.with_turn_penalties(network_type, penalties);
+        }
+        Ok(sg)
+    }
+
+    fn tags(&mut self) -> Result<Vec<XmlTag>, OsmGraphError> {
+        let count = self.len()?;
+        let mut tags = Vec::with_capacity(count.min(64));
+        for _ in 0..count {
+            let key = self.string()?;
+            let value = self.string()?;
+            tags.push(XmlTag { key, value });
+        }
+        Ok(tags)
+    }
+
+    fn string(&mut self) -> Result<TagStr, OsmGraphError> {
+        let index = self.len()?;
+        self.strings
+            .get(index)
+            .cloned()
+            .ok_or_else(|| corrupt("string index out of range"))
+    }
+
+    fn way(&mut self) -> Result<XmlWay, OsmGraphError> {
+        let id = self.i64()?;
+        let tags = self.tags()?;
+        let length = self.f64()?;
+        let speed_kph = self.f64()?;
+        let walk_travel_time = self.f64()?;
+        let bike_travel_time = self.f64()?;
+        let drive_travel_time = self.f64()?;
+        let node_delays = NodeDelays {
+            walk_s: self.f64()?,
+            bike_s: self.f64()?,
+            drive_s: self.f64()?,
+        };
+        let bike_push_m = self.f64()?;
+        let count = self.len()?;
+        let geometry = (0..count)
+            .map(|_| Ok((self.f64()?, self.f64()?)))
+            .collect::<Result<Vec<_>, OsmGraphError>>()?
+            .into();
+        let count = self.len()?;
+        let way_ids = (0..count).map(|_| self.i64()).collect::<Result<_, _>>()?;
+        let count = self.len()?;
+        let closed_during = (0..count)
+            .map(|_| {
+                let (days, start, end) = (self.u8()?, self.u16()?, self.u16()?);
+                TimeWindow::from_parts(days, start, end).ok_or_else(|| corrupt("invalid closure"))
+            })
+            .collect::<Result<_, _>>()?;
+        let closed_by_default = self.u8()? != 0;
+        let count = self.len()?;
+        let elevations_m = (0..count).map(|_| self.f64()).collect::<Result<_, _>>()?;
+        let hourly_speeds = match self.len()? {
+            0 => None,
+            index => Some(Arc::clone(
+                self.speeds
+                    .get(index - 1)
+                    .ok_or_else(|| corrupt("speed table index out of range"))?,
+            )),
+        };
+        Ok(XmlWay {
+            id,
+            nodes: Vec::new(),
+            tags: tags.into(),
+            length,
+            speed_kph,
+            walk_travel_time,
+            bike_travel_time,
+            drive_travel_time,
+            node_delays,
+            bike_push_m,
+            geometry,
+            way_ids,
+            closed_during,
+            closed_by_default,
+            elevations_m,
+            hourly_speeds,
+        })
+    }
+
+    fn snap(&mut self, node_count: usize, edge_count: usize) -> Result<SnapResult, OsmGraphError> {
+        let mut snap = SnapResult {
+            input_lat: self.f64()?,
+            input_lon: self.f64()?,
+            node_index: self.node_index(node_count)?,
+            node_id: self.i64()?,
+            node_lat: self.f64()?,
+            node_lon: self.f64()?,
+            distance_m: self.f64()?,
+            snapped_lat: self.f64()?,
+            snapped_lon: self.f64()?,
+            edge_index: None,
+            edge_node_ids: None,
+            edge_offset_m: 0.0,
+        };
+        if self.u8()? != 0 {
+            let index = self.len()?;
+            if index >= edge_count {
+                return Err(corrupt("edge index out of range"));
+            }
+            snap.edge_index = Some(EdgeIndex::new(index));
+            snap.edge_node_ids = Some((self.i64()?, self.i64()?));
+            snap.edge_offset_m = self.f64()?;
+        }
+        Ok(snap)
+    }
+}
+
+fn corrupt(what: &str) -> OsmGraphError {
+    OsmGraphError::GraphFile(format!("corrupt file: {what}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conditional::DepartureTime;
+    use crate::poi::Poi;
+    use crate::speed::SpeedProfile;
+    use crate::traffic::HourlySpeeds;
+
+    const XML: &str = r#"<osm>
+          <node id="1" lat="48.000" lon="11.000" />
+          <node id="2" lat="48.000" lon="11.001"><tag k="highway" v="traffic_signals" /></node>
+          <node id="3" lat="48.000" lon="11.002" />
+          <node id="4" lat="48.001" lon="11.001" />
+          <way id="10"><nd ref="1" /><nd ref="2" /><tag k="highway" v="residential" /></way>
+          <way id="11"><nd ref="2" /><nd ref="3" /><tag k="highway" v="residential" />
+            <tag k="access:conditional" v="no @ (Mo-Fr 07:00-09:00)" /></way>
+          <way id="12"><nd ref="2" /><nd ref="4" /><tag k="highway" v="service" /></way>
+          <relation id="99">
+            <member type="way" ref="10" role="from" />
+            <member type="node" ref="2" role="via" />
+            <member type="way" ref="12" role="to" />
+            <tag k="type" v="restriction" /><tag k="restriction" v="no_left_turn" />
+          </relation>
+        </osm>"#;
+
+    fn temp_path() -> std::path::PathBuf {
+        std::env::temp_dir().join(format!(
+            "graphways_graph_{}.bin",
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .subsec_nanos()
+        ))
+    }
+
+    #[test]
+    fn round_trips_graph_and_query_state() {
+        let profile = SpeedProfile {
+            hourly_speeds: Some(Arc::new(
+                HourlySpeeds::parse_csv("10,both,Mo-Fr,8,12\n12,forward,Su,3,8").unwrap(),
+            )),
+            ..SpeedProfile::default()
+        };
+        let mut sg = SpatialGraph::from_osm(XML, NetworkType::Drive, Some(true), Some(&profile))
+            .unwrap()
+            .with_turn_penalties(
+                NetworkType::Drive,
+                TurnPenalties::for_network(NetworkType::Drive),
+            );
+        sg.snap_pois(&[Poi {
+            id: 500,
+            lat: 48.0011,
+            lon: 11.0011,
+            tags: HashMap::new(),
+        }]);
+        let path = temp_path();
+        sg.save(&path).unwrap();
+        let loaded = SpatialGraph::load(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(loaded.graph.node_count(), sg.graph.node_count());
+        assert_eq!(loaded.graph.edge_count(), sg.graph.edge_count());
+        for (a, b) in sg.graph.edge_weights().zip(loaded.graph.edge_weights()) {
+            assert_eq!(a.id, b.id);
+            assert_eq!(a.geometry, b.geometry);
+            assert_eq!(a.way_ids, b.way_ids);
+            assert_eq!(a.closed_during, b.closed_during);
+            assert_eq!(a.closed_by_default, b.closed_by_default);
+            assert_eq!(a.node_delays, b.node_delays);
+            assert_eq!(a.bike_push_m, b.bike_push_m);
+            assert_eq!(a.hourly_speeds, b.hourly_speeds);
+            assert_eq!(a.drive_travel_time, b.drive_travel_time);
+            assert_eq!(a.tags.len(), b.tags.len());
+        }
+        assert_eq!(
+            loaded.graph[NodeIndex::new(1)].tags[0].value,
+            "traffic_signals"
+        );
+        assert_eq!(loaded.turn_restrictions(), sg.turn_restrictions());
+        assert_eq!(
+            loaded.turn_penalties(NetworkType::Drive),
+            sg.turn_penalties(NetworkType::Drive)
+        );
+        let snap = loaded.poi_snaps.as_ref().unwrap()[&500].snap;
+        assert_eq!(snap.node_id, 4);
+        assert_eq!(
+            loaded.nearest_node(48.0009, 11.0009),
+            sg.nearest_node(48.0009, 11.0009)
+        );
+
+        let route = |g: &SpatialGraph, departure: &str| {
+            let departure: DepartureTime = departure.parse().unwrap();
+            g.route_at(
+                48.0,
+                11.0,
+                48.0,
+                11.002,
+                NetworkType::Drive,
+                None,
+                departure,
+            )
+            .map(|r| r.duration_s)
+            .ok()
+        };
+        assert!(route(&loaded, "Mo 08:00").is_none());
+        assert_eq!(route(&loaded, "Mo 10:00"), route(&sg, "Mo 10:00"));
+        assert!(route(&loaded, "Mo 10:00").is_some());
+    }
+
+    #[test]
+    fn rejects_foreign_and_incompatible_files() {
+        let sg = SpatialGraph::from_osm(XML, NetworkType::Walk, None, None).unwrap();
+        let path = temp_path();
+        sg.save(&path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+
+        let mut newer = bytes.clone();
+        newer[8..12].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        std::fs::write(&path, &newer).unwrap();
+        let err = SpatialGraph::load(&path).err().unwrap();
+        assert!(matches!(err, OsmGraphError::GraphFile(ref msg) if msg.contains("version")));
+
+        std::fs::write(&path, &bytes[..bytes.len() - 3]).unwrap();
+        assert!(matches!(
+            SpatialGraph::load(&path),
+            Err(OsmGraphError::GraphFile(_))
+        ));
+
+        std::fs::write(&path, b"<osm></osm>").unwrap();
+        assert!(matches!(
+            SpatialGraph::load(&path),
+            Err(OsmGraphError::GraphFile(_))
+        ));
+        let _ = std::fs::remove_file(&path);
+    }
+}
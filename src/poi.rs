@@ -138,7 +138,11 @@ pub(crate) async fn fetch_pois_within(polygon: &Polygon<f64>) -> Result<Vec<Poi>
             id: n.id,
             lat: n.lat,
             lon: n.lon,
-            tags: n.tags.into_iter().map(|t| (t.key, t.value)).collect(),
+            tags: n
+                .tags
+                .into_iter()
+                .map(|t| (t.key.to_string(), t.value.to_string()))
+                .collect(),
         })
         .collect();
 
@@ -184,7 +188,11 @@ pub(crate) async fn fetch_pois_within_reachability(
                     id: n.id,
                     lat: n.lat,
                     lon: n.lon,
-                    tags: n.tags.into_iter().map(|t| (t.key, t.value)).collect(),
+                    tags: n
+                        .tags
+                        .into_iter()
+                        .map(|t| (t.key.to_string(), t.value.to_string()))
+                        .collect(),
                 },
                 travel_time_s,
                 snap_node_id: snapped.snap.node_id,
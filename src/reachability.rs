@@ -11,8 +11,10 @@ use std::collections::{BinaryHeap, HashMap};
 use petgraph::graph::{DiGraph, EdgeIndex, NodeIndex};
 use petgraph::visit::EdgeRef;
 
+use crate::conditional::DepartureTime;
 use crate::graph::{SpatialGraph, XmlNode, XmlWay};
 use crate::overpass::NetworkType;
+use crate::search::{edge_based_search, Anchor, SearchDirection};
 
 /// Result of a one-to-many shortest-path search from a single origin.
 ///
@@ -20,6 +22,8 @@ use crate::overpass::NetworkType;
 /// keyed by `NodeIndex`, with values in seconds for the chosen `NetworkType`.
 #[derive(Debug, Clone)]
 pub struct ReachabilityResult {
+    /// The start node, or for a start part-way along an edge the nearer end
+    /// of that edge.
     pub start: NodeIndex,
     pub max_cost: f64,
     pub distances: HashMap<NodeIndex, f64>,
@@ -86,8 +90,9 @@ impl Ord for SearchState {
 ///
 /// The closure is called once per edge relaxation. Use it to inject
 /// density-based traffic penalties, externally-supplied multipliers from a
-/// traffic API, time-of-day adjustments, or any custom cost model. Costs must
-/// be non-negative and finite or Dijkstra's invariants break.
+/// traffic API, or any custom cost model. Costs must be non-negative and
+/// finite or Dijkstra's invariants break. For costs that depend on when the
+/// edge is reached, use [`compute_time_dependent_reachability_with`].
 pub fn compute_reachability_with<F>(
     graph: &DiGraph<XmlNode, XmlWay>,
     start: NodeIndex,
@@ -97,21 +102,79 @@ pub fn compute_reachability_with<F>(
 where
     F: FnMut(EdgeInfo<'_>) -> f64,
 {
-    if max_cost.is_nan() || max_cost < 0.0 {
-        return ReachabilityResult {
-            start,
-            max_cost,
-            distances: HashMap::new(),
-        };
+    compute_time_dependent_reachability_with(graph, start, max_cost, |edge, _| cost(edge))
+}
+
+/// [`compute_reachability_with`] for time-dependent costs: the closure also
+/// receives the cost accumulated when the search reaches the edge, i.e. the
+/// seconds since departure for travel-time costs. Add them to a departure
+/// time to price each edge at the time of day it is entered.
+pub fn compute_time_dependent_reachability_with<F>(
+    graph: &DiGraph<XmlNode, XmlWay>,
+    start: NodeIndex,
+    max_cost: f64,
+    cost: F,
+) -> ReachabilityResult
+where
+    F: FnMut(EdgeInfo<'_>, f64) -> f64,
+{
+    let start = Anchor::node(graph, start);
+    ReachabilityResult {
+        start: start.node,
+        max_cost,
+        distances: reachability_search(graph, &start, SearchDirection::Forward, max_cost, cost),
     }
+}
 
+/// Node-based Dijkstra behind [`compute_time_dependent_reachability_with`], returning the
+/// cost of every node within `max_cost` of `start` (or, backward, from every
+/// node to `start`). `cost` also receives the cost of the node the edge is
+/// relaxed from, for time-dependent edge costs.
+pub(crate) fn reachability_search<F>(
+    graph: &DiGraph<XmlNode, XmlWay>,
+    start: &Anchor,
+    direction: SearchDirection,
+    max_cost: f64,
+    mut cost: F,
+) -> HashMap<NodeIndex, f64>
+where
+    F: FnMut(EdgeInfo<'_>, f64) -> f64,
+{
     let mut distances = HashMap::new();
+    if max_cost.is_nan() || max_cost < 0.0 {
+        return distances;
+    }
+
+    let petgraph_direction = match direction {
+        SearchDirection::Forward => petgraph::Outgoing,
+        SearchDirection::Backward => petgraph::Incoming,
+    };
+    let next_node = |source: NodeIndex, target: NodeIndex| match direction {
+        SearchDirection::Forward => target,
+        SearchDirection::Backward => source,
+    };
+    let seeds = if start.is_node() {
+        vec![(start.node, 0.0)]
+    } else {
+        start
+            .partial_edges(graph, direction, &mut cost)
+            .into_iter()
+            .map(|(edge, edge_cost)| {
+                let (source, target) = graph.edge_endpoints(edge).unwrap();
+                (next_node(source, target), edge_cost)
+            })
+            .collect()
+    };
     let mut heap = BinaryHeap::new();
-    distances.insert(start, 0.0);
-    heap.push(SearchState {
-        cost: 0.0,
-        node: start,
-    });
+    for (node, node_cost) in seeds {
+        if node_cost <= max_cost && node_cost < *distances.get(&node).unwrap_or(&f64::INFINITY) {
+            distances.insert(node, node_cost);
+            heap.push(SearchState {
+                cost: node_cost,
+                node,
+            });
+        }
+    }
 
     while let Some(SearchState {
         cost: node_cost,
@@ -125,17 +188,20 @@ where
             continue;
         }
 
-        for edge in graph.edges(node) {
-            let edge_cost = cost(EdgeInfo {
-                id: edge.id(),
-                source: edge.source(),
-                target: edge.target(),
-                weight: edge.weight(),
-            });
+        for edge in graph.edges_directed(node, petgraph_direction) {
+            let edge_cost = cost(
+                EdgeInfo {
+                    id: edge.id(),
+                    source: edge.source(),
+                    target: edge.target(),
+                    weight: edge.weight(),
+                },
+                node_cost,
+            );
             if !edge_cost.is_finite() || edge_cost < 0.0 {
                 continue;
             }
-            let next = edge.target();
+            let next = next_node(edge.source(), edge.target());
             let next_cost = node_cost + edge_cost;
             if next_cost > max_cost {
                 continue;
@@ -150,11 +216,7 @@ where
         }
     }
 
-    ReachabilityResult {
-        start,
-        max_cost,
-        distances,
-    }
+    distances
 }
 
 /// Compute reachability from `start` up to `max_cost` seconds for the given
@@ -193,7 +255,7 @@ fn reachable_subgraph(sg: &SpatialGraph, result: &ReachabilityResult) -> Spatial
         subgraph.add_edge(source, target, edge.weight().clone());
     }
 
-    SpatialGraph::new(subgraph)
+    sg.derive(subgraph)
 }
 
 impl ReachableGraph {
@@ -213,17 +275,14 @@ impl ReachableGraph {
     }
 
     pub fn contains_node_id(&self, node_id: i64) -> bool {
-        self.result
-            .distances
-            .keys()
-            .any(|&idx| self.graph.graph[idx].id == node_id)
+        self.graph
+            .node_index_for_osm_id(node_id)
+            .is_some_and(|idx| self.result.distances.contains_key(&idx))
     }
 
     pub fn travel_time_to_node_id(&self, node_id: i64) -> Option<f64> {
-        self.result
-            .distances
-            .iter()
-            .find_map(|(&idx, &time)| (self.graph.graph[idx].id == node_id).then_some(time))
+        let idx = self.graph.node_index_for_osm_id(node_id)?;
+        self.result.distances.get(&idx).copied()
     }
 
     pub fn materialize(&self) -> SpatialGraph {
@@ -280,8 +339,10 @@ impl SpatialGraph {
         })
     }
 
-    /// Return every node reachable from the nearest graph node to `(lat, lon)`
-    /// within `max_time` seconds, along with the travel time to each.
+    /// Return every node reachable from `(lat, lon)` within `max_time`
+    /// seconds, along with the travel time to each. The search starts from
+    /// the point snapped on the closest edge, so a start part-way along an
+    /// edge reaches its ends after the matching share of its travel time.
     ///
     /// This is the primary entry point for reachability queries. The returned
     /// [`ReachabilityResult`] can be passed directly to
@@ -294,13 +355,85 @@ impl SpatialGraph {
         network_type: NetworkType,
         max_snap_m: Option<f64>,
     ) -> Option<ReachabilityResult> {
-        let start = self.nearest_node_within(lat, lon, max_snap_m)?;
-        Some(compute_reachability(
+        let start = self.anchor_within(lat, lon, max_snap_m)?;
+        Some(self.reachability_from(&start, max_time, network_type, None))
+    }
+
+    /// Like [`SpatialGraph::reachability`], leaving at `departure`: edges are
+    /// unusable while a conditional closure applies at the time the search
+    /// reaches them.
+    pub fn reachability_at(
+        &self,
+        lat: f64,
+        lon: f64,
+        max_time: f64,
+        network_type: NetworkType,
+        max_snap_m: Option<f64>,
+        departure: DepartureTime,
+    ) -> Option<ReachabilityResult> {
+        let start = self.anchor_within(lat, lon, max_snap_m)?;
+        Some(self.reachability_from(&start, max_time, network_type, Some(departure)))
+    }
+
+    /// Like [`SpatialGraph::reachable_graph`], leaving at `departure`.
+    pub fn reachable_graph_at(
+        &self,
+        lat: f64,
+        lon: f64,
+        max_time: f64,
+        network_type: NetworkType,
+        max_snap_m: Option<f64>,
+        departure: DepartureTime,
+    ) -> Option<ReachableGraph> {
+        let result =
+            self.reachability_at(lat, lon, max_time, network_type, max_snap_m, departure)?;
+        Some(ReachableGraph {
+            graph: self.clone(),
+            result,
+            network_type,
+        })
+    }
+
+    /// Reachability from a search anchor, honouring the graph's turn
+    /// restrictions, turn penalties and closures when it has any, and
+    /// conditional closures when a departure time is given.
+    pub(crate) fn reachability_from(
+        &self,
+        start: &Anchor,
+        max_cost: f64,
+        network_type: NetworkType,
+        departure: Option<DepartureTime>,
+    ) -> ReachabilityResult {
+        let cost =
+            |e: EdgeInfo<'_>, elapsed_s| self.search_cost(e.id, network_type, departure, elapsed_s);
+        let Some(turns) = self.turn_model(network_type) else {
+            return ReachabilityResult {
+                start: start.node,
+                max_cost,
+                distances: reachability_search(
+                    &self.graph,
+                    start,
+                    SearchDirection::Forward,
+                    max_cost,
+                    cost,
+                ),
+            };
+        };
+        let search = edge_based_search(
             &self.graph,
+            turns,
             start,
-            max_time,
-            network_type,
-        ))
+            SearchDirection::Forward,
+            &[],
+            max_cost,
+            cost,
+            |_| 0.0,
+        );
+        ReachabilityResult {
+            start: start.node,
+            max_cost,
+            distances: search.distances,
+        }
     }
 
     /// Fetch POIs reachable from `(lat, lon)` within `max_time` seconds,
@@ -327,6 +460,7 @@ mod tests {
     use super::*;
     use crate::graph::create_graph;
     use crate::graph::{XmlNode, XmlNodeRef, XmlTag, XmlWay};
+    use crate::storage::EdgeGeometry;
 
     fn node(id: i64, lat: f64, lon: f64) -> XmlNode {
         XmlNode {
@@ -334,6 +468,7 @@ mod tests {
             lat,
             lon,
             tags: vec![],
+            elevation_m: None,
         }
     }
 
@@ -356,7 +491,14 @@ mod tests {
             walk_travel_time: 0.0,
             bike_travel_time: 0.0,
             drive_travel_time: 0.0,
-            geometry: Vec::new(),
+            geometry: EdgeGeometry::default(),
+            way_ids: Vec::new(),
+            closed_during: Vec::new(),
+            closed_by_default: false,
+            node_delays: Default::default(),
+            bike_push_m: 0.0,
+            elevations_m: Vec::new(),
+            hourly_speeds: None,
         }
     }
 
@@ -460,6 +602,30 @@ mod tests {
         }
     }
 
+    #[test]
+    fn time_dependent_closure_sees_the_time_each_edge_is_reached() {
+        let nodes = vec![node(1, 0.0, 0.0), node(2, 0.0, 0.001), node(3, 0.0, 0.002)];
+        let w = way(vec![1, 2, 3], vec![("highway", "residential")]);
+        let g = create_graph(nodes, vec![w], true, false);
+
+        // Edges entered after 5 s take twice as long.
+        let start = g.node_indices().find(|&i| g[i].id == 1).unwrap();
+        let mut entered = Vec::new();
+        let result = compute_time_dependent_reachability_with(&g, start, 100.0, |_, elapsed| {
+            entered.push(elapsed);
+            if elapsed < 5.0 {
+                10.0
+            } else {
+                20.0
+            }
+        });
+
+        let time_to = |id| result.distances[&g.node_indices().find(|&i| g[i].id == id).unwrap()];
+        assert_eq!(time_to(2), 10.0);
+        assert_eq!(time_to(3), 30.0);
+        assert!(entered.contains(&0.0) && entered.contains(&10.0));
+    }
+
     #[test]
     fn reachable_graph_view_exposes_induced_counts_and_travel_times() {
         let nodes = vec![node(1, 0.0, 0.0), node(2, 0.0, 0.001), node(3, 0.0, 0.002)];
@@ -480,4 +646,22 @@ mod tests {
         assert_eq!(reachable.graph.graph.node_count(), 3);
         assert_eq!(reachable.materialize().graph.node_count(), 2);
     }
+
+    #[test]
+    fn reachability_starts_part_way_along_an_edge() {
+        let nodes = vec![node(1, 0.0, 0.0), node(2, 0.0, 0.001), node(3, 0.0, 0.002)];
+        let w = way(vec![1, 2, 3], vec![("highway", "residential")]);
+        let graph = SpatialGraph::new(create_graph(nodes, vec![w], true, false));
+        let edge_time = graph.graph.edge_weights().next().unwrap().drive_travel_time;
+
+        let result = graph
+            .reachability(0.0, 0.00025, f64::INFINITY, NetworkType::Drive, None)
+            .unwrap();
+
+        let time_to = |id| result.distances[&graph.node_index_for_osm_id(id).unwrap()];
+        assert_eq!(graph.graph[result.start].id, 1);
+        assert!((time_to(1) - 0.25 * edge_time).abs() < 1e-3);
+        assert!((time_to(2) - 0.75 * edge_time).abs() < 1e-3);
+        assert!((time_to(3) - 1.75 * edge_time).abs() < 1e-3);
+    }
 }
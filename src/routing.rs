@@ -3,9 +3,15 @@ use petgraph::visit::EdgeRef;
 use std::cmp::Ordering;
 use std::collections::{BinaryHeap, HashMap};
 
+use crate::conditional::DepartureTime;
 use crate::error::OsmGraphError;
-use crate::graph::{SnapResult, SpatialGraph};
+use crate::graph::{directed_edge_elevations, directed_edge_geometry, SnapResult, SpatialGraph};
+use crate::maneuvers::{route_maneuvers, Maneuver, ManeuverKind};
 use crate::overpass::NetworkType;
+use crate::reachability::EdgeInfo;
+use crate::search::{
+    edge_based_search, edge_info, same_edge_path, Anchor, SearchDirection, SearchPath,
+};
 use crate::utils::calculate_distance;
 
 #[derive(Debug, Clone)]
@@ -18,12 +24,80 @@ pub struct Route {
     pub distance_m: f64,
     /// Total travel time in seconds for the given network type
     pub duration_s: f64,
+    /// Ground elevation in metres at each coordinate (parallel to
+    /// `coordinates`). Empty unless every edge on the route has elevations,
+    /// see [`SpatialGraph::with_elevation`].
+    pub elevations_m: Vec<f64>,
+    /// Total climb in metres along the route.
+    pub ascent_m: f64,
+    /// Total drop in metres along the route.
+    pub descent_m: f64,
+    /// Stretches of the route travelled by ferry, in route order.
+    pub ferry_legs: Vec<FerryLeg>,
+    /// Turn-by-turn steps from departure to arrival.
+    pub maneuvers: Vec<Maneuver>,
+    /// The waypoint-to-waypoint parts of the route; a single leg unless the
+    /// route comes from [`SpatialGraph::route_via`].
+    pub legs: Vec<RouteLeg>,
     /// Snap diagnostics for the requested origin coordinate.
     pub origin_snap: SnapResult,
     /// Snap diagnostics for the requested destination coordinate.
     pub destination_snap: SnapResult,
 }
 
+/// A `route=ferry` crossing on a [`Route`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FerryLeg {
+    /// Index into [`Route::coordinates`] where the crossing starts.
+    pub start_index: usize,
+    /// Index into [`Route::coordinates`] where the crossing ends.
+    pub end_index: usize,
+    /// Distance in metres covered by ferry.
+    pub distance_m: f64,
+    /// Travel time in seconds on the ferry, including boarding.
+    pub duration_s: f64,
+}
+
+/// The part of a [`Route`] between two consecutive waypoints.
+#[derive(Debug, Clone)]
+pub struct RouteLeg {
+    /// Index into [`Route::coordinates`] where the leg starts.
+    pub start_index: usize,
+    /// Index into [`Route::coordinates`] where the leg ends.
+    pub end_index: usize,
+    /// Distance in metres covered by the leg.
+    pub distance_m: f64,
+    /// Travel time in seconds for the leg.
+    pub duration_s: f64,
+    /// Snap diagnostics for the waypoint the leg starts from.
+    pub origin_snap: SnapResult,
+    /// Snap diagnostics for the waypoint the leg ends at.
+    pub destination_snap: SnapResult,
+}
+
+impl Route {
+    /// `(distance along the route in metres, elevation in metres)` for each
+    /// coordinate, or an empty vector when the route has no elevations.
+    pub fn elevation_profile(&self) -> Vec<(f64, f64)> {
+        if self.elevations_m.len() != self.coordinates.len() {
+            return Vec::new();
+        }
+        let mut distance_m = 0.0;
+        self.coordinates
+            .iter()
+            .enumerate()
+            .zip(&self.elevations_m)
+            .map(|((i, &(lat, lon)), &elevation)| {
+                if i > 0 {
+                    let (prev_lat, prev_lon) = self.coordinates[i - 1];
+                    distance_m += calculate_distance(prev_lat, prev_lon, lat, lon);
+                }
+                (distance_m, elevation)
+            })
+            .collect()
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 struct SearchState {
     estimated_total: f64,
@@ -54,60 +128,190 @@ impl Ord for SearchState {
     }
 }
 
-fn shortest_path_edges(
+/// The cheapest path between two anchors: from the contraction hierarchy
+/// when one applies, otherwise by A*, turn-aware when the graph needs it.
+pub(crate) fn shortest_path(
+    sg: &SpatialGraph,
+    origin: &Anchor,
+    destination: &Anchor,
+    network_type: NetworkType,
+    departure: Option<DepartureTime>,
+) -> Option<SearchPath> {
+    let cost =
+        |e: EdgeInfo<'_>, elapsed_s| sg.search_cost(e.id, network_type, departure, elapsed_s);
+
+    if let Some(hierarchy) = departure
+        .is_none()
+        .then(|| sg.contraction_hierarchy(network_type))
+        .flatten()
+    {
+        // Restrictions only take routes away, so a hierarchy route without a
+        // restricted turn is also the cheapest restricted route.
+        let path = hierarchy.shortest_path(&sg.graph, origin, destination, cost);
+        let turns = sg.turn_model(network_type);
+        if path
+            .as_ref()
+            .is_none_or(|path| turns.is_none_or(|turns| turns.allows_path(&path.edges)))
+        {
+            return path;
+        }
+    }
+    a_star(sg, origin, destination, network_type, cost)
+}
+
+/// A* between two anchors with edge costs from `cost`, which receives the
+/// edge and the cost accumulated when the search reaches it. Costs must not
+/// fall below travel times, or the distance heuristic overestimates.
+pub(crate) fn a_star<F>(
     sg: &SpatialGraph,
-    origin: NodeIndex,
-    dest: NodeIndex,
+    origin: &Anchor,
+    destination: &Anchor,
     network_type: NetworkType,
-) -> Option<(f64, Vec<NodeIndex>, Vec<EdgeIndex>)> {
+    cost: F,
+) -> Option<SearchPath>
+where
+    F: Fn(EdgeInfo<'_>, f64) -> f64 + Copy,
+{
     let heuristic = |node: NodeIndex| -> f64 {
         let n = &sg.graph[node];
-        let d = &sg.graph[dest];
-        let dist = calculate_distance(n.lat, n.lon, d.lat, d.lon);
+        let dist = calculate_distance(n.lat, n.lon, destination.lat, destination.lon);
         let max_speed_m_per_s = 200.0 / 3.6;
         dist / max_speed_m_per_s
     };
 
+    if let Some(turns) = sg.turn_model(network_type) {
+        return edge_based_search(
+            &sg.graph,
+            turns,
+            origin,
+            SearchDirection::Forward,
+            &[destination],
+            f64::INFINITY,
+            cost,
+            heuristic,
+        )
+        .paths
+        .pop()
+        .flatten();
+    }
+    if origin.is_node() && destination.is_node() && origin.node == destination.node {
+        return Some(SearchPath {
+            cost: 0.0,
+            edges: Vec::new(),
+            start_fraction: 0.0,
+            end_fraction: 1.0,
+        });
+    }
+
     let mut heap = BinaryHeap::new();
     let mut best: HashMap<NodeIndex, f64> = HashMap::new();
-    let mut predecessor: HashMap<NodeIndex, (NodeIndex, EdgeIndex)> = HashMap::new();
+    // Node → (previous node, edge into it); no previous node for the nodes
+    // reached straight from an origin part-way along an edge.
+    let mut predecessor: HashMap<NodeIndex, (Option<NodeIndex>, EdgeIndex)> = HashMap::new();
+    let mut exits: HashMap<NodeIndex, Vec<(EdgeIndex, f64)>> = HashMap::new();
+    for &(edge, fraction) in &destination.edges {
+        let (source, _) = sg.graph.edge_endpoints(edge).unwrap();
+        exits.entry(source).or_default().push((edge, fraction));
+    }
+    // Cheapest way found so far to stop part-way along a destination edge:
+    // (cost, node the edge leaves from, edge, fraction).
+    let mut exit: Option<(f64, Option<NodeIndex>, EdgeIndex, f64)> =
+        same_edge_path(&sg.graph, origin, destination, cost)
+            .map(|path| (path.cost, None, path.edges[0], path.end_fraction));
 
-    best.insert(origin, 0.0);
-    heap.push(SearchState {
-        estimated_total: heuristic(origin),
-        cost: 0.0,
-        node: origin,
-    });
+    let seeds = if origin.is_node() {
+        vec![(origin.node, 0.0, None)]
+    } else {
+        origin
+            .partial_edges(&sg.graph, SearchDirection::Forward, cost)
+            .into_iter()
+            .map(|(edge, edge_cost)| {
+                (
+                    sg.graph.edge_endpoints(edge).unwrap().1,
+                    edge_cost,
+                    Some(edge),
+                )
+            })
+            .collect()
+    };
+    for (node, node_cost, edge) in seeds {
+        if node_cost < *best.get(&node).unwrap_or(&f64::INFINITY) {
+            best.insert(node, node_cost);
+            if let Some(edge) = edge {
+                predecessor.insert(node, (None, edge));
+            }
+            heap.push(SearchState {
+                estimated_total: node_cost + heuristic(node),
+                cost: node_cost,
+                node,
+            });
+        }
+    }
+
+    let edges_to = |predecessor: &HashMap<NodeIndex, (Option<NodeIndex>, EdgeIndex)>,
+                    node: NodeIndex| {
+        let mut edges = Vec::new();
+        let mut current = node;
+        while let Some(&(previous, edge)) = predecessor.get(&current) {
+            edges.push(edge);
+            match previous {
+                Some(previous) => current = previous,
+                None => break,
+            }
+        }
+        edges.reverse();
+        edges
+    };
+    let start_fraction = |edges: &[EdgeIndex]| {
+        edges
+            .first()
+            .and_then(|&edge| origin.fraction_on(edge))
+            .unwrap_or(0.0)
+    };
 
-    while let Some(SearchState { cost, node, .. }) = heap.pop() {
-        if cost > *best.get(&node).unwrap_or(&f64::INFINITY) {
+    while let Some(SearchState {
+        estimated_total,
+        cost: node_cost,
+        node,
+    }) = heap.pop()
+    {
+        if exit.is_some_and(|(best, ..)| estimated_total >= best) {
+            break;
+        }
+        if node_cost > *best.get(&node).unwrap_or(&f64::INFINITY) {
             continue;
         }
-        if node == dest {
-            let mut nodes = vec![dest];
-            let mut edges = Vec::new();
-            let mut current = dest;
-            while current != origin {
-                let (prev, edge) = predecessor[&current];
-                edges.push(edge);
-                nodes.push(prev);
-                current = prev;
+        if destination.is_node() && node == destination.node {
+            let edges = edges_to(&predecessor, node);
+            return Some(SearchPath {
+                cost: node_cost,
+                start_fraction: start_fraction(&edges),
+                end_fraction: 1.0,
+                edges,
+            });
+        }
+
+        for &(edge, fraction) in exits.get(&node).into_iter().flatten() {
+            let edge_cost = cost(edge_info(&sg.graph, edge), node_cost);
+            let total = node_cost + fraction * edge_cost;
+            if edge_cost.is_finite()
+                && edge_cost >= 0.0
+                && exit.is_none_or(|(best, ..)| total < best)
+            {
+                exit = Some((total, Some(node), edge, fraction));
             }
-            nodes.reverse();
-            edges.reverse();
-            return Some((cost, nodes, edges));
         }
 
         for edge in sg.graph.edges(node) {
             let next = edge.target();
-            let edge_cost = edge.weight().travel_time(network_type);
+            let edge_cost = cost(edge_info(&sg.graph, edge.id()), node_cost);
             if !edge_cost.is_finite() || edge_cost < 0.0 {
                 continue;
             }
-            let next_cost = cost + edge_cost;
+            let next_cost = node_cost + edge_cost;
             if next_cost < *best.get(&next).unwrap_or(&f64::INFINITY) {
                 best.insert(next, next_cost);
-                predecessor.insert(next, (node, edge.id()));
+                predecessor.insert(next, (Some(node), edge.id()));
                 heap.push(SearchState {
                     estimated_total: next_cost + heuristic(next),
                     cost: next_cost,
@@ -117,66 +321,138 @@ fn shortest_path_edges(
         }
     }
 
-    None
+    let (cost, from, edge, fraction) = exit?;
+    let mut edges = from
+        .map(|node| edges_to(&predecessor, node))
+        .unwrap_or_default();
+    edges.push(edge);
+    Some(SearchPath {
+        cost,
+        start_fraction: start_fraction(&edges),
+        end_fraction: fraction,
+        edges,
+    })
 }
 
-fn directed_edge_geometry(sg: &SpatialGraph, edge: EdgeIndex) -> Vec<(f64, f64)> {
-    let (source, target) = sg.graph.edge_endpoints(edge).unwrap();
-    let way = sg.graph.edge_weight(edge).unwrap();
-    let mut points = if way.geometry.len() >= 2 {
-        way.geometry.clone()
-    } else {
-        vec![
-            (sg.graph[source].lat, sg.graph[source].lon),
-            (sg.graph[target].lat, sg.graph[target].lon),
-        ]
+/// The part of `points` between fractions `from` and `to` of its length,
+/// with `values` (parallel to `points`, or empty) interpolated alongside.
+fn clip_polyline(
+    points: &[(f64, f64)],
+    values: &[f64],
+    from: f64,
+    to: f64,
+) -> (Vec<(f64, f64)>, Vec<f64>) {
+    if from <= 0.0 && to >= 1.0 {
+        return (points.to_vec(), values.to_vec());
+    }
+    let has_values = values.len() == points.len();
+    let lengths: Vec<f64> = points
+        .windows(2)
+        .map(|pair| calculate_distance(pair[0].0, pair[0].1, pair[1].0, pair[1].1))
+        .collect();
+    let total: f64 = lengths.iter().sum();
+    let (start, end) = ((from * total).min(total), (to * total).min(total));
+
+    let mut clipped = Vec::new();
+    let mut clipped_values = Vec::new();
+    let mut push = |segment: usize, t: f64| {
+        let (a, b) = (points[segment], points[segment + 1]);
+        clipped.push((a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t));
+        if has_values {
+            let (a, b) = (values[segment], values[segment + 1]);
+            clipped_values.push(a + (b - a) * t);
+        }
     };
+    let mut walked = 0.0;
+    let mut started = false;
+    for (segment, &length) in lengths.iter().enumerate() {
+        let along = |distance: f64| {
+            if length > 0.0 {
+                ((distance - walked) / length).clamp(0.0, 1.0)
+            } else {
+                0.0
+            }
+        };
+        let segment_end = walked + length;
+        if !started && start <= segment_end {
+            push(segment, along(start));
+            started = true;
+        }
+        if started {
+            if end <= segment_end {
+                push(segment, along(end));
+                break;
+            }
+            push(segment, 1.0);
+        }
+        walked = segment_end;
+    }
+    (clipped, clipped_values)
+}
 
-    let source_point = (sg.graph[source].lat, sg.graph[source].lon);
-    let target_point = (sg.graph[target].lat, sg.graph[target].lon);
-    let first = *points.first().unwrap();
-    let last = *points.last().unwrap();
-    let matches_forward = calculate_distance(first.0, first.1, source_point.0, source_point.1)
-        + calculate_distance(last.0, last.1, target_point.0, target_point.1)
-        <= calculate_distance(first.0, first.1, target_point.0, target_point.1)
-            + calculate_distance(last.0, last.1, source_point.0, source_point.1);
-    if !matches_forward {
-        points.reverse();
-    }
-    points
+/// Coordinates, cumulative times, distance, duration and ferry legs of a
+/// route along a [`SearchPath`], plus the coordinate index each edge starts
+/// at.
+struct RouteGeometry {
+    coordinates: Vec<(f64, f64)>,
+    cumulative_times_s: Vec<f64>,
+    distance_m: f64,
+    duration_s: f64,
+    ferry_legs: Vec<FerryLeg>,
+    edge_starts: Vec<usize>,
 }
 
+/// `turn_delays[i]` is the turn penalty between `edges[i]` and
+/// `edges[i + 1]`; it is charged at the start of the later edge. The first
+/// and last edges are cut where the path starts and ends.
 fn route_geometry_and_times(
     sg: &SpatialGraph,
-    nodes: &[NodeIndex],
-    edges: &[EdgeIndex],
+    origin: &Anchor,
+    path: &SearchPath,
+    turn_delays: &[f64],
     network_type: NetworkType,
-) -> (Vec<(f64, f64)>, Vec<f64>, f64, f64) {
-    if edges.is_empty() {
-        let node = &sg.graph[nodes[0]];
-        return (vec![(node.lat, node.lon)], vec![0.0], 0.0, 0.0);
+    departure: Option<DepartureTime>,
+) -> RouteGeometry {
+    if path.edges.is_empty() {
+        return RouteGeometry {
+            coordinates: vec![(origin.lat, origin.lon)],
+            cumulative_times_s: vec![0.0],
+            distance_m: 0.0,
+            duration_s: 0.0,
+            ferry_legs: Vec::new(),
+            edge_starts: Vec::new(),
+        };
     }
 
     let mut coordinates = Vec::new();
     let mut cumulative_times_s = Vec::new();
     let mut distance_m = 0.0;
     let mut duration_s = 0.0;
+    let mut ferry_legs: Vec<FerryLeg> = Vec::new();
+    let mut edge_starts = Vec::with_capacity(path.edges.len());
 
-    for &edge in edges {
+    for (i, &edge) in path.edges.iter().enumerate() {
         let way = sg.graph.edge_weight(edge).unwrap();
-        let points = directed_edge_geometry(sg, edge);
-        let edge_time = way.travel_time(network_type);
+        let (from, to) = path.edge_span(i);
+        let (points, _) = clip_polyline(&directed_edge_geometry(&sg.graph, edge), &[], from, to);
         let segment_lengths: Vec<f64> = points
             .windows(2)
             .map(|pair| calculate_distance(pair[0].0, pair[0].1, pair[1].0, pair[1].1))
             .collect();
         let geometry_length: f64 = segment_lengths.iter().sum();
+        if i > 0 {
+            duration_s += turn_delays.get(i - 1).copied().unwrap_or(0.0);
+        }
         let edge_start_time = duration_s;
+        let edge_time =
+            sg.search_cost(edge, network_type, departure, edge_start_time) * (to - from);
 
         if coordinates.is_empty() {
             coordinates.push(points[0]);
             cumulative_times_s.push(duration_s);
         }
+        let edge_start_index = coordinates.len() - 1;
+        edge_starts.push(edge_start_index);
 
         let mut elapsed_on_edge = 0.0;
         for (i, point) in points.iter().enumerate().skip(1) {
@@ -191,14 +467,77 @@ fn route_geometry_and_times(
             cumulative_times_s.push(edge_start_time + elapsed_on_edge);
         }
 
-        distance_m += way.length;
+        distance_m += way.length * (to - from);
         duration_s += edge_time;
         if let Some(last) = cumulative_times_s.last_mut() {
             *last = duration_s;
         }
+
+        if way.is_ferry() {
+            let end_index = coordinates.len() - 1;
+            match ferry_legs.last_mut() {
+                Some(leg) if leg.end_index == edge_start_index => {
+                    leg.end_index = end_index;
+                    leg.distance_m += way.length * (to - from);
+                    leg.duration_s += edge_time;
+                }
+                _ => ferry_legs.push(FerryLeg {
+                    start_index: edge_start_index,
+                    end_index,
+                    distance_m: way.length * (to - from),
+                    duration_s: edge_time,
+                }),
+            }
+        }
+    }
+
+    RouteGeometry {
+        coordinates,
+        cumulative_times_s,
+        distance_m,
+        duration_s,
+        ferry_legs,
+        edge_starts,
+    }
+}
+
+/// Elevations parallel to the coordinates produced by
+/// [`route_geometry_and_times`], plus total ascent and descent. Elevations are
+/// empty when any edge on the route lacks them.
+fn route_elevations(sg: &SpatialGraph, origin: &Anchor, path: &SearchPath) -> (Vec<f64>, f64, f64) {
+    if path.edges.is_empty() {
+        let elevations = sg.graph[origin.node].elevation_m.into_iter().collect();
+        return (elevations, 0.0, 0.0);
     }
 
-    (coordinates, cumulative_times_s, distance_m, duration_s)
+    let mut elevations = Some(Vec::new());
+    let mut ascent_m = 0.0;
+    let mut descent_m = 0.0;
+    for (i, &edge) in path.edges.iter().enumerate() {
+        let way = &sg.graph[edge];
+        let (from, to) = path.edge_span(i);
+        let mut edge_elevations = directed_edge_elevations(&sg.graph, edge);
+        if from <= 0.0 && to >= 1.0 {
+            ascent_m += way.ascent_m();
+            descent_m += way.descent_m();
+        } else if !edge_elevations.is_empty() {
+            let points = directed_edge_geometry(&sg.graph, edge);
+            edge_elevations = clip_polyline(&points, &edge_elevations, from, to).1;
+            for pair in edge_elevations.windows(2) {
+                ascent_m += (pair[1] - pair[0]).max(0.0);
+                descent_m += (pair[0] - pair[1]).max(0.0);
+            }
+        }
+        match elevations.as_mut() {
+            Some(_) if edge_elevations.is_empty() => elevations = None,
+            Some(route) => {
+                let skip = usize::from(!route.is_empty());
+                route.extend(edge_elevations.into_iter().skip(skip));
+            }
+            None => {}
+        }
+    }
+    (elevations.unwrap_or_default(), ascent_m, descent_m)
 }
 
 pub fn route(
@@ -210,6 +549,19 @@ pub fn route(
     network_type: NetworkType,
     max_snap_m: Option<f64>,
 ) -> Result<Route, OsmGraphError> {
+    let (origin_snap, destination_snap) =
+        snap_endpoints(sg, origin_lat, origin_lon, dest_lat, dest_lon, max_snap_m)?;
+    route_between_snaps(sg, origin_snap, destination_snap, network_type, None)
+}
+
+pub(crate) fn snap_endpoints(
+    sg: &SpatialGraph,
+    origin_lat: f64,
+    origin_lon: f64,
+    dest_lat: f64,
+    dest_lon: f64,
+    max_snap_m: Option<f64>,
+) -> Result<(SnapResult, SnapResult), OsmGraphError> {
     let origin_snap = sg
         .snap_point(origin_lat, origin_lon)
         .ok_or(OsmGraphError::OriginNodeNotFound)?;
@@ -232,34 +584,153 @@ pub fn route(
             });
         }
     }
+    Ok((origin_snap, destination_snap))
+}
 
-    let result = shortest_path_edges(
+fn route_between_snaps(
+    sg: &SpatialGraph,
+    origin_snap: SnapResult,
+    destination_snap: SnapResult,
+    network_type: NetworkType,
+    departure: Option<DepartureTime>,
+) -> Result<Route, OsmGraphError> {
+    let origin = sg.anchor(&origin_snap);
+    let destination = sg.anchor(&destination_snap);
+    let path = shortest_path(sg, &origin, &destination, network_type, departure)
+        .ok_or(OsmGraphError::PathNotFound)?;
+    Ok(route_along(
         sg,
-        origin_snap.node_index,
-        destination_snap.node_index,
+        origin_snap,
+        destination_snap,
+        &origin,
+        &path,
         network_type,
-    )
-    .ok_or(OsmGraphError::PathNotFound)?;
+        departure,
+    ))
+}
 
-    let (_, path, edge_path) = result;
-    let (coordinates, cumulative_times_s, distance_m, duration_s) =
-        route_geometry_and_times(sg, &path, &edge_path, network_type);
+/// The [`Route`] following `path` from `origin`, timed with turn penalties
+/// and the travel times in effect when each edge is reached.
+pub(crate) fn route_along(
+    sg: &SpatialGraph,
+    origin_snap: SnapResult,
+    destination_snap: SnapResult,
+    origin: &Anchor,
+    path: &SearchPath,
+    network_type: NetworkType,
+    departure: Option<DepartureTime>,
+) -> Route {
+    let turn_delays: Vec<f64> = match sg.turn_model(network_type) {
+        Some(turns) => path
+            .edges
+            .windows(2)
+            .map(|pair| turns.turn_penalty(pair[0], pair[1]))
+            .collect(),
+        None => Vec::new(),
+    };
+    let geometry =
+        route_geometry_and_times(sg, origin, path, &turn_delays, network_type, departure);
+    let (elevations_m, ascent_m, descent_m) = route_elevations(sg, origin, path);
+    let maneuvers = route_maneuvers(
+        &sg.graph,
+        path,
+        &geometry.edge_starts,
+        &geometry.coordinates,
+        &geometry.cumulative_times_s,
+        network_type,
+    );
 
-    Ok(Route {
-        coordinates,
-        cumulative_times_s,
-        distance_m,
-        duration_s,
+    let leg = RouteLeg {
+        start_index: 0,
+        end_index: geometry.coordinates.len() - 1,
+        distance_m: geometry.distance_m,
+        duration_s: geometry.duration_s,
         origin_snap,
         destination_snap,
-    })
+    };
+    Route {
+        coordinates: geometry.coordinates,
+        cumulative_times_s: geometry.cumulative_times_s,
+        distance_m: geometry.distance_m,
+        duration_s: geometry.duration_s,
+        elevations_m,
+        ascent_m,
+        descent_m,
+        ferry_legs: geometry.ferry_legs,
+        maneuvers,
+        legs: vec![leg],
+        origin_snap,
+        destination_snap,
+    }
+}
+
+/// One continuous route through the legs in order. Each leg starts where the
+/// previous one ended, so the shared point appears once; the arrival at each
+/// intermediate waypoint becomes a [`ManeuverKind::Waypoint`], and only the
+/// first leg keeps its [`ManeuverKind::Depart`].
+fn join_legs(legs: Vec<Route>) -> Route {
+    let mut legs = legs.into_iter();
+    let mut joined = legs.next().expect("a route has at least one leg");
+    for (waypoint, leg) in legs.enumerate() {
+        let offset = joined.coordinates.len() - 1;
+        let elapsed_s = joined.duration_s;
+        if let Some(arrival) = joined.maneuvers.last_mut() {
+            arrival.kind = ManeuverKind::Waypoint {
+                index: waypoint + 1,
+            };
+        }
+        joined.elevations_m = if joined.elevations_m.is_empty() || leg.elevations_m.is_empty() {
+            Vec::new()
+        } else {
+            joined
+                .elevations_m
+                .iter()
+                .chain(&leg.elevations_m[1..])
+                .copied()
+                .collect()
+        };
+        joined.coordinates.extend_from_slice(&leg.coordinates[1..]);
+        joined
+            .cumulative_times_s
+            .extend(leg.cumulative_times_s[1..].iter().map(|t| t + elapsed_s));
+        joined
+            .ferry_legs
+            .extend(leg.ferry_legs.into_iter().map(|ferry| FerryLeg {
+                start_index: ferry.start_index + offset,
+                end_index: ferry.end_index + offset,
+                ..ferry
+            }));
+        joined.maneuvers.extend(
+            leg.maneuvers
+                .into_iter()
+                .filter(|maneuver| maneuver.kind != ManeuverKind::Depart)
+                .map(|maneuver| Maneuver {
+                    coordinate_index: maneuver.coordinate_index + offset,
+                    ..maneuver
+                }),
+        );
+        joined
+            .legs
+            .extend(leg.legs.into_iter().map(|part| RouteLeg {
+                start_index: part.start_index + offset,
+                end_index: part.end_index + offset,
+                ..part
+            }));
+        joined.distance_m += leg.distance_m;
+        joined.duration_s += leg.duration_s;
+        joined.ascent_m += leg.ascent_m;
+        joined.descent_m += leg.descent_m;
+        joined.destination_snap = leg.destination_snap;
+    }
+    joined
 }
 
 impl SpatialGraph {
     /// Find the shortest route between two lat/lon points.
     ///
-    /// Snaps both points to the nearest graph nodes, then runs A* to find the
-    /// optimal path. Returns [`OsmGraphError::OriginNodeNotFound`] or
+    /// Snaps both points to the closest point on an edge, then runs A* to
+    /// find the optimal path. Routes starting or ending part-way along an
+    /// edge cover only that part of it, at a pro-rated travel time. Turn restrictions attached to the graph are honoured. Returns [`OsmGraphError::OriginNodeNotFound`] or
     /// [`OsmGraphError::DestinationNodeNotFound`] if snapping fails, and
     /// [`OsmGraphError::PathNotFound`] if the snapped nodes are disconnected.
     pub fn route(
@@ -281,6 +752,77 @@ impl SpatialGraph {
             max_snap_m,
         )
     }
+
+    /// The fastest route through `points` in order, as one continuous
+    /// [`Route`] with a [`RouteLeg`] per consecutive pair of points.
+    ///
+    /// Every point is snapped as in [`SpatialGraph::route`], honouring
+    /// `max_snap_m`. A leg that cannot be snapped or routed fails the whole
+    /// route with [`OsmGraphError::LegFailed`], which names the leg and wraps
+    /// the underlying error. Fewer than two points is
+    /// [`OsmGraphError::InvalidInput`].
+    ///
+    /// Each leg is searched on its own, so turn restrictions and penalties
+    /// do not carry across a waypoint: the next leg may leave it in any
+    /// direction, including back the way the route came.
+    pub fn route_via(
+        &self,
+        points: &[(f64, f64)],
+        network_type: NetworkType,
+        max_snap_m: Option<f64>,
+    ) -> Result<Route, OsmGraphError> {
+        if points.len() < 2 {
+            return Err(OsmGraphError::InvalidInput(
+                "route_via needs at least two points".to_string(),
+            ));
+        }
+        let legs = points
+            .windows(2)
+            .enumerate()
+            .map(|(leg, pair)| {
+                let ((origin_lat, origin_lon), (dest_lat, dest_lon)) = (pair[0], pair[1]);
+                route(
+                    self,
+                    origin_lat,
+                    origin_lon,
+                    dest_lat,
+                    dest_lon,
+                    network_type,
+                    max_snap_m,
+                )
+                .map_err(|error| OsmGraphError::LegFailed {
+                    leg,
+                    error: Box::new(error),
+                })
+            })
+            .collect::<Result<Vec<Route>, OsmGraphError>>()?;
+        Ok(join_legs(legs))
+    }
+
+    /// Like [`SpatialGraph::route`], leaving at `departure`: edges are not
+    /// used while a conditional closure applies at the time the route would
+    /// reach them.
+    #[allow(clippy::too_many_arguments)]
+    pub fn route_at(
+        &self,
+        origin_lat: f64,
+        origin_lon: f64,
+        dest_lat: f64,
+        dest_lon: f64,
+        network_type: NetworkType,
+        max_snap_m: Option<f64>,
+        departure: DepartureTime,
+    ) -> Result<Route, OsmGraphError> {
+        let (origin_snap, destination_snap) =
+            snap_endpoints(self, origin_lat, origin_lon, dest_lat, dest_lon, max_snap_m)?;
+        route_between_snaps(
+            self,
+            origin_snap,
+            destination_snap,
+            network_type,
+            Some(departure),
+        )
+    }
 }
 
 #[cfg(test)]
@@ -288,7 +830,9 @@ mod tests {
     use super::*;
     use crate::graph::{SpatialGraph, XmlNode, XmlTag, XmlWay};
     use crate::overpass::NetworkType;
+    use crate::storage::EdgeGeometry;
     use petgraph::graph::DiGraph;
+    use std::sync::Arc;
 
     fn make_node(id: i64, lat: f64, lon: f64) -> XmlNode {
         XmlNode {
@@ -296,6 +840,7 @@ mod tests {
             lat,
             lon,
             tags: vec![],
+            elevation_m: None,
         }
     }
 
@@ -303,16 +848,23 @@ mod tests {
         XmlWay {
             id: 1,
             nodes: vec![],
-            tags: vec![XmlTag {
+            tags: Arc::from([XmlTag {
                 key: "highway".into(),
                 value: "residential".into(),
-            }],
+            }]),
             length,
             speed_kph: 50.0,
             walk_travel_time: length / (5.0 / 3.6),
             bike_travel_time: length / (15.0 / 3.6),
             drive_travel_time,
-            geometry: Vec::new(),
+            geometry: EdgeGeometry::default(),
+            way_ids: Vec::new(),
+            closed_during: Vec::new(),
+            closed_by_default: false,
+            node_delays: Default::default(),
+            bike_push_m: 0.0,
+            elevations_m: Vec::new(),
+            hourly_speeds: None,
         }
     }
 
@@ -320,16 +872,23 @@ mod tests {
         XmlWay {
             id: 1,
             nodes: vec![],
-            tags: vec![XmlTag {
+            tags: Arc::from([XmlTag {
                 key: "highway".into(),
                 value: "residential".into(),
-            }],
+            }]),
             length,
             speed_kph: 50.0,
             walk_travel_time,
             bike_travel_time: walk_travel_time,
             drive_travel_time,
-            geometry: Vec::new(),
+            geometry: EdgeGeometry::default(),
+            way_ids: Vec::new(),
+            closed_during: Vec::new(),
+            closed_by_default: false,
+            node_delays: Default::default(),
+            bike_push_m: 0.0,
+            elevations_m: Vec::new(),
+            hourly_speeds: None,
         }
     }
 
@@ -339,7 +898,7 @@ mod tests {
         geometry: Vec<(f64, f64)>,
     ) -> XmlWay {
         XmlWay {
-            geometry,
+            geometry: geometry.into(),
             ..make_way(drive_travel_time, length)
         }
     }
@@ -523,4 +1082,132 @@ mod tests {
             Err(OsmGraphError::SnapDistanceExceeded { role: "origin", .. })
         ));
     }
+
+    #[test]
+    fn test_route_starts_and_ends_part_way_along_edges() {
+        let sg = linear_graph();
+
+        let route = route(&sg, 0.0005, 0.0001, 0.0015, 0.0, NetworkType::Drive, None).unwrap();
+
+        let expected = [(0.0005, 0.0), (0.001, 0.0), (0.0015, 0.0)];
+        assert_eq!(route.coordinates.len(), expected.len());
+        for (&(lat, lon), (expected_lat, expected_lon)) in route.coordinates.iter().zip(expected) {
+            assert!((lat - expected_lat).abs() < 1e-12 && (lon - expected_lon).abs() < 1e-12);
+        }
+        assert!((route.duration_s - 10.0).abs() < 1e-6);
+        assert!((route.distance_m - 111.0).abs() < 1e-6);
+        assert!((route.cumulative_times_s[1] - 5.0).abs() < 1e-6);
+        assert!(route.origin_snap.edge_index.is_some());
+        assert_eq!(route.origin_snap.edge_node_ids, Some((1, 2)));
+        assert!((route.origin_snap.edge_offset_m - 55.6).abs() < 0.1);
+        assert!((route.origin_snap.distance_m - 11.1).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_route_along_part_of_a_single_edge() {
+        let sg = linear_graph();
+
+        let ahead = route(&sg, 0.0002, 0.0, 0.0008, 0.0, NetworkType::Drive, None).unwrap();
+        let behind = route(&sg, 0.0008, 0.0, 0.0002, 0.0, NetworkType::Drive, None);
+
+        assert_eq!(ahead.coordinates.len(), 2);
+        assert!((ahead.duration_s - 6.0).abs() < 1e-6);
+        assert!(matches!(behind, Err(OsmGraphError::PathNotFound)));
+    }
+
+    #[test]
+    fn test_route_flags_ferry_legs() {
+        let xml = r#"<osm>
+          <node id="1" lat="48.000" lon="11.000" />
+          <node id="2" lat="48.001" lon="11.000" />
+          <node id="3" lat="48.011" lon="11.000" />
+          <node id="4" lat="48.012" lon="11.000" />
+          <way id="10"><nd ref="1" /><nd ref="2" /><tag k="highway" v="residential" /></way>
+          <way id="20"><nd ref="2" /><nd ref="3" />
+            <tag k="route" v="ferry" /><tag k="duration" v="00:30" /></way>
+          <way id="30"><nd ref="3" /><nd ref="4" /><tag k="highway" v="residential" /></way>
+        </osm>"#;
+        let profile = crate::speed::SpeedProfile {
+            ferry_boarding_s: 300.0,
+            ..Default::default()
+        };
+        let sg = SpatialGraph::from_osm(xml, NetworkType::Walk, None, Some(&profile)).unwrap();
+
+        let route = sg
+            .route(48.000, 11.000, 48.012, 11.000, NetworkType::Walk, None)
+            .unwrap();
+
+        assert_eq!(route.ferry_legs.len(), 1);
+        let leg = &route.ferry_legs[0];
+        assert_eq!(route.coordinates[leg.start_index], (48.001, 11.000));
+        assert_eq!(route.coordinates[leg.end_index], (48.011, 11.000));
+        assert!((leg.duration_s - 2100.0).abs() < 1e-6);
+        assert!((leg.distance_m - 1112.0).abs() < 1.0);
+        let walk_s = route.duration_s - leg.duration_s;
+        assert!((walk_s - 2.0 * 111.2 / (5.0 / 3.6)).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_route_via_joins_legs_into_one_route() {
+        let sg = linear_graph();
+        let direct = route(&sg, 0.0, 0.0, 0.002, 0.0, NetworkType::Drive, None).unwrap();
+        let via = sg
+            .route_via(
+                &[(0.0, 0.0), (0.001, 0.0), (0.002, 0.0)],
+                NetworkType::Drive,
+                None,
+            )
+            .unwrap();
+
+        assert_eq!(via.coordinates, direct.coordinates);
+        assert_eq!(via.cumulative_times_s, direct.cumulative_times_s);
+        assert!((via.distance_m - direct.distance_m).abs() < 1e-9);
+        assert!((via.duration_s - direct.duration_s).abs() < 1e-9);
+        assert_eq!(via.legs.len(), 2);
+        assert_eq!((via.legs[0].start_index, via.legs[0].end_index), (0, 1));
+        assert_eq!((via.legs[1].start_index, via.legs[1].end_index), (1, 2));
+        assert!((via.legs[1].duration_s - 10.0).abs() < 1e-9);
+        assert_eq!(via.legs[1].origin_snap.snapped_lat, 0.001);
+        assert_eq!(via.destination_snap.snapped_lat, 0.002);
+        let kinds: Vec<ManeuverKind> = via.maneuvers.iter().map(|m| m.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                ManeuverKind::Depart,
+                ManeuverKind::Waypoint { index: 1 },
+                ManeuverKind::Arrive,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_route_via_names_the_failing_leg() {
+        let sg = linear_graph();
+
+        // The edges are one-way, so the second leg cannot go back.
+        let result = sg.route_via(
+            &[(0.0, 0.0), (0.002, 0.0), (0.001, 0.0)],
+            NetworkType::Drive,
+            None,
+        );
+        assert!(matches!(
+            result,
+            Err(OsmGraphError::LegFailed { leg: 1, ref error })
+                if matches!(**error, OsmGraphError::PathNotFound)
+        ));
+
+        let result = sg.route_via(
+            &[(0.0, 0.0), (0.001, 0.0), (0.5, 0.0)],
+            NetworkType::Drive,
+            Some(50.0),
+        );
+        let error = result.unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("Leg 1 (point 1 to point 2) failed"));
+        assert!(matches!(
+            sg.route_via(&[(0.0, 0.0)], NetworkType::Drive, None),
+            Err(OsmGraphError::InvalidInput(_))
+        ));
+    }
 }
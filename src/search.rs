@@ -0,0 +1,447 @@
+//! Edge-based label-setting search.
+//!
+//! The node-based searches in `routing`, `reachability` and `feasibility`
+//! settle each node once, so they cannot express rules that depend on how a
+//! node was entered. When a graph carries turn restrictions, or turn
+//! penalties for the queried network type, the `SpatialGraph` entry points
+//! switch to this search instead: labels are `(edge, restriction progress)`
+//! states, every transition is checked and priced by the [`TurnModel`], and
+//! node results are the cheapest label over each node's incoming (or,
+//! searching backward, outgoing) edges.
+//!
+//! Searches start and end at an [`Anchor`]: a graph node, or a point snapped
+//! part-way along an edge, which enters the search as partial edges.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use petgraph::graph::{DiGraph, EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
+
+use crate::graph::{XmlNode, XmlWay};
+use crate::reachability::EdgeInfo;
+use crate::turns::{Progress, TurnModel};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SearchDirection {
+    /// Follow edges from `start` in travel direction.
+    Forward,
+    /// Follow edges into `start` against travel direction, yielding the time
+    /// from each node *to* `start`.
+    Backward,
+}
+
+/// Where a search starts or ends: a graph node, or a point part-way along an
+/// edge (and along its reverse twin on two-way streets).
+#[derive(Debug, Clone)]
+pub(crate) struct Anchor {
+    /// The anchor node, or for a point on an edge the nearer edge endpoint.
+    pub node: NodeIndex,
+    /// Position of the anchor, for distance heuristics.
+    pub lat: f64,
+    pub lon: f64,
+    /// Directed edges the point lies on, each with the fraction of the edge
+    /// before the point. Empty when the anchor is `node` itself.
+    pub edges: Vec<(EdgeIndex, f64)>,
+}
+
+impl Anchor {
+    pub(crate) fn node(graph: &DiGraph<XmlNode, XmlWay>, node: NodeIndex) -> Self {
+        Self {
+            node,
+            lat: graph[node].lat,
+            lon: graph[node].lon,
+            edges: Vec::new(),
+        }
+    }
+
+    pub(crate) fn is_node(&self) -> bool {
+        self.edges.is_empty()
+    }
+
+    /// Fraction of `edge` before the anchor, if the anchor lies on it.
+    pub(crate) fn fraction_on(&self, edge: EdgeIndex) -> Option<f64> {
+        self.edges
+            .iter()
+            .find(|&&(candidate, _)| candidate == edge)
+            .map(|&(_, fraction)| fraction)
+    }
+
+    /// The anchor's edges cut at the anchor, as `(edge, partial cost)`: the
+    /// part after the anchor searching forward, the part before it searching
+    /// backward. Edges whose cost blocks them are left out.
+    pub(crate) fn partial_edges<F>(
+        &self,
+        graph: &DiGraph<XmlNode, XmlWay>,
+        direction: SearchDirection,
+        mut cost: F,
+    ) -> Vec<(EdgeIndex, f64)>
+    where
+        F: FnMut(EdgeInfo<'_>, f64) -> f64,
+    {
+        self.edges
+            .iter()
+            .filter_map(|&(edge, fraction)| {
+                let share = match direction {
+                    SearchDirection::Forward => 1.0 - fraction,
+                    SearchDirection::Backward => fraction,
+                };
+                let edge_cost = cost(edge_info(graph, edge), 0.0);
+                (edge_cost.is_finite() && edge_cost >= 0.0).then_some((edge, share * edge_cost))
+            })
+            .collect()
+    }
+}
+
+/// A path between two anchors, in travel order. The first and last edges
+/// are only partly travelled when an anchor lies mid-edge.
+#[derive(Debug, Clone)]
+pub(crate) struct SearchPath {
+    pub cost: f64,
+    /// Edges travelled; empty when both anchors are the same node.
+    pub edges: Vec<EdgeIndex>,
+    /// Fraction of the first edge before the route starts.
+    pub start_fraction: f64,
+    /// Fraction of the last edge travelled before the route ends.
+    pub end_fraction: f64,
+}
+
+impl SearchPath {
+    /// Fraction of `edges[index]` at which the path enters and leaves it.
+    pub(crate) fn edge_span(&self, index: usize) -> (f64, f64) {
+        let from = if index == 0 { self.start_fraction } else { 0.0 };
+        let to = if index + 1 == self.edges.len() {
+            self.end_fraction
+        } else {
+            1.0
+        };
+        (from, to)
+    }
+}
+
+pub(crate) fn edge_info(graph: &DiGraph<XmlNode, XmlWay>, edge: EdgeIndex) -> EdgeInfo<'_> {
+    let (source, target) = graph.edge_endpoints(edge).unwrap();
+    EdgeInfo {
+        id: edge,
+        source,
+        target,
+        weight: &graph[edge],
+    }
+}
+
+/// The path from `origin` to `destination` along a single edge both lie on,
+/// when the destination is ahead of the origin on it.
+pub(crate) fn same_edge_path<F>(
+    graph: &DiGraph<XmlNode, XmlWay>,
+    origin: &Anchor,
+    destination: &Anchor,
+    mut cost: F,
+) -> Option<SearchPath>
+where
+    F: FnMut(EdgeInfo<'_>, f64) -> f64,
+{
+    origin
+        .edges
+        .iter()
+        .filter_map(|&(edge, from)| {
+            let to = destination.fraction_on(edge).filter(|&to| to >= from)?;
+            let edge_cost = cost(edge_info(graph, edge), 0.0);
+            (edge_cost.is_finite() && edge_cost >= 0.0).then(|| SearchPath {
+                cost: (to - from) * edge_cost,
+                edges: vec![edge],
+                start_fraction: from,
+                end_fraction: to,
+            })
+        })
+        .min_by(|a, b| a.cost.total_cmp(&b.cost))
+}
+
+type LabelKey = (EdgeIndex, Progress);
+
+#[derive(Clone, Copy, Debug)]
+struct Label {
+    estimated_total: f64,
+    cost: f64,
+    key: LabelKey,
+}
+
+impl PartialEq for Label {
+    fn eq(&self, other: &Self) -> bool {
+        self.estimated_total == other.estimated_total && self.key == other.key
+    }
+}
+
+impl Eq for Label {}
+
+impl PartialOrd for Label {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Label {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .estimated_total
+            .partial_cmp(&self.estimated_total)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+/// Output of [`edge_based_search`].
+pub(crate) struct EdgeSearchResult {
+    /// Cheapest cost to (or, backward, from) every node settled within the
+    /// budget, including a node `start` at `0.0`.
+    pub distances: HashMap<NodeIndex, f64>,
+    /// The cheapest path to each of the targets, in the order given, or
+    /// `None` for a target that was not reached.
+    pub paths: Vec<Option<SearchPath>>,
+}
+
+/// A way to end the search at a target: its cost, the label it leaves
+/// from (`None` straight from the start) and, for a target part-way along
+/// an edge, that edge and the fraction of it travelled.
+type Finish = (f64, Option<LabelKey>, Option<(EdgeIndex, f64)>);
+
+/// The cheapest known [`Finish`] for each target.
+struct Finishes {
+    best: Vec<Option<Finish>>,
+    unreached: usize,
+    /// Once every target has a finish, the most expensive of them: labels
+    /// at least this costly cannot improve any target.
+    bound: f64,
+}
+
+impl Finishes {
+    fn new(targets: usize) -> Self {
+        Self {
+            best: vec![None; targets],
+            unreached: targets,
+            bound: f64::INFINITY,
+        }
+    }
+
+    fn offer(&mut self, target: usize, finish: Finish) {
+        let previous = self.best[target].map(|(cost, ..)| cost);
+        if previous.is_some_and(|cost| finish.0 >= cost) {
+            return;
+        }
+        self.best[target] = Some(finish);
+        if previous.is_none() {
+            self.unreached -= 1;
+        }
+        if self.unreached == 0 && previous.is_none_or(|cost| cost >= self.bound) {
+            self.bound = self
+                .best
+                .iter()
+                .flatten()
+                .map(|&(cost, ..)| cost)
+                .fold(0.0, f64::max);
+        }
+    }
+}
+
+/// Run a turn-aware search from `start`. Turn penalties are added to the
+/// cost of the edge being entered.
+///
+/// `cost` receives each edge together with the cost already accumulated when
+/// the search enters it (including any turn penalty), which lets
+/// time-dependent costs evaluate the edge at the moment it is reached. A
+/// `start` part-way along an edge is charged the matching share of it.
+///
+/// Without `targets` the search explores everything within `max_cost`.
+/// With `targets`, which are only supported searching forward, it stops as
+/// soon as the cheapest path to each of them is known; `heuristic` must then
+/// be an admissible, consistent lower bound on the remaining cost to every
+/// target (use `|_| 0.0` for plain Dijkstra). Edge costs that are negative
+/// or non-finite block the edge, matching the node-based searches.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn edge_based_search<F, H>(
+    graph: &DiGraph<XmlNode, XmlWay>,
+    turns: TurnModel<'_>,
+    start: &Anchor,
+    direction: SearchDirection,
+    targets: &[&Anchor],
+    max_cost: f64,
+    mut cost: F,
+    heuristic: H,
+) -> EdgeSearchResult
+where
+    F: FnMut(EdgeInfo<'_>, f64) -> f64,
+    H: Fn(NodeIndex) -> f64,
+{
+    debug_assert!(targets.is_empty() || direction == SearchDirection::Forward);
+    let mut result = EdgeSearchResult {
+        distances: HashMap::new(),
+        paths: vec![None; targets.len()],
+    };
+    if max_cost.is_nan() || max_cost < 0.0 {
+        return result;
+    }
+    if start.is_node() {
+        result.distances.insert(start.node, 0.0);
+    }
+
+    let pivot = |edge: EdgeIndex| {
+        let (source, target) = graph.edge_endpoints(edge).unwrap();
+        match direction {
+            SearchDirection::Forward => target,
+            SearchDirection::Backward => source,
+        }
+    };
+    let petgraph_direction = match direction {
+        SearchDirection::Forward => petgraph::Outgoing,
+        SearchDirection::Backward => petgraph::Incoming,
+    };
+
+    // Targets at a node, and target edges with the fraction of the edge
+    // before the target.
+    let mut at_node: HashMap<NodeIndex, Vec<usize>> = HashMap::new();
+    let mut on_edge: HashMap<EdgeIndex, Vec<(usize, f64)>> = HashMap::new();
+    let mut finishes = Finishes::new(targets.len());
+    for (index, target) in targets.iter().enumerate() {
+        if target.is_node() {
+            if start.is_node() && target.node == start.node {
+                finishes.offer(index, (0.0, None, None));
+            }
+            at_node.entry(target.node).or_default().push(index);
+        }
+        for &(edge, fraction) in &target.edges {
+            on_edge.entry(edge).or_default().push((index, fraction));
+        }
+        if let Some(path) = same_edge_path(graph, start, target, &mut cost) {
+            finishes.offer(
+                index,
+                (path.cost, None, Some((path.edges[0], path.end_fraction))),
+            );
+        }
+    }
+    let consider_exit = |finishes: &mut Finishes,
+                         from: Option<LabelKey>,
+                         edge: EdgeIndex,
+                         entered_at: f64,
+                         edge_cost: f64| {
+        for &(index, fraction) in on_edge.get(&edge).into_iter().flatten() {
+            let total = entered_at + fraction * edge_cost;
+            if total <= max_cost {
+                finishes.offer(index, (total, from, Some((edge, fraction))));
+            }
+        }
+    };
+
+    let mut heap = BinaryHeap::new();
+    let mut best: HashMap<LabelKey, f64> = HashMap::new();
+    let mut predecessor: HashMap<LabelKey, LabelKey> = HashMap::new();
+    let seeds: Vec<(EdgeIndex, f64)> = if start.is_node() {
+        graph
+            .edges_directed(start.node, petgraph_direction)
+            .map(|edge| (edge.id(), cost(edge_info(graph, edge.id()), 0.0)))
+            .collect()
+    } else {
+        start.partial_edges(graph, direction, &mut cost)
+    };
+    for (edge, edge_cost) in seeds {
+        if !edge_cost.is_finite() || edge_cost < 0.0 {
+            continue;
+        }
+        if start.is_node() {
+            consider_exit(&mut finishes, None, edge, 0.0, edge_cost);
+        }
+        if edge_cost > max_cost {
+            continue;
+        }
+        let key = (edge, turns.initial_progress(direction, edge));
+        if edge_cost < *best.get(&key).unwrap_or(&f64::INFINITY) {
+            best.insert(key, edge_cost);
+            heap.push(Label {
+                estimated_total: edge_cost + heuristic(pivot(edge)),
+                cost: edge_cost,
+                key,
+            });
+        }
+    }
+
+    while let Some(Label {
+        estimated_total,
+        cost: label_cost,
+        key,
+    }) = heap.pop()
+    {
+        if estimated_total >= finishes.bound {
+            break;
+        }
+        if label_cost > *best.get(&key).unwrap_or(&f64::INFINITY) {
+            continue;
+        }
+        let node = pivot(key.0);
+        let distance = result.distances.entry(node).or_insert(label_cost);
+        if label_cost < *distance {
+            *distance = label_cost;
+        }
+        for &index in at_node.get(&node).into_iter().flatten() {
+            finishes.offer(index, (label_cost, Some(key), None));
+        }
+
+        for edge in graph.edges_directed(node, petgraph_direction) {
+            let Some((progress, penalty)) = turns.transition(direction, key.0, key.1, edge.id())
+            else {
+                continue;
+            };
+            let entered_at = label_cost + penalty;
+            let edge_cost = cost(edge_info(graph, edge.id()), entered_at);
+            if !edge_cost.is_finite() || edge_cost < 0.0 {
+                continue;
+            }
+            consider_exit(&mut finishes, Some(key), edge.id(), entered_at, edge_cost);
+            let next_cost = entered_at + edge_cost;
+            if next_cost > max_cost {
+                continue;
+            }
+            let next_key = (edge.id(), progress);
+            if next_cost < *best.get(&next_key).unwrap_or(&f64::INFINITY) {
+                best.insert(next_key, next_cost);
+                predecessor.insert(next_key, key);
+                heap.push(Label {
+                    estimated_total: next_cost + heuristic(pivot(edge.id())),
+                    cost: next_cost,
+                    key: next_key,
+                });
+            }
+        }
+    }
+
+    let edges_to = |key: LabelKey| {
+        let mut edges = vec![key.0];
+        let mut current = key;
+        while let Some(&previous) = predecessor.get(&current) {
+            edges.push(previous.0);
+            current = previous;
+        }
+        if direction == SearchDirection::Forward {
+            edges.reverse();
+        }
+        edges
+    };
+    for (path, finish) in result.paths.iter_mut().zip(finishes.best) {
+        *path = finish.map(|(cost, from, exit)| {
+            let mut edges = from.map(edges_to).unwrap_or_default();
+            let end_fraction = match exit {
+                Some((edge, fraction)) => {
+                    edges.push(edge);
+                    fraction
+                }
+                None => 1.0,
+            };
+            SearchPath {
+                cost,
+                start_fraction: edges
+                    .first()
+                    .and_then(|&edge| start.fraction_on(edge))
+                    .unwrap_or(0.0),
+                end_fraction,
+                edges,
+            }
+        });
+    }
+    result
+}
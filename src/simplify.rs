@@ -3,23 +3,35 @@ use petgraph::visit::EdgeRef;
 use rstar::{PointDistance, RTree, RTreeObject, AABB};
 use std::collections::{HashMap, HashSet};
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
-use crate::graph::{XmlNode, XmlTag, XmlWay};
+use crate::conditional::TimeWindow;
+use crate::graph::{directed_edge_elevations, NodeDelays, XmlNode, XmlTag, XmlWay};
+use crate::traffic::WeekSpeeds;
 use crate::utils::calculate_distance;
 
 static ID_COUNTER: AtomicUsize = AtomicUsize::new(1);
 
 const CONSOLIDATION_DISTANCE_M: f64 = 5.0;
 
-pub fn simplify_graph(graph: &DiGraph<XmlNode, XmlWay>) -> DiGraph<XmlNode, XmlWay> {
-    let (consolidated_graph, _) = consolidate_intersections(graph, CONSOLIDATION_DISTANCE_M);
+/// Simplify `graph`, keeping every node whose OSM id is in `protected` as a
+/// distinct endpoint: it is never merged during consolidation and chains are
+/// always split at it.
+pub fn simplify_graph(
+    graph: &DiGraph<XmlNode, XmlWay>,
+    protected: &HashSet<i64>,
+) -> DiGraph<XmlNode, XmlWay> {
+    let (consolidated_graph, _) =
+        consolidate_intersections(graph, CONSOLIDATION_DISTANCE_M, protected);
 
     let mut simplified_graph = DiGraph::new();
     let mut endpoints: HashSet<NodeIndex> = HashSet::new();
     let mut index_map: HashMap<NodeIndex, NodeIndex> = HashMap::new();
 
     for node in consolidated_graph.node_indices() {
-        if is_endpoint(&consolidated_graph, node) {
+        if protected.contains(&consolidated_graph[node].id)
+            || is_endpoint(&consolidated_graph, node)
+        {
             endpoints.insert(node);
             let new_index = simplified_graph.add_node(consolidated_graph[node].clone());
             index_map.insert(node, new_index);
@@ -68,9 +80,16 @@ fn collapse_path_edges(graph: &DiGraph<XmlNode, XmlWay>, edges: &[EdgeIndex]) ->
     let mut total_walk = 0.0;
     let mut total_bike = 0.0;
     let mut total_drive = 0.0;
+    let mut node_delays = NodeDelays::default();
+    let mut bike_push_m = 0.0;
     let mut weighted_speed_sum = 0.0;
-    let mut tags: Option<Vec<XmlTag>> = None;
+    let mut tags: Option<Arc<[XmlTag]>> = None;
     let mut geometry: Vec<(f64, f64)> = Vec::new();
+    let mut way_ids: Vec<i64> = Vec::new();
+    let mut closed_during: Vec<TimeWindow> = Vec::new();
+    let mut closed_by_default = false;
+    // Elevations survive only when every collapsed edge carries them.
+    let mut elevations_m: Option<Vec<f64>> = Some(Vec::new());
 
     for &edge in edges {
         let way = graph.edge_weight(edge).unwrap();
@@ -78,11 +97,36 @@ fn collapse_path_edges(graph: &DiGraph<XmlNode, XmlWay>, edges: &[EdgeIndex]) ->
         total_walk += way.walk_travel_time;
         total_bike += way.bike_travel_time;
         total_drive += way.drive_travel_time;
+        node_delays.walk_s += way.node_delays.walk_s;
+        node_delays.bike_s += way.node_delays.bike_s;
+        node_delays.drive_s += way.node_delays.drive_s;
+        bike_push_m += way.bike_push_m;
         weighted_speed_sum += way.speed_kph * way.length;
         if tags.is_none() {
             tags = Some(way.tags.clone());
         }
         append_edge_geometry(graph, edge, &mut geometry);
+        if let Some(collapsed) = elevations_m.as_mut() {
+            let edge_elevations = directed_edge_elevations(graph, edge);
+            if edge_elevations.is_empty() {
+                elevations_m = None;
+            } else if collapsed.is_empty() {
+                collapsed.extend(edge_elevations);
+            } else {
+                collapsed.extend(edge_elevations.into_iter().skip(1));
+            }
+        }
+        for &way_id in way.osm_way_ids() {
+            if way_ids.last() != Some(&way_id) {
+                way_ids.push(way_id);
+            }
+        }
+        for window in &way.closed_during {
+            if !closed_during.contains(window) {
+                closed_during.push(*window);
+            }
+        }
+        closed_by_default |= way.closed_by_default;
     }
 
     let speed_kph = if total_length > 0.0 {
@@ -94,13 +138,22 @@ fn collapse_path_edges(graph: &DiGraph<XmlNode, XmlWay>, edges: &[EdgeIndex]) ->
     XmlWay {
         id: get_unique_id(),
         nodes: Vec::new(),
-        tags: tags.unwrap_or_default(),
+        tags: tags.unwrap_or_else(|| Arc::from([])),
         length: total_length,
         speed_kph,
         walk_travel_time: total_walk,
         bike_travel_time: total_bike,
         drive_travel_time: total_drive,
-        geometry,
+        node_delays,
+        bike_push_m,
+        geometry: geometry.into(),
+        way_ids,
+        closed_during,
+        closed_by_default,
+        elevations_m: elevations_m.unwrap_or_default(),
+        hourly_speeds: edges
+            .first()
+            .and_then(|&edge| graph[edge].hourly_speeds.clone()),
     }
 }
 
@@ -112,7 +165,7 @@ fn append_edge_geometry(
     let (source, target) = graph.edge_endpoints(edge).unwrap();
     let way = graph.edge_weight(edge).unwrap();
     let mut points = if way.geometry.len() >= 2 {
-        way.geometry.clone()
+        way.geometry.to_vec()
     } else {
         vec![
             (graph[source].lat, graph[source].lon),
@@ -139,10 +192,27 @@ fn append_edge_geometry(
     }
 }
 
+type DedupKey<'a> = (
+    NodeIndex,
+    NodeIndex,
+    &'a [TimeWindow],
+    bool,
+    Option<*const WeekSpeeds>,
+);
+
 fn deduplicate_edges(graph: DiGraph<XmlNode, XmlWay>) -> DiGraph<XmlNode, XmlWay> {
-    let mut best: HashMap<(NodeIndex, NodeIndex), &XmlWay> = HashMap::new();
+    // Parallel edges with different conditional closures or hourly speeds
+    // are both kept: the faster one may be unusable or slow at the time a
+    // query reaches it.
+    let mut best: HashMap<DedupKey<'_>, &XmlWay> = HashMap::new();
     for edge in graph.edge_references() {
-        let key = (edge.source(), edge.target());
+        let key = (
+            edge.source(),
+            edge.target(),
+            edge.weight().closed_during.as_slice(),
+            edge.weight().closed_by_default,
+            edge.weight().hourly_speeds.as_ref().map(Arc::as_ptr),
+        );
         let way = edge.weight();
         best.entry(key)
             .and_modify(|existing| {
@@ -159,7 +229,7 @@ fn deduplicate_edges(graph: DiGraph<XmlNode, XmlWay>) -> DiGraph<XmlNode, XmlWay
         let new_idx = deduped.add_node(graph[old_idx].clone());
         node_map.insert(old_idx, new_idx);
     }
-    for ((src, dst), way) in &best {
+    for ((src, dst, ..), way) in &best {
         deduped.add_edge(node_map[src], node_map[dst], (*way).clone());
     }
 
@@ -242,6 +312,30 @@ fn is_endpoint(graph: &DiGraph<XmlNode, XmlWay>, node_index: NodeIndex) -> bool
         return true;
     }
 
+    // Keep ferry terminals so ferry legs never merge into road edges.
+    let mut edges = graph
+        .edges_directed(node_index, petgraph::Outgoing)
+        .chain(graph.edges_directed(node_index, petgraph::Incoming));
+    let first_is_ferry = edges.next().is_some_and(|edge| edge.weight().is_ferry());
+    if edges.any(|edge| edge.weight().is_ferry() != first_is_ferry) {
+        return true;
+    }
+
+    // Keep the ends of ways with hourly speeds, which apply to their own
+    // edges only.
+    let mut edges = graph
+        .edges_directed(node_index, petgraph::Outgoing)
+        .chain(graph.edges_directed(node_index, petgraph::Incoming));
+    if edges
+        .clone()
+        .any(|edge| edge.weight().hourly_speeds.is_some())
+    {
+        let first_way = edges.next().map(|edge| edge.weight().id);
+        if edges.any(|edge| Some(edge.weight().id) != first_way) {
+            return true;
+        }
+    }
+
     let mut neighbors = out;
     neighbors.extend(incoming);
     neighbors.sort_unstable();
@@ -252,6 +346,7 @@ fn is_endpoint(graph: &DiGraph<XmlNode, XmlWay>, node_index: NodeIndex) -> bool
 fn consolidate_intersections(
     graph: &DiGraph<XmlNode, XmlWay>,
     merge_distance_m: f64,
+    protected: &HashSet<i64>,
 ) -> (DiGraph<XmlNode, XmlWay>, HashMap<NodeIndex, NodeIndex>) {
     let entries: Vec<NodeEntry> = graph
         .node_indices()
@@ -261,13 +356,18 @@ fn consolidate_intersections(
         })
         .collect();
     let tree = RTree::bulk_load(entries);
-    let clusters = cluster_nodes_by_distance(graph, &tree, merge_distance_m);
+    let clusters = cluster_nodes_by_distance(graph, &tree, merge_distance_m, protected);
 
     let mut new_graph = DiGraph::new();
     let mut old_to_new: HashMap<NodeIndex, NodeIndex> = HashMap::new();
 
     for cluster in clusters {
-        let merged = merge_nodes(graph, &cluster.members);
+        // A lone node keeps its OSM id and tags; only real merges synthesize
+        // a new node.
+        let merged = match cluster.members.as_slice() {
+            [single] => graph[*single].clone(),
+            members => merge_nodes(graph, members),
+        };
         let new_idx = new_graph.add_node(merged);
         for &old_idx in &cluster.members {
             old_to_new.insert(old_idx, new_idx);
@@ -282,7 +382,17 @@ fn consolidate_intersections(
             continue;
         }
         if seen_edges.insert((new_src, new_dst)) {
-            new_graph.add_edge(new_src, new_dst, edge.weight().clone());
+            let mut way = edge.weight().clone();
+            // Merged nodes move, so straight edges touching them keep the
+            // original endpoint coordinates as explicit shape points.
+            if way.geometry.is_empty()
+                && (graph[edge.source()].id != new_graph[new_src].id
+                    || graph[edge.target()].id != new_graph[new_dst].id)
+            {
+                let (source, target) = (&graph[edge.source()], &graph[edge.target()]);
+                way.geometry = vec![(source.lat, source.lon), (target.lat, target.lon)].into();
+            }
+            new_graph.add_edge(new_src, new_dst, way);
         }
     }
 
@@ -297,6 +407,7 @@ fn cluster_nodes_by_distance(
     graph: &DiGraph<XmlNode, XmlWay>,
     tree: &RTree<NodeEntry>,
     merge_distance_m: f64,
+    protected: &HashSet<i64>,
 ) -> Vec<Cluster> {
     let mut clusters: Vec<Cluster> = Vec::new();
     let mut assigned: HashSet<NodeIndex> = HashSet::new();
@@ -306,6 +417,11 @@ fn cluster_nodes_by_distance(
             continue;
         }
         let node = &graph[idx];
+        if protected.contains(&node.id) {
+            assigned.insert(idx);
+            clusters.push(Cluster { members: vec![idx] });
+            continue;
+        }
         let center = projected_point(node.lat, node.lon);
         let members: Vec<NodeIndex> = tree
             .locate_within_distance(center, merge_distance_m * merge_distance_m)
@@ -314,6 +430,9 @@ fn cluster_nodes_by_distance(
                     return None;
                 }
                 let candidate = &graph[entry.index];
+                if protected.contains(&candidate.id) {
+                    return None;
+                }
                 (calculate_distance(node.lat, node.lon, candidate.lat, candidate.lon)
                     <= merge_distance_m)
                     .then_some(entry.index)
@@ -363,23 +482,33 @@ fn merge_nodes(graph: &DiGraph<XmlNode, XmlWay>, indices: &[NodeIndex]) -> XmlNo
     let count = indices.len() as f64;
     let avg_lat = indices.iter().map(|&i| graph[i].lat).sum::<f64>() / count;
     let avg_lon = indices.iter().map(|&i| graph[i].lon).sum::<f64>() / count;
+    let elevations: Vec<f64> = indices
+        .iter()
+        .filter_map(|&i| graph[i].elevation_m)
+        .collect();
+    let elevation_m =
+        (!elevations.is_empty()).then(|| elevations.iter().sum::<f64>() / elevations.len() as f64);
 
     XmlNode {
         id: get_unique_id(),
         lat: avg_lat,
         lon: avg_lon,
         tags: Vec::new(),
+        elevation_m,
     }
 }
 
+/// Synthetic ids for merged nodes and collapsed edges. They are negative so
+/// they can never collide with the OSM ids kept by unmerged nodes.
 fn get_unique_id() -> i64 {
-    ID_COUNTER.fetch_add(1, Ordering::Relaxed) as i64
+    -(ID_COUNTER.fetch_add(1, Ordering::Relaxed) as i64)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::graph::{XmlNode, XmlTag, XmlWay};
+    use crate::storage::EdgeGeometry;
 
     fn make_node(id: i64, lat: f64, lon: f64) -> XmlNode {
         XmlNode {
@@ -387,6 +516,7 @@ mod tests {
             lat,
             lon,
             tags: Vec::new(),
+            elevation_m: None,
         }
     }
 
@@ -401,13 +531,20 @@ mod tests {
         XmlWay {
             id,
             nodes: Vec::new(),
-            tags: Vec::new(),
+            tags: Arc::from([]),
             length: 100.0,
             speed_kph: 50.0,
             walk_travel_time: 72.0,
             bike_travel_time: 24.0,
             drive_travel_time,
-            geometry: Vec::new(),
+            geometry: EdgeGeometry::default(),
+            way_ids: Vec::new(),
+            closed_during: Vec::new(),
+            closed_by_default: false,
+            node_delays: Default::default(),
+            bike_push_m: 0.0,
+            elevations_m: Vec::new(),
+            hourly_speeds: None,
         }
     }
 
@@ -424,7 +561,7 @@ mod tests {
         geometry: Vec<(f64, f64)>,
     ) -> XmlWay {
         XmlWay {
-            geometry,
+            geometry: geometry.into(),
             ..make_way(id, drive_travel_time)
         }
     }
@@ -438,7 +575,7 @@ mod tests {
         graph.add_edge(a, b, make_way(2, 50.0));
 
         assert_eq!(graph.edge_count(), 2);
-        let deduped = simplify_graph(&graph);
+        let deduped = simplify_graph(&graph, &HashSet::new());
         assert!(
             deduped.edge_count() <= 1,
             "Expected at most 1 edge, got {}",
@@ -453,7 +590,7 @@ mod tests {
         let b = graph.add_node(make_node(2, 38.0001, -77.0));
         graph.add_edge(a, b, make_way(1, 1.0));
 
-        let (consolidated, map) = consolidate_intersections(&graph, 5.0);
+        let (consolidated, map) = consolidate_intersections(&graph, 5.0, &HashSet::new());
 
         assert_eq!(consolidated.node_count(), 2);
         assert_ne!(map[&a], map[&b]);
@@ -472,6 +609,31 @@ mod tests {
         assert!(merged.tags.is_empty());
     }
 
+    #[test]
+    fn protected_nodes_are_not_merged_or_collapsed() {
+        let mut graph = DiGraph::new();
+        let a = graph.add_node(make_node(1, 0.0, 0.0));
+        let b = graph.add_node(make_node(2, 0.001, 0.0));
+        let b2 = graph.add_node(make_node(5, 0.001_000_1, 0.0));
+        let c = graph.add_node(make_node(3, 0.002, 0.0));
+        graph.add_edge(a, b, make_way(1, 10.0));
+        graph.add_edge(b, b2, make_way(1, 1.0));
+        graph.add_edge(b2, c, make_way(2, 20.0));
+
+        let protected = HashSet::from([2]);
+        let simplified = simplify_graph(&graph, &protected);
+
+        let ids: HashSet<i64> = simplified.node_weights().map(|n| n.id).collect();
+        assert!(ids.contains(&2));
+        assert_eq!(simplified.edge_count(), 2);
+        let way_ids: Vec<Vec<i64>> = simplified
+            .edge_weights()
+            .map(|w| w.way_ids.clone())
+            .collect();
+        assert!(way_ids.contains(&vec![1]));
+        assert!(way_ids.contains(&vec![1, 2]));
+    }
+
     #[test]
     fn path_aggregation_uses_traversed_edge() {
         let mut graph = DiGraph::new();
@@ -503,7 +665,7 @@ mod tests {
         graph.add_edge(b, c, make_way_with_length(2, 20.0, 200.0));
         graph.add_edge(c, d, make_way_with_length(3, 30.0, 300.0));
 
-        let simplified = simplify_graph(&graph);
+        let simplified = simplify_graph(&graph, &HashSet::new());
 
         assert_eq!(simplified.node_count(), 2);
         assert_eq!(simplified.edge_count(), 1);
@@ -535,7 +697,7 @@ mod tests {
             make_way_with_geometry(3, 30.0, vec![(0.002, 0.0), (0.0025, 0.0002), (0.003, 0.0)]),
         );
 
-        let simplified = simplify_graph(&graph);
+        let simplified = simplify_graph(&graph, &HashSet::new());
         let edge = simplified.edge_weights().next().unwrap();
 
         assert_eq!(
@@ -563,7 +725,7 @@ mod tests {
         graph.add_edge(center, east, make_way(2, 10.0));
         graph.add_edge(center, north, make_way(3, 10.0));
 
-        let simplified = simplify_graph(&graph);
+        let simplified = simplify_graph(&graph, &HashSet::new());
 
         assert_eq!(simplified.node_count(), 4);
         assert_eq!(simplified.edge_count(), 3);
@@ -578,7 +740,7 @@ mod tests {
         graph.add_edge(a, b, make_way(1, 10.0));
         graph.add_edge(b, c, make_way(2, 10.0));
 
-        let simplified = simplify_graph(&graph);
+        let simplified = simplify_graph(&graph, &HashSet::new());
         let edge = simplified.edge_references().next().unwrap();
         let source = &simplified[edge.source()];
         let target = &simplified[edge.target()];
@@ -587,6 +749,32 @@ mod tests {
         assert!(source.lat < target.lat);
     }
 
+    #[test]
+    fn ferry_terminals_split_chains() {
+        let mut graph = DiGraph::new();
+        let a = graph.add_node(make_node(1, 0.0, 0.0));
+        let b = graph.add_node(make_node(2, 0.001, 0.0));
+        let c = graph.add_node(make_node(3, 0.002, 0.0));
+        let d = graph.add_node(make_node(4, 0.003, 0.0));
+        let ferry = || XmlWay {
+            tags: Arc::from([make_tag("route", "ferry")]),
+            ..make_way(2, 10.0)
+        };
+        graph.add_edge(a, b, make_way(1, 10.0));
+        graph.add_edge(b, c, ferry());
+        graph.add_edge(c, d, ferry());
+
+        let simplified = simplify_graph(&graph, &HashSet::new());
+
+        assert_eq!(simplified.node_count(), 3);
+        let ferries: Vec<f64> = simplified
+            .edge_weights()
+            .filter(|way| way.is_ferry())
+            .map(|way| way.drive_travel_time)
+            .collect();
+        assert_eq!(ferries, vec![20.0]);
+    }
+
     #[test]
     fn simplification_does_not_connect_near_crossing_roads() {
         let mut graph = DiGraph::new();
@@ -597,7 +785,7 @@ mod tests {
         graph.add_edge(west, east, make_way(1, 10.0));
         graph.add_edge(south, north, make_way(2, 10.0));
 
-        let simplified = simplify_graph(&graph);
+        let simplified = simplify_graph(&graph, &HashSet::new());
 
         assert_eq!(simplified.node_count(), 4);
         assert_eq!(simplified.edge_count(), 2);
@@ -0,0 +1,260 @@
+//! Snapping restricted to edges a query can actually use.
+//!
+//! [`SpatialGraph::snap_point`] takes the closest edge of any kind, so a walk
+//! query on an `All` graph can land on a motorway slip road and a drive query
+//! on a service road island. A [`SnapFilter`] narrows the candidate edges by
+//! highway class, network type, component size or an arbitrary predicate, and
+//! [`SpatialGraph::snap_candidates`] returns several candidates so callers can
+//! retry with the next one when a query fails.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+
+use petgraph::graph::EdgeIndex;
+
+use crate::components::Connectivity;
+use crate::filters::way_passes_road_filter;
+use crate::graph::{SnapResult, SpatialGraph, XmlWay};
+use crate::overpass::NetworkType;
+
+/// Planar index distances may undershoot true distances by a fraction of a
+/// percent; candidates are searched this much beyond `max_distance_m`.
+const PLANAR_DISTANCE_SLACK: f64 = 1.01;
+
+type EdgePredicate = Arc<dyn Fn(&XmlWay) -> bool + Send + Sync>;
+
+/// Conditions an edge must meet to be snapped to. The default filter
+/// accepts every edge; each `with_*` call adds a condition.
+#[derive(Clone, Default)]
+pub struct SnapFilter {
+    highways: Option<HashSet<String>>,
+    network_type: Option<NetworkType>,
+    min_component: Option<(usize, NetworkType, Connectivity)>,
+    predicate: Option<EdgePredicate>,
+}
+
+impl fmt::Debug for SnapFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SnapFilter")
+            .field("highways", &self.highways)
+            .field("network_type", &self.network_type)
+            .field("min_component", &self.min_component)
+            .field("predicate", &self.predicate.is_some())
+            .finish()
+    }
+}
+
+impl SnapFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only snap to edges whose `highway` tag is one of `classes`.
+    pub fn with_highways<I, S>(mut self, classes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.highways = Some(classes.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Only snap to edges that a graph built for `network_type` would
+    /// contain, e.g. no motorways for [`NetworkType::Walk`].
+    pub fn with_network_type(mut self, network_type: NetworkType) -> Self {
+        self.network_type = Some(network_type);
+        self
+    }
+
+    /// Only snap to edges whose ends both lie in a `connectivity` component
+    /// for `network_type` of at least `nodes` nodes, skipping islands that
+    /// lead nowhere.
+    pub fn with_min_component_size(
+        mut self,
+        nodes: usize,
+        network_type: NetworkType,
+        connectivity: Connectivity,
+    ) -> Self {
+        self.min_component = Some((nodes, network_type, connectivity));
+        self
+    }
+
+    /// Only snap to edges for which `predicate` returns `true`.
+    pub fn with_edge_filter<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&XmlWay) -> bool + Send + Sync + 'static,
+    {
+        self.predicate = Some(Arc::new(predicate));
+        self
+    }
+
+    /// Whether `edge` of `sg` meets every condition.
+    pub fn accepts(&self, sg: &SpatialGraph, edge: EdgeIndex) -> bool {
+        let way = &sg.graph[edge];
+        if let Some(highways) = &self.highways {
+            let highway = way.tags.iter().find(|tag| tag.key == "highway");
+            if !highway.is_some_and(|tag| highways.contains(tag.value.as_str())) {
+                return false;
+            }
+        }
+        if let Some(network_type) = self.network_type {
+            let tags: Vec<(String, String)> = way
+                .tags
+                .iter()
+                .map(|tag| (tag.key.to_string(), tag.value.to_string()))
+                .collect();
+            if !way_passes_road_filter(&tags, network_type) {
+                return false;
+            }
+        }
+        if let Some((nodes, network_type, connectivity)) = self.min_component {
+            let (source, target) = sg.graph.edge_endpoints(edge).unwrap();
+            if sg.component_size(source, network_type, connectivity) < nodes
+                || sg.component_size(target, network_type, connectivity) < nodes
+            {
+                return false;
+            }
+        }
+        self.predicate
+            .as_ref()
+            .is_none_or(|predicate| predicate(way))
+    }
+}
+
+impl SpatialGraph {
+    /// Like [`SpatialGraph::snap_point_within`], but only onto edges that
+    /// pass `filter`. Returns `None` when no such edge is close enough.
+    pub fn snap_point_filtered(
+        &self,
+        lat: f64,
+        lon: f64,
+        max_distance_m: Option<f64>,
+        filter: &SnapFilter,
+    ) -> Option<SnapResult> {
+        self.snap_candidates(lat, lon, 1, max_distance_m, filter)
+            .into_iter()
+            .next()
+    }
+
+    /// Up to `k` snaps of `(lat, lon)` onto distinct edges that pass
+    /// `filter`, closest first, each with its `distance_m`. Of two edges
+    /// running opposite ways over the same street only one is listed.
+    ///
+    /// Pass the `snapped_lat`/`snapped_lon` of a candidate to `route`,
+    /// `reachability` and friends to start exactly there. Without
+    /// `max_distance_m`, a filter that rejects most edges may scan the whole
+    /// graph.
+    pub fn snap_candidates(
+        &self,
+        lat: f64,
+        lon: f64,
+        k: usize,
+        max_distance_m: Option<f64>,
+        filter: &SnapFilter,
+    ) -> Vec<SnapResult> {
+        let mut candidates = Vec::new();
+        if k == 0 {
+            return candidates;
+        }
+        let mut seen = HashSet::new();
+        for (snap, planar_m) in self.edge_snaps(lat, lon) {
+            if max_distance_m.is_some_and(|max| planar_m > max * PLANAR_DISTANCE_SLACK) {
+                break;
+            }
+            let edge = snap.edge_index.unwrap();
+            if !seen.insert(edge)
+                || max_distance_m.is_some_and(|max| snap.distance_m > max)
+                || !filter.accepts(self, edge)
+            {
+                continue;
+            }
+            candidates.push(snap);
+            if candidates.len() == k {
+                break;
+            }
+        }
+        candidates.sort_by(|a, b| a.distance_m.total_cmp(&b.distance_m));
+        candidates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A residential street along 48.000 with a service-road island just
+    // north of it and a motorway just south.
+    const XML: &str = r#"<osm>
+          <node id="1" lat="48.0000" lon="11.000" />
+          <node id="2" lat="48.0000" lon="11.002" />
+          <node id="3" lat="48.0000" lon="11.004" />
+          <node id="4" lat="48.0003" lon="11.000" />
+          <node id="5" lat="48.0003" lon="11.004" />
+          <node id="6" lat="47.9998" lon="11.000" />
+          <node id="7" lat="47.9998" lon="11.004" />
+          <way id="10"><nd ref="1" /><nd ref="2" /><nd ref="3" />
+            <tag k="highway" v="residential" /></way>
+          <way id="11"><nd ref="4" /><nd ref="5" /><tag k="highway" v="service" /></way>
+          <way id="12"><nd ref="6" /><nd ref="7" /><tag k="highway" v="motorway" /></way>
+        </osm>"#;
+
+    fn graph() -> SpatialGraph {
+        SpatialGraph::from_osm(XML, NetworkType::All, Some(true), None).unwrap()
+    }
+
+    fn edge_ids(snaps: &[SnapResult]) -> Vec<(i64, i64)> {
+        snaps
+            .iter()
+            .map(|snap| {
+                let (from, to) = snap.edge_node_ids.unwrap();
+                (from.min(to), from.max(to))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn candidates_are_distinct_edges_closest_first() {
+        let sg = graph();
+
+        let candidates = sg.snap_candidates(47.9999, 11.001, 5, None, &SnapFilter::new());
+
+        assert_eq!(edge_ids(&candidates), vec![(6, 7), (1, 2), (4, 5), (2, 3)]);
+        assert!(candidates
+            .windows(2)
+            .all(|pair| pair[0].distance_m <= pair[1].distance_m));
+        let near = sg.snap_candidates(47.9999, 11.001, 5, Some(20.0), &SnapFilter::new());
+        assert_eq!(edge_ids(&near), vec![(6, 7), (1, 2)]);
+    }
+
+    #[test]
+    fn filters_skip_unsuitable_edges() {
+        let sg = graph();
+        let snap = |filter: &SnapFilter| {
+            let snap = sg
+                .snap_point_filtered(47.9999, 11.001, None, filter)
+                .unwrap();
+            edge_ids(&[snap])[0]
+        };
+
+        assert_eq!(snap(&SnapFilter::new()), (6, 7));
+        assert_eq!(
+            snap(&SnapFilter::new().with_network_type(NetworkType::Walk)),
+            (1, 2)
+        );
+        assert_eq!(snap(&SnapFilter::new().with_highways(["service"])), (4, 5));
+        assert_eq!(
+            snap(&SnapFilter::new().with_edge_filter(|way| way.speed_kph < 60.0)),
+            (1, 2)
+        );
+        let big =
+            SnapFilter::new().with_min_component_size(3, NetworkType::All, Connectivity::Weak);
+        assert_eq!(
+            edge_ids(&sg.snap_candidates(48.0003, 11.001, 5, None, &big)),
+            vec![(1, 2), (2, 3)]
+        );
+        assert!(sg
+            .snap_point_filtered(47.9999, 11.001, Some(5.0), &big)
+            .is_none());
+    }
+}
@@ -0,0 +1,490 @@
+//! Speed assumptions used to turn edge lengths into travel times.
+//!
+//! A [`SpeedProfile`] is applied once, at graph construction, to fill the
+//! `speed_kph` and `walk/bike/drive_travel_time` fields of every edge. The
+//! default profile reproduces the crate's historical built-in speeds.
+//!
+//! [`parse_maxspeed`] turns OSM `maxspeed`-style values into km/h, including
+//! implicit limits such as `DE:urban` or `RU:living_street`, which are looked
+//! up in a per-country table of statutory defaults.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::traffic::HourlySpeeds;
+
+/// Per-highway drive speeds, walk and bike base speeds, and maxspeed handling.
+///
+/// Drive speed for a way is its tagged `maxspeed` (parsed with
+/// [`parse_maxspeed`]) multiplied by `maxspeed_factor`, or the `highway_speeds_kph` entry for its `highway`
+/// class, or `fallback_speed_kph`. The result is then capped at
+/// `max_speed_kph` when set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpeedProfile {
+    /// Drive speed (km/h) by `highway` tag value, used when a way has no
+    /// usable `maxspeed`.
+    pub highway_speeds_kph: HashMap<String, f64>,
+    /// Drive speed (km/h) for highway classes missing from
+    /// `highway_speeds_kph`.
+    pub fallback_speed_kph: f64,
+    /// Walking speed (km/h) on every edge.
+    pub walk_speed_kph: f64,
+    /// Cycling speed (km/h) on every edge.
+    pub bike_speed_kph: f64,
+    /// Multiplier applied to tagged `maxspeed` values, e.g. `0.9` when
+    /// vehicles typically travel below the posted limit.
+    pub maxspeed_factor: f64,
+    /// Upper bound (km/h) on every drive speed, e.g. for speed-limited
+    /// vehicles. `None` leaves speeds uncapped.
+    pub max_speed_kph: Option<f64>,
+    /// Walking speed (km/h) for pushing a bike against a oneway. When set,
+    /// bike graphs get a reverse edge at this speed for every way cyclists
+    /// may not ride against; `None` leaves such ways one-directional.
+    pub bike_push_speed_kph: Option<f64>,
+    /// Cycling speed multipliers keyed by `key=value` for the `surface`,
+    /// `smoothness`, `tracktype` and `mtb:scale` tags. A way gets the lowest
+    /// factor among its tags, or 1.0 when none is listed.
+    pub bike_surface_factors: HashMap<String, f64>,
+    /// Ways whose surface factor is below this cannot be cycled, e.g. `0.8`
+    /// to keep road bikes off gravel and cobbles. `None` allows every
+    /// surface.
+    pub bike_min_surface_factor: Option<f64>,
+    /// Speed (km/h) of `route=ferry` crossings without a usable `duration`
+    /// tag, for every mode.
+    pub ferry_speed_kph: f64,
+    /// Seconds added to each ferry crossing for boarding and waiting for
+    /// departure.
+    pub ferry_boarding_s: f64,
+    /// Seconds lost by vehicles and cyclists passing a
+    /// `highway=traffic_signals` node, and by walkers at a
+    /// `crossing=traffic_signals` node.
+    pub traffic_signal_delay_s: f64,
+    /// Seconds lost by vehicles and cyclists passing a `highway=stop` node.
+    pub stop_delay_s: f64,
+    /// Seconds lost by walkers passing an unsignalised `crossing=*` or
+    /// `highway=crossing` node.
+    pub crossing_delay_s: f64,
+    /// Drive speeds by hour of the week per way and direction, used instead
+    /// of the static drive speed by queries with a departure time. Capped at
+    /// `max_speed_kph`.
+    pub hourly_speeds: Option<Arc<HourlySpeeds>>,
+}
+
+impl Default for SpeedProfile {
+    fn default() -> Self {
+        let highway_speeds_kph = [
+            ("motorway", 110.0),
+            ("motorway_link", 60.0),
+            ("trunk", 90.0),
+            ("trunk_link", 45.0),
+            ("primary", 65.0),
+            ("primary_link", 45.0),
+            ("secondary", 55.0),
+            ("secondary_link", 40.0),
+            ("tertiary", 45.0),
+            ("tertiary_link", 35.0),
+            ("unclassified", 45.0),
+            ("residential", 30.0),
+            ("living_street", 10.0),
+            ("service", 20.0),
+            ("track", 20.0),
+            ("road", 50.0),
+        ]
+        .into_iter()
+        .map(|(highway, speed)| (highway.to_string(), speed))
+        .collect();
+
+        let bike_surface_factors = [
+            ("surface=paved", 1.0),
+            ("surface=asphalt", 1.0),
+            ("surface=concrete", 1.0),
+            ("surface=paving_stones", 0.9),
+            ("surface=wood", 0.9),
+            ("surface=compacted", 0.85),
+            ("surface=fine_gravel", 0.8),
+            ("surface=sett", 0.75),
+            ("surface=unhewn_cobblestone", 0.6),
+            ("surface=cobblestone", 0.6),
+            ("surface=unpaved", 0.7),
+            ("surface=gravel", 0.7),
+            ("surface=pebblestone", 0.6),
+            ("surface=ground", 0.6),
+            ("surface=dirt", 0.6),
+            ("surface=earth", 0.6),
+            ("surface=grass", 0.5),
+            ("surface=mud", 0.4),
+            ("surface=sand", 0.4),
+            ("smoothness=excellent", 1.0),
+            ("smoothness=good", 1.0),
+            ("smoothness=intermediate", 0.9),
+            ("smoothness=bad", 0.7),
+            ("smoothness=very_bad", 0.5),
+            ("smoothness=horrible", 0.3),
+            ("smoothness=very_horrible", 0.2),
+            ("smoothness=impassable", 0.1),
+            ("tracktype=grade1", 1.0),
+            ("tracktype=grade2", 0.85),
+            ("tracktype=grade3", 0.7),
+            ("tracktype=grade4", 0.55),
+            ("tracktype=grade5", 0.4),
+            ("mtb:scale=0", 0.8),
+            ("mtb:scale=1", 0.6),
+            ("mtb:scale=2", 0.4),
+            ("mtb:scale=3", 0.25),
+            ("mtb:scale=4", 0.15),
+            ("mtb:scale=5", 0.1),
+            ("mtb:scale=6", 0.1),
+        ]
+        .into_iter()
+        .map(|(tag, factor)| (tag.to_string(), factor))
+        .collect();
+
+        SpeedProfile {
+            highway_speeds_kph,
+            fallback_speed_kph: 50.0,
+            walk_speed_kph: 5.0,
+            bike_speed_kph: 15.0,
+            maxspeed_factor: 1.0,
+            max_speed_kph: None,
+            bike_push_speed_kph: None,
+            bike_surface_factors,
+            bike_min_surface_factor: None,
+            ferry_speed_kph: 20.0,
+            ferry_boarding_s: 0.0,
+            traffic_signal_delay_s: 15.0,
+            stop_delay_s: 5.0,
+            crossing_delay_s: 5.0,
+            hourly_speeds: None,
+        }
+    }
+}
+
+impl SpeedProfile {
+    /// Default speeds with cycling restricted to surfaces suitable for road
+    /// bikes (surface factor of at least 0.8).
+    pub fn road_bike() -> Self {
+        SpeedProfile {
+            bike_min_surface_factor: Some(0.8),
+            ..SpeedProfile::default()
+        }
+    }
+
+    /// Drive speed (km/h) for the `highway` class, falling back to
+    /// `fallback_speed_kph` for unknown or missing classes.
+    pub fn highway_speed_kph(&self, highway: Option<&str>) -> f64 {
+        highway
+            .and_then(|highway| self.highway_speeds_kph.get(highway))
+            .copied()
+            .unwrap_or(self.fallback_speed_kph)
+    }
+
+    /// Drive speed (km/h) for a way with the given parsed `maxspeed` and
+    /// `highway` class.
+    pub fn drive_speed_kph(&self, maxspeed_kph: Option<f64>, highway: Option<&str>) -> f64 {
+        let speed = match maxspeed_kph {
+            Some(maxspeed) => maxspeed * self.maxspeed_factor,
+            None => self.highway_speed_kph(highway),
+        };
+        match self.max_speed_kph {
+            Some(cap) => speed.min(cap),
+            None => speed,
+        }
+    }
+
+    /// Cycling speed multiplier for a way with the given `(key, value)` tags:
+    /// the lowest matching `bike_surface_factors` entry, or 1.0.
+    pub fn bike_surface_factor<'a>(
+        &self,
+        tags: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> f64 {
+        tags.into_iter()
+            .filter(|(key, _)| SURFACE_KEYS.contains(key))
+            .filter_map(|(key, value)| {
+                self.bike_surface_factors
+                    .get(&format!("{key}={value}"))
+                    .copied()
+            })
+            .reduce(f64::min)
+            .unwrap_or(1.0)
+    }
+
+    /// Cycling speed (km/h) on a way with the given tags, or `None` when the
+    /// surface is below `bike_min_surface_factor`.
+    pub fn bike_way_speed_kph<'a>(
+        &self,
+        tags: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Option<f64> {
+        let factor = self.bike_surface_factor(tags);
+        match self.bike_min_surface_factor {
+            Some(min_factor) if factor < min_factor => None,
+            _ => Some(self.bike_speed_kph * factor),
+        }
+    }
+
+    /// Seconds to cross `length_m` of a ferry route with the given `duration`
+    /// tag: the tagged duration, or the length at `ferry_speed_kph`, plus
+    /// `ferry_boarding_s`.
+    pub fn ferry_time_s(&self, duration: Option<&str>, length_m: f64) -> f64 {
+        let crossing_s = duration
+            .and_then(parse_duration_s)
+            .unwrap_or_else(|| length_m / (self.ferry_speed_kph / 3.6));
+        crossing_s + self.ferry_boarding_s
+    }
+}
+
+/// Parse an OSM `duration` value into seconds.
+///
+/// Accepts `mm`, `hh:mm`, `hh:mm:ss` and ISO 8601 durations such as
+/// `PT1H30M`. Returns `None` for anything else.
+pub fn parse_duration_s(value: &str) -> Option<f64> {
+    let value = value.trim();
+    if let Some(iso) = value
+        .strip_prefix("PT")
+        .or_else(|| value.strip_prefix("pt"))
+    {
+        let mut seconds = 0.0;
+        let mut number = String::new();
+        for c in iso.chars() {
+            match c.to_ascii_uppercase() {
+                c if c.is_ascii_digit() || c == '.' => number.push(c),
+                unit @ ('H' | 'M' | 'S') => {
+                    let amount: f64 = std::mem::take(&mut number).parse().ok()?;
+                    seconds += amount
+                        * match unit {
+                            'H' => 3600.0,
+                            'M' => 60.0,
+                            _ => 1.0,
+                        };
+                }
+                _ => return None,
+            }
+        }
+        return (number.is_empty() && !iso.is_empty()).then_some(seconds);
+    }
+
+    let parts: Vec<f64> = value
+        .split(':')
+        .map(|part| part.trim().parse::<f64>().ok())
+        .collect::<Option<_>>()?;
+    if parts.iter().any(|&part| part < 0.0) {
+        return None;
+    }
+    match parts[..] {
+        [minutes] => Some(minutes * 60.0),
+        [hours, minutes] => Some(hours * 3600.0 + minutes * 60.0),
+        [hours, minutes, seconds] => Some(hours * 3600.0 + minutes * 60.0 + seconds),
+        _ => None,
+    }
+}
+
+const SURFACE_KEYS: &[&str] = &["surface", "smoothness", "tracktype", "mtb:scale"];
+const MPH_TO_KPH: f64 = 1.60934;
+const KNOTS_TO_KPH: f64 = 1.852;
+
+/// Statutory default limits (km/h) by lowercase ISO country code and zone.
+/// Entries for `""` apply to countries without a more specific row.
+const IMPLICIT_MAXSPEEDS: &[(&str, &str, f64)] = &[
+    ("", "urban", 50.0),
+    ("", "rural", 90.0),
+    ("", "trunk", 100.0),
+    ("", "motorway", 120.0),
+    ("", "living_street", 7.0),
+    ("", "walk", 7.0),
+    ("", "bicycle_road", 30.0),
+    // No posted limit: use the common advisory motorway speed.
+    ("", "none", 130.0),
+    ("at", "rural", 100.0),
+    ("at", "motorway", 130.0),
+    ("be", "rural", 70.0),
+    ("ch", "rural", 80.0),
+    ("cz", "trunk", 110.0),
+    ("cz", "motorway", 130.0),
+    ("de", "rural", 100.0),
+    ("de", "motorway", 130.0),
+    ("dk", "rural", 80.0),
+    ("dk", "motorway", 130.0),
+    ("fi", "rural", 80.0),
+    ("fr", "rural", 80.0),
+    ("fr", "trunk", 110.0),
+    ("fr", "motorway", 130.0),
+    ("gb", "urban", 30.0 * MPH_TO_KPH),
+    ("gb", "rural", 60.0 * MPH_TO_KPH),
+    ("gb", "nsl_single", 60.0 * MPH_TO_KPH),
+    ("gb", "nsl_dual", 70.0 * MPH_TO_KPH),
+    ("gb", "motorway", 70.0 * MPH_TO_KPH),
+    ("hu", "trunk", 110.0),
+    ("hu", "motorway", 130.0),
+    ("it", "trunk", 110.0),
+    ("it", "motorway", 130.0),
+    ("nl", "rural", 80.0),
+    ("nl", "motorway", 100.0),
+    ("no", "rural", 80.0),
+    ("no", "motorway", 110.0),
+    ("pl", "trunk", 120.0),
+    ("pl", "motorway", 140.0),
+    ("ru", "urban", 60.0),
+    ("ru", "motorway", 110.0),
+    ("ru", "living_street", 20.0),
+    ("se", "rural", 70.0),
+    ("se", "motorway", 110.0),
+    ("ua", "trunk", 110.0),
+    ("ua", "motorway", 130.0),
+    ("ua", "living_street", 20.0),
+];
+
+/// Default limit (km/h) for an implicit `zone` in `country` (ISO code, any
+/// case), falling back to the generic table when the country has no entry.
+///
+/// Besides named zones (`urban`, `rural`, `motorway`, `living_street`, ...)
+/// numeric zones such as `zone30` or `zone:30` are accepted; they are read as
+/// mph in `GB` and `US`.
+pub fn implicit_maxspeed_kph(country: &str, zone: &str) -> Option<f64> {
+    let country = country.trim().to_ascii_lowercase();
+    let zone = zone.trim().to_ascii_lowercase();
+    let lookup = |country: &str| {
+        IMPLICIT_MAXSPEEDS
+            .iter()
+            .find(|(c, z, _)| *c == country && *z == zone)
+            .map(|&(_, _, speed)| speed)
+    };
+    if let Some(speed) = lookup(&country).or_else(|| lookup("")) {
+        return Some(speed);
+    }
+
+    let numeric = zone
+        .strip_prefix("zone")
+        .unwrap_or(&zone)
+        .trim_start_matches([':', ' ']);
+    let speed = parse_numeric_maxspeed(numeric)?;
+    let imperial = matches!(country.as_str(), "gb" | "us");
+    if imperial && !numeric.contains("mph") {
+        Some(speed * MPH_TO_KPH)
+    } else {
+        Some(speed)
+    }
+}
+
+/// Parse an OSM `maxspeed`-style value into km/h.
+///
+/// Handles plain numbers (`50`), units (`30 mph`, `10 knots`), implicit
+/// country zones (`DE:urban`, `FR:rural`, `DE:zone30`) and the special values
+/// `walk`, `living_street` and `none`. Returns `None` for values that carry
+/// no speed, such as `signals` or `variable`.
+pub fn parse_maxspeed(value: &str) -> Option<f64> {
+    let value = value.trim().to_ascii_lowercase();
+    if let Some((country, zone)) = value.split_once(':') {
+        if country.len() == 2 && country.chars().all(|c| c.is_ascii_alphabetic()) {
+            return implicit_maxspeed_kph(country, zone);
+        }
+    }
+    match value.as_str() {
+        "walk" | "living_street" | "none" => implicit_maxspeed_kph("", &value),
+        _ => parse_numeric_maxspeed(&value),
+    }
+}
+
+fn parse_numeric_maxspeed(value: &str) -> Option<f64> {
+    let numeric_prefix: String = value
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+    let speed = numeric_prefix.parse::<f64>().ok()?;
+    if speed <= 0.0 {
+        return None;
+    }
+
+    if value.contains("mph") {
+        Some(speed * MPH_TO_KPH)
+    } else if value.contains("knots") {
+        Some(speed * KNOTS_TO_KPH)
+    } else {
+        Some(speed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_profile_matches_builtin_speeds() {
+        let profile = SpeedProfile::default();
+
+        assert_eq!(profile.highway_speed_kph(Some("motorway")), 110.0);
+        assert_eq!(profile.highway_speed_kph(Some("residential")), 30.0);
+        assert_eq!(profile.highway_speed_kph(Some("footway")), 50.0);
+        assert_eq!(profile.highway_speed_kph(None), 50.0);
+        assert_eq!(profile.drive_speed_kph(Some(70.0), Some("primary")), 70.0);
+    }
+
+    #[test]
+    fn factor_applies_to_maxspeed_and_cap_to_everything() {
+        let profile = SpeedProfile {
+            maxspeed_factor: 0.5,
+            max_speed_kph: Some(80.0),
+            ..SpeedProfile::default()
+        };
+
+        assert_eq!(profile.drive_speed_kph(Some(100.0), Some("primary")), 50.0);
+        assert_eq!(profile.drive_speed_kph(Some(200.0), None), 80.0);
+        assert_eq!(profile.drive_speed_kph(None, Some("motorway")), 80.0);
+        assert_eq!(profile.drive_speed_kph(None, Some("residential")), 30.0);
+    }
+
+    #[test]
+    fn surface_factor_takes_the_worst_matching_tag() {
+        let mut profile = SpeedProfile::default();
+        assert_eq!(profile.bike_surface_factor([("highway", "track")]), 1.0);
+        assert_eq!(
+            profile.bike_surface_factor([("surface", "asphalt"), ("smoothness", "bad")]),
+            0.7
+        );
+        assert_eq!(profile.bike_surface_factor([("name", "surface=sand")]), 1.0);
+
+        profile
+            .bike_surface_factors
+            .insert("surface=asphalt".into(), 1.2);
+        assert_eq!(
+            profile.bike_way_speed_kph([("surface", "asphalt")]),
+            Some(18.0)
+        );
+        profile.bike_min_surface_factor = Some(0.8);
+        assert_eq!(profile.bike_way_speed_kph([("surface", "sett")]), None);
+    }
+
+    #[test]
+    fn ferry_time_prefers_the_duration_tag() {
+        let mut profile = SpeedProfile::default();
+        assert_eq!(parse_duration_s("45"), Some(2700.0));
+        assert_eq!(parse_duration_s("01:30"), Some(5400.0));
+        assert_eq!(parse_duration_s("0:20:30"), Some(1230.0));
+        assert_eq!(parse_duration_s("PT1H15M"), Some(4500.0));
+        assert_eq!(parse_duration_s("PT"), None);
+        assert_eq!(parse_duration_s("about an hour"), None);
+
+        assert_eq!(profile.ferry_time_s(Some("00:20"), 5_000.0), 1200.0);
+        assert_eq!(profile.ferry_time_s(Some("unknown"), 5_000.0), 900.0);
+        profile.ferry_boarding_s = 600.0;
+        assert_eq!(profile.ferry_time_s(None, 5_000.0), 1500.0);
+    }
+
+    #[test]
+    fn parses_numeric_and_implicit_maxspeeds() {
+        assert_eq!(parse_maxspeed("50"), Some(50.0));
+        assert!((parse_maxspeed("30 mph").unwrap() - 48.2802).abs() < 1e-4);
+        assert!((parse_maxspeed("10 knots").unwrap() - 18.52).abs() < 1e-9);
+        assert_eq!(parse_maxspeed("DE:urban"), Some(50.0));
+        assert_eq!(parse_maxspeed("DE:rural"), Some(100.0));
+        assert_eq!(parse_maxspeed("FR:rural"), Some(80.0));
+        assert_eq!(parse_maxspeed("RU:living_street"), Some(20.0));
+        assert_eq!(parse_maxspeed("DE:zone30"), Some(30.0));
+        assert_eq!(parse_maxspeed("DE:zone:20"), Some(20.0));
+        assert!((parse_maxspeed("GB:nsl_single").unwrap() - 96.5604).abs() < 1e-4);
+        assert!((parse_maxspeed("GB:zone20").unwrap() - 32.1868).abs() < 1e-4);
+        assert_eq!(parse_maxspeed("XX:urban"), Some(50.0));
+        assert_eq!(parse_maxspeed("walk"), Some(7.0));
+        assert_eq!(parse_maxspeed("none"), Some(130.0));
+        assert_eq!(parse_maxspeed("signals"), None);
+        assert_eq!(parse_maxspeed("DE:sign"), None);
+    }
+}
@@ -0,0 +1,298 @@
+//! Compact storage for edge attributes.
+//!
+//! A graph holds two directed edges per segment of every two-way street, so
+//! anything stored per edge is paid for many times over. Tag strings are
+//! interned ([`TagStr`]), every edge cut from one way shares a single tag
+//! record, and shape points of all edges live in one flat coordinate buffer
+//! ([`EdgeGeometry`]). Edges of a single straight segment store no shape
+//! points at all; their geometry is the two endpoint coordinates.
+
+use std::borrow::Borrow;
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Arc;
+
+use petgraph::graph::DiGraph;
+use serde::{Deserialize, Deserializer};
+
+use crate::graph::{XmlNode, XmlTag, XmlWay};
+
+/// Immutable, cheaply cloneable string used for tag keys and values.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TagStr(Arc<str>);
+
+impl TagStr {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for TagStr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for TagStr {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for TagStr {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for TagStr {
+    fn from(value: &str) -> Self {
+        Self(value.into())
+    }
+}
+
+impl From<String> for TagStr {
+    fn from(value: String) -> Self {
+        Self(value.into())
+    }
+}
+
+impl PartialEq<str> for TagStr {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for TagStr {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl fmt::Debug for TagStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Display for TagStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&*self.0, f)
+    }
+}
+
+impl<'de> Deserialize<'de> for TagStr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::from)
+    }
+}
+
+/// Deduplicates tag strings and whole tag records while a graph is built.
+#[derive(Debug, Default)]
+pub struct TagInterner {
+    strings: HashSet<TagStr>,
+    records: HashSet<Arc<[XmlTag]>>,
+}
+
+impl TagInterner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The shared copy of `value`.
+    pub fn string(&mut self, value: &str) -> TagStr {
+        if let Some(existing) = self.strings.get(value) {
+            return existing.clone();
+        }
+        let interned = TagStr::from(value);
+        self.strings.insert(interned.clone());
+        interned
+    }
+
+    /// `tag` with interned key and value.
+    pub fn tag(&mut self, tag: &XmlTag) -> XmlTag {
+        XmlTag {
+            key: self.string(&tag.key),
+            value: self.string(&tag.value),
+        }
+    }
+
+    /// The shared record equal to `tags`. Ways with identical tags, such as
+    /// unnamed residential streets, all point at the same record.
+    pub fn record(&mut self, tags: &[XmlTag]) -> Arc<[XmlTag]> {
+        if let Some(existing) = self.records.get(tags) {
+            return Arc::clone(existing);
+        }
+        let record: Arc<[XmlTag]> = tags.iter().map(|tag| self.tag(tag)).collect();
+        self.records.insert(Arc::clone(&record));
+        record
+    }
+}
+
+/// Shape points of one edge as a range of a coordinate buffer shared by
+/// many edges. Dereferences to the `(lat, lon)` points.
+#[derive(Clone, Default)]
+pub struct EdgeGeometry {
+    buffer: Option<Arc<[(f64, f64)]>>,
+    start: u32,
+    len: u32,
+}
+
+impl EdgeGeometry {
+    fn slice(buffer: &Arc<[(f64, f64)]>, start: usize, len: usize) -> Self {
+        Self {
+            buffer: (len > 0).then(|| Arc::clone(buffer)),
+            start: start as u32,
+            len: len as u32,
+        }
+    }
+
+    pub fn as_slice(&self) -> &[(f64, f64)] {
+        match &self.buffer {
+            Some(buffer) => &buffer[self.start as usize..(self.start + self.len) as usize],
+            None => &[],
+        }
+    }
+}
+
+impl Deref for EdgeGeometry {
+    type Target = [(f64, f64)];
+
+    fn deref(&self) -> &[(f64, f64)] {
+        self.as_slice()
+    }
+}
+
+impl From<Vec<(f64, f64)>> for EdgeGeometry {
+    fn from(points: Vec<(f64, f64)>) -> Self {
+        let len = points.len();
+        Self::slice(&Arc::from(points), 0, len)
+    }
+}
+
+impl PartialEq for EdgeGeometry {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl PartialEq<Vec<(f64, f64)>> for EdgeGeometry {
+    fn eq(&self, other: &Vec<(f64, f64)>) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl fmt::Debug for EdgeGeometry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.as_slice()).finish()
+    }
+}
+
+impl<'de> Deserialize<'de> for EdgeGeometry {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<(f64, f64)>::deserialize(deserializer).map(Self::from)
+    }
+}
+
+/// Move the shape points of every edge in `graph` into one shared buffer.
+pub(crate) fn pack_edge_geometry(graph: &mut DiGraph<XmlNode, XmlWay>) {
+    let total = graph.edge_weights().map(|way| way.geometry.len()).sum();
+    if total == 0 {
+        return;
+    }
+    let mut points = Vec::with_capacity(total);
+    for way in graph.edge_weights() {
+        points.extend_from_slice(&way.geometry);
+    }
+    let buffer: Arc<[(f64, f64)]> = points.into();
+    let mut start = 0;
+    for way in graph.edge_weights_mut() {
+        let len = way.geometry.len();
+        way.geometry = EdgeGeometry::slice(&buffer, start, len);
+        start += len;
+    }
+}
+
+/// Intern the tag strings of every node and the tag records of every edge.
+pub(crate) fn intern_graph_tags(graph: &mut DiGraph<XmlNode, XmlWay>) {
+    let mut interner = TagInterner::new();
+    for node in graph.node_weights_mut() {
+        for tag in &mut node.tags {
+            *tag = interner.tag(tag);
+        }
+    }
+    for way in graph.edge_weights_mut() {
+        way.tags = interner.record(&way.tags);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(key: &str, value: &str) -> XmlTag {
+        XmlTag {
+            key: key.into(),
+            value: value.into(),
+        }
+    }
+
+    #[test]
+    fn interner_shares_strings_and_records() {
+        let mut interner = TagInterner::new();
+        let a = interner.record(&[tag("highway", "residential")]);
+        let b = interner.record(&[tag("highway", "residential")]);
+        let c = interner.record(&[tag("highway", "residential"), tag("name", "Elm")]);
+        assert!(Arc::ptr_eq(&a, &b));
+        assert!(!Arc::ptr_eq(&a, &c));
+        assert!(Arc::ptr_eq(&a[0].key.0, &c[0].key.0));
+        assert_eq!(c[1].value, "Elm");
+    }
+
+    #[test]
+    fn packed_geometry_keeps_every_edge_shape() {
+        let node = |id| XmlNode {
+            id,
+            lat: 0.0,
+            lon: 0.0,
+            tags: Vec::new(),
+            elevation_m: None,
+        };
+        let way = |geometry: Vec<(f64, f64)>| XmlWay {
+            id: 1,
+            nodes: Vec::new(),
+            tags: Arc::from([]),
+            length: 0.0,
+            speed_kph: 0.0,
+            walk_travel_time: 0.0,
+            bike_travel_time: 0.0,
+            drive_travel_time: 0.0,
+            geometry: geometry.into(),
+            way_ids: Vec::new(),
+            closed_during: Vec::new(),
+            closed_by_default: false,
+            node_delays: Default::default(),
+            bike_push_m: 0.0,
+            elevations_m: Vec::new(),
+            hourly_speeds: None,
+        };
+        let mut graph = DiGraph::new();
+        let a = graph.add_node(node(1));
+        let b = graph.add_node(node(2));
+        graph.add_edge(a, b, way(vec![(0.0, 0.0), (0.5, 0.5), (1.0, 1.0)]));
+        graph.add_edge(b, a, way(Vec::new()));
+        graph.add_edge(a, b, way(vec![(2.0, 2.0), (3.0, 3.0)]));
+
+        pack_edge_geometry(&mut graph);
+
+        let shapes: Vec<&EdgeGeometry> = graph.edge_weights().map(|way| &way.geometry).collect();
+        assert_eq!(*shapes[0], vec![(0.0, 0.0), (0.5, 0.5), (1.0, 1.0)]);
+        assert!(shapes[1].is_empty());
+        assert_eq!(*shapes[2], vec![(2.0, 2.0), (3.0, 3.0)]);
+        let buffer = shapes[0].buffer.as_ref().unwrap();
+        assert!(Arc::ptr_eq(buffer, shapes[2].buffer.as_ref().unwrap()));
+    }
+}
@@ -0,0 +1,572 @@
+//! Stop-order optimisation for delivery tours.
+//!
+//! [`SpatialGraph::optimize_tour`] orders the stops of a tour that starts at
+//! a depot. Travel times between every pair of points come from
+//! [`SpatialGraph::travel_time_matrix`], so one-way streets and turn
+//! restrictions shape the order. A nearest-insertion tour is improved by
+//! 2-opt (reversing a run of stops) and Or-opt (moving a run of up to three
+//! stops elsewhere) until neither finds a better order or
+//! [`TourOptions::max_evaluations`] candidates have been tried, and the
+//! result is routed through [`SpatialGraph::route_via`]. Each candidate is
+//! costed from the first stop it moves rather than from the depot, without
+//! building the candidate order.
+//!
+//! Time windows are soft: arriving early means waiting until the window
+//! opens, and each second of lateness costs as much as
+//! [`LATENESS_WEIGHT`] seconds of travel.
+
+use crate::error::OsmGraphError;
+use crate::graph::SpatialGraph;
+use crate::overpass::NetworkType;
+use crate::routing::Route;
+
+/// Seconds of tour duration one second of lateness is worth when comparing
+/// orders.
+pub const LATENESS_WEIGHT: f64 = 1000.0;
+
+/// Improvement passes over the whole tour before settling for the current
+/// order.
+const MAX_PASSES: usize = 50;
+
+/// Longest run of stops Or-opt moves at once.
+const MAX_OR_OPT_RUN: usize = 3;
+
+/// Default for [`TourOptions::max_evaluations`].
+pub const DEFAULT_MAX_EVALUATIONS: usize = 1_000_000;
+
+/// When a stop may be served, in seconds after leaving the depot.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeWindow {
+    pub earliest_s: f64,
+    pub latest_s: f64,
+}
+
+/// Settings for [`SpatialGraph::optimize_tour`].
+#[derive(Debug, Clone)]
+pub struct TourOptions {
+    /// Whether the tour ends back at the depot.
+    pub return_to_depot: bool,
+    /// A window per stop, parallel to the stops; empty for no windows.
+    pub time_windows: Vec<Option<TimeWindow>>,
+    /// Most candidate orders 2-opt and Or-opt may try before the current
+    /// order is kept. Each try costs time linear in the number of stops.
+    pub max_evaluations: usize,
+}
+
+impl Default for TourOptions {
+    fn default() -> Self {
+        Self {
+            return_to_depot: true,
+            time_windows: Vec::new(),
+            max_evaluations: DEFAULT_MAX_EVALUATIONS,
+        }
+    }
+}
+
+/// A visiting order and the route that follows it.
+#[derive(Debug, Clone)]
+pub struct Tour {
+    /// Indices into the stops, in visiting order.
+    pub order: Vec<usize>,
+    /// Seconds after leaving the depot at which each stop in `order` is
+    /// reached, before any wait for its time window.
+    pub arrival_times_s: Vec<f64>,
+    /// Seconds from leaving the depot to the end of the tour, including
+    /// waits for time windows.
+    pub duration_s: f64,
+    /// Total seconds by which stops are reached after their windows close.
+    pub lateness_s: f64,
+    /// The route from the depot through the stops in order, with one leg per
+    /// hop (and a last leg back to the depot when the tour returns).
+    pub route: Route,
+}
+
+/// Travel times between the depot (point 0) and the stops (points `1..`).
+struct TourProblem<'a> {
+    durations_s: Vec<Vec<f64>>,
+    windows: &'a [Option<TimeWindow>],
+    return_to_depot: bool,
+    max_evaluations: usize,
+}
+
+/// Seconds spent and lateness collected after visiting a prefix of an order.
+#[derive(Debug, Clone, Copy, Default)]
+struct Progress {
+    time_s: f64,
+    lateness_s: f64,
+}
+
+/// Duration, lateness and arrival times of visiting points in `order`.
+struct Schedule {
+    duration_s: f64,
+    lateness_s: f64,
+    arrival_times_s: Vec<f64>,
+}
+
+impl TourProblem<'_> {
+    /// Travel from `previous` to `point` and wait for its window, returning
+    /// the arrival time and the progress once the stop is served.
+    fn visit(&self, progress: Progress, previous: usize, point: usize) -> (f64, Progress) {
+        let arrival_s = progress.time_s + self.durations_s[previous][point];
+        let mut next = Progress {
+            time_s: arrival_s,
+            lateness_s: progress.lateness_s,
+        };
+        if let Some(window) = self.windows.get(point - 1).copied().flatten() {
+            next.time_s = arrival_s.max(window.earliest_s);
+            next.lateness_s += (next.time_s - window.latest_s).max(0.0);
+        }
+        (arrival_s, next)
+    }
+
+    /// Tour duration once `last` is served with `progress`.
+    fn duration(&self, progress: Progress, last: usize) -> f64 {
+        if self.return_to_depot {
+            progress.time_s + self.durations_s[last][0]
+        } else {
+            progress.time_s
+        }
+    }
+
+    fn schedule(&self, order: &[usize]) -> Schedule {
+        let mut progress = Progress::default();
+        let mut arrival_times_s = Vec::with_capacity(order.len());
+        let mut previous = 0;
+        for &point in order {
+            let (arrival_s, next) = self.visit(progress, previous, point);
+            arrival_times_s.push(arrival_s);
+            progress = next;
+            previous = point;
+        }
+        Schedule {
+            duration_s: self.duration(progress, previous),
+            lateness_s: progress.lateness_s,
+            arrival_times_s,
+        }
+    }
+
+    /// What the heuristics minimise.
+    fn cost(&self, order: &[usize]) -> f64 {
+        let schedule = self.schedule(order);
+        schedule.duration_s + LATENESS_WEIGHT * schedule.lateness_s
+    }
+
+    /// Fill `prefixes` with the progress after each prefix of `order`, so
+    /// `prefixes[k]` holds it once `order[..k]` is served.
+    fn prefixes(&self, order: &[usize], prefixes: &mut Vec<Progress>) {
+        prefixes.clear();
+        prefixes.push(Progress::default());
+        let mut previous = 0;
+        for &point in order {
+            let (_, next) = self.visit(*prefixes.last().unwrap(), previous, point);
+            prefixes.push(next);
+            previous = point;
+        }
+    }
+
+    /// Cost of the order that keeps `order[..position]` and continues with
+    /// `rest`, reusing the `prefixes` of `order` instead of rebuilding the
+    /// schedule from the depot.
+    fn cost_from(
+        &self,
+        order: &[usize],
+        prefixes: &[Progress],
+        position: usize,
+        rest: impl IntoIterator<Item = usize>,
+    ) -> f64 {
+        let mut progress = prefixes[position];
+        let mut previous = if position == 0 {
+            0
+        } else {
+            order[position - 1]
+        };
+        for point in rest {
+            progress = self.visit(progress, previous, point).1;
+            previous = point;
+        }
+        self.duration(progress, previous) + LATENESS_WEIGHT * progress.lateness_s
+    }
+
+    /// Insert the stop closest to the tour so far, at its cheapest position,
+    /// until every stop is placed.
+    fn nearest_insertion(&self, stops: usize) -> Vec<usize> {
+        let mut order: Vec<usize> = Vec::with_capacity(stops);
+        let mut distance_to_tour: Vec<f64> = (0..=stops)
+            .map(|point| self.durations_s[0][point].min(self.durations_s[point][0]))
+            .collect();
+        let mut placed = vec![false; stops + 1];
+        placed[0] = true;
+        let mut prefixes = Vec::with_capacity(stops + 1);
+        for _ in 0..stops {
+            let next = (1..=stops)
+                .filter(|&point| !placed[point])
+                .min_by(|&a, &b| distance_to_tour[a].total_cmp(&distance_to_tour[b]))
+                .unwrap();
+            self.prefixes(&order, &mut prefixes);
+            let position = (0..=order.len())
+                .map(|position| {
+                    let rest = std::iter::once(next).chain(order[position..].iter().copied());
+                    (position, self.cost_from(&order, &prefixes, position, rest))
+                })
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .unwrap()
+                .0;
+            order.insert(position, next);
+            placed[next] = true;
+            for (point, distance) in distance_to_tour.iter_mut().enumerate() {
+                let hop = self.durations_s[next][point].min(self.durations_s[point][next]);
+                *distance = distance.min(hop);
+            }
+        }
+        order
+    }
+
+    /// Cost of moving `order[start..start + run]` to `target` in the order
+    /// without it.
+    fn or_opt_cost(
+        &self,
+        order: &[usize],
+        prefixes: &[Progress],
+        start: usize,
+        run: usize,
+        target: usize,
+    ) -> f64 {
+        let moved = &order[start..start + run];
+        if target < start {
+            let rest = moved
+                .iter()
+                .chain(&order[target..start])
+                .chain(&order[start + run..]);
+            self.cost_from(order, prefixes, target, rest.copied())
+        } else {
+            let rest = order[start + run..target + run]
+                .iter()
+                .chain(moved)
+                .chain(&order[target + run..]);
+            self.cost_from(order, prefixes, start, rest.copied())
+        }
+    }
+
+    /// Apply improving 2-opt and Or-opt moves until none is left, or until
+    /// `max_evaluations` candidate orders have been tried.
+    ///
+    /// A move leaves the order before its first changed position alone, so
+    /// each candidate is costed from there on with [`Self::cost_from`].
+    fn improve(&self, order: &mut [usize]) {
+        let mut prefixes = Vec::with_capacity(order.len() + 1);
+        self.prefixes(order, &mut prefixes);
+        let mut best = self.cost(order);
+        let mut evaluations = 0;
+        for _ in 0..MAX_PASSES {
+            let mut improved = false;
+            for i in 0..order.len() {
+                for j in i + 1..order.len() {
+                    if evaluations == self.max_evaluations {
+                        return;
+                    }
+                    evaluations += 1;
+                    let reversed = order[i..=j].iter().rev();
+                    let rest = reversed.chain(&order[j + 1..]).copied();
+                    let cost = self.cost_from(order, &prefixes, i, rest);
+                    if cost < best - 1e-9 {
+                        order[i..=j].reverse();
+                        self.prefixes(order, &mut prefixes);
+                        best = cost;
+                        improved = true;
+                    }
+                }
+            }
+            for run in 1..=MAX_OR_OPT_RUN.min(order.len()) {
+                for start in 0..=order.len() - run {
+                    for target in 0..=order.len() - run {
+                        if target == start {
+                            continue;
+                        }
+                        if evaluations == self.max_evaluations {
+                            return;
+                        }
+                        evaluations += 1;
+                        let cost = self.or_opt_cost(order, &prefixes, start, run, target);
+                        if cost < best - 1e-9 {
+                            if target < start {
+                                order[target..start + run].rotate_right(run);
+                            } else {
+                                order[start..target + run].rotate_left(run);
+                            }
+                            self.prefixes(order, &mut prefixes);
+                            best = cost;
+                            improved = true;
+                        }
+                    }
+                }
+            }
+            if !improved {
+                break;
+            }
+        }
+    }
+}
+
+impl SpatialGraph {
+    /// A good order to visit `stops` from `depot`, all `(lat, lon)`, and the
+    /// route that follows it.
+    ///
+    /// Every point must snap within `max_snap_m`; otherwise the call fails
+    /// with [`OsmGraphError::InvalidInput`] naming the point, as it does
+    /// when `options.time_windows` is neither empty nor one per stop.
+    /// Returns [`OsmGraphError::PathNotFound`] when some stop cannot be
+    /// reached on the network.
+    pub fn optimize_tour(
+        &self,
+        depot: (f64, f64),
+        stops: &[(f64, f64)],
+        network_type: NetworkType,
+        max_snap_m: Option<f64>,
+        options: &TourOptions,
+    ) -> Result<Tour, OsmGraphError> {
+        if stops.is_empty() {
+            return Err(OsmGraphError::InvalidInput(
+                "optimize_tour needs at least one stop".to_string(),
+            ));
+        }
+        if !options.time_windows.is_empty() && options.time_windows.len() != stops.len() {
+            return Err(OsmGraphError::InvalidInput(format!(
+                "{} time windows given for {} stops",
+                options.time_windows.len(),
+                stops.len()
+            )));
+        }
+        let points: Vec<(f64, f64)> = std::iter::once(depot)
+            .chain(stops.iter().copied())
+            .collect();
+        let matrix = self.travel_time_matrix(&points, &points, network_type, max_snap_m)?;
+        if let Some(point) = matrix.origin_snaps.iter().position(Option::is_none) {
+            let name = match point {
+                0 => "the depot".to_string(),
+                stop => format!("stop {}", stop - 1),
+            };
+            return Err(OsmGraphError::InvalidInput(format!(
+                "{name} is not within max_snap_m of the network"
+            )));
+        }
+
+        let problem = TourProblem {
+            durations_s: matrix
+                .durations_s
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|cell| cell.unwrap_or(f64::INFINITY))
+                        .collect()
+                })
+                .collect(),
+            windows: &options.time_windows,
+            return_to_depot: options.return_to_depot,
+            max_evaluations: options.max_evaluations,
+        };
+        let mut order = problem.nearest_insertion(stops.len());
+        problem.improve(&mut order);
+        let schedule = problem.schedule(&order);
+        if !schedule.duration_s.is_finite() {
+            return Err(OsmGraphError::PathNotFound);
+        }
+
+        let mut waypoints: Vec<(f64, f64)> = std::iter::once(depot)
+            .chain(order.iter().map(|&point| points[point]))
+            .collect();
+        if options.return_to_depot {
+            waypoints.push(depot);
+        }
+        let route = self.route_via(&waypoints, network_type, max_snap_m)?;
+        Ok(Tour {
+            order: order.iter().map(|&point| point - 1).collect(),
+            arrival_times_s: schedule.arrival_times_s,
+            duration_s: schedule.duration_s,
+            lateness_s: schedule.lateness_s,
+            route,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problem(durations_s: Vec<Vec<f64>>, windows: &[Option<TimeWindow>]) -> TourProblem<'_> {
+        TourProblem {
+            durations_s,
+            windows,
+            return_to_depot: true,
+            max_evaluations: DEFAULT_MAX_EVALUATIONS,
+        }
+    }
+
+    /// Points on a line at the given positions, one second per unit apart.
+    fn line(positions: &[f64]) -> Vec<Vec<f64>> {
+        positions
+            .iter()
+            .map(|a| positions.iter().map(|b| (a - b).abs()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn heuristics_find_the_sweep_along_a_line() {
+        let problem = problem(line(&[0.0, 5.0, 1.0, 4.0, 2.0, 3.0]), &[]);
+        let mut order = problem.nearest_insertion(5);
+        problem.improve(&mut order);
+
+        assert_eq!(problem.cost(&order), 10.0);
+        assert!(order == [2, 4, 5, 3, 1] || order == [1, 3, 5, 4, 2]);
+    }
+
+    #[test]
+    fn time_windows_reorder_stops_and_make_the_tour_wait() {
+        // The far stop must be served first, which costs a detour.
+        let windows = [
+            Some(TimeWindow {
+                earliest_s: 8.0,
+                latest_s: 12.0,
+            }),
+            Some(TimeWindow {
+                earliest_s: 0.0,
+                latest_s: 5.0,
+            }),
+            Some(TimeWindow {
+                earliest_s: 20.0,
+                latest_s: 30.0,
+            }),
+        ];
+        let problem = problem(line(&[0.0, 1.0, 5.0, 2.0]), &windows);
+        let mut order = problem.nearest_insertion(3);
+        problem.improve(&mut order);
+
+        assert_eq!(order, [2, 1, 3]);
+        let schedule = problem.schedule(&order);
+        assert_eq!(schedule.arrival_times_s, [5.0, 9.0, 10.0]);
+        assert_eq!(schedule.lateness_s, 0.0);
+        // Waits from 10 s to 20 s at the last stop, then drives 2 s home.
+        assert_eq!(schedule.duration_s, 22.0);
+    }
+
+    #[test]
+    fn moves_are_costed_like_the_orders_they_make() {
+        let windows = [
+            None,
+            Some(TimeWindow {
+                earliest_s: 6.0,
+                latest_s: 7.0,
+            }),
+            None,
+            Some(TimeWindow {
+                earliest_s: 0.0,
+                latest_s: 2.0,
+            }),
+            None,
+        ];
+        let problem = problem(line(&[0.0, 5.0, 1.0, 4.0, 2.0, 3.0]), &windows);
+        let order = [3, 1, 5, 2, 4];
+        let mut prefixes = Vec::new();
+        problem.prefixes(&order, &mut prefixes);
+        for i in 0..order.len() {
+            for j in i + 1..order.len() {
+                let mut candidate = order;
+                candidate[i..=j].reverse();
+                let rest = candidate[i..].iter().copied();
+                let cost = problem.cost_from(&order, &prefixes, i, rest);
+                assert_eq!(cost, problem.cost(&candidate));
+            }
+        }
+        for run in 1..=MAX_OR_OPT_RUN {
+            for start in 0..=order.len() - run {
+                for target in (0..=order.len() - run).filter(|&target| target != start) {
+                    let mut candidate = order.to_vec();
+                    let moved: Vec<usize> = candidate.drain(start..start + run).collect();
+                    candidate.splice(target..target, moved);
+                    let cost = problem.or_opt_cost(&order, &prefixes, start, run, target);
+                    assert_eq!(cost, problem.cost(&candidate));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn improvement_stops_after_max_evaluations() {
+        let mut problem = problem(line(&[0.0, 5.0, 1.0, 4.0, 2.0, 3.0]), &[]);
+        problem.max_evaluations = 0;
+        let mut order = vec![1, 2, 3, 4, 5];
+        problem.improve(&mut order);
+        assert_eq!(order, [1, 2, 3, 4, 5]);
+
+        problem.max_evaluations = DEFAULT_MAX_EVALUATIONS;
+        problem.improve(&mut order);
+        assert_eq!(problem.cost(&order), 10.0);
+    }
+
+    // A one-way ring 1 → 2 → 3 → 4 → 1 of residential streets.
+    const XML: &str = r#"<osm>
+          <node id="1" lat="48.000" lon="11.000" />
+          <node id="2" lat="48.000" lon="11.004" />
+          <node id="3" lat="48.003" lon="11.004" />
+          <node id="4" lat="48.003" lon="11.000" />
+          <way id="10"><nd ref="1" /><nd ref="2" /><nd ref="3" /><nd ref="4" /><nd ref="1" />
+            <tag k="highway" v="residential" /><tag k="oneway" v="yes" /></way>
+        </osm>"#;
+
+    #[test]
+    fn tours_follow_one_way_streets_and_return_to_the_depot() {
+        let sg = SpatialGraph::from_osm(XML, NetworkType::Drive, Some(true), None).unwrap();
+        let stops = [(48.003, 11.000), (48.000, 11.004), (48.003, 11.004)];
+        let tour = sg
+            .optimize_tour(
+                (48.0, 11.0),
+                &stops,
+                NetworkType::Drive,
+                Some(50.0),
+                &TourOptions::default(),
+            )
+            .unwrap();
+
+        assert_eq!(tour.order, [1, 2, 0]);
+        assert_eq!(tour.route.legs.len(), 4);
+        assert!((tour.route.duration_s - tour.duration_s).abs() < 1e-6);
+        assert_eq!(*tour.route.coordinates.last().unwrap(), (48.0, 11.0));
+        for (leg, &arrival) in tour.route.legs.iter().zip(&tour.arrival_times_s) {
+            let reached = tour.route.cumulative_times_s[leg.end_index];
+            assert!((reached - arrival).abs() < 1e-6);
+        }
+
+        let one_way = TourOptions {
+            return_to_depot: false,
+            ..TourOptions::default()
+        };
+        let tour = sg
+            .optimize_tour(
+                (48.0, 11.0),
+                &stops,
+                NetworkType::Drive,
+                Some(50.0),
+                &one_way,
+            )
+            .unwrap();
+        assert_eq!(tour.route.legs.len(), 3);
+        assert_eq!(*tour.route.coordinates.last().unwrap(), (48.003, 11.000));
+    }
+
+    #[test]
+    fn unsnappable_stops_are_named() {
+        let sg = SpatialGraph::from_osm(XML, NetworkType::Drive, Some(true), None).unwrap();
+        let error = sg
+            .optimize_tour(
+                (48.0, 11.0),
+                &[(48.003, 11.0), (49.0, 12.0)],
+                NetworkType::Drive,
+                Some(50.0),
+                &TourOptions::default(),
+            )
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid input: stop 1 is not within max_snap_m of the network"
+        );
+    }
+}
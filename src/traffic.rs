@@ -0,0 +1,255 @@
+//! Hourly drive speeds by time of week.
+//!
+//! Static drive times cannot tell rush hour from the middle of the night.
+//! [`HourlySpeeds`] holds measured speeds per OSM way and direction for each
+//! hour of the week, loaded from a local CSV. Set it as
+//! [`SpeedProfile::hourly_speeds`](crate::speed::SpeedProfile::hourly_speeds)
+//! before building a graph and every edge keeps the [`WeekSpeeds`] of its way.
+//! Queries given a [`DepartureTime`] then cost each edge at its speed for the
+//! hour in which the search enters it; queries without one use the static
+//! drive times.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::conditional::{parse_day_selector, DepartureTime};
+use crate::error::OsmGraphError;
+
+pub const HOURS_PER_WEEK: usize = 7 * 24;
+
+/// Drive speed (km/h) for each hour of the week, from Monday 00:00. Hours
+/// without a measurement are `None` and keep the edge's static speed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeekSpeeds {
+    kph: [Option<f32>; HOURS_PER_WEEK],
+}
+
+impl Default for WeekSpeeds {
+    fn default() -> Self {
+        Self {
+            kph: [None; HOURS_PER_WEEK],
+        }
+    }
+}
+
+impl WeekSpeeds {
+    /// Speeds indexed by hour of the week, Monday 00:00 = 0.
+    pub fn from_hours(kph: [Option<f32>; HOURS_PER_WEEK]) -> Self {
+        Self { kph }
+    }
+
+    pub fn hours(&self) -> &[Option<f32>; HOURS_PER_WEEK] {
+        &self.kph
+    }
+
+    /// Speed for the hour containing `time`.
+    pub fn speed_kph_at(&self, time: DepartureTime) -> Option<f64> {
+        let hour = (time.week_seconds() / 3600.0) as usize;
+        self.kph[hour.min(HOURS_PER_WEEK - 1)].map(f64::from)
+    }
+
+    /// These speeds with every value above `max_kph` lowered to it.
+    pub(crate) fn capped(self: &Arc<Self>, max_kph: Option<f64>) -> Arc<Self> {
+        let Some(max_kph) = max_kph else {
+            return Arc::clone(self);
+        };
+        let mut capped = WeekSpeeds::clone(self);
+        for kph in capped.kph.iter_mut().flatten() {
+            *kph = kph.min(max_kph as f32);
+        }
+        Arc::new(capped)
+    }
+}
+
+/// Hourly speeds per OSM way, separately for travel along (`forward`) and
+/// against the way's node order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HourlySpeeds {
+    by_way: HashMap<(i64, bool), Arc<WeekSpeeds>>,
+}
+
+impl HourlySpeeds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the speeds of `way_id` in one direction.
+    pub fn insert(&mut self, way_id: i64, forward: bool, speeds: WeekSpeeds) {
+        self.by_way.insert((way_id, forward), Arc::new(speeds));
+    }
+
+    /// Speeds of `way_id` along (`forward`) or against its node order.
+    pub fn get(&self, way_id: i64, forward: bool) -> Option<&Arc<WeekSpeeds>> {
+        self.by_way.get(&(way_id, forward))
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_way.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_way.is_empty()
+    }
+
+    /// Read speeds from a CSV file; see [`HourlySpeeds::parse_csv`].
+    pub fn from_csv(path: impl AsRef<Path>) -> Result<Self, OsmGraphError> {
+        Self::parse_csv(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parse CSV rows of `way_id,direction,day,hour,speed_kph`.
+    ///
+    /// `direction` is `forward` or `backward` relative to the way's node
+    /// order, or `both`. `day` is an opening-hours weekday or range such as
+    /// `Mo-Fr`, or empty for every day; `hour` runs from 0 to 23. A header
+    /// row, blank lines and `#` comments are skipped. Later rows override
+    /// earlier ones for the same hour.
+    pub fn parse_csv(csv: &str) -> Result<Self, OsmGraphError> {
+        let mut weeks: HashMap<(i64, bool), WeekSpeeds> = HashMap::new();
+        for (number, line) in csv.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with("way_id") {
+                continue;
+            }
+            let invalid = |what: &str| {
+                OsmGraphError::InvalidInput(format!(
+                    "hourly speeds line {}: {what}: {line:?}",
+                    number + 1
+                ))
+            };
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let [way_id, direction, day, hour, speed_kph] = fields[..] else {
+                return Err(invalid("expected way_id,direction,day,hour,speed_kph"));
+            };
+            let way_id: i64 = way_id.parse().map_err(|_| invalid("invalid way id"))?;
+            let directions: &[bool] = match direction.to_ascii_lowercase().as_str() {
+                "forward" => &[true],
+                "backward" => &[false],
+                "both" => &[true, false],
+                _ => return Err(invalid("direction must be forward, backward or both")),
+            };
+            let days = parse_day_selector(day).ok_or_else(|| invalid("invalid day"))?;
+            let hour: usize = hour
+                .parse()
+                .ok()
+                .filter(|&hour| hour < 24)
+                .ok_or_else(|| invalid("hour must be 0 to 23"))?;
+            let speed_kph: f32 = speed_kph
+                .parse()
+                .ok()
+                .filter(|speed: &f32| speed.is_finite() && *speed > 0.0)
+                .ok_or_else(|| invalid("speed must be positive"))?;
+            for &forward in directions {
+                let week = weeks.entry((way_id, forward)).or_default();
+                for day in (0..7).filter(|day| days & (1 << day) != 0) {
+                    week.kph[day * 24 + hour] = Some(speed_kph);
+                }
+            }
+        }
+        Ok(Self {
+            by_way: weeks
+                .into_iter()
+                .map(|(key, week)| (key, Arc::new(week)))
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::SpatialGraph;
+    use crate::overpass::NetworkType;
+    use crate::speed::SpeedProfile;
+
+    fn at(s: &str) -> DepartureTime {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_days_hours_and_directions() {
+        let speeds = HourlySpeeds::parse_csv(
+            "way_id,direction,day,hour,speed_kph\n\
+             # rush hour\n\
+             10,both,Mo-Fr,8,12.5\n\
+             10,forward,,3,60\n\
+             10,forward,Fr,8,20\n",
+        )
+        .unwrap();
+
+        let forward = speeds.get(10, true).unwrap();
+        let backward = speeds.get(10, false).unwrap();
+        assert_eq!(forward.speed_kph_at(at("Tu 08:30")), Some(12.5));
+        assert_eq!(forward.speed_kph_at(at("Fr 08:00")), Some(20.0));
+        assert_eq!(backward.speed_kph_at(at("Fr 08:00")), Some(12.5));
+        assert_eq!(forward.speed_kph_at(at("Su 03:59")), Some(60.0));
+        assert_eq!(backward.speed_kph_at(at("Su 03:59")), None);
+        assert_eq!(forward.speed_kph_at(at("Sa 08:00")), None);
+        assert!(speeds.get(11, true).is_none());
+
+        for bad in [
+            "10,up,Mo,8,20",
+            "10,both,Xy,8,20",
+            "10,both,Mo,24,20",
+            "10,both,Mo,8,0",
+        ] {
+            assert!(HourlySpeeds::parse_csv(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn departures_use_the_speed_of_the_hour_they_reach_an_edge() {
+        // Way 11 leads from node 4 onto way 10, 1.1 km per segment.
+        let xml = r#"<osm>
+              <node id="1" lat="48.00" lon="11.00" />
+              <node id="2" lat="48.01" lon="11.00" />
+              <node id="3" lat="48.02" lon="11.00" />
+              <node id="4" lat="47.99" lon="11.00" />
+              <way id="10"><nd ref="1" /><nd ref="2" /><nd ref="3" />
+                <tag k="highway" v="residential" /></way>
+              <way id="11"><nd ref="4" /><nd ref="1" /><tag k="highway" v="residential" /></way>
+            </osm>"#;
+        let profile = SpeedProfile {
+            hourly_speeds: Some(Arc::new(
+                HourlySpeeds::parse_csv("10,forward,Mo,8,10").unwrap(),
+            )),
+            ..SpeedProfile::default()
+        };
+        let sg = SpatialGraph::from_osm(xml, NetworkType::Drive, None, Some(&profile)).unwrap();
+        // Way 10 with hourly speeds does not merge into way 11.
+        assert_eq!(sg.graph.edge_count(), 4);
+
+        let duration = |departure: &str| {
+            sg.route_at(
+                47.99,
+                11.0,
+                48.02,
+                11.0,
+                NetworkType::Drive,
+                None,
+                at(departure),
+            )
+            .unwrap()
+            .duration_s
+        };
+        let static_s = sg
+            .route(47.99, 11.0, 48.02, 11.0, NetworkType::Drive, None)
+            .unwrap()
+            .duration_s;
+        let way_10_m = 2.0 * 1_111.95;
+        let rush_s = static_s - way_10_m / (30.0 / 3.6) + way_10_m / (10.0 / 3.6);
+        assert!((duration("Mo 03:00") - static_s).abs() < 1e-6);
+        assert!((duration("Mo 08:00") - rush_s).abs() < 1.0);
+        // Way 10 is reached about two minutes after leaving.
+        assert!((duration("Mo 07:58") - rush_s).abs() < 1.0);
+        assert!((duration("Mo 07:50") - static_s).abs() < 1e-6);
+
+        let reachable = |departure: &str| {
+            sg.reachability_at(48.0, 11.0, 600.0, NetworkType::Drive, None, at(departure))
+                .unwrap()
+                .distances
+                .len()
+        };
+        assert!(reachable("Mo 08:00") < reachable("Mo 03:00"));
+    }
+}
@@ -0,0 +1,1272 @@
+//! Turn handling: OSM turn restrictions and angle-based turn penalties.
+//!
+//! Restrictions (`type=restriction` relations) are parsed into
+//! [`TurnRestriction`] values that reference OSM way and node ids, so they
+//! survive graph simplification and subgraph extraction. Each records the
+//! modes it binds, so one graph can serve bike and car queries.
+//! `TurnRestrictions` resolves them against one concrete graph into
+//! edge-level rules per mode.
+//!
+//! [`TurnPenalties`] charge extra seconds for left turns, right turns and
+//! U-turns, classified from the bearings of consecutive edges' geometry.
+//!
+//! Both are consulted on every edge-to-edge transition by the edge-based
+//! search in `search.rs`.
+
+use crate::graph::{directed_edge_geometry, XmlNode, XmlRelation, XmlWay};
+use crate::overpass::NetworkType;
+use crate::search::SearchDirection;
+use crate::utils::calculate_bearing;
+use petgraph::graph::{DiGraph, EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Longest chain of via edges followed when resolving via-way restrictions.
+const MAX_VIA_EDGES: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RestrictionKind {
+    /// `no_*`: the manoeuvre is forbidden.
+    No,
+    /// `only_*`: the manoeuvre is the only one allowed after the from-way.
+    Only,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RestrictionVia {
+    Node(i64),
+    Ways(Vec<i64>),
+}
+
+/// The travel modes a [`TurnRestriction`] binds. Pedestrians are never
+/// bound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RestrictionModes {
+    pub bicycle: bool,
+    pub motor_vehicle: bool,
+}
+
+impl RestrictionModes {
+    /// Binds bikes and motor vehicles alike, as a plain `restriction` tag does.
+    pub const ALL: Self = Self {
+        bicycle: true,
+        motor_vehicle: true,
+    };
+
+    /// Whether the restriction applies to `network_type` queries: bike
+    /// queries follow `bicycle`, every other type except walking follows
+    /// `motor_vehicle`.
+    pub fn applies_to(&self, network_type: NetworkType) -> bool {
+        match network_type {
+            NetworkType::Walk => false,
+            NetworkType::Bike => self.bicycle,
+            NetworkType::Drive
+            | NetworkType::DriveService
+            | NetworkType::All
+            | NetworkType::AllPrivate => self.motor_vehicle,
+        }
+    }
+}
+
+/// One `from → via → to` manoeuvre from a restriction relation, expressed in
+/// OSM ids.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TurnRestriction {
+    pub relation_id: i64,
+    pub kind: RestrictionKind,
+    pub from_way: i64,
+    pub via: RestrictionVia,
+    pub to_way: i64,
+    /// The modes bound by the manoeuvre; queries of other modes ignore it.
+    pub modes: RestrictionModes,
+}
+
+/// Restriction tags consulted for bikes, most specific first.
+const BICYCLE_MODES: &[&str] = &["bicycle", "vehicle"];
+/// Restriction tags consulted for motor vehicles, most specific first.
+const MOTOR_VEHICLE_MODES: &[&str] = &["motorcar", "motor_vehicle", "vehicle"];
+
+impl TurnRestriction {
+    /// Parse a relation into the restrictions that apply to a graph built
+    /// for `network_type`.
+    ///
+    /// Pedestrians are not bound by turn restrictions. Bikes honour
+    /// `restriction:bicycle` and the generic `restriction` tag; motor vehicles
+    /// honour `restriction:motorcar`, `restriction:motor_vehicle`,
+    /// `restriction:vehicle` and `restriction`. Relations whose `except` tag
+    /// names the mode are skipped for it. `All` and `AllPrivate` graphs serve
+    /// every mode and keep the restrictions of both; a relation restricting
+    /// bikes and cars differently yields one restriction per mode.
+    /// `no_entry`/`no_exit` relations with several from or to ways expand
+    /// into one restriction per pair.
+    pub fn from_relation(relation: &XmlRelation, network_type: NetworkType) -> Vec<Self> {
+        let tag = |key: &str| {
+            relation
+                .tags
+                .iter()
+                .find(|tag| tag.key == key)
+                .map(|tag| tag.value.as_str())
+        };
+        if tag("type") != Some("restriction") {
+            return Vec::new();
+        }
+
+        let kind_for = |modes: &[&str]| {
+            if tag("except")
+                .is_some_and(|except| except.split(';').any(|mode| modes.contains(&mode.trim())))
+            {
+                return None;
+            }
+            let value = modes
+                .iter()
+                .find_map(|mode| tag(&format!("restriction:{mode}")))
+                .or_else(|| tag("restriction"))?;
+            if value.starts_with("no_") {
+                Some(RestrictionKind::No)
+            } else if value.starts_with("only_") {
+                Some(RestrictionKind::Only)
+            } else {
+                None
+            }
+        };
+        let bicycle = kind_for(BICYCLE_MODES);
+        let motor_vehicle = kind_for(MOTOR_VEHICLE_MODES);
+        let kinds: Vec<(RestrictionKind, RestrictionModes)> = if bicycle == motor_vehicle {
+            bicycle
+                .map(|kind| (kind, RestrictionModes::ALL))
+                .into_iter()
+                .collect()
+        } else {
+            let only = |bicycle, motor_vehicle| RestrictionModes {
+                bicycle,
+                motor_vehicle,
+            };
+            bicycle
+                .map(|kind| (kind, only(true, false)))
+                .into_iter()
+                .chain(motor_vehicle.map(|kind| (kind, only(false, true))))
+                .collect()
+        };
+        let serves_every_mode = matches!(network_type, NetworkType::All | NetworkType::AllPrivate);
+        let kinds: Vec<(RestrictionKind, RestrictionModes)> = kinds
+            .into_iter()
+            .filter(|(_, modes)| serves_every_mode || modes.applies_to(network_type))
+            .collect();
+        if kinds.is_empty() {
+            return Vec::new();
+        }
+
+        let mut from_ways = Vec::new();
+        let mut to_ways = Vec::new();
+        let mut via_nodes = Vec::new();
+        let mut via_ways = Vec::new();
+        for member in &relation.members {
+            match (member.member_type.as_str(), member.role.as_str()) {
+                ("way", "from") => from_ways.push(member.member_ref),
+                ("way", "to") => to_ways.push(member.member_ref),
+                ("way", "via") => via_ways.push(member.member_ref),
+                ("node", "via") => via_nodes.push(member.member_ref),
+                _ => {}
+            }
+        }
+
+        let via = match (via_nodes.as_slice(), via_ways.is_empty()) {
+            ([node], true) => RestrictionVia::Node(*node),
+            ([], false) => RestrictionVia::Ways(via_ways),
+            _ => return Vec::new(),
+        };
+
+        let mut restrictions = Vec::new();
+        for &(kind, modes) in &kinds {
+            for &from_way in &from_ways {
+                for &to_way in &to_ways {
+                    restrictions.push(TurnRestriction {
+                        relation_id: relation.id,
+                        kind,
+                        from_way,
+                        via: via.clone(),
+                        to_way,
+                        modes,
+                    });
+                }
+            }
+        }
+        restrictions
+    }
+}
+
+/// OSM node ids that graph simplification must keep as distinct endpoints so
+/// that `restrictions` can still be matched afterwards: via nodes, and every
+/// node a via way shares with another member way.
+pub(crate) fn protected_node_ids(
+    restrictions: &[TurnRestriction],
+    ways: &[XmlWay],
+) -> HashSet<i64> {
+    let mut protected = HashSet::new();
+    let mut via_way_ids = HashSet::new();
+    for restriction in restrictions {
+        match &restriction.via {
+            RestrictionVia::Node(node) => {
+                protected.insert(*node);
+            }
+            RestrictionVia::Ways(via) => via_way_ids.extend(via.iter().copied()),
+        }
+    }
+    if via_way_ids.is_empty() {
+        return protected;
+    }
+
+    let way_nodes: HashMap<i64, Vec<i64>> = ways
+        .iter()
+        .map(|way| (way.id, way.nodes.iter().map(|n| n.node_id).collect()))
+        .collect();
+    for restriction in restrictions {
+        let RestrictionVia::Ways(via) = &restriction.via else {
+            continue;
+        };
+        let members: Vec<&Vec<i64>> = via
+            .iter()
+            .chain([&restriction.from_way, &restriction.to_way])
+            .filter_map(|id| way_nodes.get(id))
+            .collect();
+        for via_id in via {
+            let Some(via_nodes) = way_nodes.get(via_id) else {
+                continue;
+            };
+            for node in via_nodes {
+                let shared = members.iter().filter(|nodes| nodes.contains(node)).count();
+                if shared > 1 {
+                    protected.insert(*node);
+                }
+            }
+        }
+    }
+    protected
+}
+
+/// Restriction progress carried by an edge-based search label: a state of the
+/// [`SequenceMatcher`] for the search direction, `None` when the label is not
+/// part way through any forbidden edge sequence.
+pub(crate) type Progress = Option<u32>;
+
+/// Matches every forbidden edge sequence at once (an Aho–Corasick automaton
+/// over edges).
+///
+/// A state stands for the longest tail of the edges walked so far that is
+/// also a prefix of some sequence, so one state tracks all partially matched
+/// sequences, including several sharing a first edge.
+#[derive(Debug, Clone, Default)]
+struct SequenceMatcher {
+    next: HashMap<(u32, EdgeIndex), u32>,
+    /// Fallback state: the longest proper tail of a state that is itself a
+    /// state.
+    fallback: Vec<u32>,
+    /// Whether reaching a state completes a forbidden sequence.
+    complete: Vec<bool>,
+}
+
+impl SequenceMatcher {
+    const ROOT: u32 = 0;
+
+    /// Build the matcher over `sequences`, read back to front when `reversed`.
+    fn new(sequences: &[Vec<EdgeIndex>], reversed: bool) -> Self {
+        let mut matcher = SequenceMatcher {
+            next: HashMap::new(),
+            fallback: vec![Self::ROOT],
+            complete: vec![false],
+        };
+        let mut children: Vec<Vec<(EdgeIndex, u32)>> = vec![Vec::new()];
+        for sequence in sequences {
+            let mut state = Self::ROOT;
+            for i in 0..sequence.len() {
+                let edge = sequence[if reversed { sequence.len() - 1 - i } else { i }];
+                state = match matcher.next.get(&(state, edge)) {
+                    Some(&child) => child,
+                    None => {
+                        let child = matcher.complete.len() as u32;
+                        matcher.next.insert((state, edge), child);
+                        matcher.fallback.push(Self::ROOT);
+                        matcher.complete.push(false);
+                        children[state as usize].push((edge, child));
+                        children.push(Vec::new());
+                        child
+                    }
+                };
+            }
+            matcher.complete[state as usize] = true;
+        }
+
+        // Breadth-first, so every fallback is final before it is followed.
+        let mut queue: VecDeque<u32> = children[0].iter().map(|&(_, child)| child).collect();
+        while let Some(state) = queue.pop_front() {
+            for &(edge, child) in &children[state as usize] {
+                let fallback = matcher.step(matcher.fallback[state as usize], edge);
+                matcher.fallback[child as usize] = fallback;
+                matcher.complete[child as usize] |= matcher.complete[fallback as usize];
+                queue.push_back(child);
+            }
+        }
+        matcher
+    }
+
+    /// The state after walking `edge` from `state`.
+    fn step(&self, mut state: u32, edge: EdgeIndex) -> u32 {
+        loop {
+            if let Some(&next) = self.next.get(&(state, edge)) {
+                return next;
+            }
+            if state == Self::ROOT {
+                return Self::ROOT;
+            }
+            state = self.fallback[state as usize];
+        }
+    }
+}
+
+/// Turn restrictions resolved to the edges of one graph, once for the
+/// restrictions binding bikes and once for those binding motor vehicles.
+#[derive(Debug, Clone)]
+pub(crate) struct TurnRestrictions {
+    restrictions: Vec<TurnRestriction>,
+    bicycle: TurnRestrictionIndex,
+    motor_vehicle: TurnRestrictionIndex,
+}
+
+impl TurnRestrictions {
+    /// Resolve `restrictions` against the edges of `graph`.
+    pub fn resolve(graph: &DiGraph<XmlNode, XmlWay>, restrictions: Vec<TurnRestriction>) -> Self {
+        let index_for = |network_type| {
+            TurnRestrictionIndex::resolve(
+                graph,
+                restrictions
+                    .iter()
+                    .filter(|restriction| restriction.modes.applies_to(network_type)),
+            )
+        };
+        Self {
+            bicycle: index_for(NetworkType::Bike),
+            motor_vehicle: index_for(NetworkType::Drive),
+            restrictions,
+        }
+    }
+
+    /// The OSM-level restrictions these were resolved from.
+    pub fn restrictions(&self) -> &[TurnRestriction] {
+        &self.restrictions
+    }
+
+    /// The rules binding `network_type` queries, or `None` when none of
+    /// them matched an edge of the graph.
+    pub fn for_network(&self, network_type: NetworkType) -> Option<&TurnRestrictionIndex> {
+        let index = match network_type {
+            NetworkType::Walk => return None,
+            NetworkType::Bike => &self.bicycle,
+            NetworkType::Drive
+            | NetworkType::DriveService
+            | NetworkType::All
+            | NetworkType::AllPrivate => &self.motor_vehicle,
+        };
+        (!index.is_empty()).then_some(index)
+    }
+}
+
+/// Turn restrictions of one mode resolved to the edges of one graph.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TurnRestrictionIndex {
+    /// `(from_edge, to_edge)` pairs that may not be traversed consecutively.
+    forbidden_turns: HashSet<(EdgeIndex, EdgeIndex)>,
+    /// Edges after which only the listed edges may be taken.
+    mandatory_turns: HashMap<EdgeIndex, Vec<EdgeIndex>>,
+    /// Edge sequences of length three or more (via-way restrictions) that may
+    /// not be traversed in full, in travel order.
+    forbidden_paths: Vec<Vec<EdgeIndex>>,
+    /// Matcher over `forbidden_paths` in travel order, for forward searches.
+    forward_paths: SequenceMatcher,
+    /// Matcher over `forbidden_paths` in reverse, for backward searches.
+    backward_paths: SequenceMatcher,
+}
+
+impl TurnRestrictionIndex {
+    /// Resolve `restrictions` against the edges of `graph`.
+    fn resolve<'a>(
+        graph: &DiGraph<XmlNode, XmlWay>,
+        restrictions: impl IntoIterator<Item = &'a TurnRestriction>,
+    ) -> Self {
+        let node_by_id: HashMap<i64, NodeIndex> = graph
+            .node_indices()
+            .map(|index| (graph[index].id, index))
+            .collect();
+        let mut edges_by_first_way: HashMap<i64, Vec<EdgeIndex>> = HashMap::new();
+        let mut edges_by_last_way: HashMap<i64, Vec<EdgeIndex>> = HashMap::new();
+        for edge in graph.edge_references() {
+            edges_by_first_way
+                .entry(edge.weight().first_way_id())
+                .or_default()
+                .push(edge.id());
+            edges_by_last_way
+                .entry(edge.weight().last_way_id())
+                .or_default()
+                .push(edge.id());
+        }
+
+        let mut index = TurnRestrictionIndex::default();
+        let mut forbidden_paths: HashSet<Vec<EdgeIndex>> = HashSet::new();
+
+        for restriction in restrictions {
+            let from_edges = edges_by_last_way
+                .get(&restriction.from_way)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            let to_edges = edges_by_first_way
+                .get(&restriction.to_way)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+
+            match &restriction.via {
+                RestrictionVia::Node(via) => {
+                    let Some(&via) = node_by_id.get(via) else {
+                        continue;
+                    };
+                    let into_via = from_edges
+                        .iter()
+                        .filter(|&&e| graph.edge_endpoints(e).is_some_and(|(_, t)| t == via));
+                    let out_of_via: Vec<EdgeIndex> = to_edges
+                        .iter()
+                        .copied()
+                        .filter(|&e| graph.edge_endpoints(e).is_some_and(|(s, _)| s == via))
+                        .collect();
+                    if out_of_via.is_empty() {
+                        continue;
+                    }
+                    for &from in into_via {
+                        match restriction.kind {
+                            RestrictionKind::No => {
+                                for &to in &out_of_via {
+                                    index.forbidden_turns.insert((from, to));
+                                }
+                            }
+                            RestrictionKind::Only => index
+                                .mandatory_turns
+                                .entry(from)
+                                .or_default()
+                                .extend(out_of_via.iter().copied()),
+                        }
+                    }
+                }
+                RestrictionVia::Ways(via_ways) => {
+                    let via_ways: HashSet<i64> = via_ways.iter().copied().collect();
+                    let paths = via_way_paths(graph, from_edges, &via_ways, restriction.to_way);
+                    match restriction.kind {
+                        RestrictionKind::No => forbidden_paths.extend(paths),
+                        RestrictionKind::Only => {
+                            only_paths_to_rules(graph, &paths, &mut index, &mut forbidden_paths)
+                        }
+                    }
+                }
+            }
+        }
+
+        for allowed in index.mandatory_turns.values_mut() {
+            allowed.sort_unstable();
+            allowed.dedup();
+        }
+        let mut forbidden_paths: Vec<Vec<EdgeIndex>> = forbidden_paths.into_iter().collect();
+        forbidden_paths.sort_unstable();
+        index.forward_paths = SequenceMatcher::new(&forbidden_paths, false);
+        index.backward_paths = SequenceMatcher::new(&forbidden_paths, true);
+        index.forbidden_paths = forbidden_paths;
+        index
+    }
+
+    /// `true` when no restriction matched any edge of the graph.
+    pub fn is_empty(&self) -> bool {
+        self.forbidden_turns.is_empty()
+            && self.mandatory_turns.is_empty()
+            && self.forbidden_paths.is_empty()
+    }
+
+    /// Restriction progress for a search label that starts on `edge`.
+    pub(crate) fn initial_progress(&self, direction: SearchDirection, edge: EdgeIndex) -> Progress {
+        let matcher = self.paths_matcher(direction);
+        let state = matcher.step(SequenceMatcher::ROOT, edge);
+        (state != SequenceMatcher::ROOT).then_some(state)
+    }
+
+    fn paths_matcher(&self, direction: SearchDirection) -> &SequenceMatcher {
+        match direction {
+            SearchDirection::Forward => &self.forward_paths,
+            SearchDirection::Backward => &self.backward_paths,
+        }
+    }
+
+    /// Check the transition from label `(current, progress)` onto `next`.
+    ///
+    /// Returns `None` when the turn is restricted, otherwise the progress for
+    /// the new label. In a backward search `next` precedes `current` in
+    /// travel order.
+    pub(crate) fn transition(
+        &self,
+        direction: SearchDirection,
+        current: EdgeIndex,
+        progress: Progress,
+        next: EdgeIndex,
+    ) -> Option<Progress> {
+        let (from, to) = match direction {
+            SearchDirection::Forward => (current, next),
+            SearchDirection::Backward => (next, current),
+        };
+        if self.forbidden_turns.contains(&(from, to)) {
+            return None;
+        }
+        if self
+            .mandatory_turns
+            .get(&from)
+            .is_some_and(|allowed| !allowed.contains(&to))
+        {
+            return None;
+        }
+
+        let matcher = self.paths_matcher(direction);
+        let state = matcher.step(progress.unwrap_or(SequenceMatcher::ROOT), next);
+        if matcher.complete[state as usize] {
+            return None;
+        }
+        Some((state != SequenceMatcher::ROOT).then_some(state))
+    }
+}
+
+/// Every edge sequence `from → via… → to` through ways in `via_ways`.
+fn via_way_paths(
+    graph: &DiGraph<XmlNode, XmlWay>,
+    from_edges: &[EdgeIndex],
+    via_ways: &HashSet<i64>,
+    to_way: i64,
+) -> Vec<Vec<EdgeIndex>> {
+    fn extend(
+        graph: &DiGraph<XmlNode, XmlWay>,
+        path: &mut Vec<EdgeIndex>,
+        visited: &mut Vec<NodeIndex>,
+        via_ways: &HashSet<i64>,
+        to_way: i64,
+        out: &mut Vec<Vec<EdgeIndex>>,
+    ) {
+        let node = *visited.last().unwrap();
+        for edge in graph.edges_directed(node, petgraph::Outgoing) {
+            if path.len() > 1 && edge.weight().first_way_id() == to_way {
+                let mut full = path.clone();
+                full.push(edge.id());
+                out.push(full);
+            }
+            let is_via = edge
+                .weight()
+                .osm_way_ids()
+                .iter()
+                .all(|way| via_ways.contains(way));
+            if is_via && path.len() <= MAX_VIA_EDGES && !visited.contains(&edge.target()) {
+                path.push(edge.id());
+                visited.push(edge.target());
+                extend(graph, path, visited, via_ways, to_way, out);
+                visited.pop();
+                path.pop();
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+    for &from in from_edges {
+        let (source, target) = graph.edge_endpoints(from).unwrap();
+        let mut path = vec![from];
+        let mut visited = vec![source, target];
+        extend(graph, &mut path, &mut visited, via_ways, to_way, &mut out);
+    }
+    out
+}
+
+/// Turn `only_*` via-way paths into rules: the first turn becomes mandatory
+/// and every deviation from the allowed continuation inside the via chain
+/// becomes a forbidden sequence.
+fn only_paths_to_rules(
+    graph: &DiGraph<XmlNode, XmlWay>,
+    paths: &[Vec<EdgeIndex>],
+    index: &mut TurnRestrictionIndex,
+    forbidden_paths: &mut HashSet<Vec<EdgeIndex>>,
+) {
+    for path in paths {
+        index
+            .mandatory_turns
+            .entry(path[0])
+            .or_default()
+            .push(path[1]);
+        for len in 2..path.len() {
+            let prefix = &path[..len];
+            let allowed: HashSet<EdgeIndex> = paths
+                .iter()
+                .filter(|other| other.len() > len && other.starts_with(prefix))
+                .map(|other| other[len])
+                .collect();
+            let (_, node) = graph.edge_endpoints(prefix[len - 1]).unwrap();
+            for edge in graph.edges_directed(node, petgraph::Outgoing) {
+                if !allowed.contains(&edge.id()) {
+                    let mut sequence = prefix.to_vec();
+                    sequence.push(edge.id());
+                    forbidden_paths.insert(sequence);
+                }
+            }
+        }
+    }
+}
+
+/// Extra seconds charged for turning from one edge onto the next.
+///
+/// The turn angle is the change in bearing between the end of the incoming
+/// edge's geometry and the start of the outgoing edge's geometry. Positive
+/// angles are right turns, negative angles left turns. Left and right turns
+/// are only charged at junctions (nodes with more than two neighbours), so
+/// bends along an unsimplified road are free; U-turns are always charged.
+///
+/// The penalties assume nothing about the side of the road traffic drives on:
+/// for left-hand traffic, swap `left_s` and `right_s`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TurnPenalties {
+    /// Seconds added for a left turn.
+    pub left_s: f64,
+    /// Seconds added for a right turn.
+    pub right_s: f64,
+    /// Seconds added for a U-turn.
+    pub u_turn_s: f64,
+    /// Turns within this many degrees of straight ahead are free.
+    pub straight_tolerance_deg: f64,
+    /// Turns of at least this many degrees count as U-turns.
+    pub u_turn_threshold_deg: f64,
+}
+
+impl TurnPenalties {
+    /// Suggested penalties for a network type, tuned for right-hand traffic.
+    ///
+    /// Drive profiles charge the most for crossing oncoming traffic, bikes a
+    /// little, and pedestrians nothing.
+    pub fn for_network(network_type: NetworkType) -> Self {
+        let (left_s, right_s, u_turn_s) = match network_type {
+            NetworkType::Drive
+            | NetworkType::DriveService
+            | NetworkType::All
+            | NetworkType::AllPrivate => (8.0, 2.0, 30.0),
+            NetworkType::Bike => (3.0, 1.0, 10.0),
+            NetworkType::Walk => (0.0, 0.0, 0.0),
+        };
+        TurnPenalties {
+            left_s,
+            right_s,
+            u_turn_s,
+            straight_tolerance_deg: 30.0,
+            u_turn_threshold_deg: 150.0,
+        }
+    }
+
+    /// Penalty for a signed turn angle in degrees (negative = left).
+    /// `at_junction` is `false` at nodes where the road merely continues.
+    pub fn penalty(&self, angle_deg: f64, at_junction: bool) -> f64 {
+        if !angle_deg.is_finite() {
+            return 0.0;
+        }
+        let magnitude = angle_deg.abs();
+        if magnitude >= self.u_turn_threshold_deg {
+            self.u_turn_s
+        } else if !at_junction || magnitude <= self.straight_tolerance_deg {
+            0.0
+        } else if angle_deg > 0.0 {
+            self.right_s
+        } else {
+            self.left_s
+        }
+    }
+}
+
+/// Per-edge bearings needed to classify turns, computed once per graph.
+#[derive(Debug, Clone)]
+pub(crate) struct TurnGeometry {
+    /// `(start_bearing, end_bearing, target_is_junction)` by edge index.
+    edges: Vec<(f64, f64, bool)>,
+}
+
+impl TurnGeometry {
+    pub(crate) fn new(graph: &DiGraph<XmlNode, XmlWay>) -> Self {
+        let junction: Vec<bool> = graph
+            .node_indices()
+            .map(|node| {
+                let mut neighbors: Vec<NodeIndex> = graph
+                    .neighbors_undirected(node)
+                    .filter(|&n| n != node)
+                    .collect();
+                neighbors.sort_unstable();
+                neighbors.dedup();
+                neighbors.len() > 2
+            })
+            .collect();
+        let edges = graph
+            .edge_references()
+            .map(|edge| {
+                let points = directed_edge_geometry(graph, edge.id());
+                let bearing = |a: (f64, f64), b: (f64, f64)| {
+                    if a == b {
+                        f64::NAN
+                    } else {
+                        calculate_bearing(a.0, a.1, b.0, b.1)
+                    }
+                };
+                let n = points.len();
+                (
+                    bearing(points[0], points[1]),
+                    bearing(points[n - 2], points[n - 1]),
+                    junction[edge.target().index()],
+                )
+            })
+            .collect();
+        TurnGeometry { edges }
+    }
+
+    /// Signed turn angle in degrees from `from` onto `to` (negative = left).
+    fn turn_angle(&self, from: EdgeIndex, to: EdgeIndex) -> f64 {
+        let incoming = self.edges[from.index()].1;
+        let outgoing = self.edges[to.index()].0;
+        (outgoing - incoming + 180.0).rem_euclid(360.0) - 180.0
+    }
+}
+
+/// Everything the edge-based search needs to know about turns for one query:
+/// the graph's restrictions (if any) and the penalties for the queried
+/// network type (if configured).
+#[derive(Clone, Copy)]
+pub(crate) struct TurnModel<'a> {
+    pub(crate) restrictions: Option<&'a TurnRestrictionIndex>,
+    pub(crate) penalties: Option<(&'a TurnPenalties, &'a TurnGeometry)>,
+}
+
+impl TurnModel<'_> {
+    pub(crate) fn initial_progress(&self, direction: SearchDirection, edge: EdgeIndex) -> Progress {
+        self.restrictions
+            .and_then(|index| index.initial_progress(direction, edge))
+    }
+
+    /// Check the transition from label `(current, progress)` onto `next`,
+    /// returning the new progress and the turn penalty in seconds, or `None`
+    /// when the turn is restricted.
+    pub(crate) fn transition(
+        &self,
+        direction: SearchDirection,
+        current: EdgeIndex,
+        progress: Progress,
+        next: EdgeIndex,
+    ) -> Option<(Progress, f64)> {
+        let progress = match self.restrictions {
+            Some(index) => index.transition(direction, current, progress, next)?,
+            None => None,
+        };
+        let penalty = match direction {
+            SearchDirection::Forward => self.turn_penalty(current, next),
+            SearchDirection::Backward => self.turn_penalty(next, current),
+        };
+        Some((progress, penalty))
+    }
+
+    /// Whether `edges`, in travel order, take no restricted turn.
+    pub(crate) fn allows_path(&self, edges: &[EdgeIndex]) -> bool {
+        let Some(&first) = edges.first() else {
+            return true;
+        };
+        let mut progress = self.initial_progress(SearchDirection::Forward, first);
+        for pair in edges.windows(2) {
+            match self.transition(SearchDirection::Forward, pair[0], progress, pair[1]) {
+                Some((next, _)) => progress = next,
+                None => return false,
+            }
+        }
+        true
+    }
+
+    /// Penalty in seconds for travelling from edge `from` straight onto `to`.
+    pub(crate) fn turn_penalty(&self, from: EdgeIndex, to: EdgeIndex) -> f64 {
+        let Some((penalties, geometry)) = self.penalties else {
+            return 0.0;
+        };
+        let at_junction = geometry.edges[from.index()].2;
+        penalties.penalty(geometry.turn_angle(from, to), at_junction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::{parse_xml, SpatialGraph};
+    use crate::maneuvers::ManeuverKind;
+    use crate::speed::SpeedProfile;
+
+    // W(1) - C(2) - E(4)
+    //         |      |
+    //        N(3) - NE(6)
+    //         |
+    //        S(5) below C
+    fn grid_graph(relations: &str, network_type: NetworkType) -> SpatialGraph {
+        let xml = format!(
+            r#"<osm>
+              <node id="1" lat="48.000" lon="11.000" />
+              <node id="2" lat="48.000" lon="11.001" />
+              <node id="3" lat="48.001" lon="11.001" />
+              <node id="4" lat="48.000" lon="11.002" />
+              <node id="5" lat="47.999" lon="11.001" />
+              <node id="6" lat="48.001" lon="11.002" />
+              <way id="10"><nd ref="1" /><nd ref="2" /><tag k="highway" v="residential" /></way>
+              <way id="11"><nd ref="2" /><nd ref="4" /><tag k="highway" v="residential" /></way>
+              <way id="12"><nd ref="2" /><nd ref="3" /><tag k="highway" v="residential" /></way>
+              <way id="13"><nd ref="5" /><nd ref="2" /><tag k="highway" v="residential" /></way>
+              <way id="14"><nd ref="4" /><nd ref="6" /><nd ref="3" /><tag k="highway" v="residential" /></way>
+              {relations}
+            </osm>"#
+        );
+        SpatialGraph::from_parsed_osm(
+            parse_xml(&xml).unwrap(),
+            network_type,
+            true,
+            &SpeedProfile::default(),
+        )
+    }
+
+    fn restriction(id: i64, value: &str, from: i64, via: &str, to: i64) -> String {
+        format!(
+            r#"<relation id="{id}">
+                <member type="way" ref="{from}" role="from" />
+                {via}
+                <member type="way" ref="{to}" role="to" />
+                <tag k="type" v="restriction" />
+                <tag k="restriction" v="{value}" />
+              </relation>"#
+        )
+    }
+
+    fn via_node(id: i64) -> String {
+        format!(r#"<member type="node" ref="{id}" role="via" />"#)
+    }
+
+    fn node_index(sg: &SpatialGraph, id: i64) -> NodeIndex {
+        sg.graph
+            .node_indices()
+            .find(|&index| sg.graph[index].id == id)
+            .unwrap()
+    }
+
+    const W: (f64, f64) = (48.000, 11.000);
+    const N: (f64, f64) = (48.001, 11.001);
+    const E: (f64, f64) = (48.000, 11.002);
+    const S: (f64, f64) = (47.999, 11.001);
+    const NE: (f64, f64) = (48.001, 11.002);
+
+    #[test]
+    fn relation_parsing_respects_mode_tags_and_exceptions() {
+        let xml = format!(
+            "<osm>{}{}</osm>",
+            restriction(1, "no_left_turn", 10, &via_node(2), 12),
+            restriction(2, "only_straight_on", 13, &via_node(2), 12).replace(
+                r#"v="restriction" />"#,
+                r#"v="restriction" /><tag k="except" v="bicycle" />"#
+            ),
+        );
+        let data = parse_xml(&xml).unwrap();
+        assert_eq!(data.relations.len(), 2);
+
+        let drive: Vec<TurnRestriction> = data
+            .relations
+            .iter()
+            .flat_map(|r| TurnRestriction::from_relation(r, NetworkType::Drive))
+            .collect();
+        assert_eq!(drive.len(), 2);
+        assert_eq!(drive[0].kind, RestrictionKind::No);
+        assert_eq!(drive[0].via, RestrictionVia::Node(2));
+        assert_eq!((drive[0].from_way, drive[0].to_way), (10, 12));
+        assert_eq!(drive[1].kind, RestrictionKind::Only);
+
+        let bike: Vec<TurnRestriction> = data
+            .relations
+            .iter()
+            .flat_map(|r| TurnRestriction::from_relation(r, NetworkType::Bike))
+            .collect();
+        assert_eq!(bike.len(), 1);
+        assert_eq!(bike[0].relation_id, 1);
+
+        assert!(TurnRestriction::from_relation(&data.relations[0], NetworkType::Walk).is_empty());
+    }
+
+    #[test]
+    fn mode_specific_restriction_only_applies_to_that_mode() {
+        let xml = format!(
+            "<osm>{}</osm>",
+            restriction(1, "no_left_turn", 10, &via_node(2), 12)
+                .replace(r#"k="restriction""#, r#"k="restriction:bicycle""#)
+        );
+        let relation = &parse_xml(&xml).unwrap().relations[0];
+
+        assert!(TurnRestriction::from_relation(relation, NetworkType::Drive).is_empty());
+        assert_eq!(
+            TurnRestriction::from_relation(relation, NetworkType::Bike).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn route_avoids_no_turn_via_node() {
+        let relation = restriction(1, "no_left_turn", 10, &via_node(2), 12);
+        let restricted = grid_graph(&relation, NetworkType::Drive);
+        let unrestricted = grid_graph("", NetworkType::Drive);
+
+        let direct = unrestricted
+            .route(W.0, W.1, N.0, N.1, NetworkType::Drive, None)
+            .unwrap();
+        let detour = restricted
+            .route(W.0, W.1, N.0, N.1, NetworkType::Drive, None)
+            .unwrap();
+
+        assert!(!direct.coordinates.contains(&E));
+        assert!(detour.coordinates.contains(&E));
+        assert!(detour.coordinates.contains(&NE));
+        assert!(detour.duration_s > direct.duration_s);
+    }
+
+    #[test]
+    fn route_via_resets_turn_state_at_waypoints() {
+        let relation = restriction(1, "no_left_turn", 10, &via_node(2), 12);
+        let sg = grid_graph(&relation, NetworkType::Drive);
+        let c = (48.000, 11.001);
+
+        let via = sg.route_via(&[W, c, N], NetworkType::Drive, None).unwrap();
+
+        // The second leg starts afresh at C, so the banned left turn is taken.
+        assert!(!via.coordinates.contains(&E));
+        let departs = via
+            .maneuvers
+            .iter()
+            .filter(|maneuver| maneuver.kind == ManeuverKind::Depart)
+            .count();
+        assert_eq!(departs, 1);
+    }
+
+    #[test]
+    fn walk_graph_ignores_restrictions() {
+        let relation = restriction(1, "no_left_turn", 10, &via_node(2), 12);
+        let walk = grid_graph(&relation, NetworkType::Walk);
+
+        let route = walk
+            .route(W.0, W.1, N.0, N.1, NetworkType::Walk, None)
+            .unwrap();
+
+        assert!(walk.turn_restrictions().is_empty());
+        assert!(!route.coordinates.contains(&E));
+    }
+
+    #[test]
+    fn all_graph_applies_restrictions_by_query_mode() {
+        let everyone = restriction(1, "no_left_turn", 10, &via_node(2), 12);
+        let cars_only = everyone.replace(r#"k="restriction""#, r#"k="restriction:motorcar""#);
+        let takes_left = |sg: &SpatialGraph, network_type| {
+            let route = sg.route(W.0, W.1, N.0, N.1, network_type, None).unwrap();
+            !route.coordinates.contains(&E)
+        };
+
+        let sg = grid_graph(&everyone, NetworkType::All);
+        assert_eq!(sg.turn_restrictions().len(), 1);
+        assert!(takes_left(&sg, NetworkType::Walk));
+        assert!(!takes_left(&sg, NetworkType::Bike));
+        assert!(!takes_left(&sg, NetworkType::Drive));
+
+        let sg = grid_graph(&cars_only, NetworkType::All);
+        assert!(!sg.turn_restrictions()[0].modes.bicycle);
+        assert!(takes_left(&sg, NetworkType::Walk));
+        assert!(takes_left(&sg, NetworkType::Bike));
+        assert!(!takes_left(&sg, NetworkType::Drive));
+    }
+
+    #[test]
+    fn relation_restricting_modes_differently_yields_one_restriction_per_mode() {
+        let xml = format!(
+            "<osm>{}</osm>",
+            restriction(1, "no_left_turn", 10, &via_node(2), 12).replace(
+                r#"<tag k="type""#,
+                r#"<tag k="restriction:bicycle" v="only_straight_on" /><tag k="type""#
+            )
+        );
+        let relation = &parse_xml(&xml).unwrap().relations[0];
+
+        let all = TurnRestriction::from_relation(relation, NetworkType::All);
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].kind, RestrictionKind::Only);
+        assert!(all[0].modes.bicycle && !all[0].modes.motor_vehicle);
+        assert_eq!(all[1].kind, RestrictionKind::No);
+        assert!(!all[1].modes.bicycle && all[1].modes.motor_vehicle);
+        let bike = TurnRestriction::from_relation(relation, NetworkType::Bike);
+        assert_eq!(bike, all[..1]);
+    }
+
+    #[test]
+    fn only_turn_forbids_every_other_exit() {
+        let relation = restriction(1, "only_straight_on", 13, &via_node(2), 12);
+        let sg = grid_graph(&relation, NetworkType::Drive);
+
+        let route = sg
+            .route(S.0, S.1, E.0, E.1, NetworkType::Drive, None)
+            .unwrap();
+
+        assert!(route.coordinates.contains(&N));
+        assert!(route.coordinates.contains(&NE));
+    }
+
+    #[test]
+    fn via_way_restriction_blocks_full_sequence_only() {
+        let via_way = r#"<member type="way" ref="11" role="via" />"#;
+        let relation = restriction(1, "no_left_turn", 10, via_way, 14);
+        let sg = grid_graph(&relation, NetworkType::Drive);
+
+        let to_ne = sg
+            .route(W.0, W.1, NE.0, NE.1, NetworkType::Drive, None)
+            .unwrap();
+        let to_e = sg
+            .route(W.0, W.1, E.0, E.1, NetworkType::Drive, None)
+            .unwrap();
+
+        assert!(to_ne.coordinates.contains(&N));
+        assert!(!to_ne.coordinates.contains(&E));
+        assert_eq!(to_e.coordinates, vec![W, (48.000, 11.001), E]);
+    }
+
+    #[test]
+    fn via_way_only_restriction_blocks_every_other_exit() {
+        // W(1) - C(2) - E(4) - F(7), with NE(6) and SE(8) next to E and
+        // detours to both from C over 3 and 5.
+        let xml = format!(
+            r#"<osm>
+              <node id="1" lat="48.000" lon="11.000" />
+              <node id="2" lat="48.000" lon="11.001" />
+              <node id="3" lat="48.002" lon="11.001" />
+              <node id="4" lat="48.000" lon="11.002" />
+              <node id="5" lat="47.998" lon="11.001" />
+              <node id="6" lat="48.001" lon="11.002" />
+              <node id="7" lat="48.000" lon="11.003" />
+              <node id="8" lat="47.999" lon="11.002" />
+              <way id="10"><nd ref="1" /><nd ref="2" /><tag k="highway" v="residential" /></way>
+              <way id="11"><nd ref="2" /><nd ref="4" /><tag k="highway" v="residential" /></way>
+              <way id="12"><nd ref="2" /><nd ref="3" /><nd ref="6" /><tag k="highway" v="residential" /></way>
+              <way id="13"><nd ref="2" /><nd ref="5" /><nd ref="8" /><tag k="highway" v="residential" /></way>
+              <way id="14"><nd ref="4" /><nd ref="6" /><tag k="highway" v="residential" /></way>
+              <way id="15"><nd ref="4" /><nd ref="7" /><tag k="highway" v="residential" /></way>
+              <way id="16"><nd ref="4" /><nd ref="8" /><tag k="highway" v="residential" /></way>
+              {}
+            </osm>"#,
+            restriction(
+                1,
+                "only_straight_on",
+                10,
+                r#"<member type="way" ref="11" role="via" />"#,
+                15
+            )
+        );
+        let sg = SpatialGraph::from_parsed_osm(
+            parse_xml(&xml).unwrap(),
+            NetworkType::Drive,
+            true,
+            &SpeedProfile::default(),
+        );
+        let route = |to: (f64, f64)| {
+            sg.route(W.0, W.1, to.0, to.1, NetworkType::Drive, None)
+                .unwrap()
+                .coordinates
+        };
+        // Turning around at F is allowed; turning off at E is not.
+        let turns_at_e = |coordinates: &[(f64, f64)], to: (f64, f64)| {
+            coordinates
+                .windows(3)
+                .any(|window| window == [(48.000, 11.001), E, to])
+        };
+
+        let f = (48.000, 11.003);
+        assert_eq!(route(f), vec![W, (48.000, 11.001), E, f]);
+        assert!(!turns_at_e(&route(NE), NE));
+        let se = (47.999, 11.002);
+        assert!(!turns_at_e(&route(se), se));
+    }
+
+    #[test]
+    fn reachability_and_prism_use_restricted_times() {
+        let relation = restriction(1, "no_left_turn", 10, &via_node(2), 12);
+        let restricted = grid_graph(&relation, NetworkType::Drive);
+        let unrestricted = grid_graph("", NetworkType::Drive);
+        let route = restricted
+            .route(W.0, W.1, N.0, N.1, NetworkType::Drive, None)
+            .unwrap();
+
+        let reach = restricted
+            .reachability(W.0, W.1, 3600.0, NetworkType::Drive, None)
+            .unwrap();
+        let free_reach = unrestricted
+            .reachability(W.0, W.1, 3600.0, NetworkType::Drive, None)
+            .unwrap();
+        let n = node_index(&restricted, 3);
+        assert!((reach.distances[&n] - route.duration_s).abs() < 1e-9);
+        assert!(reach.distances[&n] > free_reach.distances[&node_index(&unrestricted, 3)]);
+
+        let prism = restricted
+            .prism(W.0, W.1, N.0, N.1, 3600.0, NetworkType::Drive, None)
+            .unwrap()
+            .unwrap();
+        assert!((prism.result.direct_time - route.duration_s).abs() < 1e-9);
+        let origin = &prism.result.feasible[&node_index(&restricted, 1)];
+        assert!((origin.outbound_time - route.duration_s).abs() < 1e-9);
+    }
+
+    #[test]
+    fn hierarchy_routes_fall_back_on_restricted_turns() {
+        let relation = restriction(1, "no_left_turn", 10, &via_node(2), 12);
+        let restricted = grid_graph(&relation, NetworkType::Drive);
+        let fast = restricted
+            .clone()
+            .with_contraction_hierarchy(NetworkType::Drive);
+        assert!(fast.contraction_hierarchy(NetworkType::Drive).is_some());
+
+        for (to_lat, to_lon) in [N, E, S, NE] {
+            let duration = |sg: &SpatialGraph| {
+                sg.route(W.0, W.1, to_lat, to_lon, NetworkType::Drive, None)
+                    .unwrap()
+                    .duration_s
+            };
+            assert!((duration(&fast) - duration(&restricted)).abs() < 1e-9);
+        }
+        let unrestricted = grid_graph("", NetworkType::Drive);
+        let to_n = |sg: &SpatialGraph| {
+            sg.route(W.0, W.1, N.0, N.1, NetworkType::Drive, None)
+                .unwrap()
+                .duration_s
+        };
+        assert!(to_n(&fast) > to_n(&unrestricted));
+
+        let penalized = fast.with_turn_penalties(
+            NetworkType::Drive,
+            TurnPenalties::for_network(NetworkType::Drive),
+        );
+        assert!(penalized
+            .contraction_hierarchy(NetworkType::Drive)
+            .is_none());
+    }
+
+    #[test]
+    fn materialized_views_keep_restrictions() {
+        let relation = restriction(1, "no_left_turn", 10, &via_node(2), 12);
+        let sg = grid_graph(&relation, NetworkType::Drive);
+
+        let view = sg
+            .reachable_graph(W.0, W.1, 3600.0, NetworkType::Drive, None)
+            .unwrap();
+        let route = view.route(W.0, W.1, N.0, N.1, None).unwrap();
+
+        assert_eq!(view.materialize().turn_restrictions().len(), 1);
+        assert!(route.coordinates.contains(&E));
+    }
+
+    #[test]
+    fn penalty_classifies_turn_angles() {
+        let penalties = TurnPenalties::for_network(NetworkType::Drive);
+
+        assert_eq!(penalties.penalty(10.0, true), 0.0);
+        assert_eq!(penalties.penalty(-90.0, true), penalties.left_s);
+        assert_eq!(penalties.penalty(90.0, true), penalties.right_s);
+        assert_eq!(penalties.penalty(179.0, true), penalties.u_turn_s);
+        assert_eq!(penalties.penalty(-90.0, false), 0.0);
+        assert_eq!(penalties.penalty(-175.0, false), penalties.u_turn_s);
+        assert_eq!(penalties.penalty(f64::NAN, true), 0.0);
+    }
+
+    #[test]
+    fn route_and_reachability_charge_turn_penalties() {
+        let plain = grid_graph("", NetworkType::Drive);
+        let penalties = TurnPenalties::for_network(NetworkType::Drive);
+        let penalized = plain
+            .clone()
+            .with_turn_penalties(NetworkType::Drive, penalties);
+
+        let left_plain = plain
+            .route(W.0, W.1, N.0, N.1, NetworkType::Drive, None)
+            .unwrap();
+        let left = penalized
+            .route(W.0, W.1, N.0, N.1, NetworkType::Drive, None)
+            .unwrap();
+        let right_plain = plain
+            .route(W.0, W.1, S.0, S.1, NetworkType::Drive, None)
+            .unwrap();
+        let right = penalized
+            .route(W.0, W.1, S.0, S.1, NetworkType::Drive, None)
+            .unwrap();
+
+        assert!((left.duration_s - left_plain.duration_s - penalties.left_s).abs() < 1e-9);
+        assert!((right.duration_s - right_plain.duration_s - penalties.right_s).abs() < 1e-9);
+        assert_eq!(*left.cumulative_times_s.last().unwrap(), left.duration_s);
+
+        let reach = penalized
+            .reachability(W.0, W.1, 3600.0, NetworkType::Drive, None)
+            .unwrap();
+        assert!((reach.distances[&node_index(&penalized, 3)] - left.duration_s).abs() < 1e-9);
+
+        // Penalties are per network type.
+        let walk = penalized
+            .route(W.0, W.1, N.0, N.1, NetworkType::Walk, None)
+            .unwrap();
+        assert!(penalized.turn_penalties(NetworkType::Walk).is_none());
+        assert_eq!(
+            walk.duration_s,
+            plain
+                .route(W.0, W.1, N.0, N.1, NetworkType::Walk, None)
+                .unwrap()
+                .duration_s
+        );
+    }
+
+    #[test]
+    fn turn_penalties_apply_between_points_part_way_along_edges() {
+        let plain = grid_graph("", NetworkType::Drive);
+        let penalties = TurnPenalties::for_network(NetworkType::Drive);
+        let penalized = plain
+            .clone()
+            .with_turn_penalties(NetworkType::Drive, penalties);
+        // Halfway along W → centre, then halfway along centre → N.
+        let (from, to) = ((48.000, 11.0005), (48.0005, 11.001));
+
+        let left_plain = plain
+            .route(from.0, from.1, to.0, to.1, NetworkType::Drive, None)
+            .unwrap();
+        let left = penalized
+            .route(from.0, from.1, to.0, to.1, NetworkType::Drive, None)
+            .unwrap();
+
+        assert_eq!(left.coordinates.len(), 3);
+        assert!((left.duration_s - left_plain.duration_s - penalties.left_s).abs() < 1e-9);
+        assert_eq!(*left.cumulative_times_s.last().unwrap(), left.duration_s);
+    }
+
+    #[test]
+    fn expensive_left_turns_cause_detours() {
+        let penalties = TurnPenalties {
+            left_s: 1000.0,
+            ..TurnPenalties::for_network(NetworkType::Drive)
+        };
+        let sg =
+            grid_graph("", NetworkType::Drive).with_turn_penalties(NetworkType::Drive, penalties);
+
+        let route = sg
+            .route(W.0, W.1, N.0, N.1, NetworkType::Drive, None)
+            .unwrap();
+
+        assert!(route.coordinates.contains(&E));
+        assert!(route.duration_s < 1000.0);
+        let view = sg
+            .reachable_graph(W.0, W.1, 3600.0, NetworkType::Drive, None)
+            .unwrap();
+        assert_eq!(
+            view.materialize().turn_penalties(NetworkType::Drive),
+            Some(&penalties)
+        );
+    }
+}
@@ -16,6 +16,19 @@ pub fn calculate_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
     EARTH_RADIUS_M * c
 }
 
+/// Initial compass bearing in degrees (`0` = north, `90` = east) of the great
+/// circle from the first point to the second, in `[0, 360)`.
+pub fn calculate_bearing(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let lat1 = lat1.to_radians();
+    let lat2 = lat2.to_radians();
+    let dlon = (lon2 - lon1).to_radians();
+
+    let y = dlon.sin() * lat2.cos();
+    let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * dlon.cos();
+
+    y.atan2(x).to_degrees().rem_euclid(360.0)
+}
+
 pub fn calculate_travel_time(length: f64, speed_kph: f64) -> f64 {
     if !length.is_finite() || !speed_kph.is_finite() || length < 0.0 || speed_kph <= 0.0 {
         return f64::INFINITY;
@@ -73,6 +86,14 @@ mod tests {
         assert!((d1 - d2).abs() < 1e-6);
     }
 
+    #[test]
+    fn test_bearing_cardinal_directions() {
+        assert!(calculate_bearing(48.0, 11.0, 48.01, 11.0).abs() < 1e-6);
+        assert!((calculate_bearing(48.0, 11.0, 48.0, 11.01) - 90.0).abs() < 0.01);
+        assert!((calculate_bearing(48.0, 11.0, 47.99, 11.0) - 180.0).abs() < 1e-6);
+        assert!((calculate_bearing(48.0, 11.0, 48.0, 10.99) - 270.0).abs() < 0.01);
+    }
+
     #[test]
     fn test_travel_time_basic() {
         // 1000m at 36 kph = 100 seconds
@@ -1,4 +1,5 @@
 import json
+import tempfile
 import unittest
 from pathlib import Path
 
@@ -30,6 +31,23 @@ class PythonApiTests(unittest.TestCase):
         self.assertEqual(geojson["geometry"]["type"], "LineString")
         self.assertIn("origin_snap", geojson["properties"])
 
+    def test_route_lists_maneuvers(self):
+        # Walks leave Main Test Street at node 2 for the unnamed driveway.
+        route = self.graph.route((48.0, 11.0), (48.003, 11.0))
+
+        kinds = [step["type"] for step in route.maneuvers]
+        self.assertEqual(kinds, ["depart", "turn-slight-right", "arrive"])
+        self.assertEqual(route.maneuvers[0]["instruction"], "Head north on Main Test Street")
+        self.assertIsNone(route.maneuvers[1]["street"])
+        self.assertAlmostEqual(
+            sum(step["distance_m"] for step in route.maneuvers), route.distance_m
+        )
+
+        geojson = json.loads(route.to_geojson())
+        self.assertEqual(
+            [step["type"] for step in geojson["properties"]["maneuvers"]], kinds
+        )
+
     def test_isochrone_returns_structured_results(self):
         isochrones = self.graph.isochrone((48.0, 11.0), [1, 3])
 
@@ -45,6 +63,116 @@ class PythonApiTests(unittest.TestCase):
         self.assertEqual(snap.node_id, 1)
         self.assertAlmostEqual(snap.distance_m, 0.0)
         self.assertEqual(snap.as_dict()["node_id"], 1)
+        self.assertAlmostEqual(snap.snapped_lat, 48.0)
+        self.assertAlmostEqual(snap.edge_offset_m, 0.0)
+
+    def test_snap_candidates_apply_filters(self):
+        candidates = self.graph.snap_candidates(48.0, 11.0, k=3)
+
+        self.assertTrue(1 <= len(candidates) <= 3)
+        distances = [snap.distance_m for snap in candidates]
+        self.assertEqual(distances, sorted(distances))
+        self.assertIsNone(self.graph.snap_point(48.0, 11.0, highways=["motorway"]))
+
+    def test_closures_apply_to_a_copy_of_the_graph(self):
+        closed = self.graph.with_closures(way_ids=[10])
+
+        with self.assertRaises(LookupError):
+            closed.route((48.002, 11.0), (48.001, 11.0))
+        self.assertGreater(self.graph.route((48.002, 11.0), (48.001, 11.0)).distance_m, 0)
+        with self.assertRaises(ValueError):
+            self.graph.with_closures(areas=["not geojson"])
+
+    def test_hourly_speeds_apply_at_the_departure_time(self):
+        xml = """
+        <osm>
+          <node id="1" lat="0" lon="0" />
+          <node id="2" lat="0" lon="0.01" />
+          <way id="10"><nd ref="1" /><nd ref="2" /><tag k="highway" v="residential" /></way>
+        </osm>
+        """
+        with tempfile.TemporaryDirectory() as tmp:
+            csv = Path(tmp) / "speeds.csv"
+            csv.write_text("way_id,direction,day,hour,speed_kph\n10,both,Mo-Fr,8,10\n")
+            profile = gw.SpeedProfile(hourly_speeds_csv=str(csv))
+        graph = gw.SpatialGraph.from_osm(xml, "drive", speed_profile=profile)
+
+        def duration(departure):
+            return graph.route((0, 0), (0, 0.01), departure=departure).duration_s
+
+        self.assertAlmostEqual(duration("Mo 08:00"), 3 * duration("Mo 03:00"), places=3)
+        self.assertEqual(duration("Sa 08:00"), graph.route((0, 0), (0, 0.01)).duration_s)
+        with self.assertRaises(ValueError):
+            gw.SpeedProfile(hourly_speeds_csv=__file__)
+
+    def test_contraction_hierarchy_returns_the_same_routes(self):
+        fast = self.graph.with_contraction_hierarchy()
+
+        self.assertTrue(fast.has_contraction_hierarchy())
+        self.assertFalse(self.graph.has_contraction_hierarchy())
+        for destination in [(48.001, 11.0), (48.002, 11.0)]:
+            expected = self.graph.route((48.0, 11.0), destination)
+            route = fast.route((48.0, 11.0), destination)
+            self.assertAlmostEqual(route.duration_s, expected.duration_s)
+            self.assertEqual(len(route.coordinates), len(expected.coordinates))
+
+    def test_travel_time_matrix_matches_routes(self):
+        points = [(48.0, 11.0), (48.001, 11.0), (48.002, 11.0)]
+        matrix = self.graph.travel_time_matrix(points, points + [(10.0, 10.0)])
+
+        self.assertEqual(matrix.shape, (3, 4))
+        for i, origin in enumerate(points):
+            for j, destination in enumerate(points):
+                route = self.graph.route(origin, destination)
+                self.assertAlmostEqual(matrix.durations_s[i][j], route.duration_s)
+                self.assertAlmostEqual(matrix.distances_m[i][j], route.distance_m, places=3)
+            self.assertIsNone(matrix.durations_s[i][3])
+        self.assertIsNone(matrix.destination_snaps[3])
+        self.assertIs(matrix.durations_s, matrix.durations_s)
+
+    def test_route_via_joins_legs(self):
+        route = self.graph.route_via([(48.0, 11.0), (48.001, 11.0), (48.002, 11.0)])
+        direct = self.graph.route((48.0, 11.0), (48.002, 11.0))
+
+        self.assertEqual(len(route.legs), 2)
+        self.assertAlmostEqual(route.distance_m, direct.distance_m)
+        self.assertAlmostEqual(
+            sum(leg["duration_s"] for leg in route.legs), route.duration_s
+        )
+        self.assertEqual(route.legs[1]["start_index"], route.legs[0]["end_index"])
+        self.assertIn("waypoint", [step["type"] for step in route.maneuvers])
+
+        with self.assertRaisesRegex(LookupError, "Leg 1"):
+            self.graph.route_via([(48.0, 11.0), (48.001, 11.0), (47.0, 11.0)])
+
+    def test_optimize_tour_orders_stops_and_routes_them(self):
+        stops = [(48.002, 11.0), (48.001, 11.0)]
+        one_way = self.graph.optimize_tour((48.0, 11.0), stops, return_to_depot=False)
+
+        self.assertEqual(one_way.order, [1, 0])
+        self.assertEqual(len(one_way.arrival_times_s), 2)
+        self.assertEqual(len(one_way.route.legs), 2)
+        self.assertAlmostEqual(one_way.route.duration_s, one_way.duration_s)
+
+        # Nobody walks the 111 m to the nearer stop within a second.
+        tour = self.graph.optimize_tour((48.0, 11.0), stops, time_windows=[None, (0.0, 1.0)])
+        self.assertEqual(len(tour.route.legs), 3)
+        self.assertGreater(tour.lateness_s, 0)
+
+    def test_route_alternatives_start_with_the_fastest_route(self):
+        expected = self.graph.route((48.0, 11.0), (48.002, 11.0))
+        routes = self.graph.route_alternatives((48.0, 11.0), (48.002, 11.0), k=2)
+
+        self.assertGreaterEqual(len(routes), 1)
+        self.assertLessEqual(len(routes), 2)
+        self.assertAlmostEqual(routes[0].duration_s, expected.duration_s)
+        self.assertEqual(routes[0].coordinates, expected.coordinates)
+        for route in routes:
+            self.assertEqual(len(route.cumulative_times_s), len(route.coordinates))
+
+    def test_route_alternatives_reject_zero_routes(self):
+        with self.assertRaises(ValueError):
+            self.graph.route_alternatives((48.0, 11.0), (48.002, 11.0), k=0)
 
     def test_graph_views_return_structured_route_and_isochrones(self):
         reachable = self.graph.reachable((48.0, 11.0), minutes=5)
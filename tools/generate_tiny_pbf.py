@@ -1,13 +1,15 @@
 #!/usr/bin/env python3
 """
-Generate tests/fixtures/tiny_map.osm.pbf without external dependencies.
+Generate tests/fixtures/tiny_map*.osm.pbf without external dependencies.
 
 The fixture intentionally uses only basic OSM PBF primitives:
   - OSMHeader block with OsmSchema-V0.6
-  - OSMData block with plain Node and Way messages
+  - OSMData block with plain Node, Way and Relation messages
   - uncompressed Blob.raw payloads
 
-It mirrors tests/fixtures/tiny_map.osm.
+tiny_map.osm.pbf mirrors tests/fixtures/tiny_map.osm. tiny_map_south.osm.pbf
+and tiny_map_north.osm.pbf split the same map into two neighbouring extracts
+that both carry the elements along their seam, like adjacent Geofabrik files.
 """
 
 from __future__ import annotations
@@ -17,7 +19,7 @@ import struct
 
 
 ROOT = Path(__file__).resolve().parents[1]
-OUT = ROOT / "tests" / "fixtures" / "tiny_map.osm.pbf"
+FIXTURES = ROOT / "tests" / "fixtures"
 
 NODES = [
     (1, 48.0000000, 11.0000000, []),
@@ -39,6 +41,23 @@ WAYS = [
     (40, [4, 5], [("highway", "footway")]),
 ]
 
+# Element ids of each extract; nodes 2, 3 and 5 and way 30 are in both.
+EXTRACTS = {
+    "tiny_map.osm.pbf": ({1, 2, 3, 4, 5, 100}, {10, 20, 30, 40}, {900}),
+    "tiny_map_south.osm.pbf": ({1, 2, 3, 5, 100}, {10, 30}, {900}),
+    "tiny_map_north.osm.pbf": ({2, 3, 4, 5}, {20, 30, 40}, set()),
+}
+
+MEMBER_TYPES = {"node": 0, "way": 1, "relation": 2}
+
+RELATIONS = [
+    (
+        900,
+        [("way", 10, "from"), ("node", 3, "via"), ("way", 10, "to")],
+        [("type", "restriction"), ("restriction", "no_u_turn")],
+    ),
+]
+
 
 def varint(value: int) -> bytes:
     out = bytearray()
@@ -87,12 +106,16 @@ def packed_sint64_field(field: int, values: list[int]) -> bytes:
     return bytes_field(field, payload)
 
 
-def string_table() -> tuple[bytes, dict[str, int]]:
+def string_table(nodes, ways, relations) -> tuple[bytes, dict[str, int]]:
     values = [""]
-    for _, _, _, tags in NODES:
+    for _, _, _, tags in nodes:
+        for k, v in tags:
+            values.extend([k, v])
+    for _, _, tags in ways:
         for k, v in tags:
             values.extend([k, v])
-    for _, _, tags in WAYS:
+    for _, members, tags in relations:
+        values.extend(role for _, _, role in members)
         for k, v in tags:
             values.extend([k, v])
 
@@ -145,13 +168,41 @@ def way_message(way_id: int, refs: list[int], tags: list[tuple[str, str]], index
     return bytes(payload)
 
 
-def primitive_block() -> bytes:
-    table, indexes = string_table()
+def relation_message(
+    relation_id: int,
+    members: list[tuple[str, int, str]],
+    tags: list[tuple[str, str]],
+    indexes: dict[str, int],
+) -> bytes:
+    keys, vals = tag_indexes(tags, indexes)
+    deltas = []
+    previous = 0
+    for _, ref, _ in members:
+        deltas.append(ref - previous)
+        previous = ref
+
+    payload = bytearray()
+    payload += int64_field(1, relation_id)
+    payload += packed_uint32_field(2, keys)
+    payload += packed_uint32_field(3, vals)
+    payload += packed_uint32_field(8, [indexes[role] for _, _, role in members])
+    payload += packed_sint64_field(9, deltas)
+    payload += packed_uint32_field(10, [MEMBER_TYPES[kind] for kind, _, _ in members])
+    return bytes(payload)
+
+
+def primitive_block(node_ids: set[int], way_ids: set[int], relation_ids: set[int]) -> bytes:
+    nodes = [node for node in NODES if node[0] in node_ids]
+    ways = [way for way in WAYS if way[0] in way_ids]
+    relations = [relation for relation in RELATIONS if relation[0] in relation_ids]
+    table, indexes = string_table(nodes, ways, relations)
     group = bytearray()
-    for node in NODES:
+    for node in nodes:
         group += bytes_field(1, node_message(*node, indexes))
-    for way in WAYS:
+    for way in ways:
         group += bytes_field(3, way_message(*way, indexes))
+    for relation in relations:
+        group += bytes_field(4, relation_message(*relation, indexes))
 
     block = bytearray()
     block += bytes_field(1, table)
@@ -176,11 +227,13 @@ def write_fileblock(handle, block_type: str, payload: bytes) -> None:
 
 
 def main() -> None:
-    OUT.parent.mkdir(parents=True, exist_ok=True)
-    with OUT.open("wb") as handle:
-        write_fileblock(handle, "OSMHeader", header_block())
-        write_fileblock(handle, "OSMData", primitive_block())
-    print(f"Wrote {OUT}")
+    FIXTURES.mkdir(parents=True, exist_ok=True)
+    for name, ids in EXTRACTS.items():
+        out = FIXTURES / name
+        with out.open("wb") as handle:
+            write_fileblock(handle, "OSMHeader", header_block())
+            write_fileblock(handle, "OSMData", primitive_block(*ids))
+        print(f"Wrote {out}")
 
 
 if __name__ == "__main__":